@@ -0,0 +1,338 @@
+//! Constructive solid geometry on convex polygons with binary space partitioning trees.
+
+use glam::DVec3;
+
+use crate::Tolerance;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct SplitPlane {
+    pub normal: DVec3,
+    pub w: f64,
+}
+
+impl SplitPlane {
+    fn from_points(a: DVec3, b: DVec3, c: DVec3) -> Option<Self> {
+        let normal = (b - a).cross(c - a).try_normalize()?;
+        Some(Self {
+            normal,
+            w: normal.dot(a),
+        })
+    }
+
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.w = -self.w;
+    }
+
+    fn distance(&self, p: DVec3) -> f64 {
+        self.normal.dot(p) - self.w
+    }
+}
+
+/// A convex planar polygon with counter-clockwise vertices seen from the front of its plane.
+#[derive(Debug, Clone)]
+pub(super) struct Polygon {
+    pub vertices: Vec<DVec3>,
+    pub plane: SplitPlane,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<DVec3>, normal: DVec3) -> Self {
+        let plane = (0..vertices.len())
+            .find_map(|i| {
+                let n = vertices.len();
+                let plane = SplitPlane::from_points(
+                    vertices[i],
+                    vertices[(i + 1) % n],
+                    vertices[(i + 2) % n],
+                )?;
+                (plane.normal.dot(normal) > 0.).then_some(plane)
+            })
+            .unwrap_or(SplitPlane {
+                normal,
+                w: normal.dot(vertices[0]),
+            });
+
+        Self { vertices, plane }
+    }
+
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        self.plane.flip();
+    }
+}
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+/// How many polygons are tried as the splitting plane of a node.
+const CANDIDATES: usize = 16;
+
+/// The side of `plane` a point lies on. Points within the linear tolerance of the plane lie on
+/// it.
+fn side(plane: &SplitPlane, p: DVec3, tolerance: f64) -> u8 {
+    let d = plane.distance(p);
+    if d < -tolerance {
+        BACK
+    } else if d > tolerance {
+        FRONT
+    } else {
+        COPLANAR
+    }
+}
+
+/// The plane of one of a few polygons spread over `polygons` that splits the fewest of them,
+/// and of those the one dividing the rest most evenly. Always taking the first plane cuts the
+/// facets of curved faces, which reach across the planes of their neighbours, into many more
+/// pieces.
+fn choose_plane(polygons: &[Polygon]) -> SplitPlane {
+    let tolerance = Tolerance::current().linear;
+    let step = polygons.len().div_ceil(CANDIDATES);
+
+    polygons
+        .iter()
+        .step_by(step)
+        .map(|candidate| {
+            let (mut spanning, mut front, mut back) = (0, 0i64, 0i64);
+            for polygon in polygons {
+                let kind = polygon.vertices.iter().fold(COPLANAR, |acc, &v| {
+                    acc | side(&candidate.plane, v, tolerance)
+                });
+                match kind {
+                    FRONT => front += 1,
+                    BACK => back += 1,
+                    SPANNING => spanning += 1,
+                    _ => {}
+                }
+            }
+            (candidate.plane, (spanning, (front - back).abs()))
+        })
+        .min_by_key(|&(_, score)| score)
+        .map(|(plane, _)| plane)
+        .unwrap_or(polygons[0].plane)
+}
+
+/// Where a polygon ends up relative to a splitting plane.
+#[derive(Default)]
+struct Split {
+    coplanar_front: Vec<Polygon>,
+    coplanar_back: Vec<Polygon>,
+    front: Vec<Polygon>,
+    back: Vec<Polygon>,
+}
+
+fn split_polygon(plane: &SplitPlane, polygon: Polygon, out: &mut Split) {
+    let tolerance = Tolerance::current().linear;
+    let types: Vec<u8> = polygon
+        .vertices
+        .iter()
+        .map(|&v| side(plane, v, tolerance))
+        .collect();
+    let kind = types.iter().fold(COPLANAR, |acc, t| acc | t);
+
+    match kind {
+        COPLANAR => {
+            if plane.normal.dot(polygon.plane.normal) > 0. {
+                out.coplanar_front.push(polygon);
+            } else {
+                out.coplanar_back.push(polygon);
+            }
+        }
+        FRONT => out.front.push(polygon),
+        BACK => out.back.push(polygon),
+        _ => {
+            let n = polygon.vertices.len();
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let (ti, tj) = (types[i], types[j]);
+                let (vi, vj) = (polygon.vertices[i], polygon.vertices[j]);
+
+                if ti != BACK {
+                    front.push(vi);
+                }
+                if ti != FRONT {
+                    back.push(vi);
+                }
+                if ti | tj == SPANNING {
+                    let t = plane.distance(vi) / (plane.distance(vi) - plane.distance(vj));
+                    let v = vi.lerp(vj, t);
+                    front.push(v);
+                    back.push(v);
+                }
+            }
+
+            if front.len() >= 3 {
+                out.front.push(Polygon {
+                    vertices: front,
+                    plane: polygon.plane,
+                });
+            }
+            if back.len() >= 3 {
+                out.back.push(Polygon {
+                    vertices: back,
+                    plane: polygon.plane,
+                });
+            }
+        }
+    }
+}
+
+/// A node of a BSP tree. The polygons of a node lie in its plane; the front subtree holds what
+/// is in front of the plane and the back subtree what is behind it.
+#[derive(Debug, Default)]
+pub(super) struct Node {
+    plane: Option<SplitPlane>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+    polygons: Vec<Polygon>,
+}
+
+impl Node {
+    pub fn new(polygons: Vec<Polygon>) -> Self {
+        let mut node = Node::default();
+        node.build(polygons);
+        node
+    }
+
+    /// Swaps solid and empty space.
+    pub fn invert(&mut self) {
+        for polygon in &mut self.polygons {
+            polygon.flip();
+        }
+        if let Some(plane) = &mut self.plane {
+            plane.flip();
+        }
+        if let Some(front) = &mut self.front {
+            front.invert();
+        }
+        if let Some(back) = &mut self.back {
+            back.invert();
+        }
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Removes the parts of `polygons` that are inside the solid of this tree.
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let Some(plane) = &self.plane else {
+            return polygons;
+        };
+
+        let mut split = Split::default();
+        for polygon in polygons {
+            split_polygon(plane, polygon, &mut split);
+        }
+
+        let mut front = split.front;
+        front.extend(split.coplanar_front);
+        let mut back = split.back;
+        back.extend(split.coplanar_back);
+
+        let mut front = match &self.front {
+            Some(node) => node.clip_polygons(front),
+            None => front,
+        };
+        let back = match &self.back {
+            Some(node) => node.clip_polygons(back),
+            None => Vec::new(),
+        };
+
+        front.extend(back);
+        front
+    }
+
+    /// Removes the parts of this tree's polygons that are inside the solid of `other`.
+    pub fn clip_to(&mut self, other: &Node) {
+        self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+        if let Some(front) = &mut self.front {
+            front.clip_to(other);
+        }
+        if let Some(back) = &mut self.back {
+            back.clip_to(other);
+        }
+    }
+
+    pub fn all_polygons(&self) -> Vec<Polygon> {
+        let mut out = self.polygons.clone();
+        if let Some(front) = &self.front {
+            out.extend(front.all_polygons());
+        }
+        if let Some(back) = &self.back {
+            out.extend(back.all_polygons());
+        }
+        out
+    }
+
+    pub fn build(&mut self, polygons: Vec<Polygon>) {
+        if polygons.is_empty() {
+            return;
+        }
+
+        let plane = *self.plane.get_or_insert_with(|| choose_plane(&polygons));
+
+        let mut split = Split::default();
+        for polygon in polygons {
+            split_polygon(&plane, polygon, &mut split);
+        }
+
+        self.polygons.extend(split.coplanar_front);
+        self.polygons.extend(split.coplanar_back);
+
+        if !split.front.is_empty() {
+            self.front
+                .get_or_insert_with(Default::default)
+                .build(split.front);
+        }
+        if !split.back.is_empty() {
+            self.back
+                .get_or_insert_with(Default::default)
+                .build(split.back);
+        }
+    }
+}
+
+pub(super) fn union(a: Vec<Polygon>, b: Vec<Polygon>) -> Vec<Polygon> {
+    let mut a = Node::new(a);
+    let mut b = Node::new(b);
+
+    a.clip_to(&b);
+    b.clip_to(&a);
+    b.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.build(b.all_polygons());
+    a.all_polygons()
+}
+
+pub(super) fn subtract(a: Vec<Polygon>, b: Vec<Polygon>) -> Vec<Polygon> {
+    let mut a = Node::new(a);
+    let mut b = Node::new(b);
+
+    a.invert();
+    a.clip_to(&b);
+    b.clip_to(&a);
+    b.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.build(b.all_polygons());
+    a.invert();
+    a.all_polygons()
+}
+
+pub(super) fn intersect(a: Vec<Polygon>, b: Vec<Polygon>) -> Vec<Polygon> {
+    let mut a = Node::new(a);
+    let mut b = Node::new(b);
+
+    a.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.clip_to(&b);
+    b.clip_to(&a);
+    a.build(b.all_polygons());
+    a.invert();
+    a.all_polygons()
+}
@@ -0,0 +1,422 @@
+//! Boolean operations between solids.
+//!
+//! The faces of both solids are split against each other with BSP trees, the pieces that
+//! belong to the result are kept and the result is rebuilt into new solids, merging coplanar
+//! pieces back into single faces.
+//!
+//! Only planar faces are split exactly. [`Brep::boolean`] refuses solids with curved faces;
+//! [`Brep::boolean_with`] approximates them by triangles within a [`TesselationTolerance`]
+//! first, so its result is bounded by planar faces that stay within the tolerance of the
+//! curved ones.
+
+mod bsp;
+mod rebuild;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use glam::{DVec2, DVec3};
+
+use crate::surface::Surface;
+use crate::tesselation::triangulate;
+use crate::{Brep, BrepError, EdgeId, FaceId, SolidId, TesselationTolerance};
+
+use bsp::Polygon;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    /// Material in either solid.
+    Union,
+    /// Material in the first solid but not the second.
+    Subtract,
+    /// Material in both solids.
+    Intersect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanError {
+    /// An operand has a curved face, which only [`Brep::boolean_with`] approximates.
+    CurvedSurface(FaceId),
+    /// A curved face of an operand cannot be split into triangles, see
+    /// [`crate::TesselationError::UnsupportedFace`].
+    UnsupportedSurface(FaceId),
+    /// The operation leaves no material.
+    EmptyResult,
+    /// The rebuilt result is not a valid solid.
+    InvalidResult(BrepError),
+}
+
+impl fmt::Display for BooleanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BooleanError::CurvedSurface(face) => {
+                write!(
+                    f,
+                    "face {} is curved and would have to be approximated",
+                    face.0
+                )
+            }
+            BooleanError::UnsupportedSurface(face) => {
+                write!(f, "face {} cannot be tesselated", face.0)
+            }
+            BooleanError::EmptyResult => write!(f, "result is empty"),
+            BooleanError::InvalidResult(e) => write!(f, "result is invalid: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BooleanError {}
+
+impl Brep {
+    /// Combines two solids of the B-rep into new ones, leaving the operands in place. The
+    /// result can fall apart into several solids, each with its voids as inner shells. Both
+    /// solids have to be bounded by planar faces; use [`Brep::boolean_with`] to approximate
+    /// curved ones.
+    pub fn boolean(
+        &mut self,
+        a: SolidId,
+        b: SolidId,
+        op: BooleanOp,
+    ) -> Result<Vec<SolidId>, BooleanError> {
+        let curved = [a, b]
+            .into_iter()
+            .flat_map(|solid| self.solid_faces(solid))
+            .find(|&f| !matches!(self.face(f).surface, Surface::Plane(_)));
+        if let Some(face) = curved {
+            return Err(BooleanError::CurvedSurface(face));
+        }
+        self.boolean_with(a, b, op, &TesselationTolerance::default())
+    }
+
+    /// Like [`Brep::boolean`], but approximating curved faces: they are split into triangles
+    /// within `tolerance` first, and the result is bounded by planar faces only. A bore through
+    /// a block comes out as a prism whose sides are facets of the cylinder, not the cylinder
+    /// itself. Finer tolerances follow curved faces more closely with more faces.
+    pub fn boolean_with(
+        &mut self,
+        a: SolidId,
+        b: SolidId,
+        op: BooleanOp,
+        tolerance: &TesselationTolerance,
+    ) -> Result<Vec<SolidId>, BooleanError> {
+        let a = self.solid_polygons(a, tolerance)?;
+        let b = self.solid_polygons(b, tolerance)?;
+
+        let polygons = match op {
+            BooleanOp::Union => bsp::union(a, b),
+            BooleanOp::Subtract => bsp::subtract(a, b),
+            BooleanOp::Intersect => bsp::intersect(a, b),
+        };
+
+        let mut result = self.clone();
+        let solids = rebuild::rebuild(&mut result, polygons);
+        if solids.is_empty() {
+            return Err(BooleanError::EmptyResult);
+        }
+        result.validate().map_err(BooleanError::InvalidResult)?;
+
+        *self = result;
+        Ok(solids)
+    }
+
+    pub fn union(&mut self, a: SolidId, b: SolidId) -> Result<Vec<SolidId>, BooleanError> {
+        self.boolean(a, b, BooleanOp::Union)
+    }
+
+    pub fn subtract(&mut self, a: SolidId, b: SolidId) -> Result<Vec<SolidId>, BooleanError> {
+        self.boolean(a, b, BooleanOp::Subtract)
+    }
+
+    pub fn intersect(&mut self, a: SolidId, b: SolidId) -> Result<Vec<SolidId>, BooleanError> {
+        self.boolean(a, b, BooleanOp::Intersect)
+    }
+
+    /// The faces of a solid as convex polygons facing out of the material. Curved faces and
+    /// curved edges are split within `tolerance`, each edge once for the faces on either side.
+    fn solid_polygons(
+        &self,
+        solid: SolidId,
+        tolerance: &TesselationTolerance,
+    ) -> Result<Vec<Polygon>, BooleanError> {
+        let mut polygons = Vec::new();
+        let mut edges: HashMap<EdgeId, Vec<DVec3>> = HashMap::new();
+
+        for face_id in self.solid_faces(solid) {
+            let face = self.face(face_id);
+            let Surface::Plane(plane) = &face.surface else {
+                let mesh = self
+                    .tesselate_face(face_id, tolerance, &mut edges)
+                    .map_err(|_| BooleanError::UnsupportedSurface(face_id))?;
+                for t in &mesh.indices {
+                    let [a, b, c] = t.map(|i| mesh.positions[i as usize]);
+                    let normal = (b - a).cross(c - a);
+                    if normal != DVec3::ZERO {
+                        polygons.push(Polygon::new(vec![a, b, c], normal.normalize()));
+                    }
+                }
+                continue;
+            };
+
+            let mut normal = plane.normal;
+            if face.reversed {
+                normal = -normal;
+            }
+
+            // Every coedge from its start up to where the next one starts
+            let loops: Vec<Vec<DVec3>> = self
+                .face_loops(face_id)
+                .into_iter()
+                .map(|l| {
+                    self.loop_coedges(l)
+                        .into_iter()
+                        .flat_map(|c| {
+                            let coedge = self.coedge(c);
+                            let mut points = edges
+                                .entry(coedge.edge)
+                                .or_insert_with(|| self.edge_points(coedge.edge, tolerance))
+                                .clone();
+                            if coedge.reversed {
+                                points.reverse();
+                            }
+                            points.pop();
+                            points
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let u = normal.any_orthonormal_vector();
+            let v = normal.cross(u);
+            let flat = |l: &Vec<DVec3>| -> Vec<DVec2> {
+                l.iter().map(|p| DVec2::new(p.dot(u), p.dot(v))).collect()
+            };
+
+            if loops.len() == 1 && is_convex(&flat(&loops[0])) {
+                polygons.push(Polygon::new(loops[0].clone(), normal));
+                continue;
+            }
+
+            let outer = flat(&loops[0]);
+            let holes: Vec<Vec<DVec2>> = loops[1..].iter().map(flat).collect();
+            let points: Vec<DVec3> = loops.concat();
+
+            for t in triangulate(&outer, &holes) {
+                let vertices = t.iter().map(|&i| points[i]).collect();
+                polygons.push(Polygon::new(vertices, normal));
+            }
+        }
+
+        Ok(polygons)
+    }
+}
+
+fn is_convex(polygon: &[DVec2]) -> bool {
+    let n = polygon.len();
+
+    (0..n).all(|i| {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % n];
+        let c = polygon[(i + 2) % n];
+        (b - a).perp_dot(c - b) >= 0.
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use crate::surface::Surface;
+    use crate::test_util::{block, circle};
+    use crate::*;
+
+    /// The volume enclosed by the planar faces of a solid.
+    fn volume(brep: &Brep, solid: SolidId) -> f64 {
+        brep.solid_faces(solid)
+            .into_iter()
+            .flat_map(|f| brep.face_loops(f))
+            .map(|l| {
+                let points: Vec<DVec3> = brep
+                    .loop_coedges(l)
+                    .into_iter()
+                    .map(|c| brep.vertex(brep.coedge_start(c)).point.0)
+                    .collect();
+                (1..points.len() - 1)
+                    .map(|k| points[0].dot(points[k].cross(points[k + 1])) / 6.)
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_union_merges_faces() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::splat(2.));
+        let b = block(&mut brep, DVec3::new(1., 0., 0.), DVec3::new(3., 2., 2.));
+
+        let result = brep.union(a, b).unwrap();
+
+        assert_eq!(result.len(), 1);
+        let solid = result[0];
+        // The overlapping boxes form one box again
+        assert_eq!(brep.solid_faces(solid).len(), 6);
+        assert_eq!(brep.solid_edges(solid).len(), 12);
+        assert_eq!(brep.solid_vertices(solid).len(), 8);
+        assert!((volume(&brep, solid) - 12.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_union_of_touching_boxes() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::ONE);
+        let b = block(&mut brep, DVec3::new(1., 0., 0.), DVec3::new(2., 1., 1.));
+
+        let result = brep.union(a, b).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(brep.solid_faces(result[0]).len(), 6);
+        assert!((volume(&brep, result[0]) - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_subtract_through_hole() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::new(4., 4., 1.));
+        let b = block(&mut brep, DVec3::new(1., 1., -1.), DVec3::new(2., 3., 2.));
+
+        let result = brep.subtract(a, b).unwrap();
+
+        assert_eq!(result.len(), 1);
+        let solid = result[0];
+        assert_eq!(brep.genus(solid), Some(1));
+        assert_eq!(brep.solid_faces(solid).len(), 10);
+        assert!((volume(&brep, solid) - 14.).abs() < 1e-4);
+
+        // The caps keep the hole as an inner loop
+        let caps = brep
+            .solid_faces(solid)
+            .into_iter()
+            .filter(|&f| !brep.face(f).inner.is_empty())
+            .count();
+        assert_eq!(caps, 2);
+    }
+
+    #[test]
+    fn test_subtract_internal_void() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::splat(3.));
+        let b = block(&mut brep, DVec3::ONE, DVec3::splat(2.));
+
+        let result = brep.subtract(a, b).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(brep.solid(result[0]).shells.len(), 2);
+        assert!((volume(&brep, result[0]) - 26.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_intersect_and_disjoint_union() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::splat(2.));
+        let b = block(&mut brep, DVec3::ONE, DVec3::splat(3.));
+        let c = block(&mut brep, DVec3::splat(5.), DVec3::splat(6.));
+
+        let result = brep.intersect(a, b).unwrap();
+        assert_eq!(result.len(), 1);
+        assert!((volume(&brep, result[0]) - 1.).abs() < 1e-4);
+
+        let result = brep.union(a, c).unwrap();
+        assert_eq!(result.len(), 2);
+
+        assert_eq!(brep.intersect(a, c), Err(BooleanError::EmptyResult));
+    }
+
+    /// A cylinder around the vertical line through `(x, y)`, from `z = -1` to `z = 2`.
+    fn cylinder(brep: &mut Brep, (x, y): (f64, f64), radius: f64) -> SolidId {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        circle(&mut sketch, (x, y), radius);
+        let extent = ExtrudeExtent::TwoSided {
+            forward: 2.,
+            backward: 1.,
+        };
+        brep.extrude(&sketch.find_regions()[0], DVec3::Z, extent)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_subtract_cylinder() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::new(2., 2., 1.));
+        let b = cylinder(&mut brep, (1., 1.), 0.5);
+
+        // Only an explicit tolerance lets the side be approximated
+        let side = brep
+            .solid_faces(b)
+            .into_iter()
+            .find(|&f| !matches!(brep.face(f).surface, Surface::Plane(_)))
+            .unwrap();
+        assert_eq!(brep.subtract(a, b), Err(BooleanError::CurvedSurface(side)));
+
+        let tolerance = TesselationTolerance::new(1e-3, 0.2);
+        let result = brep
+            .boolean_with(a, b, BooleanOp::Subtract, &tolerance)
+            .unwrap();
+
+        // The bore is faceted within the tolerance
+        assert_eq!(result.len(), 1);
+        let solid = result[0];
+        assert_eq!(brep.genus(solid), Some(1));
+        let bore = std::f64::consts::PI * 0.25;
+        assert!((volume(&brep, solid) - (4. - bore)).abs() < 1e-2);
+        assert!(brep
+            .solid_faces(solid)
+            .into_iter()
+            .all(|f| matches!(brep.face(f).surface, Surface::Plane(_))));
+    }
+
+    #[test]
+    fn test_union_and_intersect_cylinder() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::ONE);
+        let b = cylinder(&mut brep, (1., 1.), 0.5);
+        let quarter = std::f64::consts::PI * 0.25 / 4.;
+        let tolerance = TesselationTolerance::default();
+
+        let result = brep
+            .boolean_with(a, b, BooleanOp::Intersect, &tolerance)
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert!((volume(&brep, result[0]) - quarter).abs() < 1e-2);
+
+        let result = brep
+            .boolean_with(a, b, BooleanOp::Union, &tolerance)
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        let cylinder = std::f64::consts::PI * 0.25 * 3.;
+        assert!((volume(&brep, result[0]) - (1. + cylinder - quarter)).abs() < 2e-2);
+    }
+
+    #[test]
+    fn test_subtract_cylinder_at_fine_chord() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::new(1., 1., 0.01));
+        let b = cylinder(&mut brep, (0.5, 0.5), 0.3);
+
+        let tolerance = TesselationTolerance::new(2e-5, std::f64::consts::PI / 12.);
+        let result = brep
+            .boolean_with(a, b, BooleanOp::Subtract, &tolerance)
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        let solid = result[0];
+        assert_eq!(brep.genus(solid), Some(1));
+        let bore = std::f64::consts::PI * 0.09 * 0.01;
+        assert!((volume(&brep, solid) - (0.01 - bore)).abs() < 1e-6);
+        // The top and bottom stay single faces around the bore
+        let holed = brep
+            .solid_faces(solid)
+            .into_iter()
+            .filter(|&f| !brep.face(f).inner.is_empty())
+            .count();
+        assert_eq!(holed, 2);
+    }
+}
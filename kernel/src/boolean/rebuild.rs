@@ -0,0 +1,518 @@
+//! Turns the polygons left by the BSP operations back into B-rep solids.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use glam::{DVec2, DVec3};
+
+use crate::surface::Surface;
+use crate::{Brep, EdgeId, FaceId, Plane, SolidId, Tolerance, VertexId};
+
+use super::bsp::Polygon;
+
+/// A face of the result before it is added to the B-rep: loops of point indices, the outer
+/// loop first.
+struct MergedFace {
+    normal: DVec3,
+    loops: Vec<Vec<usize>>,
+}
+
+/// Adds the solids bounded by `polygons` to the B-rep. Coplanar neighbouring polygons are
+/// merged into single faces and vertices in the middle of straight edges are removed.
+pub(super) fn rebuild(brep: &mut Brep, polygons: Vec<Polygon>) -> Vec<SolidId> {
+    // Cells about as long as an edge hold a few points each, and an edge crosses few of them
+    let (total, count) = polygons
+        .iter()
+        .flat_map(|polygon| {
+            let n = polygon.vertices.len();
+            (0..n).map(move |i| polygon.vertices[i].distance(polygon.vertices[(i + 1) % n]))
+        })
+        .fold((0., 0), |(total, count), length| {
+            (total + length, count + 1)
+        });
+    let cell = (total / count.max(1) as f64).max(2. * Tolerance::current().linear);
+
+    let mut points: Vec<DVec3> = Vec::new();
+    let mut grid = Grid::new(cell);
+    let mut welded: Vec<Vec<usize>> = polygons
+        .iter()
+        .map(|polygon| weld(&mut points, &mut grid, &polygon.vertices))
+        .filter(|l| l.len() >= 3)
+        .collect();
+
+    split_t_junctions(&points, &grid, &mut welded);
+
+    let faces = merge_coplanar(&points, &welded);
+    let faces = remove_collinear_vertices(&points, faces);
+    add_solids(brep, &points, &faces)
+}
+
+/// The points of the result bucketed into cubic cells, to find those near a point or an edge
+/// without looking at all of them.
+struct Grid {
+    cell: f64,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl Grid {
+    fn new(cell: f64) -> Self {
+        Self {
+            cell,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn key(&self, p: DVec3) -> [i64; 3] {
+        (p / self.cell).floor().as_i64vec3().to_array()
+    }
+
+    fn insert(&mut self, k: usize, p: DVec3) {
+        self.cells.entry(self.key(p)).or_default().push(k);
+    }
+
+    /// The points in the cells overlapping the box from `min` to `max`, in no particular
+    /// order.
+    fn in_box(&self, min: DVec3, max: DVec3) -> Vec<usize> {
+        let (lo, hi) = (self.key(min), self.key(max));
+        let inside = |key: &[i64; 3]| (0..3).all(|i| lo[i] <= key[i] && key[i] <= hi[i]);
+
+        // Boxes across more cells than are taken are cheaper to check cell by cell
+        let span = (0..3).map(|i| (hi[i] - lo[i] + 1) as f64).product::<f64>();
+        if span > self.cells.len() as f64 {
+            return self
+                .cells
+                .iter()
+                .filter(|(key, _)| inside(key))
+                .flat_map(|(_, points)| points.iter().copied())
+                .collect();
+        }
+
+        let mut out = Vec::new();
+        for x in lo[0]..=hi[0] {
+            for y in lo[1]..=hi[1] {
+                for z in lo[2]..=hi[2] {
+                    if let Some(points) = self.cells.get(&[x, y, z]) {
+                        out.extend_from_slice(points);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn weld(points: &mut Vec<DVec3>, grid: &mut Grid, vertices: &[DVec3]) -> Vec<usize> {
+    let tolerance = Tolerance::current();
+    let reach = DVec3::splat(tolerance.linear);
+    let mut out: Vec<usize> = Vec::new();
+
+    for &v in vertices {
+        // The first point taken wins, wherever the grid lists it
+        let nearby = grid
+            .in_box(v - reach, v + reach)
+            .into_iter()
+            .filter(|&k| tolerance.coincident(points[k], v))
+            .min();
+        let index = match nearby {
+            Some(i) => i,
+            None => {
+                points.push(v);
+                grid.insert(points.len() - 1, v);
+                points.len() - 1
+            }
+        };
+
+        if out.last() != Some(&index) {
+            out.push(index);
+        }
+    }
+
+    while out.len() > 1 && out.first() == out.last() {
+        out.pop();
+    }
+    out
+}
+
+/// Inserts every point that lies inside an edge of a polygon into that edge, so neighbouring
+/// polygons share their vertices.
+fn split_t_junctions(points: &[DVec3], grid: &Grid, polygons: &mut [Vec<usize>]) {
+    let tolerance = Tolerance::current();
+    let reach = DVec3::splat(tolerance.linear);
+    for polygon in polygons.iter_mut() {
+        let mut out = Vec::with_capacity(polygon.len());
+
+        for i in 0..polygon.len() {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let (pa, pb) = (points[a], points[b]);
+            let d = pb - pa;
+            let length_squared = d.length_squared();
+
+            let mut inside: Vec<(f64, usize)> = grid
+                .in_box(pa.min(pb) - reach, pa.max(pb) + reach)
+                .into_iter()
+                .filter(|&k| k != a && k != b)
+                .filter_map(|k| {
+                    let t = (points[k] - pa).dot(d) / length_squared;
+                    let on_line = tolerance.coincident(pa + t * d, points[k]);
+                    let margin = tolerance.linear / length_squared.sqrt();
+                    (on_line && t > margin && t < 1. - margin).then_some((t, k))
+                })
+                .collect();
+            inside.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+            out.push(a);
+            out.extend(inside.into_iter().map(|(_, k)| k));
+        }
+
+        *polygon = out;
+    }
+}
+
+/// A unit vector perpendicular to `normal` and the one completing a right-handed frame.
+fn basis(normal: DVec3) -> (DVec3, DVec3) {
+    let u = normal.any_orthonormal_vector();
+    (u, normal.cross(u))
+}
+
+fn newell_normal(points: &[DVec3], polygon: &[usize]) -> DVec3 {
+    let mut normal = DVec3::ZERO;
+    for i in 0..polygon.len() {
+        let a = points[polygon[i]];
+        let b = points[polygon[(i + 1) % polygon.len()]];
+        normal += a.cross(b);
+    }
+    normal.normalize_or_zero()
+}
+
+/// Merges polygons lying in the same plane and facing the same way into faces. Edges shared by
+/// two polygons of a plane cancel out and the remaining ones are chained into loops.
+fn merge_coplanar(points: &[DVec3], polygons: &[Vec<usize>]) -> Vec<MergedFace> {
+    let tolerance = Tolerance::current();
+    let mut groups: Vec<(DVec3, f64, Vec<usize>)> = Vec::new();
+
+    for (i, polygon) in polygons.iter().enumerate() {
+        let normal = newell_normal(points, polygon);
+        if normal == DVec3::ZERO {
+            continue;
+        }
+        let w = normal.dot(points[polygon[0]]);
+
+        match groups.iter_mut().find(|(n, gw, _)| {
+            n.dot(normal) > 0. && tolerance.parallel(*n, normal) && tolerance.is_zero_length(gw - w)
+        }) {
+            Some(group) => group.2.push(i),
+            None => groups.push((normal, w, vec![i])),
+        }
+    }
+
+    let mut faces = Vec::new();
+    for (normal, _, members) in groups {
+        let (u, v) = basis(normal);
+        let flat = |k: usize| DVec2::new(points[k].dot(u), points[k].dot(v));
+
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for &m in &members {
+            let polygon = &polygons[m];
+            for i in 0..polygon.len() {
+                let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+                match edges.iter().position(|&e| e == (b, a)) {
+                    Some(twin) => {
+                        edges.remove(twin);
+                    }
+                    None => edges.push((a, b)),
+                }
+            }
+        }
+
+        let loops = chain_loops(&edges, &flat);
+
+        let area = |l: &Vec<usize>| -> f64 {
+            (0..l.len())
+                .map(|i| flat(l[i]).perp_dot(flat(l[(i + 1) % l.len()])) / 2.)
+                .sum()
+        };
+        let (outers, holes): (Vec<_>, Vec<_>) = loops.into_iter().partition(|l| area(l) > 0.);
+
+        let mut merged: Vec<MergedFace> = outers
+            .iter()
+            .map(|outer| MergedFace {
+                normal,
+                loops: vec![outer.clone()],
+            })
+            .collect();
+
+        for hole in holes {
+            // A point just beside the hole, in the material
+            let (a, b) = (flat(hole[0]), flat(hole[1]));
+            let sample = (a + b) / 2. + (b - a).perp() * 1e-3;
+
+            let container = outers
+                .iter()
+                .enumerate()
+                .filter(|(_, outer)| {
+                    contains(&outer.iter().map(|&k| flat(k)).collect::<Vec<_>>(), sample)
+                })
+                .min_by(|(_, x), (_, y)| area(x).total_cmp(&area(y)));
+
+            if let Some((i, _)) = container {
+                merged[i].loops.push(hole);
+            }
+        }
+
+        faces.extend(merged);
+    }
+
+    faces
+}
+
+/// Chains directed edges into closed loops. Where several edges leave a vertex, the loop
+/// takes the one turning furthest left, so every loop bounds a single region on its left.
+fn chain_loops(edges: &[(usize, usize)], flat: &impl Fn(usize) -> DVec2) -> Vec<Vec<usize>> {
+    let mut used = vec![false; edges.len()];
+    let mut loops = Vec::new();
+
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+
+        let mut l = vec![edges[first].0];
+        let mut current = first;
+        used[first] = true;
+
+        loop {
+            let (from, to) = edges[current];
+            if to == l[0] {
+                loops.push(l);
+                break;
+            }
+
+            let back = flat(from) - flat(to);
+            let next = (0..edges.len())
+                .filter(|&e| !used[e] && edges[e].0 == to)
+                .min_by(|&x, &y| {
+                    // Clockwise angle from the way back to the candidate, in (0, 2π]
+                    let angle = |e: usize| {
+                        let d = flat(edges[e].1) - flat(to);
+                        let a = d.perp_dot(back).atan2(d.dot(back)).rem_euclid(2. * PI);
+                        if a <= 0. {
+                            2. * PI
+                        } else {
+                            a
+                        }
+                    };
+                    angle(x).total_cmp(&angle(y))
+                });
+
+            let Some(next) = next else {
+                break;
+            };
+            used[next] = true;
+            l.push(to);
+            current = next;
+        }
+    }
+
+    loops
+}
+
+fn contains(polygon: &[DVec2], p: DVec2) -> bool {
+    let mut inside = false;
+
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+/// Removes vertices that only join two collinear edges, such as those left where coplanar
+/// faces were merged.
+fn remove_collinear_vertices(points: &[DVec3], mut faces: Vec<MergedFace>) -> Vec<MergedFace> {
+    let tolerance = Tolerance::current();
+    loop {
+        let mut neighbours: HashMap<usize, Vec<usize>> = HashMap::new();
+        for l in faces.iter().flat_map(|f| &f.loops) {
+            for i in 0..l.len() {
+                let (a, b) = (l[i], l[(i + 1) % l.len()]);
+                for (x, y) in [(a, b), (b, a)] {
+                    let list = neighbours.entry(x).or_default();
+                    if !list.contains(&y) {
+                        list.push(y);
+                    }
+                }
+            }
+        }
+
+        let removable = |k: &usize| match neighbours[k][..] {
+            [a, b] => {
+                let (d1, d2) = (points[*k] - points[a], points[b] - points[*k]);
+                tolerance.parallel(d1, d2) && d1.dot(d2) > 0.
+            }
+            _ => false,
+        };
+
+        let mut changed = false;
+        for face in &mut faces {
+            for l in &mut face.loops {
+                let before = l.len();
+                l.retain(|k| !removable(k));
+                changed |= l.len() != before;
+            }
+        }
+
+        if !changed {
+            return faces;
+        }
+    }
+}
+
+fn add_solids(brep: &mut Brep, points: &[DVec3], faces: &[MergedFace]) -> Vec<SolidId> {
+    let mut vertices: HashMap<usize, VertexId> = HashMap::new();
+    let mut edges: HashMap<(usize, usize), EdgeId> = HashMap::new();
+    let mut face_ids = Vec::new();
+
+    for face in faces {
+        let mut loops: Vec<Vec<(EdgeId, bool)>> = Vec::new();
+
+        for l in &face.loops {
+            let mut coedges = Vec::new();
+            for i in 0..l.len() {
+                let (a, b) = (l[i], l[(i + 1) % l.len()]);
+                let key = (a.min(b), a.max(b));
+
+                let edge = match edges.get(&key) {
+                    Some(&e) => e,
+                    None => {
+                        let mut vertex = |k: usize| {
+                            *vertices
+                                .entry(k)
+                                .or_insert_with(|| brep.add_vertex(points[k]))
+                        };
+                        let (start, end) = (vertex(key.0), vertex(key.1));
+                        let e = brep.add_line_edge(start, end);
+                        edges.insert(key, e);
+                        e
+                    }
+                };
+                coedges.push((edge, a > b));
+            }
+            loops.push(coedges);
+        }
+
+        let plane = Plane::new(face.normal, points[face.loops[0][0]]);
+        let id = brep
+            .add_face(Surface::Plane(plane), false, &loops[0], &loops[1..])
+            .unwrap();
+        face_ids.push((id, face));
+    }
+
+    // Faces joined by edges form shells
+    let mut parent: Vec<usize> = (0..face_ids.len()).collect();
+    let index: HashMap<FaceId, usize> = face_ids
+        .iter()
+        .enumerate()
+        .map(|(i, (f, _))| (*f, i))
+        .collect();
+    for &(face, _) in &face_ids {
+        for edge in brep.face_edges(face) {
+            for other in brep.edge_faces(edge) {
+                let (a, b) = (
+                    find(&mut parent, index[&face]),
+                    find(&mut parent, index[&other]),
+                );
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    let mut roots: HashMap<usize, usize> = HashMap::new();
+    for i in 0..face_ids.len() {
+        let root = find(&mut parent, i);
+        let c = *roots.entry(root).or_insert_with(|| {
+            components.push(Vec::new());
+            components.len() - 1
+        });
+        components[c].push(i);
+    }
+
+    let volume = |members: &[usize]| -> f64 {
+        members
+            .iter()
+            .flat_map(|&i| &face_ids[i].1.loops)
+            .map(|l| {
+                (1..l.len().saturating_sub(1))
+                    .map(|k| points[l[0]].dot(points[l[k]].cross(points[l[k + 1]])) / 6.)
+                    .sum::<f64>()
+            })
+            .sum()
+    };
+
+    let (outers, voids): (Vec<_>, Vec<_>) = components.into_iter().partition(|c| volume(c) > 0.);
+
+    let mut solids: Vec<Vec<&Vec<usize>>> = outers.iter().map(|o| vec![o]).collect();
+    for void in &voids {
+        let sample = points[face_ids[void[0]].1.loops[0][0]];
+        let container = outers
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| {
+                winding_number(points, o.iter().map(|&i| face_ids[i].1), sample).abs() > 0.5
+            })
+            .min_by(|(_, x), (_, y)| volume(x).total_cmp(&volume(y)));
+
+        if let Some((i, _)) = container {
+            solids[i].push(void);
+        }
+    }
+
+    solids
+        .into_iter()
+        .map(|components| {
+            let shells = components
+                .into_iter()
+                .map(|c| brep.add_shell(c.iter().map(|&i| face_ids[i].0).collect()))
+                .collect();
+            brep.add_solid(shells)
+        })
+        .collect()
+}
+
+fn find(parent: &mut [usize], v: usize) -> usize {
+    let mut root = v;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[v] = root;
+    root
+}
+
+/// How many times the faces wind around `p`, from the solid angles of fans over their loops.
+fn winding_number<'a>(
+    points: &[DVec3],
+    faces: impl Iterator<Item = &'a MergedFace>,
+    p: DVec3,
+) -> f64 {
+    let mut total = 0.;
+
+    for l in faces.flat_map(|f| &f.loops) {
+        for k in 1..l.len().saturating_sub(1) {
+            let a = points[l[0]] - p;
+            let b = points[l[k]] - p;
+            let c = points[l[k + 1]] - p;
+
+            let (la, lb, lc) = (a.length(), b.length(), c.length());
+            let numerator = a.dot(b.cross(c));
+            let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+            total += 2. * numerator.atan2(denominator);
+        }
+    }
+
+    total / (4. * PI)
+}
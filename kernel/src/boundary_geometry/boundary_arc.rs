@@ -1,27 +1,39 @@
-use glam::Vec3;
+use glam::DVec3;
 
+use crate::circle::{Circle, CircleError};
 use crate::point::Point;
-use crate::Circle;
 
+/// The direction an arc runs around the normal of the plane it lies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     CW,
     CCW,
 }
 
+#[derive(Debug, Clone)]
 pub struct BoundaryArc {
-    circle: Circle,
-    start: Point,
-    end: Point,
-    direction: Direction,
+    pub circle: Circle,
+    pub start: Point,
+    pub end: Point,
+    pub direction: Direction,
 }
 
 impl BoundaryArc {
-    pub fn new(center: Vec3, radius: f32, start: Vec3, end: Vec3, direction: Direction) -> Self {
-        Self {
-            circle: Circle::new(center, radius),
+    /// An arc on the circle around `center` in the plane with `normal`, running in
+    /// `direction` around that normal.
+    pub fn new(
+        center: DVec3,
+        normal: DVec3,
+        radius: f64,
+        start: DVec3,
+        end: DVec3,
+        direction: Direction,
+    ) -> Result<Self, CircleError> {
+        Ok(Self {
+            circle: Circle::new(center, normal, radius)?,
             start: Point(start),
             end: Point(end),
             direction,
-        }
+        })
     }
 }
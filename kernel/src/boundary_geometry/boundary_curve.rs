@@ -0,0 +1,22 @@
+use crate::nurbs::NurbsCurve;
+use crate::point::Point;
+
+/// A piece of an ellipse or a spline, as an exact NURBS curve running from its start to its
+/// end over its whole domain.
+#[derive(Debug, Clone)]
+pub struct BoundaryCurve {
+    pub curve: NurbsCurve,
+    pub start: Point,
+    pub end: Point,
+}
+
+impl BoundaryCurve {
+    pub fn new(curve: NurbsCurve) -> Self {
+        let (start, end) = curve.domain();
+        Self {
+            start: Point(curve.point_at(start)),
+            end: Point(curve.point_at(end)),
+            curve,
+        }
+    }
+}
@@ -1,16 +1,17 @@
-use glam::Vec3;
+use glam::DVec3;
 
 use crate::line::{Line, TwoPointLine};
 use crate::point::Point;
 
+#[derive(Debug, Clone)]
 pub struct BoundaryLine {
-    line: Line,
-    a: Point,
-    b: Point,
+    pub line: Line,
+    pub a: Point,
+    pub b: Point,
 }
 
 impl BoundaryLine {
-    pub fn new(a: Vec3, b: Vec3) -> Self {
+    pub fn new(a: DVec3, b: DVec3) -> Self {
         Self {
             line: Line::TwoPoint(TwoPointLine::new(a, b)),
             a: Point(a),
@@ -1,11 +1,15 @@
-use super::{BoundaryArc, BoundaryLine, BoundaryPolygon};
+use super::{BoundaryArc, BoundaryCurve, BoundaryLine, BoundaryPolygon};
 
+/// A closed chain of boundary elements, each starting where the previous one ends.
+#[derive(Debug, Clone)]
 pub struct BoundaryLoop {
-    elements: Vec<BoundaryElement>,
+    pub elements: Vec<BoundaryElement>,
 }
 
+#[derive(Debug, Clone)]
 pub enum BoundaryElement {
     BoundaryLine(BoundaryLine),
     BoundaryPolygon(BoundaryPolygon),
     BoundaryArc(BoundaryArc),
+    BoundaryCurve(BoundaryCurve),
 }
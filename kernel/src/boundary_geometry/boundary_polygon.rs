@@ -1,5 +1,6 @@
 use super::BoundaryLine;
 
+#[derive(Debug, Clone)]
 pub struct BoundaryPolygon {
-    lines: Vec<BoundaryLine>,
+    pub lines: Vec<BoundaryLine>,
 }
@@ -2,12 +2,46 @@ use crate::Plane;
 
 use super::BoundaryLoop;
 
+/// A planar region bounded by an outer loop running counter-clockwise around the plane normal
+/// and any number of holes running clockwise.
+#[derive(Debug, Clone)]
 pub struct BoundarySurface {
-    plane: Plane,
-    boundary: BoundaryLoop,
+    pub plane: Plane,
+    pub boundary: BoundaryLoop,
+    pub holes: Vec<BoundaryLoop>,
+}
+
+impl BoundarySurface {
+    pub fn new(plane: Plane, boundary: BoundaryLoop, holes: Vec<BoundaryLoop>) -> Self {
+        Self {
+            plane,
+            boundary,
+            holes,
+        }
+    }
 }
 
 #[test]
 fn test_new_boundary_surface() {
-    
-}
\ No newline at end of file
+    use glam::DVec3;
+
+    use super::{BoundaryElement, BoundaryLine};
+
+    let corners = [
+        DVec3::new(0., 0., 0.),
+        DVec3::new(1., 0., 0.),
+        DVec3::new(1., 1., 0.),
+    ];
+    let boundary = BoundaryLoop {
+        elements: (0..3)
+            .map(|i| {
+                BoundaryElement::BoundaryLine(BoundaryLine::new(corners[i], corners[(i + 1) % 3]))
+            })
+            .collect(),
+    };
+
+    let surface = BoundarySurface::new(Plane::XY, boundary, Vec::new());
+
+    assert_eq!(surface.boundary.elements.len(), 3);
+    assert!(surface.holes.is_empty());
+}
@@ -1,11 +1,15 @@
 mod boundary_arc;
+mod boundary_curve;
 mod boundary_line;
 mod boundary_loop;
 mod boundary_polygon;
 mod boundary_surface;
+mod segment;
 
 pub use boundary_arc::*;
+pub use boundary_curve::*;
 pub use boundary_line::*;
 pub use boundary_loop::*;
 pub use boundary_polygon::*;
 pub use boundary_surface::*;
+pub(crate) use segment::*;
@@ -0,0 +1,218 @@
+//! Profile loops as straight, circular and free-form segments, the form features and
+//! tesselation work from.
+
+use std::f64::consts::TAU;
+
+use glam::{DQuat, DVec3};
+
+use crate::nurbs::NurbsCurve;
+use crate::{Direction, Tolerance};
+
+use super::{BoundaryElement, BoundaryLoop, BoundarySurface};
+
+/// Why the loops of a profile cannot be followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LoopError {
+    /// A loop has no elements.
+    Empty,
+    /// An element does not start where the previous one ends.
+    Open,
+}
+
+/// A straight, circular or free-form piece of a profile loop.
+#[derive(Debug, Clone)]
+pub(crate) struct Segment {
+    pub start: DVec3,
+    pub end: DVec3,
+    pub arc: Option<SegmentArc>,
+    /// A piece of an ellipse or a spline, running from the start to the end over its domain.
+    /// Never closed.
+    pub curve: Option<NurbsCurve>,
+}
+
+/// A circular segment runs counter-clockwise about `axis` from its start to its end. Equal
+/// start and end points make a full circle.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SegmentArc {
+    pub center: DVec3,
+    pub radius: f64,
+    pub axis: DVec3,
+}
+
+impl Segment {
+    fn reversed(&self) -> Self {
+        Self {
+            start: self.end,
+            end: self.start,
+            arc: self.arc.map(|arc| SegmentArc {
+                axis: -arc.axis,
+                ..arc
+            }),
+            curve: self.curve.as_ref().map(NurbsCurve::reversed),
+        }
+    }
+
+    pub fn is_straight(&self) -> bool {
+        self.arc.is_none() && self.curve.is_none()
+    }
+
+    /// The point at `t` in `[0, 1]` along the segment.
+    pub fn point(&self, t: f64) -> DVec3 {
+        if let Some(curve) = &self.curve {
+            let (start, end) = curve.domain();
+            return curve.point_at(start + t * (end - start));
+        }
+        match self.arc {
+            None => self.start.lerp(self.end, t),
+            Some(arc) => {
+                let rotation = DQuat::from_axis_angle(arc.axis, t * self.sweep());
+                arc.center + rotation * (self.start - arc.center)
+            }
+        }
+    }
+
+    pub fn middle(&self) -> DVec3 {
+        self.point(0.5)
+    }
+
+    /// The direction of the segment at its middle.
+    pub fn tangent_at_middle(&self) -> DVec3 {
+        if let Some(curve) = &self.curve {
+            let (start, end) = curve.domain();
+            return curve.derivative_at((start + end) / 2.);
+        }
+        match self.arc {
+            None => self.end - self.start,
+            Some(arc) => arc.axis.cross(self.middle() - arc.center),
+        }
+    }
+
+    /// The angle the segment sweeps around its axis, in `(0, 2π]`.
+    pub fn sweep(&self) -> f64 {
+        let Some(arc) = self.arc else {
+            return 0.;
+        };
+
+        let s = self.start - arc.center;
+        let e = self.end - arc.center;
+        let angle = s.cross(e).dot(arc.axis).atan2(s.dot(e)).rem_euclid(TAU);
+
+        if Tolerance::current().is_zero_length(angle * arc.radius) {
+            TAU
+        } else {
+            angle
+        }
+    }
+
+    /// The contribution of the segment to the signed area of its loop around `normal`.
+    fn area(&self, normal: DVec3) -> f64 {
+        if let Some(curve) = &self.curve {
+            let (start, end) = curve.domain();
+            return curve.swept_area(start, end).dot(normal);
+        }
+        match self.arc {
+            None => self.start.cross(self.end).dot(normal) / 2.,
+            Some(arc) => {
+                let sweep = self.sweep() * arc.axis.dot(normal).signum();
+                (arc.center.cross(self.end - self.start).dot(normal)
+                    + arc.radius * arc.radius * sweep)
+                    / 2.
+            }
+        }
+    }
+}
+
+/// The loops of a profile as segments, with the outer loop counter-clockwise around `up` and
+/// the holes clockwise.
+pub(crate) fn profile_loops(
+    profile: &BoundarySurface,
+    up: DVec3,
+) -> Result<Vec<Vec<Segment>>, LoopError> {
+    let normal = profile.plane.normal;
+
+    std::iter::once(&profile.boundary)
+        .chain(&profile.holes)
+        .enumerate()
+        .map(|(i, boundary)| {
+            let mut segments = loop_segments(boundary, normal)?;
+
+            let area: f64 = segments.iter().map(|s| s.area(up)).sum();
+            let outer = i == 0;
+            if (area > 0.) != outer {
+                segments = segments.iter().rev().map(Segment::reversed).collect();
+            }
+
+            Ok(segments)
+        })
+        .collect()
+}
+
+fn loop_segments(boundary: &BoundaryLoop, normal: DVec3) -> Result<Vec<Segment>, LoopError> {
+    let mut segments = Vec::new();
+
+    for element in &boundary.elements {
+        match element {
+            BoundaryElement::BoundaryLine(line) => segments.push(Segment {
+                start: line.a.0,
+                end: line.b.0,
+                arc: None,
+                curve: None,
+            }),
+            BoundaryElement::BoundaryPolygon(polygon) => {
+                segments.extend(polygon.lines.iter().map(|line| Segment {
+                    start: line.a.0,
+                    end: line.b.0,
+                    arc: None,
+                    curve: None,
+                }))
+            }
+            BoundaryElement::BoundaryArc(arc) => segments.push(Segment {
+                start: arc.start.0,
+                end: arc.end.0,
+                arc: Some(SegmentArc {
+                    center: arc.circle.center,
+                    radius: arc.circle.radius,
+                    axis: match arc.direction {
+                        Direction::CCW => normal,
+                        Direction::CW => -normal,
+                    },
+                }),
+                curve: None,
+            }),
+            BoundaryElement::BoundaryCurve(curve) => {
+                // A closed curve is split in two, so no segment starts where it ends
+                let (start, end) = curve.curve.domain();
+                let pieces = if curve.curve.is_closed() {
+                    // Cannot fail: the middle of the domain is interior
+                    let (a, b) = curve.curve.split((start + end) / 2.).unwrap();
+                    vec![a, b]
+                } else {
+                    vec![curve.curve.clone()]
+                };
+                segments.extend(pieces.into_iter().map(|piece| {
+                    let (start, end) = piece.domain();
+                    Segment {
+                        start: piece.point_at(start),
+                        end: piece.point_at(end),
+                        arc: None,
+                        curve: Some(piece),
+                    }
+                }));
+            }
+        }
+    }
+
+    if segments.is_empty() {
+        return Err(LoopError::Empty);
+    }
+
+    let tolerance = Tolerance::current();
+    for (i, segment) in segments.iter().enumerate() {
+        let next = &segments[(i + 1) % segments.len()];
+        if !tolerance.coincident(segment.end, next.start) {
+            return Err(LoopError::Open);
+        }
+    }
+
+    Ok(segments)
+}
@@ -0,0 +1,92 @@
+use crate::curve::Curve;
+use crate::point::Point;
+use crate::surface::Surface;
+
+/// Identifies a vertex by its index in [`Brep::vertices`](super::Brep::vertices).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VertexId(pub usize);
+
+/// Identifies an edge by its index in [`Brep::edges`](super::Brep::edges).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EdgeId(pub usize);
+
+/// Identifies a coedge by its index in [`Brep::coedges`](super::Brep::coedges).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CoedgeId(pub usize);
+
+/// Identifies a loop by its index in [`Brep::loops`](super::Brep::loops).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoopId(pub usize);
+
+/// Identifies a face by its index in [`Brep::faces`](super::Brep::faces).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FaceId(pub usize);
+
+/// Identifies a shell by its index in [`Brep::shells`](super::Brep::shells).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShellId(pub usize);
+
+/// Identifies a solid by its index in [`Brep::solids`](super::Brep::solids).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SolidId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct Vertex {
+    pub point: Point,
+}
+
+/// A trimmed piece of a curve running from `start` to `end` in the direction of increasing
+/// curve parameter. A closed edge, such as a full circle, starts and ends at the same vertex.
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub curve: Curve,
+    pub start: VertexId,
+    pub end: VertexId,
+    /// The uses of the edge by loops. An edge of a closed manifold solid has exactly two,
+    /// running in opposite directions.
+    pub coedges: Vec<CoedgeId>,
+}
+
+/// The use of an edge by a loop: a half-edge.
+#[derive(Debug, Clone)]
+pub struct Coedge {
+    pub edge: EdgeId,
+    /// Whether the coedge runs from the end of the edge to its start.
+    pub reversed: bool,
+    pub parent: LoopId,
+    pub next: CoedgeId,
+    pub previous: CoedgeId,
+}
+
+/// A closed chain of coedges bounding a face. Outer loops run counter-clockwise and inner
+/// loops clockwise when seen against the face normal.
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub face: FaceId,
+    /// Any one coedge of the loop.
+    pub first: CoedgeId,
+}
+
+#[derive(Debug, Clone)]
+pub struct Face {
+    pub surface: Surface,
+    /// Whether the face normal points against the surface normal.
+    pub reversed: bool,
+    pub outer: LoopId,
+    pub inner: Vec<LoopId>,
+    pub shell: Option<ShellId>,
+}
+
+/// A connected set of faces. The shells of a solid are closed, with face normals pointing
+/// out of the material.
+#[derive(Debug, Clone)]
+pub struct Shell {
+    pub faces: Vec<FaceId>,
+    pub solid: Option<SolidId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solid {
+    /// The outer shell first, followed by the shells of any voids.
+    pub shells: Vec<ShellId>,
+}
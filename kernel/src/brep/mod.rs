@@ -0,0 +1,201 @@
+//! Boundary representation of solids.
+//!
+//! All entities live in arenas owned by a [`Brep`] and refer to each other by index. Every edge
+//! is used by loops through coedges (half-edges), each of which knows the next and previous
+//! coedge of its loop, so adjacency can be walked in both directions without searching.
+
+mod entities;
+mod transform;
+mod traversal;
+mod validation;
+
+use glam::DVec3;
+
+use crate::curve::Curve;
+use crate::point::Point;
+use crate::surface::Surface;
+
+pub use entities::*;
+pub use validation::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct Brep {
+    pub vertices: Vec<Vertex>,
+    pub edges: Vec<Edge>,
+    pub coedges: Vec<Coedge>,
+    pub loops: Vec<Loop>,
+    pub faces: Vec<Face>,
+    pub shells: Vec<Shell>,
+    pub solids: Vec<Solid>,
+}
+
+impl Brep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertex(&self, id: VertexId) -> &Vertex {
+        &self.vertices[id.0]
+    }
+
+    pub fn edge(&self, id: EdgeId) -> &Edge {
+        &self.edges[id.0]
+    }
+
+    pub fn coedge(&self, id: CoedgeId) -> &Coedge {
+        &self.coedges[id.0]
+    }
+
+    pub fn get_loop(&self, id: LoopId) -> &Loop {
+        &self.loops[id.0]
+    }
+
+    pub fn face(&self, id: FaceId) -> &Face {
+        &self.faces[id.0]
+    }
+
+    pub fn shell(&self, id: ShellId) -> &Shell {
+        &self.shells[id.0]
+    }
+
+    pub fn solid(&self, id: SolidId) -> &Solid {
+        &self.solids[id.0]
+    }
+
+    pub fn add_vertex(&mut self, point: DVec3) -> VertexId {
+        self.vertices.push(Vertex {
+            point: Point::new(point),
+        });
+        VertexId(self.vertices.len() - 1)
+    }
+
+    pub fn add_edge(&mut self, curve: Curve, start: VertexId, end: VertexId) -> EdgeId {
+        self.edges.push(Edge {
+            curve,
+            start,
+            end,
+            coedges: Vec::new(),
+        });
+        EdgeId(self.edges.len() - 1)
+    }
+
+    /// Adds a straight edge between two vertices.
+    pub fn add_line_edge(&mut self, start: VertexId, end: VertexId) -> EdgeId {
+        let curve = Curve::line(self.vertex(start).point.0, self.vertex(end).point.0);
+        self.add_edge(curve, start, end)
+    }
+
+    /// Adds a face bounded by an outer loop and any number of inner loops. Every loop is a
+    /// list of edges in order, each paired with whether the loop runs along it backwards.
+    pub fn add_face(
+        &mut self,
+        surface: Surface,
+        reversed: bool,
+        outer: &[(EdgeId, bool)],
+        inner: &[Vec<(EdgeId, bool)>],
+    ) -> Result<FaceId, BrepError> {
+        for &(edge, _) in outer.iter().chain(inner.iter().flatten()) {
+            if edge.0 >= self.edges.len() {
+                return Err(BrepError::InvalidEdge(edge));
+            }
+        }
+        if outer.is_empty() || inner.iter().any(|l| l.is_empty()) {
+            return Err(BrepError::EmptyLoop);
+        }
+
+        let face = FaceId(self.faces.len());
+        let outer = self.add_loop(face, outer);
+        let inner = inner.iter().map(|l| self.add_loop(face, l)).collect();
+
+        self.faces.push(Face {
+            surface,
+            reversed,
+            outer,
+            inner,
+            shell: None,
+        });
+        Ok(face)
+    }
+
+    fn add_loop(&mut self, face: FaceId, edges: &[(EdgeId, bool)]) -> LoopId {
+        let id = LoopId(self.loops.len());
+        let first = self.coedges.len();
+        let n = edges.len();
+
+        for (i, &(edge, reversed)) in edges.iter().enumerate() {
+            let coedge = CoedgeId(first + i);
+            self.coedges.push(Coedge {
+                edge,
+                reversed,
+                parent: id,
+                next: CoedgeId(first + (i + 1) % n),
+                previous: CoedgeId(first + (i + n - 1) % n),
+            });
+            self.edges[edge.0].coedges.push(coedge);
+        }
+
+        self.loops.push(Loop {
+            face,
+            first: CoedgeId(first),
+        });
+        id
+    }
+
+    pub fn add_shell(&mut self, faces: Vec<FaceId>) -> ShellId {
+        let id = ShellId(self.shells.len());
+        for face in &faces {
+            self.faces[face.0].shell = Some(id);
+        }
+
+        self.shells.push(Shell { faces, solid: None });
+        id
+    }
+
+    /// Adds a solid from its outer shell followed by the shells of any voids.
+    pub fn add_solid(&mut self, shells: Vec<ShellId>) -> SolidId {
+        let id = SolidId(self.solids.len());
+        for shell in &shells {
+            self.shells[shell.0].solid = Some(id);
+        }
+
+        self.solids.push(Solid { shells });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+    use crate::test_util::cuboid;
+    use crate::Plane;
+
+    #[test]
+    fn test_cuboid() {
+        let (brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+
+        assert_eq!(brep.vertices.len(), 8);
+        assert_eq!(brep.edges.len(), 12);
+        assert_eq!(brep.coedges.len(), 24);
+        assert_eq!(brep.faces.len(), 6);
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+    }
+
+    #[test]
+    fn test_add_face_errors() {
+        let mut brep = Brep::new();
+        let surface = Surface::Plane(Plane::XY);
+
+        assert_eq!(
+            brep.add_face(surface.clone(), false, &[], &[]).unwrap_err(),
+            BrepError::EmptyLoop
+        );
+        assert_eq!(
+            brep.add_face(surface, false, &[(EdgeId(0), false)], &[])
+                .unwrap_err(),
+            BrepError::InvalidEdge(EdgeId(0))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(doc)]
+use crate::surface::Surface;
+use crate::transform::Transform;
+
+use super::{Brep, SolidId};
+
+impl Brep {
+    /// Moves a solid with all of its faces, edges and vertices. Entities it shares with other
+    /// solids move as well. Stretches and shears turn circular edges into ellipses and
+    /// cylinders, cones, spheres and tori into transformed surfaces, see [`Surface::transformed`].
+    ///
+    /// A mirror would leave the loops running clockwise around the face normals and the faces
+    /// pointing into the material, so the loops are reversed and faces whose surface normals
+    /// the mirror reverses are flipped to keep the solid valid.
+    pub fn transform_solid(&mut self, id: SolidId, transform: &Transform) {
+        for v in self.solid_vertices(id) {
+            let vertex = &mut self.vertices[v.0];
+            vertex.point = vertex.point.transformed(transform);
+        }
+        for e in self.solid_edges(id) {
+            let edge = &mut self.edges[e.0];
+            edge.curve = edge.curve.transformed(transform);
+        }
+
+        for f in self.solid_faces(id) {
+            let coedges: Vec<_> = self
+                .face_loops(f)
+                .into_iter()
+                .flat_map(|l| self.loop_coedges(l))
+                .collect();
+
+            let face = &mut self.faces[f.0];
+            face.reversed ^= face.surface.normal_reversed_by(transform);
+            face.surface = face.surface.transformed(transform);
+
+            if transform.is_mirror() {
+                for c in coedges {
+                    let coedge = &mut self.coedges[c.0];
+                    coedge.reversed = !coedge.reversed;
+                    std::mem::swap(&mut coedge.next, &mut coedge.previous);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+    use glam::DVec3;
+
+    use super::*;
+    use crate::arc::Arc;
+    use crate::line::{Line, ParametricLine};
+    use crate::surface::Surface;
+    use crate::test_util::{circle, cuboid, line};
+    use crate::{
+        ExtrudeExtent, FaceId, Plane, Sketch, SketchArc, SketchElement, SketchPlane, StepSchema,
+        TesselationTolerance,
+    };
+
+    /// Checks that a solid is valid and closed, and gives the volume of its mesh.
+    fn solid_volume(brep: &Brep, solid: SolidId) -> f64 {
+        assert_eq!(brep.validate(), Ok(()));
+        let tolerance = TesselationTolerance::new(1e-3, 0.2);
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_eq!(mesh.open_edges(), 0);
+        mesh.volume()
+    }
+
+    /// The normal of a face at its outer loop, against which the loop runs counter-clockwise.
+    fn face_normal(brep: &Brep, face: FaceId) -> (DVec3, DVec3) {
+        let points: Vec<DVec3> = brep
+            .loop_coedges(brep.face(face).outer)
+            .into_iter()
+            .map(|c| brep.vertex(brep.coedge_start(c)).point.0)
+            .collect();
+        let mut winding = DVec3::ZERO;
+        for (i, p) in points.iter().enumerate() {
+            winding += p.cross(points[(i + 1) % points.len()]);
+        }
+        let center = points.iter().sum::<DVec3>() / points.len() as f64;
+
+        let f = brep.face(face);
+        let mut normal = f.surface.normal_at(center);
+        if f.reversed {
+            normal = -normal;
+        }
+        assert!(winding.dot(normal) > 0., "loop of {face:?} runs clockwise");
+
+        (center, normal)
+    }
+
+    #[test]
+    fn test_rotate_solid() {
+        let (mut brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+        let rotation = Transform::rotation(DVec3::ZERO, DVec3::Z, FRAC_PI_2);
+        brep.transform_solid(solid, &rotation);
+
+        assert!(brep.validate().is_ok());
+        let points: Vec<DVec3> = brep.vertices.iter().map(|v| v.point.0).collect();
+        assert!(points
+            .iter()
+            .any(|p| p.abs_diff_eq(DVec3::new(-2., 1., 3.), 1e-12)));
+    }
+
+    #[test]
+    fn test_mirror_solid() {
+        let (mut brep, solid) = cuboid(DVec3::ONE);
+        let mirror = Transform::mirror(&Plane::new(DVec3::X, DVec3::new(-1., 0., 0.)));
+        brep.transform_solid(solid, &mirror);
+
+        assert!(brep.validate().is_ok());
+        let middle = DVec3::new(-2.5, 0.5, 0.5);
+        for face in brep.solid_faces(solid) {
+            // Every face still points out of the box
+            let (center, normal) = face_normal(&brep, face);
+            assert!(
+                (center - middle).dot(normal) > 0.,
+                "{face:?} points inwards"
+            );
+        }
+    }
+
+    #[test]
+    fn test_stretch_and_shear_cylinder() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        circle(&mut sketch, (0., 0.), 1.);
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(
+                &sketch.find_regions()[0],
+                DVec3::Z,
+                ExtrudeExtent::OneSided(2.),
+            )
+            .unwrap();
+
+        let transform = Transform::stretch(DVec3::ZERO, DVec3::X, 2.).then(&Transform::shear(
+            &Plane::XY,
+            DVec3::new(1., 1., 0.),
+            0.5,
+        ));
+        brep.transform_solid(solid, &transform);
+        assert!(brep
+            .faces
+            .iter()
+            .any(|f| matches!(f.surface, Surface::Transformed { .. })));
+
+        // Volumes grow by the factor of the stretch
+        let volume = solid_volume(&brep, solid);
+        assert!((volume - 4. * PI).abs() < 1e-2 * 4. * PI);
+
+        // It is written as NURBS surfaces and read back whole
+        let text = brep.to_step(&[solid], StepSchema::Ap214).unwrap();
+        assert!(text.contains("B_SPLINE_SURFACE"));
+        let mut read = Brep::new();
+        let import = read.import_step(&text).unwrap();
+        let read_volume = solid_volume(&read, import.solids[0]);
+        assert!((read_volume - volume).abs() < 1e-2 * volume);
+    }
+
+    #[test]
+    fn test_stretch_and_mirror_sphere() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(DVec3::ZERO, DVec3::Z, -DVec3::Y, DVec3::Y).unwrap(),
+        )));
+        line(&mut sketch, (0., 1.), (0., -1.));
+        let mut brep = Brep::new();
+        let axis = Line::Parametric(ParametricLine::new(DVec3::ZERO, DVec3::Y));
+        let solid = brep.revolve(&sketch.find_regions()[0], &axis, TAU).unwrap();
+
+        let transform = Transform::stretch(DVec3::ZERO, DVec3::new(1., 0., 1.), 0.5)
+            .then(&Transform::mirror(&Plane::new(DVec3::X, DVec3::ZERO)));
+        brep.transform_solid(solid, &transform);
+
+        let volume = 4. / 3. * PI * 0.5;
+        assert!((solid_volume(&brep, solid) - volume).abs() < 1e-2 * volume);
+        let face = brep.face(brep.solid_faces(solid)[0]);
+        let p = transform.point(DVec3::new(0.6, 0.8, 0.));
+        let mut normal = face.surface.normal_at(p);
+        if face.reversed {
+            normal = -normal;
+        }
+        assert!(p.dot(normal) > 0., "the ellipsoid points inwards");
+    }
+}
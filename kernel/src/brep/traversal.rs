@@ -0,0 +1,220 @@
+use super::{Brep, CoedgeId, EdgeId, FaceId, LoopId, SolidId, VertexId};
+
+impl Brep {
+    /// The coedges of a loop in order, starting from [`Loop::first`](super::Loop::first).
+    pub fn loop_coedges(&self, id: LoopId) -> Vec<CoedgeId> {
+        let first = self.get_loop(id).first;
+        let mut out = vec![first];
+
+        let mut c = self.coedge(first).next;
+        while c != first && out.len() <= self.coedges.len() {
+            out.push(c);
+            c = self.coedge(c).next;
+        }
+
+        out
+    }
+
+    /// The vertex a coedge starts from, following the direction of its loop.
+    pub fn coedge_start(&self, id: CoedgeId) -> VertexId {
+        let coedge = self.coedge(id);
+        let edge = self.edge(coedge.edge);
+
+        if coedge.reversed {
+            edge.end
+        } else {
+            edge.start
+        }
+    }
+
+    pub fn coedge_end(&self, id: CoedgeId) -> VertexId {
+        let coedge = self.coedge(id);
+        let edge = self.edge(coedge.edge);
+
+        if coedge.reversed {
+            edge.start
+        } else {
+            edge.end
+        }
+    }
+
+    pub fn coedge_face(&self, id: CoedgeId) -> FaceId {
+        self.get_loop(self.coedge(id).parent).face
+    }
+
+    /// The other use of the edge of a coedge, if the edge is used exactly twice.
+    pub fn mate(&self, id: CoedgeId) -> Option<CoedgeId> {
+        match self.edge(self.coedge(id).edge).coedges[..] {
+            [a, b] if a == id => Some(b),
+            [a, b] if b == id => Some(a),
+            _ => None,
+        }
+    }
+
+    /// The outer loop of a face followed by its inner loops.
+    pub fn face_loops(&self, id: FaceId) -> Vec<LoopId> {
+        let face = self.face(id);
+        std::iter::once(face.outer)
+            .chain(face.inner.iter().copied())
+            .collect()
+    }
+
+    /// The edges bounding a face, loop by loop.
+    pub fn face_edges(&self, id: FaceId) -> Vec<EdgeId> {
+        self.face_loops(id)
+            .into_iter()
+            .flat_map(|l| self.loop_coedges(l))
+            .map(|c| self.coedge(c).edge)
+            .collect()
+    }
+
+    /// The faces that use an edge, once for every use.
+    pub fn edge_faces(&self, id: EdgeId) -> Vec<FaceId> {
+        self.edge(id)
+            .coedges
+            .iter()
+            .map(|&c| self.coedge_face(c))
+            .collect()
+    }
+
+    /// The faces next to a face across its edges, without repeats.
+    pub fn adjacent_faces(&self, id: FaceId) -> Vec<FaceId> {
+        let mut out = Vec::new();
+
+        for edge in self.face_edges(id) {
+            for face in self.edge_faces(edge) {
+                if face != id && !out.contains(&face) {
+                    out.push(face);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Every edge that starts or ends at a vertex.
+    pub fn vertex_edges(&self, id: VertexId) -> Vec<EdgeId> {
+        (0..self.edges.len())
+            .map(EdgeId)
+            .filter(|&e| self.edge(e).start == id || self.edge(e).end == id)
+            .collect()
+    }
+
+    /// The coedges leaving a vertex in the order of the faces around it. Each step goes from a
+    /// coedge to the mate of the coedge before it in its loop, which turns clockwise around the
+    /// vertex when seen from outside. The fan stops early at a boundary edge.
+    pub fn vertex_fan(&self, id: VertexId) -> Vec<CoedgeId> {
+        let Some(first) = (0..self.coedges.len())
+            .map(CoedgeId)
+            .find(|&c| self.coedge_start(c) == id)
+        else {
+            return Vec::new();
+        };
+
+        let mut out = vec![first];
+        let mut c = first;
+        while let Some(next) = self.mate(self.coedge(c).previous) {
+            if next == first || out.contains(&next) {
+                break;
+            }
+            out.push(next);
+            c = next;
+        }
+
+        out
+    }
+
+    /// The faces around a vertex in fan order.
+    pub fn vertex_faces(&self, id: VertexId) -> Vec<FaceId> {
+        self.vertex_fan(id)
+            .into_iter()
+            .map(|c| self.coedge_face(c))
+            .collect()
+    }
+
+    /// Every face of a solid, shell by shell.
+    pub fn solid_faces(&self, id: SolidId) -> Vec<FaceId> {
+        self.solid(id)
+            .shells
+            .iter()
+            .flat_map(|&s| self.shell(s).faces.iter().copied())
+            .collect()
+    }
+
+    /// Every edge of a solid, without repeats.
+    pub fn solid_edges(&self, id: SolidId) -> Vec<EdgeId> {
+        let mut out = Vec::new();
+
+        for face in self.solid_faces(id) {
+            for edge in self.face_edges(face) {
+                if !out.contains(&edge) {
+                    out.push(edge);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Every vertex of a solid, without repeats.
+    pub fn solid_vertices(&self, id: SolidId) -> Vec<VertexId> {
+        let mut out = Vec::new();
+
+        for edge in self.solid_edges(id) {
+            for v in [self.edge(edge).start, self.edge(edge).end] {
+                if !out.contains(&v) {
+                    out.push(v);
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+    use crate::test_util::cuboid;
+
+    #[test]
+    fn test_cuboid_traversal() {
+        let (brep, solid) = cuboid(DVec3::ONE);
+
+        let top = FaceId(1);
+        assert_eq!(brep.face_edges(top).len(), 4);
+        assert_eq!(brep.adjacent_faces(top).len(), 4);
+        assert!(!brep.adjacent_faces(top).contains(&FaceId(0)));
+
+        for edge in brep.solid_edges(solid) {
+            let faces = brep.edge_faces(edge);
+            assert_eq!(faces.len(), 2);
+            assert_ne!(faces[0], faces[1]);
+        }
+
+        // Loops are closed chains
+        for l in 0..brep.loops.len() {
+            let coedges = brep.loop_coedges(LoopId(l));
+            assert_eq!(coedges.len(), 4);
+            for (i, &c) in coedges.iter().enumerate() {
+                let next = coedges[(i + 1) % 4];
+                assert_eq!(brep.coedge_end(c), brep.coedge_start(next));
+            }
+        }
+
+        // Every corner is shared by three faces and three edges
+        for v in brep.solid_vertices(solid) {
+            let fan = brep.vertex_fan(v);
+            assert_eq!(fan.len(), 3);
+            assert!(fan.iter().all(|&c| brep.coedge_start(c) == v));
+
+            let mut faces = brep.vertex_faces(v);
+            faces.sort();
+            faces.dedup();
+            assert_eq!(faces.len(), 3);
+            assert_eq!(brep.vertex_edges(v).len(), 3);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use super::{Brep, CoedgeId, EdgeId, FaceId, LoopId, SolidId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrepError {
+    /// A loop was given no edges.
+    EmptyLoop,
+    /// An edge ID that is not in the B-rep.
+    InvalidEdge(EdgeId),
+    /// A coedge does not end where the next one in its loop starts, or the loop links are
+    /// inconsistent.
+    OpenLoop(LoopId),
+    /// A loop belongs to a different face than the one that references it.
+    MisplacedLoop(LoopId),
+    /// An edge of a solid is used by fewer than two faces of its shell.
+    OpenShell(EdgeId),
+    /// An edge of a solid is used by more than two faces.
+    NonManifoldEdge(EdgeId),
+    /// Both uses of an edge run in the same direction, so adjacent faces face opposite ways.
+    InconsistentOrientation(EdgeId),
+    /// The counts of vertices, edges, faces and loops do not satisfy the Euler–Poincaré formula.
+    EulerCharacteristic(SolidId),
+    /// A solid has no shells.
+    EmptySolid(SolidId),
+}
+
+impl fmt::Display for BrepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrepError::EmptyLoop => write!(f, "loop has no edges"),
+            BrepError::InvalidEdge(e) => write!(f, "edge {} does not exist", e.0),
+            BrepError::OpenLoop(l) => write!(f, "loop {} is not closed", l.0),
+            BrepError::MisplacedLoop(l) => write!(f, "loop {} belongs to another face", l.0),
+            BrepError::OpenShell(e) => write!(f, "edge {} is on the boundary of a shell", e.0),
+            BrepError::NonManifoldEdge(e) => {
+                write!(f, "edge {} is used by more than two faces", e.0)
+            }
+            BrepError::InconsistentOrientation(e) => {
+                write!(
+                    f,
+                    "faces on either side of edge {} are oriented inconsistently",
+                    e.0
+                )
+            }
+            BrepError::EulerCharacteristic(s) => {
+                write!(f, "solid {} violates the Euler–Poincaré formula", s.0)
+            }
+            BrepError::EmptySolid(s) => write!(f, "solid {} has no shells", s.0),
+        }
+    }
+}
+
+impl std::error::Error for BrepError {}
+
+impl Brep {
+    /// Checks that every loop is closed and every solid is a closed, consistently oriented
+    /// manifold.
+    pub fn validate(&self) -> Result<(), BrepError> {
+        for (i, face) in self.faces.iter().enumerate() {
+            for l in std::iter::once(face.outer).chain(face.inner.iter().copied()) {
+                if self.get_loop(l).face != FaceId(i) {
+                    return Err(BrepError::MisplacedLoop(l));
+                }
+                self.validate_loop(l)?;
+            }
+        }
+
+        for s in 0..self.solids.len() {
+            let solid = SolidId(s);
+            if self.solid(solid).shells.is_empty() {
+                return Err(BrepError::EmptySolid(solid));
+            }
+
+            for &shell in &self.solid(solid).shells {
+                let faces = &self.shell(shell).faces;
+
+                for face in faces {
+                    for edge in self.face_edges(*face) {
+                        let coedges = &self.edge(edge).coedges;
+                        let in_shell = |c: &CoedgeId| faces.contains(&self.coedge_face(*c));
+
+                        match coedges.len() {
+                            0 | 1 => return Err(BrepError::OpenShell(edge)),
+                            2 => (),
+                            _ => return Err(BrepError::NonManifoldEdge(edge)),
+                        }
+                        if !coedges.iter().all(in_shell) {
+                            return Err(BrepError::OpenShell(edge));
+                        }
+                        if self.coedge(coedges[0]).reversed == self.coedge(coedges[1]).reversed {
+                            return Err(BrepError::InconsistentOrientation(edge));
+                        }
+                    }
+                }
+            }
+
+            if self.genus(solid).is_none() {
+                return Err(BrepError::EulerCharacteristic(solid));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_loop(&self, id: LoopId) -> Result<(), BrepError> {
+        let coedges = self.loop_coedges(id);
+
+        // The walk only stops early if it returns to the first coedge
+        if coedges.len() > self.coedges.len() {
+            return Err(BrepError::OpenLoop(id));
+        }
+
+        for &c in &coedges {
+            let coedge = self.coedge(c);
+            if coedge.parent != id
+                || self.coedge(coedge.next).previous != c
+                || self.coedge_end(c) != self.coedge_start(coedge.next)
+            {
+                return Err(BrepError::OpenLoop(id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of through holes of a solid, from the Euler–Poincaré formula
+    /// `V − E + F − R = 2(S − G)`, where `R` counts the inner loops of faces and `S` the
+    /// shells. `None` if the counts are inconsistent with any genus.
+    pub fn genus(&self, id: SolidId) -> Option<usize> {
+        let faces = self.solid_faces(id);
+        let inner: usize = faces.iter().map(|&f| self.face(f).inner.len()).sum();
+
+        let v = self.solid_vertices(id).len() as isize;
+        let e = self.solid_edges(id).len() as isize;
+        let f = faces.len() as isize;
+        let r = inner as isize;
+        let s = self.solid(id).shells.len() as isize;
+
+        let twice_genus = 2 * s - (v - e + f - r);
+        (twice_genus >= 0 && twice_genus % 2 == 0).then_some(twice_genus as usize / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+    use crate::test_util::cuboid;
+
+    #[test]
+    fn test_open_shell() {
+        let (mut brep, solid) = cuboid(DVec3::ONE);
+        let shell = brep.solid(solid).shells[0];
+        brep.shells[shell.0].faces.pop();
+
+        assert!(matches!(brep.validate(), Err(BrepError::OpenShell(_))));
+    }
+
+    #[test]
+    fn test_flipped_face() {
+        let (mut brep, _) = cuboid(DVec3::ONE);
+
+        // Reverse the loop of the top face without touching its neighbours
+        let outer = brep.face(FaceId(1)).outer;
+        for c in brep.loop_coedges(outer) {
+            let coedge = &mut brep.coedges[c.0];
+            coedge.reversed = !coedge.reversed;
+            std::mem::swap(&mut coedge.next, &mut coedge.previous);
+        }
+
+        assert!(matches!(
+            brep.validate(),
+            Err(BrepError::InconsistentOrientation(_))
+        ));
+    }
+}
@@ -0,0 +1,499 @@
+use std::f64::consts::TAU;
+
+use glam::{DVec2, DVec3};
+
+use crate::{
+    BoundaryElement, BoundaryLoop, BoundarySurface, Direction, Plane, Sketch, SketchElement,
+    TesselationTolerance,
+};
+
+use super::groups::GroupWriter;
+
+/// Millimetres per metre, the unit drawings are written in.
+const SCALE: f64 = 1000.;
+
+/// How far the straight pieces standing in for ellipses and splines in profile polylines may
+/// stray from them, in metres.
+const CURVE_CHORD: f64 = 1e-5;
+
+/// A DXF drawing built up from sketches and planar profiles, each on a named layer. Everything
+/// is drawn in the 2D coordinates of the plane it lies on, so sketches on different planes
+/// overlap in the drawing.
+///
+/// Arcs and circles are written as such rather than as line segments, ellipses and splines as
+/// exact ellipses and splines.
+#[derive(Debug, Clone, Default)]
+pub struct DxfDrawing {
+    layers: Vec<String>,
+    entities: Vec<(usize, DrawingEntity)>,
+}
+
+/// An entity in drawing coordinates, in metres.
+#[derive(Debug, Clone)]
+enum DrawingEntity {
+    Line {
+        a: DVec2,
+        b: DVec2,
+    },
+    Point(DVec2),
+    Circle {
+        center: DVec2,
+        radius: f64,
+    },
+    /// Running counter-clockwise between angles in radians.
+    Arc {
+        center: DVec2,
+        radius: f64,
+        start: f64,
+        end: f64,
+    },
+    Ellipse {
+        center: DVec2,
+        major: DVec2,
+        ratio: f64,
+    },
+    Spline {
+        degree: usize,
+        control_points: Vec<DVec2>,
+        weights: Vec<f64>,
+        knots: Vec<f64>,
+    },
+    /// Corners with the bulge of the segment leaving them, see [`bulge`].
+    Polyline {
+        vertices: Vec<(DVec2, f64)>,
+    },
+}
+
+impl DxfDrawing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every element of a sketch, each as an entity of its own.
+    pub fn add_sketch(&mut self, sketch: &Sketch, layer: &str) {
+        let layer = self.layer(layer);
+        let plane = &sketch.plane.0;
+        let local = |p: DVec3| plane.to_local(p);
+
+        for element in &sketch.elements {
+            let entity = match element {
+                SketchElement::Line(line) => {
+                    let Ok(line) = line.0.to_two_point_line() else {
+                        continue;
+                    };
+                    DrawingEntity::Line {
+                        a: local(line.a.0),
+                        b: local(line.b.0),
+                    }
+                }
+                SketchElement::Point(point) => DrawingEntity::Point(local(point.0 .0)),
+                SketchElement::Arc(arc) if arc.0.is_full_circle() => DrawingEntity::Circle {
+                    center: local(arc.0.center),
+                    radius: arc.0.radius,
+                },
+                SketchElement::Arc(arc) => {
+                    // Clockwise arcs are written from their end
+                    let arc = if arc.0.normal.dot(plane.normal) < 0. {
+                        arc.0.reversed()
+                    } else {
+                        arc.0.clone()
+                    };
+                    let center = local(arc.center);
+                    DrawingEntity::Arc {
+                        center,
+                        radius: arc.radius,
+                        start: angle(local(arc.start) - center),
+                        end: angle(local(arc.end) - center),
+                    }
+                }
+                SketchElement::Circle(circle) => DrawingEntity::Circle {
+                    center: local(circle.0.center),
+                    radius: circle.0.radius,
+                },
+                SketchElement::Ellipse(ellipse) => {
+                    let e = &ellipse.0;
+                    let center = local(e.center);
+                    let (axis, major, minor) = if e.major_radius >= e.minor_radius {
+                        (e.x_axis, e.major_radius, e.minor_radius)
+                    } else {
+                        (e.y_axis(), e.minor_radius, e.major_radius)
+                    };
+                    DrawingEntity::Ellipse {
+                        center,
+                        major: local(e.center + major * axis) - center,
+                        ratio: minor / major,
+                    }
+                }
+                SketchElement::Spline(spline) => {
+                    let curve = spline.curve();
+                    DrawingEntity::Spline {
+                        degree: curve.degree,
+                        control_points: curve.control_points.iter().map(|&p| local(p)).collect(),
+                        weights: curve.weights.clone(),
+                        knots: curve.knots.clone(),
+                    }
+                }
+            };
+            self.entities.push((layer, entity));
+        }
+    }
+
+    /// Adds the outline and holes of a profile, each as a closed polyline with arcs for
+    /// segments, or as a circle or a spline when it is one. This is the form cutting software
+    /// takes a closed path in. Pieces of ellipses and splines in polylines are split into
+    /// straight segments.
+    pub fn add_profile(&mut self, profile: &BoundarySurface, layer: &str) {
+        let layer = self.layer(layer);
+        for boundary in std::iter::once(&profile.boundary).chain(&profile.holes) {
+            if let Some(entity) = profile_loop(&profile.plane, boundary) {
+                self.entities.push((layer, entity));
+            }
+        }
+    }
+
+    /// The index of a layer, adding it the first time it is used.
+    fn layer(&mut self, name: &str) -> usize {
+        self.layers
+            .iter()
+            .position(|l| l == name)
+            .unwrap_or_else(|| {
+                self.layers.push(name.to_string());
+                self.layers.len() - 1
+            })
+    }
+
+    /// Writes the drawing in millimetres, with a layer table listing every layer used.
+    pub fn to_dxf(&self) -> String {
+        let mut w = GroupWriter::new();
+        // Handles are only required to be unique, so number everything in the order written
+        let mut handle = 0x10;
+        let mut next = || {
+            handle += 1;
+            format!("{handle:X}")
+        };
+
+        w.group(0, "SECTION").group(2, "HEADER");
+        w.group(9, "$ACADVER").group(1, "AC1015");
+        w.group(9, "$INSUNITS").group(70, 4);
+        w.group(9, "$MEASUREMENT").group(70, 1);
+        w.group(0, "ENDSEC");
+
+        // Layer `0` always exists
+        let mut layers = vec!["0"];
+        layers.extend(self.layers.iter().map(String::as_str).filter(|&l| l != "0"));
+        w.group(0, "SECTION").group(2, "TABLES");
+        w.group(0, "TABLE").group(2, "LAYER").group(5, next());
+        w.group(100, "AcDbSymbolTable").group(70, layers.len());
+        for layer in layers {
+            w.group(0, "LAYER").group(5, next());
+            w.group(100, "AcDbSymbolTableRecord")
+                .group(100, "AcDbLayerTableRecord");
+            w.group(2, layer)
+                .group(70, 0)
+                .group(62, 7)
+                .group(6, "CONTINUOUS");
+        }
+        w.group(0, "ENDTAB");
+        w.group(0, "ENDSEC");
+
+        w.group(0, "SECTION").group(2, "ENTITIES");
+        for (layer, entity) in &self.entities {
+            let kind = match entity {
+                DrawingEntity::Line { .. } => "LINE",
+                DrawingEntity::Point(_) => "POINT",
+                DrawingEntity::Circle { .. } => "CIRCLE",
+                DrawingEntity::Arc { .. } => "ARC",
+                DrawingEntity::Ellipse { .. } => "ELLIPSE",
+                DrawingEntity::Spline { .. } => "SPLINE",
+                DrawingEntity::Polyline { .. } => "LWPOLYLINE",
+            };
+            w.group(0, kind).group(5, next());
+            w.group(100, "AcDbEntity").group(8, &self.layers[*layer]);
+            write_entity(&mut w, entity);
+        }
+        w.group(0, "ENDSEC");
+        w.group(0, "EOF");
+
+        w.finish()
+    }
+}
+
+fn write_entity(w: &mut GroupWriter, entity: &DrawingEntity) {
+    let point = |p: DVec2| (p * SCALE).extend(0.).to_array();
+
+    match entity {
+        DrawingEntity::Line { a, b } => {
+            w.group(100, "AcDbLine")
+                .point(10, point(*a))
+                .point(11, point(*b));
+        }
+        DrawingEntity::Point(p) => {
+            w.group(100, "AcDbPoint").point(10, point(*p));
+        }
+        DrawingEntity::Circle { center, radius } => {
+            w.group(100, "AcDbCircle")
+                .point(10, point(*center))
+                .real(40, radius * SCALE);
+        }
+        DrawingEntity::Arc {
+            center,
+            radius,
+            start,
+            end,
+        } => {
+            w.group(100, "AcDbCircle")
+                .point(10, point(*center))
+                .real(40, radius * SCALE);
+            w.group(100, "AcDbArc")
+                .real(50, start.to_degrees())
+                .real(51, end.to_degrees());
+        }
+        DrawingEntity::Ellipse {
+            center,
+            major,
+            ratio,
+        } => {
+            w.group(100, "AcDbEllipse")
+                .point(10, point(*center))
+                .point(11, point(*major))
+                .real(40, *ratio)
+                .real(41, 0.)
+                .real(42, TAU);
+        }
+        DrawingEntity::Spline {
+            degree,
+            control_points,
+            weights,
+            knots,
+        } => {
+            let rational = weights.iter().any(|&w| w != 1.);
+            // Planar, and rational when the weights differ
+            let flags = 8 | if rational { 4 } else { 0 };
+            w.group(100, "AcDbSpline").point(210, [0., 0., 1.]);
+            w.group(70, flags).group(71, degree);
+            w.group(72, knots.len())
+                .group(73, control_points.len())
+                .group(74, 0);
+            for &k in knots {
+                w.real(40, k);
+            }
+            if rational {
+                for &weight in weights {
+                    w.real(41, weight);
+                }
+            }
+            for &p in control_points {
+                w.point(10, point(p));
+            }
+        }
+        DrawingEntity::Polyline { vertices } => {
+            w.group(100, "AcDbPolyline")
+                .group(90, vertices.len())
+                .group(70, 1);
+            for &(p, bulge) in vertices {
+                let p = p * SCALE;
+                w.real(10, p.x).real(20, p.y);
+                if bulge != 0. {
+                    w.real(42, bulge);
+                }
+            }
+        }
+    }
+}
+
+/// The counter-clockwise angle of a direction from the x axis, in `[0, 2π)`.
+fn angle(v: DVec2) -> f64 {
+    v.y.atan2(v.x).rem_euclid(TAU)
+}
+
+/// The bulge of a polyline segment: the tangent of a quarter of the angle its arc sweeps
+/// counter-clockwise, negative for clockwise arcs.
+fn bulge(sweep: f64) -> f64 {
+    (sweep / 4.).tan()
+}
+
+/// A loop of a profile as a closed polyline, or a circle for a loop of a single arc.
+fn profile_loop(plane: &Plane, boundary: &BoundaryLoop) -> Option<DrawingEntity> {
+    let local = |p: DVec3| plane.to_local(p);
+
+    match &boundary.elements[..] {
+        [BoundaryElement::BoundaryArc(arc)] => {
+            return Some(DrawingEntity::Circle {
+                center: local(arc.circle.center),
+                radius: arc.circle.radius,
+            });
+        }
+        [BoundaryElement::BoundaryCurve(curve)] => {
+            let curve = &curve.curve;
+            return Some(DrawingEntity::Spline {
+                degree: curve.degree,
+                control_points: curve.control_points.iter().map(|&p| local(p)).collect(),
+                weights: curve.weights.clone(),
+                knots: curve.knots.clone(),
+            });
+        }
+        _ => (),
+    }
+
+    let mut vertices = Vec::new();
+    for element in &boundary.elements {
+        match element {
+            BoundaryElement::BoundaryLine(line) => vertices.push((local(line.a.0), 0.)),
+            BoundaryElement::BoundaryPolygon(polygon) => {
+                vertices.extend(polygon.lines.iter().map(|l| (local(l.a.0), 0.)))
+            }
+            BoundaryElement::BoundaryArc(arc) => {
+                let center = local(arc.circle.center);
+                let ccw = (angle(local(arc.end.0) - center) - angle(local(arc.start.0) - center))
+                    .rem_euclid(TAU);
+                let sweep = match arc.direction {
+                    Direction::CCW => ccw,
+                    Direction::CW => ccw - TAU,
+                };
+                vertices.push((local(arc.start.0), bulge(sweep)));
+            }
+            BoundaryElement::BoundaryCurve(curve) => {
+                let tolerance = TesselationTolerance::new(CURVE_CHORD, 0.);
+                for line in curve.curve.to_lines(&tolerance) {
+                    if let Ok(line) = line.to_two_point_line() {
+                        vertices.push((local(line.a.0), 0.));
+                    }
+                }
+            }
+        }
+    }
+
+    (vertices.len() >= 2).then_some(DrawingEntity::Polyline { vertices })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DVec2, DVec3};
+
+    use crate::arc::Arc;
+    use crate::circle::Circle;
+    use crate::line::{Line, TwoPointLine};
+    use crate::nurbs::NurbsCurve;
+    use crate::*;
+
+    fn line(a: DVec3, b: DVec3) -> SketchElement {
+        SketchElement::Line(SketchLine(Line::TwoPoint(TwoPointLine::new(a, b))))
+    }
+
+    #[test]
+    fn test_sketch_round_trip() {
+        let plane = SketchPlane(Plane::new(DVec3::Y, DVec3::new(0., 2., 0.)));
+        let world = |x: f64, y: f64| plane.to_world(DVec2::new(x, y));
+        let mut sketch = Sketch::new(plane.clone());
+        sketch.add_element(line(world(0., 0.), world(0.1, 0.)));
+        // Clockwise, from (0.1, 0) over the top to (0.2, 0)
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                world(0.15, 0.),
+                -plane.0.normal,
+                world(0.1, 0.),
+                world(0.2, 0.),
+            )
+            .unwrap(),
+        )));
+        sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(world(0.05, 0.05), plane.0.normal, 0.01).unwrap(),
+        )));
+        sketch.add_element(SketchElement::Spline(SketchSpline::Control(
+            NurbsCurve::uniform(
+                3,
+                vec![
+                    world(0., 0.1),
+                    world(0.05, 0.2),
+                    world(0.1, 0.1),
+                    world(0.15, 0.2),
+                ],
+            )
+            .unwrap(),
+        )));
+
+        let mut drawing = DxfDrawing::new();
+        drawing.add_sketch(&sketch, "CUT");
+        let text = drawing.to_dxf();
+        for kind in ["LINE", "ARC", "CIRCLE", "SPLINE"] {
+            assert_eq!(text.matches(&format!("\n{kind}\n")).count(), 1);
+        }
+        assert!(text.contains("\nCUT\n"));
+
+        let mut read = Sketch::new(plane.clone());
+        let import = read.import_dxf(&text).unwrap();
+        assert!(import.warnings.is_empty());
+        assert_eq!(import.layers, ["CUT"; 4]);
+
+        let SketchElement::Arc(arc) = &read.elements[1] else {
+            panic!("expected an arc")
+        };
+        // Written counter-clockwise from its end, so it comes back reversed
+        assert!(arc.0.normal.abs_diff_eq(plane.0.normal, 1e-12));
+        assert!(arc.0.start.abs_diff_eq(world(0.2, 0.), 1e-12));
+        assert!(arc.0.midpoint().abs_diff_eq(world(0.15, 0.05), 1e-12));
+
+        let (SketchElement::Spline(a), SketchElement::Spline(b)) =
+            (&sketch.elements[3], &read.elements[3])
+        else {
+            panic!("expected splines")
+        };
+        assert_eq!(a.curve().knots, b.curve().knots);
+        for t in [0., 0.3, 1.] {
+            assert!(a
+                .curve()
+                .point_at(t)
+                .abs_diff_eq(b.curve().point_at(t), 1e-12));
+        }
+    }
+
+    #[test]
+    fn test_profile_layers() {
+        // A plate with rounded right end and a bore
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let p = |x: f64, y: f64| DVec3::new(x, y, 0.);
+        sketch.add_element(line(p(0., 0.), p(0.1, 0.)));
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(p(0.1, 0.025), DVec3::Z, p(0.1, 0.), p(0.1, 0.05)).unwrap(),
+        )));
+        sketch.add_element(line(p(0.1, 0.05), p(0., 0.05)));
+        sketch.add_element(line(p(0., 0.05), p(0., 0.)));
+        sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(p(0.05, 0.025), DVec3::Z, 0.01).unwrap(),
+        )));
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 2);
+        let plate = regions.iter().find(|r| !r.holes.is_empty()).unwrap();
+
+        let mut drawing = DxfDrawing::new();
+        drawing.add_profile(plate, "OUTSIDE");
+        drawing.add_sketch(&sketch, "0");
+        let text = drawing.to_dxf();
+
+        // The layer table, with `0` listed once besides the profile's layer
+        assert_eq!(text.matches("\nLAYER\n").count(), 3);
+        assert_eq!(text.matches("\nLWPOLYLINE\n").count(), 1);
+
+        let mut read = Sketch::new(SketchPlane::XY);
+        let import = read.import_dxf(&text).unwrap();
+        assert!(import.warnings.is_empty());
+        let outside: Vec<_> = import
+            .elements
+            .iter()
+            .zip(&import.layers)
+            .filter(|(_, l)| *l == "OUTSIDE")
+            .map(|(&id, _)| read.element(id).unwrap().clone())
+            .collect();
+        assert_eq!(outside.len(), 5);
+
+        assert!(matches!(outside[1], SketchElement::Arc(_)));
+        assert!(matches!(outside[4], SketchElement::Circle(_)));
+
+        let mut profile = Sketch::new(SketchPlane::XY);
+        for element in outside {
+            profile.add_element(element);
+        }
+        let regions = profile.find_regions();
+        assert!(regions.iter().any(|r| r.holes.len() == 1));
+    }
+}
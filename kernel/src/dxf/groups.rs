@@ -0,0 +1,117 @@
+//! The group code and value pairs every DXF file is made of, each written on two lines.
+
+use std::fmt::{self, Write};
+
+use super::DxfError;
+
+/// A value together with the code that says what it means.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub code: i32,
+    pub value: String,
+    /// The line the code is written on, counting from `1`.
+    pub line: usize,
+}
+
+impl Group {
+    pub fn as_real(&self) -> Option<f64> {
+        self.value.parse().ok()
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        self.value.parse().ok()
+    }
+
+    /// Whether this is the `0` group starting an entity, section or table entry of type
+    /// `kind`.
+    pub fn starts(&self, kind: &str) -> bool {
+        self.code == 0 && self.value == kind
+    }
+}
+
+/// Splits a file into its groups, trimming the padding around codes and values.
+pub fn parse(text: &str) -> Result<Vec<Group>, DxfError> {
+    let mut lines = text
+        .trim_end()
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()));
+    let mut groups = Vec::new();
+
+    while let Some((line, code)) = lines.next() {
+        let code = code.parse().map_err(|_| DxfError::Syntax(line))?;
+        let (_, value) = lines.next().ok_or(DxfError::Syntax(line))?;
+        groups.push(Group {
+            code,
+            value: value.to_string(),
+            line,
+        });
+    }
+
+    Ok(groups)
+}
+
+/// Writes groups one after another, codes right-aligned in three columns as most writers do.
+#[derive(Debug, Clone, Default)]
+pub struct GroupWriter {
+    out: String,
+}
+
+impl GroupWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn group(&mut self, code: i32, value: impl fmt::Display) -> &mut Self {
+        let _ = writeln!(self.out, "{code:>3}\n{value}");
+        self
+    }
+
+    /// A real, written in full rather than with an exponent.
+    pub fn real(&mut self, code: i32, value: f64) -> &mut Self {
+        // Avoid writing `-0`
+        self.group(code, value + 0.)
+    }
+
+    /// A point as its x, y and z coordinates under `code`, `code + 10` and `code + 20`.
+    pub fn point(&mut self, code: i32, [x, y, z]: [f64; 3]) -> &mut Self {
+        self.real(code, x).real(code + 10, y).real(code + 20, z)
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut writer = GroupWriter::new();
+        writer
+            .group(0, "LINE")
+            .group(8, "CUT")
+            .point(10, [1.5, -0., 1e-7]);
+        let text = writer.finish();
+        assert!(text.starts_with("  0\nLINE\n  8\nCUT\n 10\n1.5\n 20\n0\n 30\n0.0000001\n"));
+
+        let groups = parse(&text).unwrap();
+        assert_eq!(groups.len(), 5);
+        assert!(groups[0].starts("LINE"));
+        assert_eq!(groups[2].code, 10);
+        assert_eq!(groups[2].line, 5);
+        assert_eq!(groups[4].as_real(), Some(1e-7));
+
+        // Windows line endings and padded codes
+        let groups = parse("  0\r\nSECTION\r\n  2\r\nENTITIES\r\n").unwrap();
+        assert_eq!(groups[1].value, "ENTITIES");
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(parse("0\nLINE\nLINE\n10\n"), Err(DxfError::Syntax(3)));
+        assert_eq!(parse("0\nLINE\n10"), Err(DxfError::Syntax(3)));
+    }
+}
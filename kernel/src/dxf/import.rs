@@ -0,0 +1,702 @@
+use std::f64::consts::TAU;
+
+use glam::{DVec2, DVec3};
+
+use crate::arc::Arc;
+use crate::circle::Circle;
+use crate::ellipse::Ellipse;
+use crate::line::{Line, TwoPointLine};
+use crate::nurbs::NurbsCurve;
+use crate::point::Point;
+use crate::{
+    ElementId, FitSpline, Sketch, SketchArc, SketchCircle, SketchElement, SketchEllipse,
+    SketchLine, SketchPlane, SketchPoint, SketchSpline, Tolerance,
+};
+
+use super::groups::{self, Group};
+use super::{DxfError, DxfWarning};
+
+/// The outcome of reading a DXF file: the elements added to the sketch with the layer each was
+/// drawn on, and what had to be left out on the way.
+#[derive(Debug, Clone, Default)]
+pub struct DxfImport {
+    pub elements: Vec<ElementId>,
+    /// The layer of each element, in the same order.
+    pub layers: Vec<String>,
+    pub warnings: Vec<DxfWarning>,
+}
+
+type Read<T> = Result<T, DxfWarning>;
+
+impl Sketch {
+    /// Reads the lines, arcs, circles, ellipses, polylines, splines and points of a DXF file
+    /// into the sketch. The x and y coordinates of the drawing become coordinates in the sketch
+    /// plane, converted from the units in the header to metres, and z coordinates are dropped.
+    ///
+    /// Polylines become a line or an arc per segment, and splines given only by fit points are
+    /// interpolated through them. Only a file that breaks the group structure fails as a whole;
+    /// any other entity is skipped with a warning.
+    pub fn import_dxf(&mut self, text: &str) -> Result<DxfImport, DxfError> {
+        let groups = groups::parse(text)?;
+        let reader = EntityReader {
+            plane: self.plane.clone(),
+            scale: units(&groups),
+        };
+
+        let mut import = DxfImport::default();
+        for entity in entities(&groups) {
+            match reader.elements(&entity) {
+                Ok(elements) => {
+                    for element in elements {
+                        import.elements.push(self.add_element(element));
+                        import.layers.push(entity.layer());
+                    }
+                }
+                Err(warning) => import.warnings.push(warning),
+            }
+        }
+
+        Ok(import)
+    }
+}
+
+/// The length of a drawing unit in metres, by `$INSUNITS` in the header.
+fn units(groups: &[Group]) -> f64 {
+    let units = groups
+        .iter()
+        .position(|g| g.code == 9 && g.value == "$INSUNITS")
+        .and_then(|i| groups[i + 1..].iter().find(|g| g.code == 70))
+        .and_then(Group::as_integer);
+
+    match units {
+        Some(1) => 0.0254,
+        Some(2) => 0.3048,
+        Some(5) => 0.01,
+        Some(6) => 1.,
+        Some(10) => 0.9144,
+        Some(13) => 1e-6,
+        Some(14) => 0.1,
+        // Millimetres, and files without units
+        _ => 0.001,
+    }
+}
+
+/// An entity of the drawing and the groups it is made of.
+struct Entity<'a> {
+    kind: &'a str,
+    line: usize,
+    groups: &'a [Group],
+}
+
+/// The entities of the `ENTITIES` section. An old style `POLYLINE` takes its `VERTEX`
+/// entities up to the closing `SEQEND` along.
+fn entities(groups: &[Group]) -> Vec<Entity<'_>> {
+    let Some(start) = groups
+        .windows(2)
+        .position(|w| w[0].starts("SECTION") && w[1].code == 2 && w[1].value == "ENTITIES")
+    else {
+        return Vec::new();
+    };
+
+    let mut entities = Vec::new();
+    let mut i = start + 2;
+    while i < groups.len() && !groups[i].starts("ENDSEC") {
+        let kind = groups[i].value.as_str();
+        let ends = |g: &Group| {
+            g.code == 0 && !(kind == "POLYLINE" && (g.value == "VERTEX" || g.value == "SEQEND"))
+        };
+        let end = groups[i + 1..]
+            .iter()
+            .position(ends)
+            .map_or(groups.len(), |n| i + 1 + n);
+
+        entities.push(Entity {
+            kind,
+            line: groups[i].line,
+            groups: &groups[i + 1..end],
+        });
+        i = end;
+    }
+    entities
+}
+
+impl Entity<'_> {
+    fn real(&self, code: i32) -> Option<f64> {
+        self.groups.iter().find(|g| g.code == code)?.as_real()
+    }
+
+    fn integer(&self, code: i32) -> Option<i64> {
+        self.groups.iter().find(|g| g.code == code)?.as_integer()
+    }
+
+    fn reals(&self, code: i32) -> Vec<f64> {
+        self.groups
+            .iter()
+            .filter(|g| g.code == code)
+            .filter_map(Group::as_real)
+            .collect()
+    }
+
+    /// The point under `code`, with a z coordinate of `0` if it is left out.
+    fn point(&self, code: i32) -> Option<DVec3> {
+        Some(DVec3::new(
+            self.real(code)?,
+            self.real(code + 10)?,
+            self.real(code + 20).unwrap_or(0.),
+        ))
+    }
+
+    /// Every point under `code`, in order.
+    fn points(&self, code: i32) -> Vec<DVec3> {
+        let mut points = Vec::new();
+        for g in self.groups {
+            let value = g.as_real().unwrap_or(f64::NAN);
+            match g.code {
+                c if c == code => points.push(DVec3::new(value, 0., 0.)),
+                c if c == code + 10 => points.last_mut().map_or((), |p| p.y = value),
+                c if c == code + 20 => points.last_mut().map_or((), |p| p.z = value),
+                _ => (),
+            }
+        }
+        points
+    }
+
+    fn layer(&self) -> String {
+        self.groups
+            .iter()
+            .find(|g| g.code == 8)
+            .map_or("0".to_string(), |g| g.value.clone())
+    }
+
+    fn malformed(&self) -> DxfWarning {
+        DxfWarning::MalformedEntity {
+            line: self.line,
+            entity: self.kind.to_string(),
+        }
+    }
+
+    fn out_of_plane(&self) -> DxfWarning {
+        DxfWarning::OutOfPlane {
+            line: self.line,
+            entity: self.kind.to_string(),
+        }
+    }
+
+    /// Reads a required value, or warns that the entity is malformed.
+    fn require<T>(&self, value: Option<T>) -> Read<T> {
+        value.ok_or_else(|| self.malformed())
+    }
+}
+
+/// A corner of a polyline and how the segment leaving it bulges, as the tangent of a quarter of
+/// the angle its arc sweeps counter-clockwise.
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    point: DVec2,
+    bulge: f64,
+}
+
+/// Turns entities into sketch elements on the plane of the sketch.
+struct EntityReader {
+    plane: SketchPlane,
+    scale: f64,
+}
+
+impl EntityReader {
+    fn elements(&self, entity: &Entity) -> Read<Vec<SketchElement>> {
+        let element = match entity.kind {
+            "LINE" => self.line(entity)?,
+            "POINT" => {
+                let p = self.flat(entity, entity.require(entity.point(10))?)?;
+                SketchElement::Point(SketchPoint(Point(self.world(p))))
+            }
+            "CIRCLE" => self.circle(entity)?,
+            "ARC" => self.arc(entity)?,
+            "ELLIPSE" => self.ellipse(entity)?,
+            "SPLINE" => self.spline(entity)?,
+            "LWPOLYLINE" | "POLYLINE" => return self.polyline(entity),
+            kind => {
+                return Err(DxfWarning::UnsupportedEntity {
+                    line: entity.line,
+                    entity: kind.to_string(),
+                })
+            }
+        };
+        Ok(vec![element])
+    }
+
+    /// The sketch point at drawing coordinates `p`.
+    fn world(&self, p: DVec2) -> DVec3 {
+        self.plane.to_world(p * self.scale)
+    }
+
+    /// The drawing coordinates of `p`, which has to lie in the plane of the drawing.
+    fn flat(&self, entity: &Entity, p: DVec3) -> Read<DVec2> {
+        if !Tolerance::current().is_zero_length(p.z * self.scale) {
+            return Err(entity.out_of_plane());
+        }
+        Ok(p.truncate())
+    }
+
+    /// The direction in the sketch plane along drawing direction `v`.
+    fn vector(&self, v: DVec2) -> DVec3 {
+        v.x * self.plane.x_axis() + v.y * self.plane.y_axis()
+    }
+
+    /// Arcs, circles and polylines are drawn in a coordinate system around their extrusion
+    /// direction. Those along the drawing normal share its coordinates and those against it are
+    /// mirrored in x, turning their arcs clockwise. Gives `1` or `-1` respectively. Ellipses and
+    /// splines give the normal of their plane the same way.
+    fn extrusion(&self, entity: &Entity) -> Read<f64> {
+        let extrusion = entity.point(210).unwrap_or(DVec3::Z);
+        if !Tolerance::current().is_zero_length(extrusion.truncate().length()) || extrusion.z == 0.
+        {
+            return Err(entity.out_of_plane());
+        }
+        Ok(extrusion.z.signum())
+    }
+
+    fn line(&self, entity: &Entity) -> Read<SketchElement> {
+        let a = self.world(self.flat(entity, entity.require(entity.point(10))?)?);
+        let b = self.world(self.flat(entity, entity.require(entity.point(11))?)?);
+        if Tolerance::current().coincident(a, b) {
+            return Err(entity.malformed());
+        }
+        Ok(SketchElement::Line(SketchLine(Line::TwoPoint(
+            TwoPointLine::new(a, b),
+        ))))
+    }
+
+    /// The center and radius of a circle or an arc, in drawing coordinates.
+    fn center_and_radius(&self, entity: &Entity, sign: f64) -> Read<(DVec2, f64)> {
+        let center = self.flat(entity, entity.require(entity.point(10))?)?;
+        let radius = entity.require(entity.real(40))?;
+        if radius <= 0. || !radius.is_finite() {
+            return Err(entity.malformed());
+        }
+        Ok((DVec2::new(sign * center.x, center.y), radius))
+    }
+
+    fn circle(&self, entity: &Entity) -> Read<SketchElement> {
+        let sign = self.extrusion(entity)?;
+        let (center, radius) = self.center_and_radius(entity, sign)?;
+        let circle = Circle::new(self.world(center), self.plane.0.normal, radius * self.scale);
+        Ok(SketchElement::Circle(SketchCircle(
+            circle.map_err(|_| entity.malformed())?,
+        )))
+    }
+
+    fn arc(&self, entity: &Entity) -> Read<SketchElement> {
+        let sign = self.extrusion(entity)?;
+        let (center, radius) = self.center_and_radius(entity, sign)?;
+        let at = |code| -> Read<DVec2> {
+            let angle = entity.require(entity.real(code))?.to_radians();
+            Ok(center + radius * DVec2::new(sign * angle.cos(), angle.sin()))
+        };
+
+        let arc = Arc::new(
+            self.world(center),
+            sign * self.plane.0.normal,
+            self.world(at(50)?),
+            self.world(at(51)?),
+        );
+        Ok(SketchElement::Arc(SketchArc(
+            arc.map_err(|_| entity.malformed())?,
+        )))
+    }
+
+    /// Full ellipses become sketch ellipses, and elliptical arcs exact splines.
+    fn ellipse(&self, entity: &Entity) -> Read<SketchElement> {
+        let sign = self.extrusion(entity)?;
+        let center = self.flat(entity, entity.require(entity.point(10))?)?;
+        let major = self.flat(entity, entity.require(entity.point(11))?)?;
+        let ratio = entity.require(entity.real(40))?;
+        let start = entity.real(41).unwrap_or(0.);
+        let end = entity.real(42).unwrap_or(TAU);
+        if major == DVec2::ZERO || ratio <= 0. || ratio > 1. || ratio.is_nan() {
+            return Err(entity.malformed());
+        }
+
+        // The parameter runs counter-clockwise around the extrusion direction
+        let minor = sign * ratio * major.perp();
+
+        let sweep = (end - start).rem_euclid(TAU);
+        if Tolerance::current().is_zero_angle(sweep) {
+            let ellipse = Ellipse::new(
+                self.world(center),
+                self.plane.0.normal,
+                self.vector(major),
+                major.length() * self.scale,
+                minor.length() * self.scale,
+            );
+            return Ok(SketchElement::Ellipse(SketchEllipse(
+                ellipse.map_err(|_| entity.malformed())?,
+            )));
+        }
+
+        let x = start.cos() * major + start.sin() * minor;
+        let y = -start.sin() * major + start.cos() * minor;
+        Ok(SketchElement::Spline(SketchSpline::Control(
+            NurbsCurve::circular(
+                self.world(center),
+                self.vector(x * self.scale),
+                self.vector(y * self.scale),
+                1.,
+                sweep,
+            ),
+        )))
+    }
+
+    fn spline(&self, entity: &Entity) -> Read<SketchElement> {
+        self.extrusion(entity)?;
+        let points = |code| -> Read<Vec<DVec3>> {
+            entity
+                .points(code)
+                .into_iter()
+                .map(|p| Ok(self.world(self.flat(entity, p)?)))
+                .collect()
+        };
+        let control_points = points(10)?;
+
+        let spline = if !control_points.is_empty() {
+            let degree = entity.require(entity.integer(71))?;
+            let degree = usize::try_from(degree).map_err(|_| entity.malformed())?;
+            let mut weights = entity.reals(41);
+            if weights.is_empty() {
+                weights = vec![1.; control_points.len()];
+            }
+            NurbsCurve::new(degree, control_points, weights, entity.reals(40))
+                .map(SketchSpline::Control)
+        } else {
+            let tangent = |code| -> Read<Option<DVec3>> {
+                let Some(t) = entity.point(code) else {
+                    return Ok(None);
+                };
+                let t = self.vector(self.flat(entity, t)?);
+                Ok((t != DVec3::ZERO).then(|| t.normalize()))
+            };
+            FitSpline::new(points(11)?, tangent(12)?, tangent(13)?).map(SketchSpline::Fit)
+        };
+
+        spline
+            .map(SketchElement::Spline)
+            .map_err(|_| entity.malformed())
+    }
+
+    fn polyline(&self, entity: &Entity) -> Read<Vec<SketchElement>> {
+        let flags = entity.integer(70).unwrap_or(0);
+        // 3D polylines, polygon meshes and polyface meshes
+        if flags & (8 | 16 | 64) != 0 {
+            return Err(DxfWarning::UnsupportedEntity {
+                line: entity.line,
+                entity: entity.kind.to_string(),
+            });
+        }
+        let sign = self.extrusion(entity)?;
+        // The elevation of the polyline along its extrusion direction
+        let elevation = match entity.kind {
+            "LWPOLYLINE" => entity.real(38),
+            _ => entity.real(30),
+        };
+        self.flat(entity, DVec3::new(0., 0., elevation.unwrap_or(0.)))?;
+
+        let vertices = self.vertices(entity, sign);
+        if vertices.len() < 2 || vertices.iter().any(|v| !v.point.is_finite()) {
+            return Err(entity.malformed());
+        }
+
+        let segments = if flags & 1 != 0 {
+            vertices.len()
+        } else {
+            vertices.len() - 1
+        };
+        let mut elements = Vec::new();
+        for i in 0..segments {
+            let Vertex { point: a, bulge } = vertices[i];
+            let b = vertices[(i + 1) % vertices.len()].point;
+            if Tolerance::current().is_zero_length(a.distance(b) * self.scale) {
+                continue;
+            }
+
+            if Tolerance::current().is_zero_angle(bulge) {
+                elements.push(SketchElement::Line(SketchLine(Line::TwoPoint(
+                    TwoPointLine::new(self.world(a), self.world(b)),
+                ))));
+                continue;
+            }
+
+            // The center lies off the middle of the chord, on its left for counter-clockwise
+            // arcs shorter than half a turn
+            let chord = b - a;
+            let radius = chord.length() * (bulge * bulge + 1.) / (4. * bulge.abs());
+            let sagitta = bulge.abs() * chord.length() / 2.;
+            let center =
+                (a + b) / 2. + bulge.signum() * (radius - sagitta) * chord.perp().normalize();
+            let arc = Arc::new(
+                self.world(center),
+                bulge.signum() * self.plane.0.normal,
+                self.world(a),
+                self.world(b),
+            );
+            elements.push(SketchElement::Arc(SketchArc(
+                arc.map_err(|_| entity.malformed())?,
+            )));
+        }
+        Ok(elements)
+    }
+
+    /// The corners of a lightweight polyline, or the `VERTEX` entities of an old style one,
+    /// leaving out the frame of spline fit polylines.
+    fn vertices(&self, entity: &Entity, sign: f64) -> Vec<Vertex> {
+        let groups = match entity.kind {
+            "POLYLINE" => {
+                let first = entity.groups.iter().position(|g| g.starts("VERTEX"));
+                &entity.groups[first.unwrap_or(entity.groups.len())..]
+            }
+            _ => entity.groups,
+        };
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut frame = false;
+        for g in groups {
+            if g.code == 0 {
+                frame = false;
+                continue;
+            }
+            if frame {
+                continue;
+            }
+
+            let value = g.as_real().unwrap_or(f64::NAN);
+            match g.code {
+                10 => vertices.push(Vertex {
+                    point: DVec2::new(sign * value, 0.),
+                    bulge: 0.,
+                }),
+                20 => vertices.last_mut().map_or((), |v| v.point.y = value),
+                42 => vertices.last_mut().map_or((), |v| v.bulge = sign * value),
+                70 if entity.kind == "POLYLINE" && g.as_integer().unwrap_or(0) & 16 != 0 => {
+                    vertices.pop();
+                    frame = true;
+                }
+                _ => (),
+            }
+        }
+        vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use glam::{DVec2, DVec3};
+
+    use crate::*;
+
+    fn entities(body: &str) -> String {
+        format!("0\nSECTION\n2\nENTITIES\n{body}0\nENDSEC\n0\nEOF\n")
+    }
+
+    #[test]
+    fn test_lines_arcs_and_circles() {
+        let plane = SketchPlane(Plane::new(DVec3::X, DVec3::new(1., 0., 0.)));
+        let mut sketch = Sketch::new(plane.clone());
+        let text = entities(concat!(
+            "0\nLINE\n8\nCUT\n10\n0\n20\n0\n30\n0\n11\n100\n21\n0\n31\n0\n",
+            "0\nARC\n8\nCUT\n10\n0\n20\n0\n40\n50\n50\n0\n51\n90\n",
+            "0\nCIRCLE\n8\nHOLES\n10\n20\n20\n30\n40\n5\n",
+            "0\nTEXT\n8\nNOTES\n1\nhello\n",
+        ));
+
+        let import = sketch.import_dxf(&text).unwrap();
+        assert_eq!(import.elements.len(), 3);
+        assert_eq!(import.layers, ["CUT", "CUT", "HOLES"]);
+        assert_eq!(
+            import.warnings,
+            [DxfWarning::UnsupportedEntity {
+                line: 45,
+                entity: "TEXT".to_string()
+            }]
+        );
+
+        // Millimetres into metres on the sketch plane
+        let local = |p: DVec3| plane.to_local(p);
+        let SketchElement::Line(line) = &sketch.elements[0] else {
+            panic!("expected a line")
+        };
+        let line = line.0.to_two_point_line().unwrap();
+        assert!(local(line.b.0).abs_diff_eq(DVec2::new(0.1, 0.), 1e-12));
+
+        let SketchElement::Arc(arc) = &sketch.elements[1] else {
+            panic!("expected an arc")
+        };
+        assert!(arc.0.normal.abs_diff_eq(plane.0.normal, 1e-12));
+        assert!(local(arc.0.end).abs_diff_eq(DVec2::new(0., 0.05), 1e-12));
+        assert!((arc.0.sweep() - PI / 2.).abs() < 1e-9);
+
+        let SketchElement::Circle(circle) = &sketch.elements[2] else {
+            panic!("expected a circle")
+        };
+        assert!((circle.0.radius - 0.005).abs() < 1e-12);
+        assert!(local(circle.0.center).abs_diff_eq(DVec2::new(0.02, 0.03), 1e-12));
+    }
+
+    #[test]
+    fn test_units_and_extrusion() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let text = format!(
+            "0\nSECTION\n2\nHEADER\n9\n$INSUNITS\n70\n1\n0\nENDSEC\n{}",
+            entities(concat!(
+                // Mirrored: around (-1, -1), clockwise from (-2, -1) to (-1, 0)
+                "0\nARC\n10\n1\n20\n-1\n30\n0\n40\n1\n50\n0\n51\n90\n210\n0\n220\n0\n230\n-1\n",
+                "0\nCIRCLE\n10\n0\n20\n0\n40\n1\n210\n1\n220\n0\n230\n0\n",
+            ))
+        );
+
+        let import = sketch.import_dxf(&text).unwrap();
+        assert_eq!(import.elements.len(), 1);
+        assert!(matches!(
+            import.warnings[..],
+            [DxfWarning::OutOfPlane { .. }]
+        ));
+
+        let SketchElement::Arc(arc) = &sketch.elements[0] else {
+            panic!("expected an arc")
+        };
+        let inch = 0.0254;
+        assert!(arc
+            .0
+            .center
+            .abs_diff_eq(DVec3::new(-inch, -inch, 0.), 1e-12));
+        assert!(arc
+            .0
+            .start
+            .abs_diff_eq(DVec3::new(-2. * inch, -inch, 0.), 1e-12));
+        assert!(arc.0.end.abs_diff_eq(DVec3::new(-inch, 0., 0.), 1e-12));
+        assert_eq!(arc.0.normal, -DVec3::Z);
+    }
+
+    #[test]
+    fn test_polylines() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        // A closed slot: two straight sides and two half circle ends
+        let text = entities(concat!(
+            "0\nLWPOLYLINE\n8\nCUT\n90\n4\n70\n1\n",
+            "10\n0\n20\n0\n10\n10\n20\n0\n42\n1\n10\n10\n20\n10\n10\n0\n20\n10\n42\n1\n",
+            "0\nPOLYLINE\n8\nCUT\n66\n1\n10\n0\n20\n0\n30\n0\n70\n0\n",
+            "0\nVERTEX\n10\n0\n20\n20\n42\n-1\n0\nVERTEX\n10\n0\n20\n30\n",
+            "0\nSEQEND\n",
+        ));
+
+        let import = sketch.import_dxf(&text).unwrap();
+        assert!(import.warnings.is_empty());
+        assert_eq!(import.elements.len(), 5);
+        assert!(matches!(sketch.elements[0], SketchElement::Line(_)));
+
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 1);
+        let SketchElement::Arc(end) = &sketch.elements[1] else {
+            panic!("expected an arc")
+        };
+        assert!(end.0.center.abs_diff_eq(DVec3::new(0.01, 0.005, 0.), 1e-12));
+        assert!((end.0.sweep() - PI).abs() < 1e-9);
+
+        // The clockwise half circle of the old style polyline
+        let SketchElement::Arc(arc) = &sketch.elements[4] else {
+            panic!("expected an arc")
+        };
+        assert_eq!(arc.0.normal, -DVec3::Z);
+        assert!(arc.0.center.abs_diff_eq(DVec3::new(0., 0.025, 0.), 1e-12));
+    }
+
+    #[test]
+    fn test_splines_and_ellipses() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let text = entities(concat!(
+            // A quadratic through control points and a fitted one
+            "0\nSPLINE\n70\n8\n71\n2\n72\n6\n73\n3\n",
+            "40\n0\n40\n0\n40\n0\n40\n1\n40\n1\n40\n1\n",
+            "10\n0\n20\n0\n30\n0\n10\n5\n20\n10\n30\n0\n10\n10\n20\n0\n30\n0\n",
+            "0\nSPLINE\n70\n8\n71\n3\n74\n3\n",
+            "11\n0\n21\n0\n31\n0\n11\n5\n21\n5\n31\n0\n11\n10\n21\n0\n31\n0\n",
+            "0\nELLIPSE\n10\n0\n20\n0\n30\n0\n11\n20\n21\n0\n31\n0\n40\n0.5\n41\n0\n42\n6.283185307179586\n",
+            "0\nELLIPSE\n10\n0\n20\n0\n30\n0\n11\n20\n21\n0\n31\n0\n40\n0.5\n41\n0\n42\n1.5707963267948966\n",
+            "0\nSPLINE\n71\n2\n10\n0\n20\n0\n",
+        ));
+
+        let import = sketch.import_dxf(&text).unwrap();
+        assert_eq!(import.elements.len(), 4);
+        assert!(matches!(
+            import.warnings[..],
+            [DxfWarning::MalformedEntity { line: 111, .. }]
+        ));
+
+        let SketchElement::Spline(spline) = &sketch.elements[0] else {
+            panic!("expected a spline")
+        };
+        assert!(spline
+            .curve()
+            .point_at(0.5)
+            .abs_diff_eq(DVec3::new(0.005, 0.005, 0.), 1e-12));
+
+        let SketchElement::Spline(fitted) = &sketch.elements[1] else {
+            panic!("expected a spline")
+        };
+        // Fit points are kept as the definition of the spline
+        let SketchSpline::Fit(fit) = fitted else {
+            panic!("expected a fit spline")
+        };
+        assert_eq!(fit.points().len(), 3);
+        assert!(fit.start_tangent().is_none());
+        let closest = fit.curve().closest_point(DVec3::new(0.005, 0.005, 0.));
+        assert!(closest.abs_diff_eq(DVec3::new(0.005, 0.005, 0.), 1e-9));
+
+        let SketchElement::Ellipse(ellipse) = &sketch.elements[2] else {
+            panic!("expected an ellipse")
+        };
+        assert!((ellipse.0.major_radius - 0.02).abs() < 1e-12);
+        assert!((ellipse.0.minor_radius - 0.01).abs() < 1e-12);
+
+        // A quarter of the ellipse, from the end of the major axis to the end of the minor one
+        let SketchElement::Spline(quarter) = &sketch.elements[3] else {
+            panic!("expected a spline")
+        };
+        let quarter = quarter.curve();
+        let (t0, t1) = quarter.domain();
+        assert!(quarter
+            .point_at(t0)
+            .abs_diff_eq(DVec3::new(0.02, 0., 0.), 1e-12));
+        assert!(quarter
+            .point_at(t1)
+            .abs_diff_eq(DVec3::new(0., 0.01, 0.), 1e-12));
+        let p = quarter.point_at((t0 + t1) / 2.);
+        assert!(((p.x / 0.02).powi(2) + (p.y / 0.01).powi(2) - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_out_of_plane_and_malformed_entities() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let text = entities(concat!(
+            "0\nLINE\n10\n0\n20\n0\n30\n0\n11\n10\n21\n0\n31\n5\n",
+            "0\nELLIPSE\n10\n0\n20\n0\n30\n0\n11\n20\n21\n0\n31\n0\n40\n0.5\n210\n0\n220\n1\n230\n1\n",
+            "0\nSPLINE\n71\n1\n10\n0\n20\n0\n30\n1\n10\n10\n20\n0\n30\n1\n",
+            "0\nSPLINE\n71\n-1\n10\n0\n20\n0\n30\n0\n10\n10\n20\n0\n30\n0\n",
+            "0\nLWPOLYLINE\n38\n2\n90\n2\n10\n0\n20\n0\n10\n10\n20\n0\n",
+            "0\nLINE\n10\n0\n20\n0\n30\n0\n11\n10\n21\n0\n31\n0\n",
+        ));
+
+        let import = sketch.import_dxf(&text).unwrap();
+        assert_eq!(import.elements.len(), 1);
+        assert!(matches!(
+            import.warnings[..],
+            [
+                DxfWarning::OutOfPlane { .. },
+                DxfWarning::OutOfPlane { .. },
+                DxfWarning::OutOfPlane { .. },
+                DxfWarning::MalformedEntity { .. },
+                DxfWarning::OutOfPlane { .. },
+            ]
+        ));
+    }
+}
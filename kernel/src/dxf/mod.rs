@@ -0,0 +1,65 @@
+//! Exchange of sketches and planar profiles through DXF files, the format laser, plasma and
+//! waterjet cutting starts from.
+//!
+//! [`groups`] reads and writes the group code and value pairs a file is made of. On top of it,
+//! the entities of a file are read into a [`Sketch`](crate::Sketch) with
+//! [`Sketch::import_dxf`](crate::Sketch::import_dxf), and sketches and profiles are written
+//! with a [`DxfDrawing`]. Files are written in millimetres, and files without units are read
+//! as millimetres, since that is what cutting software assumes.
+
+mod export;
+pub mod groups;
+mod import;
+
+use std::fmt;
+
+pub use export::*;
+pub use import::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DxfError {
+    /// The text does not alternate between group codes and values at this line.
+    Syntax(usize),
+}
+
+impl fmt::Display for DxfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DxfError::Syntax(line) => write!(f, "syntax error on line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for DxfError {}
+
+/// An entity of a file that could not be read. It is left out, and the rest of the file is read
+/// as usual. Entities are identified by the line they start on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DxfWarning {
+    /// An entity of a type the kernel has no counterpart for, such as text or a block
+    /// reference.
+    UnsupportedEntity { line: usize, entity: String },
+    /// An entity whose values are missing or geometrically invalid.
+    MalformedEntity { line: usize, entity: String },
+    /// An entity drawn in a plane tilted against the plane of the drawing.
+    OutOfPlane { line: usize, entity: String },
+}
+
+impl fmt::Display for DxfWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DxfWarning::UnsupportedEntity { line, entity } => {
+                write!(f, "the {entity} on line {line} is not supported")
+            }
+            DxfWarning::MalformedEntity { line, entity } => {
+                write!(f, "the {entity} on line {line} is malformed")
+            }
+            DxfWarning::OutOfPlane { line, entity } => {
+                write!(
+                    f,
+                    "the {entity} on line {line} does not lie in the drawing plane"
+                )
+            }
+        }
+    }
+}
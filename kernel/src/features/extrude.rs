@@ -0,0 +1,443 @@
+use glam::DVec3;
+
+use crate::curve::Curve;
+use crate::nurbs::NurbsSurface;
+use crate::surface::Surface;
+use crate::{
+    profile_loops, BoundarySurface, Brep, EdgeId, Plane, Segment, SolidId, Tolerance, VertexId,
+};
+
+use super::FeatureError;
+
+/// How far an extrusion reaches along its direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExtrudeExtent {
+    /// From the profile plane forward by a distance.
+    OneSided(f64),
+    /// A total distance split evenly on both sides of the profile plane.
+    Symmetric(f64),
+    /// Separate distances forward and backward from the profile plane.
+    TwoSided { forward: f64, backward: f64 },
+}
+
+impl ExtrudeExtent {
+    /// The offsets of the two caps along the direction, the lower one first.
+    fn offsets(&self) -> (f64, f64) {
+        let (from, to) = match *self {
+            ExtrudeExtent::OneSided(distance) => (0., distance),
+            ExtrudeExtent::Symmetric(distance) => (-distance / 2., distance / 2.),
+            ExtrudeExtent::TwoSided { forward, backward } => (-backward, forward),
+        };
+
+        (from.min(to), from.max(to))
+    }
+}
+
+impl Brep {
+    /// Sweeps a planar profile along a straight direction into a closed solid. Lines become
+    /// planar side faces, arcs cylindrical ones and ellipses and splines NURBS surfaces; the
+    /// caps are planar with the profile holes as inner loops.
+    pub fn extrude(
+        &mut self,
+        profile: &BoundarySurface,
+        direction: DVec3,
+        extent: ExtrudeExtent,
+    ) -> Result<SolidId, FeatureError> {
+        let tolerance = Tolerance::current();
+        if tolerance.is_zero_length(direction.length()) {
+            return Err(FeatureError::ZeroDirection);
+        }
+        let direction = direction.normalize();
+        let normal = profile.plane.normal;
+
+        let along = direction.dot(normal);
+        if tolerance.perpendicular(direction, normal) {
+            return Err(FeatureError::ParallelDirection);
+        }
+
+        let (low, high) = extent.offsets();
+        if tolerance.is_zero_length(high - low) {
+            return Err(FeatureError::ZeroDistance);
+        }
+
+        // The cap the extrusion moves towards faces `up`
+        let up = normal * along.signum();
+        let loops = profile_loops(profile, up)?;
+
+        let has_arcs = loops.iter().flatten().any(|s| s.arc.is_some());
+        if has_arcs && !tolerance.parallel(direction, normal) {
+            return Err(FeatureError::ObliqueCurvedProfile);
+        }
+
+        let bottom = low * direction;
+        let top = high * direction;
+
+        let rings: Vec<Ring> = loops
+            .iter()
+            .map(|segments| Ring::new(self, segments, bottom, top))
+            .collect();
+
+        let mut faces = Vec::new();
+
+        let top_outer = rings[0].top_loop();
+        let top_inner: Vec<_> = rings[1..].iter().map(Ring::top_loop).collect();
+        let top_plane = Plane::new(up, profile.plane.center + top);
+        faces.push(
+            self.add_face(Surface::Plane(top_plane), false, &top_outer, &top_inner)
+                .unwrap(),
+        );
+
+        let bottom_outer = rings[0].bottom_loop();
+        let bottom_inner: Vec<_> = rings[1..].iter().map(Ring::bottom_loop).collect();
+        let bottom_plane = Plane::new(up, profile.plane.center + bottom);
+        faces.push(
+            self.add_face(
+                Surface::Plane(bottom_plane),
+                true,
+                &bottom_outer,
+                &bottom_inner,
+            )
+            .unwrap(),
+        );
+
+        for (ring, segments) in rings.iter().zip(&loops) {
+            for (i, segment) in segments.iter().enumerate() {
+                let (surface, reversed) = side_surface(segment, bottom, top, direction, up);
+                let face = self
+                    .add_face(surface, reversed, &ring.side_loop(i), &[])
+                    .unwrap();
+                faces.push(face);
+            }
+        }
+
+        let shell = self.add_shell(faces);
+        Ok(self.add_solid(vec![shell]))
+    }
+}
+
+/// The edges swept from one profile loop. Segment `i` runs from vertex `i` to vertex `i + 1`
+/// on both caps, and vertical edge `i` joins the two copies of vertex `i`.
+struct Ring {
+    bottom: Vec<EdgeId>,
+    top: Vec<EdgeId>,
+    vertical: Vec<EdgeId>,
+}
+
+impl Ring {
+    fn new(brep: &mut Brep, segments: &[Segment], bottom: DVec3, top: DVec3) -> Self {
+        let n = segments.len();
+
+        let bottom_vertices: Vec<VertexId> = segments
+            .iter()
+            .map(|s| brep.add_vertex(s.start + bottom))
+            .collect();
+        let top_vertices: Vec<VertexId> = segments
+            .iter()
+            .map(|s| brep.add_vertex(s.start + top))
+            .collect();
+
+        let mut cap_edges = |vertices: &[VertexId], offset: DVec3| -> Vec<EdgeId> {
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| {
+                    let (start, end) = (vertices[i], vertices[(i + 1) % n]);
+                    if let Some(curve) = &segment.curve {
+                        let curve = Curve::Nurbs(curve.map(|p| p + offset));
+                        return brep.add_edge(curve, start, end);
+                    }
+                    match segment.arc {
+                        None => brep.add_line_edge(start, end),
+                        Some(arc) => {
+                            let curve = Curve::circle(arc.center + offset, arc.axis, arc.radius)
+                                .expect("profile arcs have a radius");
+                            brep.add_edge(curve, start, end)
+                        }
+                    }
+                })
+                .collect()
+        };
+
+        let bottom_edges = cap_edges(&bottom_vertices, bottom);
+        let top_edges = cap_edges(&top_vertices, top);
+
+        let vertical = (0..n)
+            .map(|i| brep.add_line_edge(bottom_vertices[i], top_vertices[i]))
+            .collect();
+
+        Self {
+            bottom: bottom_edges,
+            top: top_edges,
+            vertical,
+        }
+    }
+
+    fn top_loop(&self) -> Vec<(EdgeId, bool)> {
+        self.top.iter().map(|&e| (e, false)).collect()
+    }
+
+    /// The bottom cap faces the other way, so its loops run backwards.
+    fn bottom_loop(&self) -> Vec<(EdgeId, bool)> {
+        self.bottom.iter().rev().map(|&e| (e, true)).collect()
+    }
+
+    /// Along the bottom edge, up the next vertical edge, back along the top edge and down.
+    fn side_loop(&self, i: usize) -> Vec<(EdgeId, bool)> {
+        let next = (i + 1) % self.vertical.len();
+
+        vec![
+            (self.bottom[i], false),
+            (self.vertical[next], false),
+            (self.top[i], true),
+            (self.vertical[i], true),
+        ]
+    }
+}
+
+/// The surface of the face swept by a segment and whether the face normal points against it.
+fn side_surface(
+    segment: &Segment,
+    bottom: DVec3,
+    top: DVec3,
+    direction: DVec3,
+    up: DVec3,
+) -> (Surface, bool) {
+    if let Some(curve) = &segment.curve {
+        // Ruled between the two copies of the curve, with its normal along the tangent
+        // crossed with the direction like the planes of lines
+        let rows = |p: &DVec3| vec![*p + bottom, *p + top];
+        let surface = NurbsSurface::new(
+            (curve.degree, 1),
+            curve.control_points.iter().map(rows).collect(),
+            curve.weights.iter().map(|&w| vec![w, w]).collect(),
+            (curve.knots.clone(), vec![0., 0., 1., 1.]),
+        );
+        // Cannot fail: the net is as valid as the curve
+        return (Surface::Nurbs(surface.unwrap()), false);
+    }
+    match segment.arc {
+        None => {
+            // The material is on the left of the segment, seen from above
+            let normal = (segment.end - segment.start).cross(direction);
+            let plane = Plane::new(normal, segment.start + bottom);
+            (Surface::Plane(plane), false)
+        }
+        Some(arc) => {
+            let surface = Surface::cylinder(arc.center + bottom, direction, arc.radius);
+            // Arcs turning clockwise, like holes, have the material outside them
+            (surface, arc.axis.dot(up) < 0.)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use crate::ellipse::Ellipse;
+    use crate::line::{Line, TwoPointLine};
+    use crate::surface::Surface;
+    use crate::test_util::{circle, rectangle};
+    use crate::*;
+
+    #[test]
+    fn test_extrude_rectangle() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        rectangle(&mut sketch, (0., 0.), (4., 2.));
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(region, DVec3::Z, ExtrudeExtent::OneSided(3.))
+            .unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+        assert_eq!(brep.solid_faces(solid).len(), 6);
+        assert_eq!(brep.solid_edges(solid).len(), 12);
+
+        // Every face normal points out of the box
+        let center = DVec3::new(2., 1., 1.5);
+        for face in brep.solid_faces(solid) {
+            let face = brep.face(face);
+            let Surface::Plane(plane) = &face.surface else {
+                panic!("not a plane");
+            };
+            let normal = if face.reversed {
+                -plane.normal
+            } else {
+                plane.normal
+            };
+            assert!((plane.center - center).dot(normal) > 0.);
+        }
+
+        let z: Vec<f64> = brep.vertices.iter().map(|v| v.point.z).collect();
+        assert!(z.iter().all(|&z| z == 0. || z == 3.));
+    }
+
+    #[test]
+    fn test_extrude_plate_with_hole() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        rectangle(&mut sketch, (0., 0.), (4., 2.));
+        circle(&mut sketch, (1., 1.), 0.5);
+
+        let region = sketch
+            .find_regions()
+            .into_iter()
+            .find(|r| !r.holes.is_empty())
+            .unwrap();
+
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&region, -DVec3::Z, ExtrudeExtent::Symmetric(2.))
+            .unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(1));
+        // Two caps, four walls and the bore
+        assert_eq!(brep.solid_faces(solid).len(), 7);
+
+        let bore = brep
+            .faces
+            .iter()
+            .find(|f| matches!(f.surface, Surface::Cylinder { .. }))
+            .unwrap();
+        // The bore faces the axis
+        assert!(bore.reversed);
+
+        let z: Vec<f64> = brep.vertices.iter().map(|v| v.point.z).collect();
+        assert!(z.iter().all(|&z| z == -1. || z == 1.));
+    }
+
+    #[test]
+    fn test_extrude_elliptical_hole() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        rectangle(&mut sketch, (0., 0.), (4., 2.));
+        sketch.add_element(SketchElement::Ellipse(SketchEllipse(
+            Ellipse::new(DVec3::new(2., 1., 0.), DVec3::Z, DVec3::X, 1., 0.5).unwrap(),
+        )));
+
+        let region = sketch
+            .find_regions()
+            .into_iter()
+            .find(|r| !r.holes.is_empty())
+            .unwrap();
+
+        // Free-form profiles may be extruded at a slant
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&region, DVec3::new(1., 0., 2.), ExtrudeExtent::OneSided(1.))
+            .unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(1));
+        // Two caps, four walls and the two halves of the bore
+        assert_eq!(brep.solid_faces(solid).len(), 8);
+        assert!(brep
+            .faces
+            .iter()
+            .any(|f| matches!(f.surface, Surface::Nurbs(_))));
+
+        let tolerance = TesselationTolerance::new(1e-3, 0.2);
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_eq!(mesh.open_edges(), 0);
+        // The height of the slanted solid is the distance along the normal
+        let height = 2. / 5f64.sqrt();
+        let volume = (8. - std::f64::consts::PI * 0.5) * height;
+        assert!((mesh.volume() - volume).abs() < 1e-2 * volume);
+    }
+
+    #[test]
+    fn test_extrude_spline_profile() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(SketchElement::Line(SketchLine(Line::TwoPoint(
+            TwoPointLine::new(DVec3::ZERO, DVec3::new(4., 0., 0.)),
+        ))));
+        let fit = FitSpline::new(
+            vec![
+                DVec3::new(4., 0., 0.),
+                DVec3::new(3., 2., 0.),
+                DVec3::new(1., 2., 0.),
+                DVec3::ZERO,
+            ],
+            None,
+            None,
+        )
+        .unwrap();
+        sketch.add_element(SketchElement::Spline(SketchSpline::Fit(fit)));
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(region, DVec3::Z, ExtrudeExtent::OneSided(2.))
+            .unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        // Two caps, the wall under the line and the wall under the spline
+        assert_eq!(brep.solid_faces(solid).len(), 4);
+
+        let tolerance = TesselationTolerance::new(1e-3, 0.2);
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_eq!(mesh.open_edges(), 0);
+
+        // The line along the X axis adds nothing to the area the spline sweeps
+        let BoundaryElement::BoundaryCurve(spline) = &region.boundary.elements[1] else {
+            panic!("expected the spline");
+        };
+        let (start, end) = spline.curve.domain();
+        let volume = 2. * spline.curve.swept_area(start, end).z;
+        assert!((mesh.volume() - volume).abs() < 1e-2 * volume);
+    }
+
+    #[test]
+    fn test_extrude_cylinder() {
+        let mut sketch = Sketch::new(SketchPlane::XZ);
+        circle(&mut sketch, (0., 0.), 1.);
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(
+                region,
+                DVec3::Y,
+                ExtrudeExtent::TwoSided {
+                    forward: 2.,
+                    backward: 1.,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+        assert_eq!(brep.solid_faces(solid).len(), 3);
+
+        let side = brep
+            .faces
+            .iter()
+            .find(|f| matches!(f.surface, Surface::Cylinder { .. }))
+            .unwrap();
+        assert!(!side.reversed);
+    }
+
+    #[test]
+    fn test_extrude_errors() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        circle(&mut sketch, (0., 0.), 1.);
+        let region = &sketch.find_regions()[0];
+        let mut brep = Brep::new();
+
+        assert_eq!(
+            brep.extrude(region, DVec3::X, ExtrudeExtent::OneSided(1.)),
+            Err(FeatureError::ParallelDirection)
+        );
+        assert_eq!(
+            brep.extrude(region, DVec3::new(1., 0., 1.), ExtrudeExtent::OneSided(1.)),
+            Err(FeatureError::ObliqueCurvedProfile)
+        );
+        assert_eq!(
+            brep.extrude(region, DVec3::Z, ExtrudeExtent::Symmetric(0.)),
+            Err(FeatureError::ZeroDistance)
+        );
+        assert!(brep.faces.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+//! Features that turn sketch profiles into solids.
+
+mod extrude;
+mod revolve;
+
+use std::fmt;
+
+use crate::LoopError;
+
+pub use extrude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureError {
+    /// The profile has a loop without elements.
+    EmptyProfile,
+    /// An element of the profile does not start where the previous one ends.
+    OpenProfile,
+    ZeroDirection,
+    /// The feature direction lies in the plane of the profile.
+    ParallelDirection,
+    /// The feature has no extent.
+    ZeroDistance,
+    /// Extruding arcs along a direction other than the profile normal would need elliptic
+    /// cylinders.
+    ObliqueCurvedProfile,
+    /// The axis of revolution is not a valid line.
+    InvalidAxis,
+    /// The axis of revolution does not lie in the profile plane.
+    AxisOutOfPlane,
+    /// The profile has parts on both sides of the axis of revolution.
+    ProfileCrossesAxis,
+    /// The revolution turns more than a full turn.
+    AngleTooLarge,
+    /// Revolving ellipses and splines would need free-form surfaces of revolution, which the
+    /// tesselation cannot follow across their seam.
+    RevolvedFreeFormProfile,
+}
+
+impl fmt::Display for FeatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureError::EmptyProfile => write!(f, "profile loop has no elements"),
+            FeatureError::OpenProfile => write!(f, "profile loop is not closed"),
+            FeatureError::ZeroDirection => write!(f, "direction has zero length"),
+            FeatureError::ParallelDirection => write!(f, "direction lies in the profile plane"),
+            FeatureError::ZeroDistance => write!(f, "feature has no extent"),
+            FeatureError::ObliqueCurvedProfile => {
+                write!(f, "arcs can only be extruded along the profile normal")
+            }
+            FeatureError::InvalidAxis => write!(f, "axis is not a valid line"),
+            FeatureError::AxisOutOfPlane => write!(f, "axis does not lie in the profile plane"),
+            FeatureError::ProfileCrossesAxis => write!(f, "profile crosses the axis"),
+            FeatureError::AngleTooLarge => write!(f, "angle is larger than a full turn"),
+            FeatureError::RevolvedFreeFormProfile => {
+                write!(f, "ellipses and splines cannot be revolved")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeatureError {}
+
+impl From<LoopError> for FeatureError {
+    fn from(error: LoopError) -> Self {
+        match error {
+            LoopError::Empty => FeatureError::EmptyProfile,
+            LoopError::Open => FeatureError::OpenProfile,
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::f64::consts::TAU;
+
+use glam::{DQuat, DVec3};
+
+use crate::curve::Curve;
+use crate::line::Line;
+use crate::surface::Surface;
+use crate::{
+    profile_loops, BoundarySurface, Brep, EdgeId, FaceId, Plane, Segment, SolidId, Tolerance,
+    VertexId,
+};
+
+use super::FeatureError;
+
+/// An axis of revolution through `origin` along the unit vector `direction`.
+struct Axis {
+    origin: DVec3,
+    direction: DVec3,
+}
+
+impl Axis {
+    /// The closest point on the axis.
+    fn foot(&self, p: DVec3) -> DVec3 {
+        self.origin + (p - self.origin).dot(self.direction) * self.direction
+    }
+
+    fn distance(&self, p: DVec3) -> f64 {
+        (p - self.foot(p)).length()
+    }
+
+    fn contains(&self, p: DVec3) -> bool {
+        Tolerance::current().is_zero_length(self.distance(p))
+    }
+}
+
+impl Brep {
+    /// Sweeps a planar profile around an axis in its plane into a closed solid. The profile
+    /// turns counter-clockwise about the axis direction by `angle` radians, up to a full
+    /// turn; a negative angle turns it the other way.
+    ///
+    /// Side faces are planes, cylinders, cones, spheres or tori depending on how each profile
+    /// segment lies relative to the axis. Segments on the axis produce no face. Partial
+    /// revolutions are closed by planar caps.
+    pub fn revolve(
+        &mut self,
+        profile: &BoundarySurface,
+        axis: &Line,
+        angle: f64,
+    ) -> Result<SolidId, FeatureError> {
+        let axis = axis
+            .to_parametric()
+            .map_err(|_| FeatureError::InvalidAxis)?;
+        let mut axis = Axis {
+            origin: axis.p,
+            direction: axis.v.normalize(),
+        };
+
+        let tolerance = Tolerance::current();
+        if tolerance.is_zero_angle(angle) {
+            return Err(FeatureError::ZeroDistance);
+        }
+        if angle.abs() > TAU + tolerance.angular {
+            return Err(FeatureError::AngleTooLarge);
+        }
+        if angle < 0. {
+            axis.direction = -axis.direction;
+        }
+        let angle = angle.abs();
+        let full = tolerance.is_zero_angle(TAU - angle);
+
+        let normal = profile.plane.normal;
+        if !tolerance.perpendicular(axis.direction, normal)
+            || !tolerance.is_zero_length((axis.origin - profile.plane.center).dot(normal))
+        {
+            return Err(FeatureError::AxisOutOfPlane);
+        }
+
+        // The profile starts moving along `up`, which depends on the side of the axis it is on
+        let radial = normal.cross(axis.direction);
+        let loops = profile_loops(profile, normal)?;
+        let side = profile_side(&loops, &axis, radial)?;
+        let up = normal * side;
+        let loops = profile_loops(profile, up)?;
+        if loops.iter().flatten().any(|s| s.curve.is_some()) {
+            return Err(FeatureError::RevolvedFreeFormProfile);
+        }
+
+        let rotation = DQuat::from_axis_angle(axis.direction, angle);
+        let rotate = |p: DVec3| axis.origin + rotation * (p - axis.origin);
+
+        let mut shells = Vec::new();
+        let mut faces = Vec::new();
+        let mut start_cap = Vec::new();
+        let mut end_cap = Vec::new();
+
+        for segments in &loops {
+            let n = segments.len();
+            let on_axis: Vec<bool> = segments.iter().map(|s| axis.contains(s.start)).collect();
+
+            let start: Vec<VertexId> = segments.iter().map(|s| self.add_vertex(s.start)).collect();
+            let end: Vec<VertexId> = (0..n)
+                .map(|i| {
+                    if full || on_axis[i] {
+                        start[i]
+                    } else {
+                        self.add_vertex(rotate(segments[i].start))
+                    }
+                })
+                .collect();
+
+            // Every vertex off the axis traces a circle
+            let circles: Vec<Option<EdgeId>> = (0..n)
+                .map(|i| {
+                    let p = segments[i].start;
+                    (!on_axis[i]).then(|| {
+                        let curve = Curve::circle(axis.foot(p), axis.direction, axis.distance(p))
+                            .expect("vertices off the axis trace circles");
+                        self.add_edge(curve, start[i], end[i])
+                    })
+                })
+                .collect();
+
+            let mut ring_faces = Vec::new();
+            let mut start_edges = Vec::new();
+            let mut end_edges = Vec::new();
+
+            for (i, segment) in segments.iter().enumerate() {
+                let next = (i + 1) % n;
+                let degenerate = segment.is_straight() && on_axis[i] && on_axis[next];
+
+                if full && degenerate {
+                    continue;
+                }
+
+                let first = self.add_segment_edge(segment, start[i], start[next], |p| p);
+                let last = if full || degenerate {
+                    first
+                } else {
+                    self.add_segment_edge(segment, end[i], end[next], rotate)
+                };
+                start_edges.push(first);
+                end_edges.push(last);
+
+                if degenerate {
+                    continue;
+                }
+
+                let mut side_loop = vec![(first, false)];
+                side_loop.extend(circles[next].map(|e| (e, false)));
+                side_loop.push((last, true));
+                side_loop.extend(circles[i].map(|e| (e, true)));
+
+                let surface = side_surface(segment, &axis);
+                // The face normal points right of the segment when looking along `up`
+                let outward = segment.tangent_at_middle().cross(up);
+                let reversed = surface.normal_at(segment.middle()).dot(outward) < 0.;
+
+                ring_faces.push(self.add_face(surface, reversed, &side_loop, &[]).unwrap());
+            }
+
+            start_cap.push(start_edges);
+            end_cap.push(end_edges);
+
+            if full {
+                shells.push(ring_faces);
+            } else {
+                faces.extend(ring_faces);
+            }
+        }
+
+        if !full {
+            let center = profile.plane.center;
+
+            let outer: Vec<_> = end_cap[0].iter().map(|&e| (e, false)).collect();
+            let inner: Vec<Vec<_>> = end_cap[1..]
+                .iter()
+                .map(|l| l.iter().map(|&e| (e, false)).collect())
+                .collect();
+            let plane = Plane::new(rotation * up, rotate(center));
+            faces.push(
+                self.add_face(Surface::Plane(plane), false, &outer, &inner)
+                    .unwrap(),
+            );
+
+            let backwards = |edges: &Vec<EdgeId>| edges.iter().rev().map(|&e| (e, true)).collect();
+            let outer: Vec<_> = backwards(&start_cap[0]);
+            let inner: Vec<Vec<_>> = start_cap[1..].iter().map(backwards).collect();
+            let plane = Plane::new(up, center);
+            faces.push(
+                self.add_face(Surface::Plane(plane), true, &outer, &inner)
+                    .unwrap(),
+            );
+
+            shells.push(faces);
+        }
+
+        let shells = shells
+            .into_iter()
+            .map(|faces: Vec<FaceId>| self.add_shell(faces))
+            .collect();
+        Ok(self.add_solid(shells))
+    }
+
+    /// Adds the edge of a profile segment moved by the rigid `transform`.
+    fn add_segment_edge(
+        &mut self,
+        segment: &Segment,
+        start: VertexId,
+        end: VertexId,
+        transform: impl Fn(DVec3) -> DVec3,
+    ) -> EdgeId {
+        match segment.arc {
+            None => self.add_line_edge(start, end),
+            Some(arc) => {
+                let normal = transform(arc.center + arc.axis) - transform(arc.center);
+                let curve = Curve::circle(transform(arc.center), normal, arc.radius)
+                    .expect("profile arcs have a radius");
+                self.add_edge(curve, start, end)
+            }
+        }
+    }
+}
+
+/// Which side of the axis the profile lies on along `radial`: `1.` or `-1.`.
+fn profile_side(loops: &[Vec<Segment>], axis: &Axis, radial: DVec3) -> Result<f64, FeatureError> {
+    let tolerance = Tolerance::current().linear;
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+
+    for segment in loops.iter().flatten() {
+        for i in 0..=16 {
+            let d = (segment.point(i as f64 / 16.) - axis.origin).dot(radial);
+            min = min.min(d);
+            max = max.max(d);
+        }
+    }
+
+    if min >= -tolerance {
+        Ok(1.)
+    } else if max <= tolerance {
+        Ok(-1.)
+    } else {
+        Err(FeatureError::ProfileCrossesAxis)
+    }
+}
+
+/// The surface swept by a segment turning around the axis.
+fn side_surface(segment: &Segment, axis: &Axis) -> Surface {
+    let a = axis.direction;
+
+    match segment.arc {
+        None => {
+            let d = (segment.end - segment.start).normalize();
+            let cos = d.dot(a);
+            let tolerance = Tolerance::current();
+
+            if tolerance.parallel(d, a) {
+                let foot = axis.foot(segment.start);
+                Surface::cylinder(foot, a, axis.distance(segment.start))
+            } else if tolerance.perpendicular(d, a) {
+                Surface::Plane(Plane::new(a, segment.start))
+            } else {
+                // The apex is where the line meets the axis
+                let (r0, r1) = (axis.distance(segment.start), axis.distance(segment.end));
+                let t = r0 / (r0 - r1);
+                let apex = segment.start + t * (segment.end - segment.start);
+                Surface::cone(apex, a, cos.abs().acos())
+            }
+        }
+        Some(arc) => {
+            if axis.contains(arc.center) {
+                Surface::Sphere {
+                    center: arc.center,
+                    radius: arc.radius,
+                }
+            } else {
+                let center = axis.foot(arc.center);
+                Surface::torus(center, a, axis.distance(arc.center), arc.radius)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_1_SQRT_2, PI, TAU};
+
+    use glam::DVec3;
+
+    use crate::arc::Arc;
+    use crate::line::{Line, ParametricLine};
+    use crate::surface::Surface;
+    use crate::test_util::{line, polygon};
+    use crate::*;
+
+    fn y_axis() -> Line {
+        Line::Parametric(ParametricLine::new(DVec3::ZERO, DVec3::Y))
+    }
+
+    fn surface_kinds(brep: &Brep) -> Vec<&'static str> {
+        let mut kinds: Vec<_> = brep
+            .faces
+            .iter()
+            .map(|f| match f.surface {
+                Surface::Plane(_) => "plane",
+                Surface::Cylinder { .. } => "cylinder",
+                Surface::Cone { .. } => "cone",
+                Surface::Sphere { .. } => "sphere",
+                Surface::Torus { .. } => "torus",
+                Surface::Nurbs(_) => "nurbs",
+                Surface::Transformed { .. } => "transformed",
+            })
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    /// Checks that the normals of faces point away from `inside`, a point in the material.
+    fn assert_outward(brep: &Brep, inside: DVec3) {
+        for face in &brep.faces {
+            let p = brep
+                .vertex(brep.coedge_start(brep.get_loop(face.outer).first))
+                .point
+                .0;
+            let mut normal = face.surface.normal_at(p);
+            if face.reversed {
+                normal = -normal;
+            }
+            assert!((p - inside).dot(normal) > 0., "{face:?}");
+        }
+    }
+
+    #[test]
+    fn test_revolve_cylinder_with_cone() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        // A cylinder with a conical tip, with one side on the axis
+        let lines = polygon(&mut sketch, &[(0., 0.), (1., 0.), (1., 2.), (0., 3.)]);
+        let region = &sketch.find_regions()[0];
+
+        let Some(SketchElement::Line(axis)) = sketch.element(lines[3]) else {
+            panic!("not a line");
+        };
+
+        let mut brep = Brep::new();
+        let solid = brep.revolve(region, &axis.0, TAU).unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+        assert_eq!(surface_kinds(&brep), vec!["cone", "cylinder", "plane"]);
+        assert_outward(&brep, DVec3::new(0., 1., 0.));
+    }
+
+    #[test]
+    fn test_revolve_partial() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        polygon(&mut sketch, &[(1., 0.), (2., 0.), (2., 1.), (1., 1.)]);
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep.revolve(region, &y_axis(), -PI / 2.).unwrap();
+
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+        assert_eq!(
+            surface_kinds(&brep),
+            vec!["cylinder", "cylinder", "plane", "plane", "plane", "plane"]
+        );
+
+        // Turning clockwise about Y carries X towards Z
+        let end = brep
+            .vertices
+            .iter()
+            .any(|v| v.point.0.abs_diff_eq(DVec3::new(0., 0., 2.), 1e-5));
+        assert!(end);
+        let middle = DVec3::new(1.5 * FRAC_1_SQRT_2, 0.5, 1.5 * FRAC_1_SQRT_2);
+        assert_outward(&brep, middle);
+    }
+
+    #[test]
+    fn test_revolve_sphere_and_torus() {
+        let arc = |center: DVec3, start: DVec3, end: DVec3| {
+            SketchElement::Arc(SketchArc(Arc::new(center, DVec3::Z, start, end).unwrap()))
+        };
+
+        // A half disc against the axis makes a sphere
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(arc(DVec3::ZERO, -DVec3::Y, DVec3::Y));
+        line(&mut sketch, (0., 1.), (0., -1.));
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep.revolve(region, &y_axis(), TAU).unwrap();
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(0));
+        assert_eq!(surface_kinds(&brep), vec!["sphere"]);
+        assert_outward(&brep, DVec3::ZERO);
+
+        // A disc away from the axis makes a torus
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let start = DVec3::new(4., 0., 0.);
+        sketch.add_element(arc(DVec3::new(3., 0., 0.), start, start));
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep.revolve(region, &y_axis(), TAU).unwrap();
+        assert_eq!(brep.validate(), Ok(()));
+        assert_eq!(brep.genus(solid), Some(1));
+        assert_eq!(surface_kinds(&brep), vec!["torus"]);
+        assert!(!brep.faces[0].reversed);
+    }
+
+    #[test]
+    fn test_revolve_errors() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        polygon(&mut sketch, &[(-1., 0.), (1., 0.), (1., 1.), (-1., 1.)]);
+        let region = &sketch.find_regions()[0];
+        let mut brep = Brep::new();
+
+        assert_eq!(
+            brep.revolve(region, &y_axis(), PI),
+            Err(FeatureError::ProfileCrossesAxis)
+        );
+
+        let out_of_plane = Line::Parametric(ParametricLine::new(DVec3::Z, DVec3::Y));
+        assert_eq!(
+            brep.revolve(region, &out_of_plane, PI),
+            Err(FeatureError::AxisOutOfPlane)
+        );
+        assert_eq!(
+            brep.revolve(region, &y_axis(), 7.),
+            Err(FeatureError::AngleTooLarge)
+        );
+    }
+}
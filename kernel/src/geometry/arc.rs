@@ -1,50 +1,170 @@
-use glam::{Mat4, Vec3};
+use std::f64::consts::TAU;
+use std::fmt;
+
+use glam::{DQuat, DVec3};
 
 use crate::line::{Line, TwoPointLine};
-use crate::PolyLine;
+use crate::nurbs::NurbsCurve;
+use crate::{TesselationTolerance, Tolerance};
 
-pub struct Arc {
-    pub radius: f32,
-    pub start: Vec3,
-    pub end: Vec3,
-    pub center: Vec3,
-    pub direction: ArcDirection,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArcError {
+    /// The start point coincides with the center.
+    ZeroRadius,
+    /// The normal has zero length.
+    ZeroNormal,
+    /// The start or the end lies off the plane through the center at right angles to the
+    /// normal.
+    NotPerpendicular,
+    /// The end is not as far from the center as the start.
+    EndOffCircle,
+}
+
+impl fmt::Display for ArcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArcError::ZeroRadius => write!(f, "arc start coincides with its center"),
+            ArcError::ZeroNormal => write!(f, "arc normal has zero length"),
+            ArcError::NotPerpendicular => {
+                write!(f, "arc start or end is not perpendicular to its normal")
+            }
+            ArcError::EndOffCircle => write!(f, "arc end is not at the radius of its start"),
+        }
+    }
 }
 
-pub enum ArcDirection {
-    CW,
-    CCW,
+impl std::error::Error for ArcError {}
+
+/// A circular arc running counter-clockwise around `normal` from `start` to `end`. Coinciding
+/// start and end points make a full circle.
+///
+/// Arcs are evaluated by the angle turned from `start`, which runs from `0` at the start to
+/// [`Arc::sweep`] at the end.
+#[derive(Debug, Clone)]
+pub struct Arc {
+    pub center: DVec3,
+    pub normal: DVec3,
+    pub radius: f64,
+    pub start: DVec3,
+    pub end: DVec3,
 }
 
 impl Arc {
-    // Adapted from http://slabode.exofire.net/circle_draw.shtml
-    pub fn to_lines(&self, segments: u32) -> Vec<Line> {
-        let start = self.start - self.center;
-        let end = self.end - self.center;
+    /// An arc around `center` from `start` to `end`, with the radius given by the distance of
+    /// `start` from the center. Both points must lie in the plane of the arc, and the end on
+    /// its circle, to within the current [`Tolerance`].
+    pub fn new(center: DVec3, normal: DVec3, start: DVec3, end: DVec3) -> Result<Self, ArcError> {
+        let tolerance = Tolerance::current();
+        let normal = normal.try_normalize().ok_or(ArcError::ZeroNormal)?;
+        let radius = start.distance(center);
+        if tolerance.is_zero_length(radius) {
+            return Err(ArcError::ZeroRadius);
+        }
+        if !tolerance.is_zero_length((start - center).dot(normal))
+            || !tolerance.is_zero_length((end - center).dot(normal))
+        {
+            return Err(ArcError::NotPerpendicular);
+        }
+        if !tolerance.is_zero_length(end.distance(center) - radius) {
+            return Err(ArcError::EndOffCircle);
+        }
+
+        Ok(Self {
+            center,
+            normal,
+            radius,
+            start,
+            end,
+        })
+    }
+
+    /// An arc around `center` from `start`, turning through `sweep` radians.
+    pub fn from_sweep(
+        center: DVec3,
+        normal: DVec3,
+        start: DVec3,
+        sweep: f64,
+    ) -> Result<Self, ArcError> {
+        let normal = normal.try_normalize().ok_or(ArcError::ZeroNormal)?;
+        let end = center + DQuat::from_axis_angle(normal, sweep) * (start - center);
+        Self::new(center, normal, start, end)
+    }
 
-        let axis = (start - self.center).cross(end - self.center).normalize();
+    /// The unit vectors from the center towards the start and a quarter turn further.
+    fn basis(&self) -> (DVec3, DVec3) {
+        let u = (self.start - self.center).normalize();
+        (u, self.normal.cross(u))
+    }
 
-        let s = match self.direction {
-            ArcDirection::CW => segments as f32,
-            ArcDirection::CCW => -(segments as f32),
-        };
+    /// The angle from the start to the end, in `(0, 2π]`.
+    pub fn sweep(&self) -> f64 {
+        let (u, v) = self.basis();
+        let e = self.end - self.center;
+        let angle = e.dot(v).atan2(e.dot(u)).rem_euclid(TAU);
 
-        let arc_angle = start.angle_between(end);
+        if Tolerance::current().is_zero_length(angle * self.radius) {
+            TAU
+        } else {
+            angle
+        }
+    }
 
-        let angle = arc_angle / s;
+    pub fn is_full_circle(&self) -> bool {
+        self.sweep() == TAU
+    }
 
-        let rotation_matrix = Mat4::from_axis_angle(axis, angle);
+    /// The point `angle` radians along the arc from its start.
+    pub fn point_at(&self, angle: f64) -> DVec3 {
+        let (u, v) = self.basis();
+        self.center + self.radius * (angle.cos() * u + angle.sin() * v)
+    }
 
-        let mut out = Vec::new();
-        let mut p = self.start;
+    /// The unit direction of travel `angle` radians along the arc from its start.
+    pub fn tangent_at(&self, angle: f64) -> DVec3 {
+        let (u, v) = self.basis();
+        -angle.sin() * u + angle.cos() * v
+    }
 
-        for _ in 0..segments {
-            let a = p;
-            let b = rotation_matrix.transform_vector3(p);
+    pub fn midpoint(&self) -> DVec3 {
+        self.point_at(self.sweep() / 2.)
+    }
 
-            out.push(Line::TwoPoint(TwoPointLine::new(a, b)));
+    pub fn length(&self) -> f64 {
+        self.radius * self.sweep()
+    }
 
-            p = b;
+    /// The same arc running the other way.
+    pub fn reversed(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            start: self.end,
+            end: self.start,
+            ..self.clone()
+        }
+    }
+
+    /// The exact NURBS form of the arc, with parameters from `0` at the start to `1` at the
+    /// end.
+    pub fn to_nurbs(&self) -> NurbsCurve {
+        let (u, v) = self.basis();
+        NurbsCurve::circular(self.center, u, v, self.radius, self.sweep())
+    }
+
+    /// The arc as straight lines within `tolerance`, from the start to the end.
+    pub fn to_lines(&self, tolerance: &TesselationTolerance) -> Vec<Line> {
+        let sweep = self.sweep();
+        let segments = tolerance.arc_segments(self.radius, sweep);
+
+        let mut out = Vec::with_capacity(segments);
+        let mut a = self.start;
+        for i in 1..=segments {
+            let b = if i == segments {
+                self.end
+            } else {
+                self.point_at(sweep * i as f64 / segments as f64)
+            };
+            out.push(Line::TwoPoint(TwoPointLine::new(a, b)));
+            a = b;
         }
 
         out
@@ -53,20 +173,133 @@ impl Arc {
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
     use super::*;
 
+    fn endpoints(line: &Line) -> (DVec3, DVec3) {
+        let tpl = line.to_two_point_line().unwrap();
+        (tpl.a.0, tpl.b.0)
+    }
+
     #[test]
-    fn test_to_polyline() {
-        let arc = Arc {
-            radius: 5.,
-            start: Vec3::new(5., 0., 0.),
-            end: Vec3::new(0., 5., 0.),
-            center: Vec3::new(0., 0., 0.),
-            direction: ArcDirection::CW,
-        };
-
-        let polyline = arc.to_polyline(6);
-
-        assert_eq!(polyline.lines.len(), 6);
+    fn test_quarter_arc_off_origin() {
+        let center = DVec3::new(2., 3., 1.);
+        let arc = Arc::new(
+            center,
+            DVec3::Z,
+            center + DVec3::X * 5.,
+            center + DVec3::Y * 5.,
+        )
+        .unwrap();
+
+        assert_eq!(arc.radius, 5.);
+        assert!((arc.sweep() - FRAC_PI_2).abs() < 1e-6);
+        assert!((arc.length() - 2.5 * PI).abs() < 1e-5);
+
+        let middle = center + DVec3::new(1., 1., 0.).normalize() * 5.;
+        assert!(arc.midpoint().abs_diff_eq(middle, 1e-5));
+        assert!(arc.point_at(arc.sweep()).abs_diff_eq(arc.end, 1e-5));
+        assert!(arc.tangent_at(0.).abs_diff_eq(DVec3::Y, 1e-6));
+        assert!(arc.tangent_at(FRAC_PI_2).abs_diff_eq(-DVec3::X, 1e-6));
+
+        let lines = arc.to_lines(&TesselationTolerance::new(1., PI / 12.));
+        assert_eq!(lines.len(), 6);
+        let mut previous = arc.start;
+        for line in &lines {
+            let (a, b) = endpoints(line);
+            assert_eq!(a, previous);
+            // Every vertex lies on the arc, not on a circle around the origin
+            assert!((b.distance(center) - 5.).abs() < 1e-5);
+            previous = b;
+        }
+        assert_eq!(previous, arc.end);
+    }
+
+    #[test]
+    fn test_reflex_arc() {
+        // Clockwise around +Z is counter-clockwise around -Z
+        let center = DVec3::new(-1., 0., 0.);
+        let arc = Arc::new(
+            center,
+            -DVec3::Z,
+            DVec3::new(0., 0., 0.),
+            DVec3::new(-1., 1., 0.),
+        )
+        .unwrap();
+
+        assert!((arc.sweep() - 3. * FRAC_PI_2).abs() < 1e-6);
+        let middle = center + DVec3::new(-1., -1., 0.).normalize();
+        assert!(arc.midpoint().abs_diff_eq(middle, 1e-5));
+        assert!(arc.tangent_at(0.).abs_diff_eq(-DVec3::Y, 1e-6));
+
+        let reversed = arc.reversed();
+        assert!((reversed.sweep() - arc.sweep()).abs() < 1e-6);
+        assert!(reversed.midpoint().abs_diff_eq(arc.midpoint(), 1e-5));
+
+        // The short way round from the same points
+        let minor = Arc::new(center, DVec3::Z, arc.start, arc.end).unwrap();
+        assert!((minor.sweep() - FRAC_PI_2).abs() < 1e-6);
+        assert!((arc.length() + minor.length() - TAU).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_full_circle() {
+        let center = DVec3::new(0., 1., 2.);
+        let start = center + DVec3::Z * 2.;
+        let arc = Arc::new(center, DVec3::X, start, start).unwrap();
+
+        assert!(arc.is_full_circle());
+        assert!((arc.length() - 4. * PI).abs() < 1e-5);
+        assert!(arc.point_at(PI).abs_diff_eq(center - DVec3::Z * 2., 1e-5));
+
+        let swept = Arc::from_sweep(center, DVec3::X, start, PI).unwrap();
+        assert!(swept.end.abs_diff_eq(center - DVec3::Z * 2., 1e-5));
+        assert!(!swept.is_full_circle());
+    }
+
+    #[test]
+    fn test_degenerate_arcs() {
+        let center = DVec3::new(1., 2., 3.);
+        assert_eq!(
+            Arc::new(center, DVec3::Z, center, center + DVec3::X).unwrap_err(),
+            ArcError::ZeroRadius
+        );
+        assert_eq!(
+            Arc::new(center, DVec3::ZERO, center + DVec3::X, center + DVec3::Y).unwrap_err(),
+            ArcError::ZeroNormal
+        );
+        assert_eq!(
+            Arc::from_sweep(center, DVec3::ZERO, center + DVec3::X, PI).unwrap_err(),
+            ArcError::ZeroNormal
+        );
+    }
+
+    #[test]
+    fn test_start_not_perpendicular() {
+        let start = DVec3::new(1., 0., 1.);
+        let end = DVec3::new(-1., 0., 1.);
+        assert_eq!(
+            Arc::new(DVec3::ZERO, DVec3::Z, start, end).unwrap_err(),
+            ArcError::NotPerpendicular
+        );
+        assert_eq!(
+            Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X, DVec3::Z).unwrap_err(),
+            ArcError::NotPerpendicular
+        );
+    }
+
+    #[test]
+    fn test_end_off_circle() {
+        assert_eq!(
+            Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X, 2. * DVec3::Y).unwrap_err(),
+            ArcError::EndOffCircle
+        );
+
+        let tolerance = Tolerance::new(1e-3, 1e-6, 1e-9);
+        let end = DVec3::Y * (1. + 1e-4);
+        assert!(tolerance
+            .with(|| Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X, end))
+            .is_ok());
     }
 }
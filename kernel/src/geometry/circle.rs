@@ -0,0 +1,196 @@
+use std::f64::consts::TAU;
+use std::fmt;
+
+use glam::DVec3;
+
+use crate::line::{Line, TwoPointLine};
+use crate::nurbs::NurbsCurve;
+use crate::{Plane, TesselationTolerance, Tolerance};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircleError {
+    /// The normal has zero length.
+    ZeroNormal,
+    /// The x axis has no part across the normal.
+    AxisAlongNormal,
+    /// The radius is not longer than the linear tolerance.
+    NonPositiveRadius,
+}
+
+impl fmt::Display for CircleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircleError::ZeroNormal => write!(f, "circle normal has zero length"),
+            CircleError::AxisAlongNormal => write!(f, "circle x axis is parallel to its normal"),
+            CircleError::NonPositiveRadius => write!(f, "circle radius is not positive"),
+        }
+    }
+}
+
+impl std::error::Error for CircleError {}
+
+/// A circle around `center` in the plane perpendicular to `normal`. The parameter is the angle
+/// counter-clockwise around the normal from `x_axis`.
+#[derive(Debug, Clone)]
+pub struct Circle {
+    pub center: DVec3,
+    pub normal: DVec3,
+    /// The unit direction from the center to the point at parameter `0`.
+    pub x_axis: DVec3,
+    pub radius: f64,
+}
+
+impl Circle {
+    /// A circle starting on the x axis of the plane through `center` (see [`Plane::x_axis`]).
+    pub fn new(center: DVec3, normal: DVec3, radius: f64) -> Result<Self, CircleError> {
+        let normal = normal.try_normalize().ok_or(CircleError::ZeroNormal)?;
+        Self::with_axis(center, normal, Plane::new(normal, center).x_axis, radius)
+    }
+
+    /// A circle starting in the direction of `x_axis`, projected onto the plane of the circle.
+    pub fn with_axis(
+        center: DVec3,
+        normal: DVec3,
+        x_axis: DVec3,
+        radius: f64,
+    ) -> Result<Self, CircleError> {
+        let tolerance = Tolerance::current();
+        let normal = normal.try_normalize().ok_or(CircleError::ZeroNormal)?;
+        if tolerance.parallel(normal, x_axis) {
+            return Err(CircleError::AxisAlongNormal);
+        }
+        if radius <= tolerance.linear || radius.is_nan() {
+            return Err(CircleError::NonPositiveRadius);
+        }
+
+        Ok(Self {
+            center,
+            normal,
+            x_axis: (x_axis - x_axis.dot(normal) * normal).normalize(),
+            radius,
+        })
+    }
+
+    pub fn y_axis(&self) -> DVec3 {
+        self.normal.cross(self.x_axis)
+    }
+
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        self.center + self.radius * (t.cos() * self.x_axis + t.sin() * self.y_axis())
+    }
+
+    /// The first derivative with respect to the parameter.
+    pub fn derivative_at(&self, t: f64) -> DVec3 {
+        self.radius * (-t.sin() * self.x_axis + t.cos() * self.y_axis())
+    }
+
+    /// The second derivative with respect to the parameter.
+    pub fn second_derivative_at(&self, t: f64) -> DVec3 {
+        -self.radius * (t.cos() * self.x_axis + t.sin() * self.y_axis())
+    }
+
+    /// The parameter in `[0, 2π)` of the point on the circle closest to `p`. Points on the
+    /// axis are equally close to every point and give `0`.
+    pub fn parameter_of(&self, p: DVec3) -> f64 {
+        let d = p - self.center;
+        d.dot(self.y_axis())
+            .atan2(d.dot(self.x_axis))
+            .rem_euclid(TAU)
+    }
+
+    pub fn closest_point(&self, p: DVec3) -> DVec3 {
+        self.point_at(self.parameter_of(p))
+    }
+
+    pub fn length(&self) -> f64 {
+        TAU * self.radius
+    }
+
+    /// The exact NURBS form of the circle, with parameters from `0` to `1` once around it,
+    /// starting along the x axis.
+    pub fn to_nurbs(&self) -> NurbsCurve {
+        NurbsCurve::circular(self.center, self.x_axis, self.y_axis(), self.radius, TAU)
+    }
+
+    /// The circle as a closed ring of straight lines within `tolerance`, starting at parameter
+    /// `0`.
+    pub fn to_lines(&self, tolerance: &TesselationTolerance) -> Vec<Line> {
+        let segments = tolerance.arc_segments(self.radius, TAU).max(3);
+        let points: Vec<DVec3> = (0..segments)
+            .map(|i| self.point_at(TAU * i as f64 / segments as f64))
+            .collect();
+
+        (0..segments)
+            .map(|i| Line::TwoPoint(TwoPointLine::new(points[i], points[(i + 1) % segments])))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+
+    #[test]
+    fn test_evaluation_and_derivatives() {
+        let center = DVec3::new(1., 2., 3.);
+        let circle = Circle::with_axis(center, DVec3::Y, DVec3::new(0., 1., 1.), 2.).unwrap();
+
+        assert_eq!(circle.x_axis, DVec3::Z);
+        assert!(circle
+            .point_at(0.)
+            .abs_diff_eq(center + DVec3::Z * 2., 1e-6));
+        // Counter-clockwise around +Y turns from +Z towards +X
+        assert!(circle
+            .point_at(FRAC_PI_2)
+            .abs_diff_eq(center + DVec3::X * 2., 1e-6));
+
+        let h = 1e-3;
+        for t in [0., 1., 2.5, 4.] {
+            let p = circle.point_at(t);
+            assert!((p.distance(center) - 2.).abs() < 1e-5);
+            assert!((circle.parameter_of(p) - t).abs() < 1e-5);
+
+            let d = (circle.point_at(t + h) - circle.point_at(t - h)) / (2. * h);
+            assert!(circle.derivative_at(t).abs_diff_eq(d, 1e-2));
+            let dd = (circle.derivative_at(t + h) - circle.derivative_at(t - h)) / (2. * h);
+            assert!(circle.second_derivative_at(t).abs_diff_eq(dd, 1e-2));
+        }
+    }
+
+    #[test]
+    fn test_closest_point_and_length() {
+        let circle = Circle::new(DVec3::ZERO, DVec3::Z, 3.).unwrap();
+
+        let p = circle.closest_point(DVec3::new(-5., 0., 7.));
+        assert!(p.abs_diff_eq(DVec3::new(-3., 0., 0.), 1e-5));
+        assert!((circle.parameter_of(p) - PI).abs() < 1e-5);
+        assert!((circle.length() - 6. * PI).abs() < 1e-5);
+
+        let lines = circle.to_lines(&TesselationTolerance::new(1., PI / 4.));
+        assert_eq!(lines.len(), 8);
+    }
+
+    #[test]
+    fn test_degenerate_circles() {
+        assert_eq!(
+            Circle::new(DVec3::ZERO, DVec3::ZERO, 1.).unwrap_err(),
+            CircleError::ZeroNormal
+        );
+        assert_eq!(
+            Circle::with_axis(DVec3::ZERO, DVec3::Z, -2. * DVec3::Z, 1.).unwrap_err(),
+            CircleError::AxisAlongNormal
+        );
+        assert_eq!(
+            Circle::with_axis(DVec3::ZERO, DVec3::Z, DVec3::ZERO, 1.).unwrap_err(),
+            CircleError::AxisAlongNormal
+        );
+        for radius in [0., -1., f64::NAN] {
+            assert_eq!(
+                Circle::new(DVec3::ZERO, DVec3::Z, radius).unwrap_err(),
+                CircleError::NonPositiveRadius
+            );
+        }
+    }
+}
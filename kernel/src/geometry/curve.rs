@@ -0,0 +1,94 @@
+use glam::DVec3;
+
+use crate::circle::{Circle, CircleError};
+use crate::ellipse::Ellipse;
+use crate::line::ParametricLine;
+use crate::nurbs::NurbsCurve;
+
+/// The unbounded geometry of a B-rep edge. Edges are trimmed by their vertices.
+#[derive(Debug, Clone)]
+pub enum Curve {
+    Line(ParametricLine),
+    Circle(Circle),
+    Ellipse(Ellipse),
+    Nurbs(NurbsCurve),
+}
+
+impl Curve {
+    pub fn line(a: DVec3, b: DVec3) -> Self {
+        Curve::Line(ParametricLine::new(a, b - a))
+    }
+
+    pub fn circle(center: DVec3, normal: DVec3, radius: f64) -> Result<Self, CircleError> {
+        Circle::new(center, normal, radius).map(Curve::Circle)
+    }
+
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        match self {
+            Curve::Line(line) => line.point_at(t),
+            Curve::Circle(circle) => circle.point_at(t),
+            Curve::Ellipse(ellipse) => ellipse.point_at(t),
+            Curve::Nurbs(curve) => curve.point_at(t),
+        }
+    }
+
+    /// The derivative with respect to the parameter.
+    pub fn tangent_at(&self, t: f64) -> DVec3 {
+        match self {
+            Curve::Line(line) => line.v,
+            Curve::Circle(circle) => circle.derivative_at(t),
+            Curve::Ellipse(ellipse) => ellipse.derivative_at(t),
+            Curve::Nurbs(curve) => curve.derivative_at(t),
+        }
+    }
+
+    /// The parameter of the point on the curve closest to `p`. Circle and ellipse parameters
+    /// are in `[0, 2π)`.
+    pub fn parameter_of(&self, p: DVec3) -> f64 {
+        match self {
+            Curve::Line(line) => (p - line.p).dot(line.v) / line.v.length_squared(),
+            Curve::Circle(circle) => circle.parameter_of(p),
+            Curve::Ellipse(ellipse) => ellipse.parameter_of(p),
+            Curve::Nurbs(curve) => curve.parameter_of(p),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Curve::Line(_) => false,
+            Curve::Circle(_) | Curve::Ellipse(_) => true,
+            Curve::Nurbs(curve) => curve.is_closed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circle_evaluation() {
+        let circle = Curve::circle(DVec3::new(1., 2., 3.), DVec3::Y, 2.).unwrap();
+
+        for t in [0., 1., 2.5, 4.] {
+            let p = circle.point_at(t);
+            assert!(((p - DVec3::new(1., 2., 3.)).length() - 2.).abs() < 1e-5);
+            assert!((p.y - 2.).abs() < 1e-6);
+            assert!((circle.parameter_of(p) - t).abs() < 1e-5);
+            assert!(circle.tangent_at(t).dot(p - DVec3::new(1., 2., 3.)).abs() < 1e-5);
+        }
+
+        // Counter-clockwise about the normal
+        let t0 = circle.tangent_at(0.);
+        let r0 = circle.point_at(0.) - DVec3::new(1., 2., 3.);
+        assert!(r0.cross(t0).dot(DVec3::Y) > 0.);
+    }
+
+    #[test]
+    fn test_line_parameter() {
+        let line = Curve::line(DVec3::ZERO, DVec3::new(2., 0., 0.));
+
+        assert_eq!(line.parameter_of(DVec3::new(1., 1., 0.)), 0.5);
+        assert_eq!(line.point_at(1.), DVec3::new(2., 0., 0.));
+    }
+}
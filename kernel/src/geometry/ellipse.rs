@@ -0,0 +1,303 @@
+use std::f64::consts::TAU;
+use std::fmt;
+
+use glam::DVec3;
+
+use crate::line::{Line, TwoPointLine};
+use crate::nurbs::NurbsCurve;
+use crate::{TesselationTolerance, Tolerance};
+
+/// The number of pieces the perimeter integral of an ellipse is split into.
+const LENGTH_STEPS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EllipseError {
+    /// The normal has zero length.
+    ZeroNormal,
+    /// The major axis has no part across the normal.
+    AxisAlongNormal,
+    /// One of the radii is not longer than the linear tolerance.
+    NonPositiveRadius,
+}
+
+impl fmt::Display for EllipseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EllipseError::ZeroNormal => write!(f, "ellipse normal has zero length"),
+            EllipseError::AxisAlongNormal => {
+                write!(f, "ellipse major axis is parallel to its normal")
+            }
+            EllipseError::NonPositiveRadius => write!(f, "ellipse radius is not positive"),
+        }
+    }
+}
+
+impl std::error::Error for EllipseError {}
+
+/// An ellipse around `center` in the plane perpendicular to `normal`, with `major_radius`
+/// along `x_axis` and `minor_radius` along `normal × x_axis`. The parameter is the eccentric
+/// angle counter-clockwise around the normal from `x_axis`.
+#[derive(Debug, Clone)]
+pub struct Ellipse {
+    pub center: DVec3,
+    pub normal: DVec3,
+    /// The unit direction of the major axis.
+    pub x_axis: DVec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Ellipse {
+    /// An ellipse with its major axis along `x_axis`, projected onto the plane of the ellipse.
+    pub fn new(
+        center: DVec3,
+        normal: DVec3,
+        x_axis: DVec3,
+        major_radius: f64,
+        minor_radius: f64,
+    ) -> Result<Self, EllipseError> {
+        let tolerance = Tolerance::current();
+        let normal = normal.try_normalize().ok_or(EllipseError::ZeroNormal)?;
+        if tolerance.parallel(normal, x_axis) {
+            return Err(EllipseError::AxisAlongNormal);
+        }
+        if [major_radius, minor_radius]
+            .iter()
+            .any(|&radius| radius <= tolerance.linear || radius.is_nan())
+        {
+            return Err(EllipseError::NonPositiveRadius);
+        }
+
+        Ok(Self {
+            center,
+            normal,
+            x_axis: (x_axis - x_axis.dot(normal) * normal).normalize(),
+            major_radius,
+            minor_radius,
+        })
+    }
+
+    pub fn y_axis(&self) -> DVec3 {
+        self.normal.cross(self.x_axis)
+    }
+
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        self.center
+            + self.major_radius * t.cos() * self.x_axis
+            + self.minor_radius * t.sin() * self.y_axis()
+    }
+
+    /// The first derivative with respect to the parameter.
+    pub fn derivative_at(&self, t: f64) -> DVec3 {
+        -self.major_radius * t.sin() * self.x_axis + self.minor_radius * t.cos() * self.y_axis()
+    }
+
+    /// The second derivative with respect to the parameter.
+    pub fn second_derivative_at(&self, t: f64) -> DVec3 {
+        self.center - self.point_at(t)
+    }
+
+    /// The parameter in `[0, 2π)` of the point on the ellipse closest to `p`, found by
+    /// Newton iteration from the closest of a few evenly spread points.
+    pub fn parameter_of(&self, p: DVec3) -> f64 {
+        let d = p - self.center;
+        let (x, y) = (d.dot(self.x_axis), d.dot(self.y_axis()));
+        let (a, b) = (self.major_radius, self.minor_radius);
+
+        // Half the derivative of the squared distance and its derivative
+        let f = |t: f64| (b * b - a * a) * t.sin() * t.cos() + a * x * t.sin() - b * y * t.cos();
+        let df = |t: f64| {
+            (b * b - a * a) * (t.cos() * t.cos() - t.sin() * t.sin())
+                + a * x * t.cos()
+                + b * y * t.sin()
+        };
+
+        let samples = 16;
+        let mut t =
+            (1..samples)
+                .map(|i| TAU * i as f64 / samples as f64)
+                .fold(0., |best: f64, t| {
+                    let closer = self.point_at(t).distance_squared(p)
+                        < self.point_at(best).distance_squared(p);
+                    if closer {
+                        t
+                    } else {
+                        best
+                    }
+                });
+
+        for _ in 0..16 {
+            let slope = df(t);
+            if slope.abs() <= f64::EPSILON {
+                break;
+            }
+            let step = f(t) / slope;
+            t -= step.clamp(-TAU / samples as f64, TAU / samples as f64);
+            if step.abs() <= Tolerance::current().parametric {
+                break;
+            }
+        }
+
+        t.rem_euclid(TAU)
+    }
+
+    pub fn closest_point(&self, p: DVec3) -> DVec3 {
+        self.point_at(self.parameter_of(p))
+    }
+
+    /// The perimeter, integrated with Simpson's rule.
+    pub fn length(&self) -> f64 {
+        let speed = |t: f64| self.derivative_at(t).length();
+        let h = TAU / LENGTH_STEPS as f64;
+
+        let inner: f64 = (1..LENGTH_STEPS)
+            .map(|i| {
+                let weight = if i % 2 == 1 { 4. } else { 2. };
+                weight * speed(i as f64 * h)
+            })
+            .sum();
+
+        (speed(0.) + inner + speed(TAU)) * h / 3.
+    }
+
+    /// The exact NURBS form of the ellipse, with parameters from `0` to `1` once around it,
+    /// starting at the end of the major axis.
+    pub fn to_nurbs(&self) -> NurbsCurve {
+        NurbsCurve::circular(
+            self.center,
+            self.major_radius * self.x_axis,
+            self.minor_radius * self.y_axis(),
+            1.,
+            TAU,
+        )
+    }
+
+    /// The ellipse as a closed ring of straight lines within `tolerance`, starting at parameter
+    /// `0`.
+    pub fn to_lines(&self, tolerance: &TesselationTolerance) -> Vec<Line> {
+        let mut parameters = tolerance.curve_parameters(0., TAU, |t| self.point_at(t));
+        parameters.pop();
+        let points: Vec<DVec3> = parameters.iter().map(|&t| self.point_at(t)).collect();
+
+        (0..points.len())
+            .map(|i| Line::TwoPoint(TwoPointLine::new(points[i], points[(i + 1) % points.len()])))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+
+    fn ellipse() -> Ellipse {
+        Ellipse::new(
+            DVec3::new(1., -1., 2.),
+            DVec3::Z,
+            DVec3::new(1., 1., 0.),
+            3.,
+            1.,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_evaluation_and_derivatives() {
+        let e = ellipse();
+        let major = DVec3::new(1., 1., 0.).normalize();
+
+        assert!(e.point_at(0.).abs_diff_eq(e.center + 3. * major, 1e-5));
+        assert!(e
+            .point_at(FRAC_PI_2)
+            .abs_diff_eq(e.center + DVec3::Z.cross(major), 1e-5));
+
+        let h = 1e-3;
+        for t in [0., 0.7, 2., 5.] {
+            let d = (e.point_at(t + h) - e.point_at(t - h)) / (2. * h);
+            assert!(e.derivative_at(t).abs_diff_eq(d, 1e-2));
+            let dd = (e.derivative_at(t + h) - e.derivative_at(t - h)) / (2. * h);
+            assert!(e.second_derivative_at(t).abs_diff_eq(dd, 1e-2));
+        }
+    }
+
+    #[test]
+    fn test_closest_point() {
+        let e = ellipse();
+
+        for t in [0.3, 1.5, 3., 4.4, 6.] {
+            // Points off the curve along its normal in the plane come back to it
+            let p = e.point_at(t);
+            let outward = e.derivative_at(t).cross(e.normal).normalize();
+            let q = p + 0.2 * outward + DVec3::Z * 4.;
+
+            assert!((e.parameter_of(q) - t).abs() < 1e-4);
+            assert!(e.closest_point(q).abs_diff_eq(p, 1e-4));
+        }
+    }
+
+    #[test]
+    fn test_length() {
+        // A circle is an ellipse with equal radii
+        let circle = Ellipse::new(DVec3::ZERO, DVec3::Z, DVec3::X, 2., 2.).unwrap();
+        assert!((circle.length() - 4. * PI).abs() < 1e-4);
+
+        // Ramanujan's approximation is exact to many digits for mild eccentricity
+        let (a, b) = (3., 1.);
+        let h = ((a - b) / (a + b)) * ((a - b) / (a + b));
+        let expected = PI * (a + b) * (1. + 3. * h / (10. + (4. - 3. * h).sqrt()));
+        assert!((ellipse().length() - expected).abs() < 1e-3);
+
+        let tolerance = TesselationTolerance::new(0.01, PI / 8.);
+        let lines = ellipse().to_lines(&tolerance);
+        assert!(lines.len() >= 16);
+    }
+
+    #[test]
+    fn test_nurbs() {
+        let e = ellipse();
+        let curve = e.to_nurbs();
+
+        assert!(curve.is_closed());
+        assert!(curve.point_at(0.).abs_diff_eq(e.point_at(0.), 1e-9));
+        assert!(curve
+            .point_at(0.25)
+            .abs_diff_eq(e.point_at(FRAC_PI_2), 1e-9));
+        for i in 0..=40 {
+            let p = curve.point_at(i as f64 / 40.);
+            assert!(e.closest_point(p).abs_diff_eq(p, 1e-6));
+        }
+
+        // Its area is π times the product of the radii
+        let area = curve.swept_area(0., 1.);
+        assert!(area.abs_diff_eq(DVec3::Z * 3. * PI, 1e-9));
+    }
+
+    #[test]
+    fn test_degenerate_ellipses() {
+        let new = |normal, x_axis, major, minor| {
+            Ellipse::new(DVec3::ZERO, normal, x_axis, major, minor).unwrap_err()
+        };
+        assert_eq!(new(DVec3::ZERO, DVec3::X, 2., 1.), EllipseError::ZeroNormal);
+        assert_eq!(
+            new(DVec3::Z, DVec3::Z, 2., 1.),
+            EllipseError::AxisAlongNormal
+        );
+        assert_eq!(
+            new(DVec3::Z, DVec3::ZERO, 2., 1.),
+            EllipseError::AxisAlongNormal
+        );
+        assert_eq!(
+            new(DVec3::Z, DVec3::X, 2., 0.),
+            EllipseError::NonPositiveRadius
+        );
+        assert_eq!(
+            new(DVec3::Z, DVec3::X, -2., 1.),
+            EllipseError::NonPositiveRadius
+        );
+        assert_eq!(
+            new(DVec3::Z, DVec3::X, f64::NAN, 1.),
+            EllipseError::NonPositiveRadius
+        );
+    }
+}
@@ -0,0 +1,914 @@
+use std::f64::consts::{PI, TAU};
+
+use glam::DVec3;
+
+use crate::arc::Arc;
+use crate::circle::Circle;
+use crate::curve::Curve;
+use crate::line::ParametricLine;
+use crate::{TesselationTolerance, Tolerance};
+
+/// The iterations spent refining an intersection of general curves or the end of an overlap.
+const REFINE_ITERATIONS: usize = 32;
+/// The chord tolerance of the tesselations in `general`, as a fraction of the curves' extent.
+const SAMPLING_CHORD: f64 = 1. / 64.;
+/// The angle the tesselations in `general` may turn through between samples.
+const SAMPLING_ANGLE: f64 = PI / 12.;
+
+/// A piece of a [`Curve`] between the parameters `start` and `end`, with `start <= end`. Lines
+/// may run to infinity in either direction.
+#[derive(Debug, Clone)]
+pub struct TrimmedCurve {
+    pub curve: Curve,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl TrimmedCurve {
+    pub fn new(curve: Curve, start: f64, end: f64) -> Self {
+        Self { curve, start, end }
+    }
+
+    /// The line segment from `a` at parameter `0` to `b` at parameter `1`.
+    pub fn segment(a: DVec3, b: DVec3) -> Self {
+        Self::new(Curve::line(a, b), 0., 1.)
+    }
+
+    /// The arc as a piece of its circle, with parameters from `0` at the start to the sweep at
+    /// the end as for [`Arc::point_at`].
+    pub fn arc(arc: &Arc) -> Self {
+        let circle = Circle {
+            center: arc.center,
+            normal: arc.normal,
+            x_axis: (arc.start - arc.center) / arc.radius,
+            radius: arc.radius,
+        };
+        Self::new(Curve::Circle(circle), 0., arc.sweep())
+    }
+
+    /// The whole curve: lines without ends, circles and ellipses once around and NURBS curves
+    /// over their domain.
+    pub fn full(curve: Curve) -> Self {
+        let (start, end) = match &curve {
+            Curve::Line(_) => (f64::NEG_INFINITY, f64::INFINITY),
+            Curve::Circle(_) | Curve::Ellipse(_) => (0., TAU),
+            Curve::Nurbs(nurbs) => nurbs.domain(),
+        };
+        Self::new(curve, start, end)
+    }
+
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        self.curve.point_at(t)
+    }
+
+    /// Whether the parameter of a point is an angle, so `t` and `t + 2π` are the same point.
+    fn is_periodic(&self) -> bool {
+        matches!(self.curve, Curve::Circle(_) | Curve::Ellipse(_))
+    }
+
+    /// How far the parameter moves when the point moves `distance` along the curve at `t`.
+    fn parameter_slack(&self, t: f64, distance: f64) -> f64 {
+        distance / self.curve.tangent_at(t).length().max(f64::EPSILON)
+    }
+
+    /// The parameter `t`, turned into the range of a circle or an ellipse, if it lies within
+    /// `slack` of the range. Parameters just outside the range are moved onto its ends.
+    fn locate(&self, t: f64, slack: f64) -> Option<f64> {
+        if !self.is_periodic() {
+            return (t >= self.start - slack && t <= self.end + slack)
+                .then(|| t.clamp(self.start, self.end));
+        }
+
+        let t = self.start + (t - self.start).rem_euclid(TAU);
+        if t <= self.end + slack {
+            Some(t.min(self.end))
+        } else if t - TAU >= self.start - slack {
+            Some(self.start)
+        } else {
+            None
+        }
+    }
+
+    /// The parameter in range of the point closest to `p`, falling back on the nearer end when
+    /// the closest point of the whole curve lies outside the range.
+    fn closest_parameter(&self, p: DVec3) -> f64 {
+        if let Some(t) = self.locate(self.curve.parameter_of(p), 0.) {
+            return t;
+        }
+
+        let distance = |t: f64| self.point_at(t).distance_squared(p);
+        if distance(self.start) <= distance(self.end) {
+            self.start
+        } else {
+            self.end
+        }
+    }
+}
+
+/// Where two curves meet, by their parameters on the first curve `t` and on the second `s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveIntersection {
+    /// The curves cross or touch at `point`.
+    Point { point: DVec3, t: f64, s: f64 },
+    /// The curves coincide from `t.0` to `t.1` on the first curve, with `t.0 < t.1`. The same
+    /// stretch runs from `s.0` to `s.1` on the second curve, which may be backwards.
+    Overlap { t: (f64, f64), s: (f64, f64) },
+}
+
+impl CurveIntersection {
+    /// The first parameter on the first curve.
+    fn t(&self) -> f64 {
+        match self {
+            CurveIntersection::Point { t, .. } => *t,
+            CurveIntersection::Overlap { t, .. } => t.0,
+        }
+    }
+
+    /// The same intersection with the curves the other way round.
+    fn swapped(self) -> Self {
+        match self {
+            CurveIntersection::Point { point, t, s } => {
+                CurveIntersection::Point { point, t: s, s: t }
+            }
+            CurveIntersection::Overlap { t, s } => {
+                let (s, t) = if s.0 <= s.1 {
+                    (s, t)
+                } else {
+                    ((s.1, s.0), (t.1, t.0))
+                };
+                CurveIntersection::Overlap { t: s, s: t }
+            }
+        }
+    }
+}
+
+/// Every point where two trimmed curves come within the linear tolerance of each other, and
+/// every stretch along which they coincide, in order along the first curve.
+///
+/// Lines, circles and arcs are intersected exactly. Other pairs are sampled and refined by
+/// Newton iteration, so their overlaps must span a few samples to be found. Curves lying in a
+/// common plane, as those of a sketch, give the intersections of 2D geometry.
+pub fn intersect(a: &TrimmedCurve, b: &TrimmedCurve) -> Vec<CurveIntersection> {
+    intersect_within(a, b, &Tolerance::current())
+}
+
+fn intersect_within(
+    a: &TrimmedCurve,
+    b: &TrimmedCurve,
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let found = match (&a.curve, &b.curve) {
+        (Curve::Line(first), Curve::Line(second)) => line_line(a, first, b, second, tolerance),
+        (Curve::Line(line), Curve::Circle(circle)) => line_circle(a, line, b, circle, tolerance),
+        (Curve::Circle(_), Curve::Line(_)) => intersect_within(b, a, tolerance)
+            .into_iter()
+            .map(CurveIntersection::swapped)
+            .collect(),
+        (Curve::Circle(first), Curve::Circle(second)) => {
+            circle_circle(a, first, b, second, tolerance)
+        }
+        _ => general(a, b, tolerance),
+    };
+
+    // Overlaps take the points that lie on them, and nearby points are one intersection
+    let (mut out, points): (Vec<_>, Vec<_>) = found
+        .into_iter()
+        .partition(|i| matches!(i, CurveIntersection::Overlap { .. }));
+    for candidate in points {
+        let CurveIntersection::Point { point, t, .. } = candidate else {
+            continue;
+        };
+        let slack = a.parameter_slack(t, tolerance.linear);
+        let known = out.iter().any(|i| match *i {
+            CurveIntersection::Point { point: p, .. } => p.distance(point) <= tolerance.linear,
+            CurveIntersection::Overlap { t: (t0, t1), .. } => t >= t0 - slack && t <= t1 + slack,
+        });
+        if !known {
+            out.push(candidate);
+        }
+    }
+
+    out.sort_by(|x, y| x.t().total_cmp(&y.t()));
+    out
+}
+
+/// The intersection at `t` on `a` and `s` on `b`, if both lie in range and the points are
+/// within `tolerance` of each other.
+fn point(
+    a: &TrimmedCurve,
+    b: &TrimmedCurve,
+    t: f64,
+    s: f64,
+    tolerance: &Tolerance,
+) -> Option<CurveIntersection> {
+    let t = a.locate(t, a.parameter_slack(t, tolerance.linear))?;
+    let s = b.locate(s, b.parameter_slack(s, tolerance.linear))?;
+    let (p, q) = (a.point_at(t), b.point_at(s));
+
+    (p.distance(q) <= tolerance.linear).then_some(CurveIntersection::Point {
+        point: (p + q) / 2.,
+        t,
+        s,
+    })
+}
+
+/// The stretch where `a` and the parameters `t0..t1` of `a` that `b` covers meet, with
+/// `s_of` mapping parameters of `a` to those of `b`. Stretches shorter than `tolerance` are
+/// points.
+fn overlap(
+    a: &TrimmedCurve,
+    b: &TrimmedCurve,
+    (t0, t1): (f64, f64),
+    s_of: impl Fn(f64) -> f64,
+    tolerance: &Tolerance,
+) -> Option<CurveIntersection> {
+    let (low, high) = (a.start.max(t0), a.end.min(t1));
+    let middle = if low.is_finite() { low } else { high };
+    if !middle.is_finite() {
+        // Both lines are unbounded
+        return Some(CurveIntersection::Overlap {
+            t: (low, high),
+            s: (s_of(low), s_of(high)),
+        });
+    }
+
+    let slack = a.parameter_slack(middle, tolerance.linear);
+    if high < low - slack {
+        None
+    } else if high - low <= slack {
+        let t = (low + high) / 2.;
+        point(a, b, t, s_of(t), tolerance)
+    } else {
+        Some(CurveIntersection::Overlap {
+            t: (low, high),
+            s: (s_of(low), s_of(high)),
+        })
+    }
+}
+
+fn line_line(
+    a: &TrimmedCurve,
+    first: &ParametricLine,
+    b: &TrimmedCurve,
+    second: &ParametricLine,
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let w = first.p - second.p;
+    let (aa, bb, cc) = (
+        first.v.dot(first.v),
+        first.v.dot(second.v),
+        second.v.dot(second.v),
+    );
+    let (d, e) = (first.v.dot(w), second.v.dot(w));
+    let denominator = aa * cc - bb * bb;
+
+    if !tolerance.parallel(first.v, second.v) {
+        let t = (bb * e - cc * d) / denominator;
+        let s = (aa * e - bb * d) / denominator;
+        return point(a, b, t, s, tolerance).into_iter().collect();
+    }
+
+    // Parallel lines overlap if they are close enough
+    if (w - d / aa * first.v).length() > tolerance.linear {
+        return Vec::new();
+    }
+    let t_of = |s: f64| (s * bb - d) / aa;
+    let s_of = |t: f64| (t * bb + e) / cc;
+    let (t0, t1) = (t_of(b.start), t_of(b.end));
+
+    overlap(a, b, (t0.min(t1), t0.max(t1)), s_of, tolerance)
+        .into_iter()
+        .collect()
+}
+
+fn line_circle(
+    a: &TrimmedCurve,
+    line: &ParametricLine,
+    b: &TrimmedCurve,
+    circle: &Circle,
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let n = circle.normal;
+    let w = line.p - circle.center;
+    let (vn, wn) = (line.v.dot(n), w.dot(n));
+
+    let parameters = if !tolerance.perpendicular(line.v, n) {
+        // The line crosses the plane of the circle once
+        vec![-wn / vn]
+    } else if wn.abs() > tolerance.linear {
+        Vec::new()
+    } else {
+        let v = line.v - vn * n;
+        let w = w - wn * n;
+        let (qa, qb, qc) = (v.dot(v), w.dot(v), w.dot(w) - circle.radius * circle.radius);
+        let middle = -qb / qa;
+        let discriminant = qb * qb - qa * qc;
+
+        if discriminant <= 0. {
+            // Lines passing within the tolerance touch the circle
+            let distance = (w.dot(w) - qb * qb / qa).max(0.).sqrt();
+            if (distance - circle.radius).abs() <= tolerance.linear {
+                vec![middle]
+            } else {
+                Vec::new()
+            }
+        } else {
+            let h = discriminant.sqrt() / qa;
+            if h * qa.sqrt() <= tolerance.linear {
+                vec![middle]
+            } else {
+                vec![middle - h, middle + h]
+            }
+        }
+    };
+
+    parameters
+        .into_iter()
+        .filter_map(|t| {
+            let s = circle.parameter_of(line.point_at(t));
+            point(a, b, t, s, tolerance)
+        })
+        .collect()
+}
+
+fn circle_circle(
+    a: &TrimmedCurve,
+    first: &Circle,
+    b: &TrimmedCurve,
+    second: &Circle,
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let n = first.normal;
+    let offset = second.center - first.center;
+    let coplanar = tolerance.parallel(n, second.normal) && offset.dot(n).abs() <= tolerance.linear;
+
+    let points = if coplanar {
+        let u = offset - offset.dot(n) * n;
+        let d = u.length();
+        if d <= tolerance.linear {
+            return if (first.radius - second.radius).abs() <= tolerance.linear {
+                same_circle(a, first, b, second, tolerance)
+            } else {
+                Vec::new()
+            };
+        }
+
+        // The chord through both intersections crosses the line between the centers at `x`
+        let u = u / d;
+        let (r1, r2) = (first.radius, second.radius);
+        let x = (d * d + r1 * r1 - r2 * r2) / (2. * d);
+        let h = (r1 * r1 - x * x).max(0.).sqrt();
+        let middle = first.center + x * u;
+        let side = n.cross(u);
+
+        if h <= tolerance.linear {
+            vec![middle]
+        } else {
+            vec![middle + h * side, middle - h * side]
+        }
+    } else {
+        // The points of the first circle in the plane of the second solve
+        // `alpha cos θ + beta sin θ + gamma = 0`
+        let m = second.normal;
+        let alpha = first.radius * first.x_axis.dot(m);
+        let beta = first.radius * first.y_axis().dot(m);
+        let gamma = (first.center - second.center).dot(m);
+        let amplitude = alpha.hypot(beta);
+
+        let ratio = -gamma / amplitude;
+        if amplitude <= f64::EPSILON || ratio.abs() > 1. + tolerance.linear / amplitude {
+            return Vec::new();
+        }
+        let phase = beta.atan2(alpha);
+        let delta = ratio.clamp(-1., 1.).acos();
+
+        if delta * first.radius <= tolerance.linear {
+            vec![first.point_at(phase)]
+        } else {
+            vec![first.point_at(phase - delta), first.point_at(phase + delta)]
+        }
+    };
+
+    points
+        .into_iter()
+        .filter_map(|p| {
+            let (t, s) = (first.parameter_of(p), second.parameter_of(p));
+            point(a, b, t, s, tolerance)
+        })
+        .collect()
+}
+
+/// The overlaps of two arcs of the same circle, which may run in opposite directions.
+fn same_circle(
+    a: &TrimmedCurve,
+    first: &Circle,
+    b: &TrimmedCurve,
+    second: &Circle,
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let direction = first.normal.dot(second.normal).signum();
+    let phase = first.parameter_of(second.point_at(0.));
+
+    // Shifting the second arc by whole turns lines up every piece of it with the first
+    let mut out = Vec::new();
+    for turns in -2..=2 {
+        let shift = phase + turns as f64 * TAU;
+        let t_of = |s: f64| shift + direction * s;
+        let s_of = |t: f64| (t - shift) * direction;
+        let (t0, t1) = (t_of(b.start), t_of(b.end));
+
+        let low = t0.min(t1);
+        let high = t0.max(t1);
+        if high < a.start - TAU || low > a.end + TAU {
+            continue;
+        }
+        out.extend(overlap(a, b, (low, high), s_of, tolerance));
+    }
+
+    out
+}
+
+/// Intersections of any pair of curves, from close pairs of segments of their tesselations.
+fn general(a: &TrimmedCurve, b: &TrimmedCurve, tolerance: &Tolerance) -> Vec<CurveIntersection> {
+    let sampling = sampling(a, b, tolerance);
+    let a = bounded(a, b, &sampling, tolerance);
+    let b = bounded(b, &a, &sampling, tolerance);
+
+    let sample = |c: &TrimmedCurve| {
+        let parameters = sampling.curve_parameters(c.start, c.end, |t| c.point_at(t));
+        let points: Vec<DVec3> = parameters.iter().map(|&t| c.point_at(t)).collect();
+        (parameters, points)
+    };
+    let (ta, pa) = sample(&a);
+    let (tb, pb) = sample(&b);
+
+    // The tesselations stray from the curves by up to the chord tolerance each
+    let reach = tolerance.linear + 2. * sampling.chord;
+
+    let mut out = overlaps(&a, &b, &ta, tolerance);
+    for i in 0..ta.len() - 1 {
+        for j in 0..tb.len() - 1 {
+            let (u, w, distance) = segment_closest(pa[i], pa[i + 1], pb[j], pb[j + 1]);
+            if distance > reach {
+                continue;
+            }
+
+            let t = ta[i] + u * (ta[i + 1] - ta[i]);
+            let s = tb[j] + w * (tb[j + 1] - tb[j]);
+            let (t, s) = refine(&a, &b, t, s, tolerance);
+            out.extend(point(&a, &b, t, s, tolerance));
+        }
+    }
+
+    out
+}
+
+/// Tesselation tolerances in proportion to the smaller of the curves, so that features of
+/// either show in the samples whatever the units of the model.
+fn sampling(a: &TrimmedCurve, b: &TrimmedCurve, tolerance: &Tolerance) -> TesselationTolerance {
+    // Samples that only follow the turning of a curve are enough to measure it
+    let coarse = TesselationTolerance::new(f64::INFINITY, SAMPLING_ANGLE);
+    let extent = |c: &TrimmedCurve| {
+        if !c.start.is_finite() || !c.end.is_finite() {
+            return f64::INFINITY;
+        }
+        let points = coarse
+            .curve_parameters(c.start, c.end, |t| c.point_at(t))
+            .into_iter()
+            .map(|t| c.point_at(t));
+        let (low, high) = points.fold((DVec3::INFINITY, DVec3::NEG_INFINITY), |(low, high), p| {
+            (low.min(p), high.max(p))
+        });
+        (high - low).length()
+    };
+
+    let chord = SAMPLING_CHORD * extent(a).min(extent(b));
+    TesselationTolerance::new(chord.max(tolerance.linear), SAMPLING_ANGLE)
+}
+
+/// The curve with the unbounded ends of a line cut off beyond the reach of `other`.
+fn bounded(
+    curve: &TrimmedCurve,
+    other: &TrimmedCurve,
+    sampling: &TesselationTolerance,
+    tolerance: &Tolerance,
+) -> TrimmedCurve {
+    let Curve::Line(line) = &curve.curve else {
+        return curve.clone();
+    };
+    if curve.start.is_finite() && curve.end.is_finite() {
+        return curve.clone();
+    }
+
+    let along = |p: DVec3| (p - line.p).dot(line.v) / line.v.length_squared();
+    let margin = (tolerance.linear + sampling.chord) / line.v.length();
+    let (low, high) = sampling
+        .curve_parameters(other.start, other.end, |t| other.point_at(t))
+        .into_iter()
+        .map(|t| along(other.point_at(t)))
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), t| {
+            (low.min(t), high.max(t))
+        });
+
+    TrimmedCurve::new(
+        curve.curve.clone(),
+        curve.start.max(low - margin),
+        curve
+            .end
+            .min(high + margin)
+            .max(curve.start.max(low - margin)),
+    )
+}
+
+/// The stretches of `a` lying within `tolerance` of `b`, found from runs of the samples `ta`
+/// of `a` and refined by bisection.
+fn overlaps(
+    a: &TrimmedCurve,
+    b: &TrimmedCurve,
+    ta: &[f64],
+    tolerance: &Tolerance,
+) -> Vec<CurveIntersection> {
+    let near = |t: f64| {
+        let p = a.point_at(t);
+        b.point_at(b.closest_parameter(p)).distance(p) <= tolerance.linear
+    };
+    let edge = |mut inside: f64, mut outside: f64| {
+        for _ in 0..REFINE_ITERATIONS {
+            let middle = (inside + outside) / 2.;
+            if near(middle) {
+                inside = middle;
+            } else {
+                outside = middle;
+            }
+        }
+        inside
+    };
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < ta.len() {
+        if !near(ta[i]) {
+            i += 1;
+            continue;
+        }
+
+        let mut j = i;
+        while j + 1 < ta.len() && near((ta[j] + ta[j + 1]) / 2.) && near(ta[j + 1]) {
+            j += 1;
+        }
+        if j > i {
+            let low = if i == 0 {
+                ta[0]
+            } else {
+                edge(ta[i], ta[i - 1])
+            };
+            let high = if j + 1 == ta.len() {
+                ta[j]
+            } else {
+                edge(ta[j], ta[j + 1])
+            };
+            out.push(CurveIntersection::Overlap {
+                t: (low, high),
+                s: (
+                    b.closest_parameter(a.point_at(low)),
+                    b.closest_parameter(a.point_at(high)),
+                ),
+            });
+        }
+        i = j + 1;
+    }
+
+    out
+}
+
+/// Gauss–Newton iteration towards parameters where the curves meet. Where their tangents are
+/// parallel, as where they touch, it falls back on projecting each point onto the other curve.
+fn refine(
+    a: &TrimmedCurve,
+    b: &TrimmedCurve,
+    mut t: f64,
+    mut s: f64,
+    tolerance: &Tolerance,
+) -> (f64, f64) {
+    for _ in 0..REFINE_ITERATIONS {
+        let r = a.point_at(t) - b.point_at(s);
+        let (da, db) = (a.curve.tangent_at(t), -b.curve.tangent_at(s));
+        let (p, q, w) = (da.dot(da), da.dot(db), db.dot(db));
+        let determinant = p * w - q * q;
+
+        let next = if determinant > 1e-6 * p * w {
+            let (gt, gs) = (da.dot(r), db.dot(r));
+            (
+                (t - (w * gt - q * gs) / determinant).clamp(a.start, a.end),
+                (s - (p * gs - q * gt) / determinant).clamp(b.start, b.end),
+            )
+        } else {
+            let s = b.closest_parameter(a.point_at(t));
+            (a.closest_parameter(b.point_at(s)), s)
+        };
+
+        let converged = tolerance.same_parameter(next.0, t) && tolerance.same_parameter(next.1, s);
+        (t, s) = next;
+        if converged {
+            break;
+        }
+    }
+
+    (t, s)
+}
+
+/// The closest points of the segments `p0..p1` and `q0..q1`, as fractions along each, and
+/// their distance.
+fn segment_closest(p0: DVec3, p1: DVec3, q0: DVec3, q1: DVec3) -> (f64, f64, f64) {
+    let (d1, d2, r) = (p1 - p0, q1 - q0, p0 - q0);
+    let (a, e, f) = (d1.dot(d1), d2.dot(d2), d2.dot(r));
+
+    let (u, w) = if a <= f64::EPSILON && e <= f64::EPSILON {
+        (0., 0.)
+    } else if a <= f64::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d1.dot(r);
+        if e <= f64::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denominator = a * e - b * b;
+            let u = if denominator > 0. {
+                ((b * f - c * e) / denominator).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let w = (b * u + f) / e;
+            if w < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if w > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (u, w)
+            }
+        }
+    };
+
+    let distance = (p0 + u * d1).distance(q0 + w * d2);
+    (u, w, distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+    use crate::ellipse::Ellipse;
+    use crate::nurbs::NurbsCurve;
+
+    const TOLERANCE: Tolerance = Tolerance {
+        linear: 1e-4,
+        ..Tolerance::DEFAULT
+    };
+
+    /// Intersects under the looser tolerance of these tests.
+    fn intersect(a: &TrimmedCurve, b: &TrimmedCurve) -> Vec<CurveIntersection> {
+        TOLERANCE.with(|| super::intersect(a, b))
+    }
+
+    fn points(intersections: &[CurveIntersection]) -> Vec<DVec3> {
+        intersections
+            .iter()
+            .map(|i| match i {
+                CurveIntersection::Point { point, .. } => *point,
+                CurveIntersection::Overlap { .. } => panic!("unexpected overlap"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_line_line() {
+        let a = TrimmedCurve::segment(DVec3::ZERO, DVec3::new(2., 2., 0.));
+        let b = TrimmedCurve::segment(DVec3::new(0., 2., 0.), DVec3::new(2., 0., 0.));
+        assert_eq!(
+            intersect(&a, &b),
+            vec![CurveIntersection::Point {
+                point: DVec3::new(1., 1., 0.),
+                t: 0.5,
+                s: 0.5
+            }]
+        );
+
+        // Skew lines in 3D miss each other, segments stop short
+        let skew = TrimmedCurve::segment(DVec3::new(0., 2., 1.), DVec3::new(2., 0., 1.));
+        assert!(intersect(&a, &skew).is_empty());
+        let short = TrimmedCurve::segment(DVec3::new(0., 2., 0.), DVec3::new(0.9, 1.1, 0.));
+        assert!(intersect(&a, &short).is_empty());
+        let full = TrimmedCurve::full(Curve::line(
+            DVec3::new(0., 2., 0.),
+            DVec3::new(0.9, 1.1, 0.),
+        ));
+        assert_eq!(intersect(&a, &full).len(), 1);
+
+        // Collinear segments running opposite ways overlap
+        let back = TrimmedCurve::segment(DVec3::new(3., 3., 0.), DVec3::new(1., 1., 0.));
+        let found = intersect(&a, &back);
+        let [CurveIntersection::Overlap { t, s }] = found[..] else {
+            panic!("{found:?}");
+        };
+        assert!((t.0 - 0.5).abs() < 1e-6 && (t.1 - 1.).abs() < 1e-6);
+        assert!((s.0 - 1.).abs() < 1e-6 && (s.1 - 0.5).abs() < 1e-6);
+
+        // Collinear segments meeting end to end touch
+        let next = TrimmedCurve::segment(DVec3::new(2., 2., 0.), DVec3::new(3., 3., 0.));
+        assert_eq!(points(&intersect(&a, &next)), vec![DVec3::new(2., 2., 0.)]);
+    }
+
+    #[test]
+    fn test_line_arc() {
+        let arc = TrimmedCurve::arc(
+            &Arc::new(
+                DVec3::ZERO,
+                DVec3::Z,
+                DVec3::new(2., 0., 0.),
+                DVec3::new(-2., 0., 0.),
+            )
+            .unwrap(),
+        );
+
+        // A horizontal line crosses the upper half circle twice
+        let line = TrimmedCurve::segment(DVec3::new(-3., 1., 0.), DVec3::new(3., 1., 0.));
+        let found = intersect(&line, &arc);
+        let p = points(&found);
+        let x = 3f64.sqrt();
+        assert_eq!(p.len(), 2);
+        assert!(p[0].abs_diff_eq(DVec3::new(-x, 1., 0.), 1e-5));
+        assert!(p[1].abs_diff_eq(DVec3::new(x, 1., 0.), 1e-5));
+        let CurveIntersection::Point { s, .. } = found[1] else {
+            unreachable!()
+        };
+        assert!((s - PI / 6.).abs() < 1e-5);
+
+        // Only the upper half is there, and a tangent line touches once
+        let below = TrimmedCurve::segment(DVec3::new(-3., -1., 0.), DVec3::new(3., -1., 0.));
+        assert!(intersect(&below, &arc).is_empty());
+        let tangent = TrimmedCurve::segment(DVec3::new(-3., 2., 0.), DVec3::new(3., 2., 0.));
+        let p = points(&intersect(&arc, &tangent));
+        assert_eq!(p.len(), 1);
+        assert!(p[0].abs_diff_eq(DVec3::new(0., 2., 0.), 1e-5));
+
+        // A line through the plane of the arc meets it at one point
+        let through = TrimmedCurve::segment(DVec3::new(0., 2., -1.), DVec3::new(0., 2., 1.));
+        assert_eq!(intersect(&through, &arc).len(), 1);
+    }
+
+    #[test]
+    fn test_arc_arc() {
+        let first = TrimmedCurve::full(Curve::circle(DVec3::ZERO, DVec3::Z, 2.).unwrap());
+        let second =
+            TrimmedCurve::full(Curve::circle(DVec3::new(2., 0., 0.), DVec3::Z, 2.).unwrap());
+        let p = points(&intersect(&first, &second));
+        let y = 3f64.sqrt();
+        assert_eq!(p.len(), 2);
+        assert!(p.iter().any(|p| p.abs_diff_eq(DVec3::new(1., y, 0.), 1e-5)));
+        assert!(p
+            .iter()
+            .any(|p| p.abs_diff_eq(DVec3::new(1., -y, 0.), 1e-5)));
+
+        // Circles touching on the outside
+        let outside =
+            TrimmedCurve::full(Curve::circle(DVec3::new(3., 0., 0.), -DVec3::Z, 1.).unwrap());
+        let p = points(&intersect(&first, &outside));
+        assert_eq!(p.len(), 1);
+        assert!(p[0].abs_diff_eq(DVec3::new(2., 0., 0.), 1e-5));
+
+        // Circles in crossing planes meet where they pass through each other's planes
+        let upright =
+            TrimmedCurve::full(Curve::circle(DVec3::new(3., 0., 0.), DVec3::Y, 1.).unwrap());
+        let p = points(&intersect(&first, &upright));
+        assert_eq!(p.len(), 1);
+        assert!(p[0].abs_diff_eq(DVec3::new(2., 0., 0.), 1e-5));
+    }
+
+    #[test]
+    fn test_arc_overlap() {
+        // A quarter arc and a half arc of the same circle, running opposite ways
+        let quarter = TrimmedCurve::arc(
+            &Arc::new(
+                DVec3::ZERO,
+                DVec3::Z,
+                DVec3::new(1., 0., 0.),
+                DVec3::new(0., 1., 0.),
+            )
+            .unwrap(),
+        );
+        let half = TrimmedCurve::arc(
+            &Arc::new(
+                DVec3::ZERO,
+                -DVec3::Z,
+                DVec3::new(-1., 1., 0.).normalize(),
+                DVec3::new(1., -1., 0.).normalize(),
+            )
+            .unwrap(),
+        );
+
+        let found = intersect(&quarter, &half);
+        let [CurveIntersection::Overlap { t, s }] = found[..] else {
+            panic!("{found:?}");
+        };
+        assert!(t.0.abs() < 1e-5 && (t.1 - FRAC_PI_2).abs() < 1e-5);
+        assert!(quarter.point_at(t.0).abs_diff_eq(half.point_at(s.0), 1e-5));
+        assert!(quarter.point_at(t.1).abs_diff_eq(half.point_at(s.1), 1e-5));
+        assert!(s.0 > s.1);
+
+        // Arcs of one circle meeting end to end touch
+        let next = TrimmedCurve::arc(
+            &Arc::new(
+                DVec3::ZERO,
+                DVec3::Z,
+                DVec3::new(0., 1., 0.),
+                DVec3::new(-1., 0., 0.),
+            )
+            .unwrap(),
+        );
+        let p = points(&intersect(&quarter, &next));
+        assert_eq!(p.len(), 1);
+        assert!(p[0].abs_diff_eq(DVec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn test_general_curves() {
+        // A parabola y = x² over [-1, 1] crossing the line y = 0.25
+        let parabola = TrimmedCurve::full(Curve::Nurbs(
+            NurbsCurve::uniform(
+                2,
+                vec![
+                    DVec3::new(-1., 1., 0.),
+                    DVec3::new(0., -1., 0.),
+                    DVec3::new(1., 1., 0.),
+                ],
+            )
+            .unwrap(),
+        ));
+        let line = TrimmedCurve::full(Curve::line(
+            DVec3::new(0., 0.25, 0.),
+            DVec3::new(1., 0.25, 0.),
+        ));
+        let p = points(&intersect(&parabola, &line));
+        assert_eq!(p.len(), 2);
+        assert!(p[0].abs_diff_eq(DVec3::new(-0.5, 0.25, 0.), 1e-4));
+        assert!(p[1].abs_diff_eq(DVec3::new(0.5, 0.25, 0.), 1e-4));
+
+        // The line is unbounded in the other order too
+        assert_eq!(intersect(&line, &parabola).len(), 2);
+
+        // An ellipse and a circle through its vertices on the minor axis
+        let ellipse = TrimmedCurve::full(Curve::Ellipse(
+            Ellipse::new(DVec3::ZERO, DVec3::Z, DVec3::X, 3., 1.).unwrap(),
+        ));
+        let circle = TrimmedCurve::full(Curve::circle(DVec3::ZERO, DVec3::Z, 2.).unwrap());
+        assert_eq!(intersect(&ellipse, &circle).len(), 4);
+
+        // A circle and its exact NURBS form coincide all the way round
+        let Curve::Circle(c) = &circle.curve else {
+            unreachable!()
+        };
+        let nurbs = TrimmedCurve::full(Curve::Nurbs(c.to_nurbs()));
+        let found = intersect(&nurbs, &circle);
+        let [CurveIntersection::Overlap { t, .. }] = found[..] else {
+            panic!("{found:?}");
+        };
+        assert_eq!(t, (0., 1.));
+    }
+
+    #[test]
+    fn test_sampling_scales_with_curves() {
+        let ellipse = |scale: f64| {
+            TrimmedCurve::full(Curve::Ellipse(
+                Ellipse::new(DVec3::ZERO, DVec3::Z, DVec3::X, 3. * scale, scale).unwrap(),
+            ))
+        };
+        let circle =
+            |scale| TrimmedCurve::full(Curve::circle(DVec3::ZERO, DVec3::Z, 2. * scale).unwrap());
+
+        // Millimetres are sampled as finely as metres, relative to their size
+        let tolerance = Tolerance::DEFAULT;
+        let metres = sampling(&ellipse(1.), &circle(1.), &tolerance);
+        let millimetres = sampling(&ellipse(1e-3), &circle(1e-3), &tolerance);
+        assert!((metres.chord / millimetres.chord - 1e3).abs() < 1e-6);
+
+        let count = |sampling: &TesselationTolerance, c: &TrimmedCurve| {
+            sampling
+                .curve_parameters(c.start, c.end, |t| c.point_at(t))
+                .len()
+        };
+        assert_eq!(
+            count(&metres, &ellipse(1.)),
+            count(&millimetres, &ellipse(1e-3))
+        );
+
+        // The smaller curve sets the sampling, down to the linear tolerance
+        let mixed = sampling(&ellipse(1.), &circle(1e-3), &tolerance);
+        assert_eq!(mixed.chord, millimetres.chord);
+        // A circle below the linear tolerance only exists in a finer context
+        let speck = Tolerance::new(1e-15, 1e-10, 1e-9).with(|| circle(1e-12));
+        let tiny = sampling(&ellipse(1.), &speck, &tolerance);
+        assert_eq!(tiny.chord, tolerance.linear);
+    }
+}
@@ -0,0 +1,7 @@
+//! Where curves and surfaces meet.
+
+mod curves;
+mod surfaces;
+
+pub use curves::*;
+pub use surfaces::*;
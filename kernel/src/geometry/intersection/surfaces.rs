@@ -0,0 +1,806 @@
+use std::f64::consts::PI;
+
+use glam::{DMat3, DVec3};
+
+use crate::circle::Circle;
+use crate::curve::Curve;
+use crate::ellipse::Ellipse;
+use crate::line::ParametricLine;
+use crate::nurbs::NurbsCurve;
+use crate::surface::Surface;
+use crate::transform::Transform;
+use crate::{Plane, Tolerance};
+
+/// The points of a grid over one surface, along each direction, between which traced curves
+/// are looked for.
+const SEED_GRID: usize = 32;
+/// The most a traced curve turns between two of its points.
+const MAX_TURN: f64 = PI / 16.;
+/// The most points a traced curve runs to in either direction from where it was found.
+const MAX_STEPS: usize = 4096;
+/// The iterations spent moving a point onto both surfaces.
+const SETTLE_ITERATIONS: usize = 16;
+
+/// How two surfaces meet.
+#[derive(Debug, Clone)]
+pub enum SurfaceIntersection {
+    /// The surfaces cross along a curve. Lines, circles and ellipses are exact and whole.
+    /// Other curves are traced and fitted with NURBS curves.
+    Curve(Curve),
+    /// The surfaces touch at a single point.
+    Point(DVec3),
+    /// The surfaces are the same.
+    Coincident,
+}
+
+/// The curves along which two surfaces cross, within the linear tolerance.
+///
+/// Planes with planes, cylinders, spheres and cones, and cylinders with parallel cylinders,
+/// meet along lines, circles and ellipses found exactly. Every other pair, including the
+/// parabolas and hyperbolas cut from cones, is traced from points where one surface crosses
+/// the other on a grid over the first, and only within `bounds`, the box from its least to its
+/// greatest corner. Tracing misses curves along which the surfaces touch without crossing.
+///
+/// A transformed surface is met where the surface it is the image of meets the other surface
+/// mapped back, so planes cut transformed cylinders, spheres and cones along exact curves too.
+pub fn intersect_surfaces(
+    a: &Surface,
+    b: &Surface,
+    bounds: (DVec3, DVec3),
+) -> Vec<SurfaceIntersection> {
+    let tolerance = &Tolerance::current();
+    let transformed = match (a, b) {
+        (Surface::Transformed { .. }, Surface::Transformed { .. }) => None,
+        (Surface::Transformed { surface, transform }, other)
+        | (other, Surface::Transformed { surface, transform }) => Some((surface, transform, other)),
+        _ => None,
+    };
+    if let Some((surface, transform, other)) = transformed {
+        let inverse = transform.inverse();
+        let mapped = other.transformed(&inverse);
+        return intersect_surfaces(surface, &mapped, mapped_bounds(bounds, &inverse))
+            .into_iter()
+            .map(|intersection| match intersection {
+                SurfaceIntersection::Curve(curve) => {
+                    SurfaceIntersection::Curve(curve.transformed(transform))
+                }
+                SurfaceIntersection::Point(p) => SurfaceIntersection::Point(transform.point(p)),
+                SurfaceIntersection::Coincident => SurfaceIntersection::Coincident,
+            })
+            .collect();
+    }
+
+    let exact = match (a, b) {
+        (Surface::Plane(first), Surface::Plane(second)) => {
+            Some(plane_plane(first, second, tolerance))
+        }
+        (Surface::Plane(plane), other) | (other, Surface::Plane(plane)) => {
+            plane_surface(plane, other, tolerance)
+        }
+        (
+            Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            },
+            Surface::Cylinder {
+                origin: other_origin,
+                axis: other_axis,
+                radius: other_radius,
+            },
+        ) => cylinder_cylinder(
+            (*origin, *axis, *radius),
+            (*other_origin, *other_axis, *other_radius),
+            tolerance,
+        ),
+        _ => None,
+    };
+
+    exact.unwrap_or_else(|| trace_all(a, b, bounds, tolerance))
+}
+
+fn line(p: DVec3, direction: DVec3) -> SurfaceIntersection {
+    SurfaceIntersection::Curve(Curve::Line(ParametricLine::new(p, direction.normalize())))
+}
+
+fn plane_plane(first: &Plane, second: &Plane, tolerance: &Tolerance) -> Vec<SurfaceIntersection> {
+    let direction = first.normal.cross(second.normal);
+    if tolerance.parallel(first.normal, second.normal) {
+        let apart = (second.center - first.center).dot(first.normal).abs();
+        return if apart <= tolerance.linear {
+            vec![SurfaceIntersection::Coincident]
+        } else {
+            Vec::new()
+        };
+    }
+
+    // The point of the line closest to the origin
+    let (h1, h2) = (
+        first.normal.dot(first.center),
+        second.normal.dot(second.center),
+    );
+    let p = (h1 * second.normal.cross(direction) + h2 * direction.cross(first.normal))
+        / direction.length_squared();
+
+    vec![line(p, direction)]
+}
+
+/// The exact intersections of a plane with another surface, or `None` if they have to be
+/// traced.
+fn plane_surface(
+    plane: &Plane,
+    surface: &Surface,
+    tolerance: &Tolerance,
+) -> Option<Vec<SurfaceIntersection>> {
+    let n = plane.normal;
+    let height = |p: DVec3| (p - plane.center).dot(n);
+
+    match *surface {
+        Surface::Sphere { center, radius } => {
+            let d = height(center);
+            let foot = center - d * n;
+
+            Some(if d.abs() > radius + tolerance.linear {
+                Vec::new()
+            } else if (d.abs() - radius).abs() <= tolerance.linear {
+                vec![SurfaceIntersection::Point(foot)]
+            } else {
+                let circle = Circle::new(foot, n, (radius * radius - d * d).sqrt()).ok()?;
+                vec![SurfaceIntersection::Curve(Curve::Circle(circle))]
+            })
+        }
+        Surface::Cylinder {
+            origin,
+            axis,
+            radius,
+        } => {
+            let cos = n.dot(axis);
+            if tolerance.perpendicular(n, axis) {
+                // Lines along the axis where the plane cuts the cylinder lengthwise
+                let d = height(origin);
+                let foot = origin - d * n;
+                let side = n.cross(axis);
+
+                return Some(if d.abs() > radius + tolerance.linear {
+                    Vec::new()
+                } else if (d.abs() - radius).abs() <= tolerance.linear {
+                    vec![line(foot, axis)]
+                } else {
+                    let w = (radius * radius - d * d).sqrt();
+                    vec![line(foot + w * side, axis), line(foot - w * side, axis)]
+                });
+            }
+
+            let center = origin - height(origin) / cos * axis;
+            let curve = if tolerance.parallel(n, axis) {
+                Curve::Circle(Circle::new(center, n, radius).ok()?)
+            } else {
+                // The minor axis is across the cylinder, the major one along its slope
+                let minor = n.cross(axis).normalize();
+                let major = n.cross(minor);
+                Curve::Ellipse(Ellipse::new(center, n, major, radius / cos.abs(), radius).ok()?)
+            };
+            Some(vec![SurfaceIntersection::Curve(curve)])
+        }
+        Surface::Cone {
+            apex,
+            axis,
+            half_angle,
+        } => plane_cone(plane, apex, axis, half_angle, tolerance),
+        _ => None,
+    }
+}
+
+fn plane_cone(
+    plane: &Plane,
+    apex: DVec3,
+    axis: DVec3,
+    half_angle: f64,
+    tolerance: &Tolerance,
+) -> Option<Vec<SurfaceIntersection>> {
+    let n = plane.normal;
+    let along = n.dot(axis);
+    let h = (apex - plane.center).dot(n);
+    let tan = half_angle.tan();
+
+    if h.abs() <= tolerance.linear {
+        // A plane through the apex holds up to two generators
+        if tolerance.parallel(n, axis) {
+            return Some(vec![SurfaceIntersection::Point(apex)]);
+        }
+        let slope = (axis - along * n).normalize();
+        let side = n.cross(slope);
+        let cos = half_angle.cos() / slope.dot(axis);
+
+        return Some(if cos > 1. + tolerance.angular {
+            vec![SurfaceIntersection::Point(apex)]
+        } else if cos >= 1. - tolerance.angular {
+            vec![line(apex, slope)]
+        } else {
+            let sin = (1. - cos * cos).sqrt();
+            vec![
+                line(apex, cos * slope + sin * side),
+                line(apex, cos * slope - sin * side),
+            ]
+        });
+    }
+
+    // Where the axis crosses the plane
+    let s0 = -h / along;
+    let crossing = apex + s0 * axis;
+
+    if tolerance.parallel(n, axis) {
+        let circle = Circle::new(crossing, n, s0.abs() * tan).ok()?;
+        return Some(vec![SurfaceIntersection::Curve(Curve::Circle(circle))]);
+    }
+    if along.abs() <= half_angle.sin() + tolerance.angular {
+        // Parabolas and hyperbolas are traced
+        return None;
+    }
+
+    // The major axis lies along the slope of the plane, between the generators in the plane
+    // through the axis and the normal
+    let slope = (axis - along * n).normalize();
+    let outward = (slope - slope.dot(axis) * axis).normalize();
+    let (ma, me) = (slope.dot(axis), slope.dot(outward));
+    let first = s0 * tan / (me - ma * tan);
+    let second = -s0 * tan / (me + ma * tan);
+
+    let center = crossing + (first + second) / 2. * slope;
+    let major = (first - second).abs() / 2.;
+
+    // The minor axis is the half chord across the cone's circle through the center
+    let d = center - apex;
+    let axial = d.dot(axis);
+    let radial = (d - axial * axis).length();
+    let minor = ((axial * tan).powi(2) - radial * radial).max(0.).sqrt();
+
+    let ellipse = Ellipse::new(center, n, slope, major, minor).ok()?;
+    Some(vec![SurfaceIntersection::Curve(Curve::Ellipse(ellipse))])
+}
+
+/// The lines where cylinders with parallel axes meet, or `None` if the axes are not parallel.
+fn cylinder_cylinder(
+    (origin, axis, radius): (DVec3, DVec3, f64),
+    (other_origin, other_axis, other_radius): (DVec3, DVec3, f64),
+    tolerance: &Tolerance,
+) -> Option<Vec<SurfaceIntersection>> {
+    if !tolerance.parallel(axis, other_axis) {
+        return None;
+    }
+
+    let offset = other_origin - origin;
+    let u = offset - offset.dot(axis) * axis;
+    let d = u.length();
+    if d <= tolerance.linear {
+        return Some(if (radius - other_radius).abs() <= tolerance.linear {
+            vec![SurfaceIntersection::Coincident]
+        } else {
+            Vec::new()
+        });
+    }
+
+    // The cross-section is two circles meeting on a chord at `x` along the line between them
+    let u = u / d;
+    let x = (d * d + radius * radius - other_radius * other_radius) / (2. * d);
+    let h2 = radius * radius - x * x;
+    let touching = (d - (radius + other_radius)).abs() <= tolerance.linear
+        || (d - (radius - other_radius).abs()).abs() <= tolerance.linear;
+
+    Some(if h2 <= 0. || touching {
+        if touching {
+            vec![line(origin + x.clamp(-radius, radius) * u, axis)]
+        } else {
+            Vec::new()
+        }
+    } else {
+        let h = h2.sqrt();
+        let side = axis.cross(u);
+        let middle = origin + x * u;
+        vec![line(middle + h * side, axis), line(middle - h * side, axis)]
+    })
+}
+
+/// Traces every curve where the surfaces cross within `bounds` and fits NURBS curves to them.
+fn trace_all(
+    a: &Surface,
+    b: &Surface,
+    bounds: (DVec3, DVec3),
+    tolerance: &Tolerance,
+) -> Vec<SurfaceIntersection> {
+    // Seeds are looked for on a NURBS surface where there is one, as its domain is bounded
+    let (a, b) = if matches!(b, Surface::Nurbs(_)) && !matches!(a, Surface::Nurbs(_)) {
+        (b, a)
+    } else {
+        (a, b)
+    };
+    let max_step = (bounds.1 - bounds.0).length() / SEED_GRID as f64;
+
+    let mut traced: Vec<(Vec<DVec3>, bool)> = Vec::new();
+    for seed in seeds(a, b, bounds, tolerance) {
+        let known = traced.iter().any(|(points, _)| {
+            points
+                .windows(2)
+                .any(|w| segment_distance(seed, w[0], w[1]) <= max_step / 2.)
+        });
+        if !known {
+            traced.push(trace(a, b, seed, bounds, max_step, tolerance));
+        }
+    }
+
+    traced
+        .into_iter()
+        .filter_map(|(points, closed)| fit(a, b, points, closed, tolerance))
+        .map(|curve| SurfaceIntersection::Curve(Curve::Nurbs(curve)))
+        .collect()
+}
+
+/// The curve through the traced `points`. Wherever it strays from either surface by more than
+/// the tolerance midway between two points, a point settled onto both is added between them and
+/// the curve fitted again, until it keeps to the surfaces or no more points can be added.
+fn fit(
+    a: &Surface,
+    b: &Surface,
+    mut points: Vec<DVec3>,
+    closed: bool,
+    tolerance: &Tolerance,
+) -> Option<NurbsCurve> {
+    // A closed curve leaves and returns to its first point along the same tangent
+    let ends = if closed {
+        let along = points[1] - points[0];
+        let t = tangent(a, b, points[0])?;
+        points.push(points[0]);
+        Some(if t.dot(along) < 0. { -t } else { t })
+    } else {
+        None
+    };
+
+    loop {
+        let curve = NurbsCurve::interpolate(&points, ends, ends).ok()?;
+
+        // The parameters the points are reached at, spaced by the chord lengths between them
+        let chords: Vec<f64> = points.windows(2).map(|p| p[0].distance(p[1])).collect();
+        let total: f64 = chords.iter().sum();
+        let mut t = 0.;
+
+        let mut refined = vec![points[0]];
+        for (i, chord) in chords.iter().enumerate() {
+            let middle = curve.point_at(t + chord / total / 2.);
+            t += chord / total;
+
+            let deviation = a.distance(middle).abs().max(b.distance(middle).abs());
+            if deviation > tolerance.linear {
+                if let Some(q) = settle(a, b, middle, tolerance) {
+                    if q.distance(middle) < *chord {
+                        refined.push(q);
+                    }
+                }
+            }
+            refined.push(points[i + 1]);
+        }
+
+        if refined.len() == points.len() || refined.len() > MAX_STEPS {
+            return Some(curve);
+        }
+        points = refined;
+    }
+}
+
+/// The corners of the box from `min` to `max`.
+fn corners((min, max): (DVec3, DVec3)) -> impl Iterator<Item = DVec3> {
+    (0..8).map(move |i| {
+        DVec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        )
+    })
+}
+
+/// The box around the image of the box `bounds`.
+fn mapped_bounds(bounds: (DVec3, DVec3), transform: &Transform) -> (DVec3, DVec3) {
+    corners(bounds)
+        .map(|c| transform.point(c))
+        .fold((DVec3::INFINITY, DVec3::NEG_INFINITY), |(min, max), p| {
+            (min.min(p), max.max(p))
+        })
+}
+
+/// The parameter ranges of `surface` to look for seeds in, with unbounded directions cut to
+/// the reach of `bounds`.
+fn seed_ranges(surface: &Surface, bounds: (DVec3, DVec3)) -> ((f64, f64), (f64, f64)) {
+    let corners = corners(bounds);
+    let range = |values: &mut dyn Iterator<Item = f64>| {
+        values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+            (low.min(v), high.max(v))
+        })
+    };
+
+    match surface {
+        Surface::Plane(_) => {
+            let parameters: Vec<_> = corners.map(|c| surface.parameters_of(c)).collect();
+            (
+                range(&mut parameters.iter().map(|p| p.0)),
+                range(&mut parameters.iter().map(|p| p.1)),
+            )
+        }
+        Surface::Cylinder {
+            origin: base, axis, ..
+        }
+        | Surface::Cone {
+            apex: base, axis, ..
+        } => (
+            surface.domain().0,
+            range(&mut corners.map(|c| (c - *base).dot(*axis))),
+        ),
+        Surface::Transformed { surface, transform } => {
+            seed_ranges(surface, mapped_bounds(bounds, &transform.inverse()))
+        }
+        _ => surface.domain(),
+    }
+}
+
+/// Points on both surfaces, found between neighbours of a grid over `a` on opposite sides of
+/// `b`.
+fn seeds(a: &Surface, b: &Surface, bounds: (DVec3, DVec3), tolerance: &Tolerance) -> Vec<DVec3> {
+    let ((u0, u1), (v0, v1)) = seed_ranges(a, bounds);
+    let n = SEED_GRID;
+
+    let grid: Vec<Vec<(DVec3, f64)>> = (0..=n)
+        .map(|i| {
+            (0..=n)
+                .map(|j| {
+                    let u = u0 + (u1 - u0) * i as f64 / n as f64;
+                    let v = v0 + (v1 - v0) * j as f64 / n as f64;
+                    let p = a.point_at(u, v);
+                    (p, b.distance(p))
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    for i in 0..=n {
+        for j in 0..=n {
+            for (k, l) in [(i + 1, j), (i, j + 1)] {
+                if k > n || l > n {
+                    continue;
+                }
+                let ((p, dp), (q, dq)) = (grid[i][j], grid[k][l]);
+                if dp * dq > 0. || !dp.is_finite() || !dq.is_finite() {
+                    continue;
+                }
+
+                let guess = if dp == dq {
+                    p
+                } else {
+                    p + (q - p) * (dp / (dp - dq))
+                };
+                if let Some(seed) = settle(a, b, guess, tolerance) {
+                    if inside(seed, bounds, tolerance) {
+                        out.push(seed);
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Moves `p` onto both surfaces, to where their tangent planes meet the plane through `p`
+/// across the direction they cross in, until it is on both within `tolerance`.
+fn settle(a: &Surface, b: &Surface, mut p: DVec3, tolerance: &Tolerance) -> Option<DVec3> {
+    for _ in 0..SETTLE_ITERATIONS {
+        let (pa, pb) = (a.closest_point(p), b.closest_point(p));
+        if pa.distance(pb) <= tolerance.linear && pa.distance(p) <= tolerance.linear {
+            return Some((pa + pb) / 2.);
+        }
+
+        let (na, nb) = (a.normal_at(pa), b.normal_at(pb));
+        let t = na.cross(nb);
+        if t.is_nan() || t.length_squared() <= 1e-8 {
+            return None;
+        }
+        let planes = DMat3::from_cols(na, nb, t).transpose();
+        p = planes.inverse() * DVec3::new(na.dot(pa), nb.dot(pb), t.dot(p));
+    }
+
+    None
+}
+
+/// The unit direction the surfaces cross in at `p`, if they are not tangent there.
+fn tangent(a: &Surface, b: &Surface, p: DVec3) -> Option<DVec3> {
+    let t = a.normal_at(p).cross(b.normal_at(p));
+    (t.length_squared() > 1e-8).then(|| t.normalize())
+}
+
+fn inside(p: DVec3, (min, max): (DVec3, DVec3), tolerance: &Tolerance) -> bool {
+    p.cmpge(min - tolerance.linear).all() && p.cmple(max + tolerance.linear).all()
+}
+
+fn segment_distance(p: DVec3, a: DVec3, b: DVec3) -> f64 {
+    let d = b - a;
+    let t = ((p - a).dot(d) / d.length_squared().max(f64::EPSILON)).clamp(0., 1.);
+    p.distance(a + t * d)
+}
+
+/// The points of the curve through `seed`, in order, and whether it closes on itself.
+fn trace(
+    a: &Surface,
+    b: &Surface,
+    seed: DVec3,
+    bounds: (DVec3, DVec3),
+    max_step: f64,
+    tolerance: &Tolerance,
+) -> (Vec<DVec3>, bool) {
+    let (forward, closed) = follow(a, b, seed, 1., bounds, max_step, tolerance);
+    if closed {
+        return (forward, true);
+    }
+
+    let (backward, _) = follow(a, b, seed, -1., bounds, max_step, tolerance);
+    let mut points: Vec<DVec3> = backward.into_iter().rev().collect();
+    points.extend(forward.into_iter().skip(1));
+    (points, false)
+}
+
+/// Steps along the curve from `seed` in one direction until it leaves `bounds`, runs off the
+/// edge of a surface or comes back round to the seed. Steps shrink where the curve turns
+/// sharply.
+fn follow(
+    a: &Surface,
+    b: &Surface,
+    seed: DVec3,
+    sign: f64,
+    bounds: (DVec3, DVec3),
+    max_step: f64,
+    tolerance: &Tolerance,
+) -> (Vec<DVec3>, bool) {
+    let min_step = (10. * tolerance.linear).min(max_step);
+    let mut points = vec![seed];
+    let Some(mut direction) = tangent(a, b, seed) else {
+        return (points, false);
+    };
+    direction *= sign;
+    let mut step = max_step;
+
+    while points.len() < MAX_STEPS {
+        let p = points[points.len() - 1];
+        let next = settle(a, b, p + step * direction, tolerance)
+            .and_then(|q| Some((q, tangent(a, b, q)?)))
+            .filter(|(q, t)| {
+                (*q - p).dot(direction) > 0. && t.dot(direction).abs() >= MAX_TURN.cos()
+            });
+
+        let Some((q, t)) = next else {
+            step /= 2.;
+            if step < min_step {
+                break;
+            }
+            continue;
+        };
+
+        if points.len() > 2 && segment_distance(seed, p, q) <= step / 10. {
+            return (points, true);
+        }
+
+        points.push(q);
+        direction = if t.dot(direction) < 0. { -t } else { t };
+        if !inside(q, bounds, tolerance) {
+            break;
+        }
+        step = (step * 1.5).min(max_step);
+    }
+
+    (points, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_4;
+
+    use super::*;
+    use crate::nurbs::NurbsSurface;
+
+    const TOLERANCE: Tolerance = Tolerance {
+        linear: 1e-4,
+        ..Tolerance::DEFAULT
+    };
+    const BOUNDS: (DVec3, DVec3) = (DVec3::splat(-5.), DVec3::splat(5.));
+
+    /// Intersects under the looser tolerance of these tests.
+    fn intersect_surfaces(
+        a: &Surface,
+        b: &Surface,
+        bounds: (DVec3, DVec3),
+    ) -> Vec<SurfaceIntersection> {
+        TOLERANCE.with(|| super::intersect_surfaces(a, b, bounds))
+    }
+
+    fn curves(found: &[SurfaceIntersection]) -> Vec<&Curve> {
+        found
+            .iter()
+            .map(|i| match i {
+                SurfaceIntersection::Curve(curve) => curve,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect()
+    }
+
+    /// Checks that points along the curve lie on both surfaces.
+    fn assert_on_both(curve: &Curve, a: &Surface, b: &Surface, tolerance: f64) {
+        let (start, end) = match curve {
+            Curve::Line(_) => (-3., 3.),
+            Curve::Circle(_) | Curve::Ellipse(_) => (0., 6.),
+            Curve::Nurbs(nurbs) => nurbs.domain(),
+        };
+        for i in 0..=20 {
+            let p = curve.point_at(start + (end - start) * i as f64 / 20.);
+            assert!(a.distance(p).abs() < tolerance, "{p} off {a:?}");
+            assert!(b.distance(p).abs() < tolerance, "{p} off {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_plane_plane() {
+        let a = Surface::Plane(Plane::new(DVec3::Z, DVec3::new(0., 0., 1.)));
+        let b = Surface::Plane(Plane::new(DVec3::new(1., 1., 0.), DVec3::new(2., 0., 0.)));
+
+        let found = intersect_surfaces(&a, &b, BOUNDS);
+        let found = curves(&found);
+        assert_eq!(found.len(), 1);
+        assert!(matches!(found[0], Curve::Line(_)));
+        assert_on_both(found[0], &a, &b, 1e-5);
+
+        let parallel = Surface::Plane(Plane::new(-DVec3::Z, DVec3::new(3., 1., 2.)));
+        assert!(intersect_surfaces(&a, &parallel, BOUNDS).is_empty());
+        let same = Surface::Plane(Plane::new(-DVec3::Z, DVec3::new(3., 1., 1.)));
+        assert!(matches!(
+            intersect_surfaces(&a, &same, BOUNDS)[..],
+            [SurfaceIntersection::Coincident]
+        ));
+    }
+
+    #[test]
+    fn test_plane_cylinder() {
+        let cylinder = Surface::cylinder(DVec3::new(1., 0., 0.), DVec3::Z, 2.);
+
+        // An oblique cut is an ellipse stretched along the slope
+        let oblique = Surface::Plane(Plane::new(DVec3::new(0., 1., 1.), DVec3::ZERO));
+        let found = intersect_surfaces(&oblique, &cylinder, BOUNDS);
+        let found = curves(&found);
+        let Curve::Ellipse(ellipse) = found[0] else {
+            panic!("{found:?}");
+        };
+        assert!((ellipse.major_radius - 2. * 2f64.sqrt()).abs() < 1e-5);
+        assert!((ellipse.minor_radius - 2.).abs() < 1e-5);
+        assert_on_both(found[0], &oblique, &cylinder, 1e-5);
+
+        let across = Surface::Plane(Plane::new(DVec3::Z, DVec3::new(0., 0., 3.)));
+        let found = intersect_surfaces(&cylinder, &across, BOUNDS);
+        assert!(matches!(curves(&found)[..], [Curve::Circle(_)]));
+        assert_on_both(curves(&found)[0], &across, &cylinder, 1e-5);
+
+        // A plane along the axis cuts two lines, or touches along one
+        let along = Surface::Plane(Plane::new(DVec3::Y, DVec3::new(0., 1., 0.)));
+        let found = intersect_surfaces(&along, &cylinder, BOUNDS);
+        assert_eq!(curves(&found).len(), 2);
+        for curve in curves(&found) {
+            assert_on_both(curve, &along, &cylinder, 1e-5);
+        }
+        let touching = Surface::Plane(Plane::new(DVec3::Y, DVec3::new(0., 2., 0.)));
+        assert_eq!(intersect_surfaces(&touching, &cylinder, BOUNDS).len(), 1);
+    }
+
+    #[test]
+    fn test_plane_sphere() {
+        let sphere = Surface::Sphere {
+            center: DVec3::ONE,
+            radius: 2.,
+        };
+
+        let plane = Surface::Plane(Plane::new(DVec3::X, DVec3::new(2., 0., 0.)));
+        let found = intersect_surfaces(&plane, &sphere, BOUNDS);
+        let found = curves(&found);
+        let Curve::Circle(circle) = found[0] else {
+            panic!("{found:?}");
+        };
+        assert!((circle.radius - 3f64.sqrt()).abs() < 1e-5);
+        assert_on_both(found[0], &plane, &sphere, 1e-5);
+
+        let touching = Surface::Plane(Plane::new(DVec3::X, DVec3::new(3., 0., 0.)));
+        let found = intersect_surfaces(&sphere, &touching, BOUNDS);
+        let [SurfaceIntersection::Point(p)] = found[..] else {
+            panic!("{found:?}");
+        };
+        assert!(p.abs_diff_eq(DVec3::new(3., 1., 1.), 1e-5));
+    }
+
+    #[test]
+    fn test_plane_cone() {
+        let cone = Surface::cone(DVec3::ZERO, DVec3::Z, FRAC_PI_4 / 2.);
+
+        // Closed sections are exact ellipses and circles
+        let tilted = Surface::Plane(Plane::new(DVec3::new(0.3, 0., 1.), DVec3::new(0., 0., 2.)));
+        let found = intersect_surfaces(&tilted, &cone, BOUNDS);
+        assert!(matches!(curves(&found)[..], [Curve::Ellipse(_)]));
+        assert_on_both(curves(&found)[0], &tilted, &cone, 1e-4);
+
+        let flat = Surface::Plane(Plane::new(DVec3::Z, DVec3::new(0., 0., -2.)));
+        let found = intersect_surfaces(&flat, &cone, BOUNDS);
+        assert!(matches!(curves(&found)[..], [Curve::Circle(_)]));
+        assert_on_both(curves(&found)[0], &flat, &cone, 1e-5);
+
+        // A plane through the axis holds two generators
+        let through = Surface::Plane(Plane::new(DVec3::Y, DVec3::ZERO));
+        let found = intersect_surfaces(&through, &cone, BOUNDS);
+        assert_eq!(curves(&found).len(), 2);
+        for curve in curves(&found) {
+            assert!(matches!(curve, Curve::Line(_)));
+            assert_on_both(curve, &through, &cone, 1e-5);
+        }
+
+        // A plane parallel to the axis cuts a hyperbola from each nappe, which are traced
+        let parallel = Surface::Plane(Plane::new(DVec3::X, DVec3::new(1., 0., 0.)));
+        let found = intersect_surfaces(&parallel, &cone, BOUNDS);
+        assert_eq!(curves(&found).len(), 2);
+        for curve in curves(&found) {
+            assert!(matches!(curve, Curve::Nurbs(_)));
+            assert_on_both(curve, &parallel, &cone, TOLERANCE.linear);
+        }
+    }
+
+    #[test]
+    fn test_cylinder_cylinder() {
+        let first = Surface::cylinder(DVec3::ZERO, DVec3::Z, 2.);
+
+        let parallel = Surface::cylinder(DVec3::new(2., 0., 0.), DVec3::Z, 2.);
+        let found = intersect_surfaces(&first, &parallel, BOUNDS);
+        assert_eq!(curves(&found).len(), 2);
+        for curve in curves(&found) {
+            assert_on_both(curve, &first, &parallel, 1e-5);
+        }
+
+        // A thinner cylinder across the first pierces it in two closed curves
+        let across = Surface::cylinder(DVec3::new(0., 0.5, 0.), DVec3::X, 1.);
+        let found = intersect_surfaces(&first, &across, BOUNDS);
+        assert_eq!(curves(&found).len(), 2);
+        for curve in curves(&found) {
+            let Curve::Nurbs(nurbs) = curve else {
+                panic!("{curve:?}");
+            };
+            assert!(nurbs.is_closed());
+            assert_on_both(curve, &first, &across, TOLERANCE.linear);
+        }
+    }
+
+    #[test]
+    fn test_nurbs_plane() {
+        // The saddle z = xy over [0, 2] x [0, 2] cut at z = 1 along the hyperbola xy = 1
+        let corners = |i: usize, j: usize| DVec3::new(i as f64, j as f64, (i * j) as f64);
+        let points = (0..3)
+            .map(|i| (0..3).map(|j| corners(i, j)).collect())
+            .collect();
+        let knots = vec![0., 0., 0., 1., 1., 1.];
+        let saddle = Surface::Nurbs(
+            NurbsSurface::non_rational((2, 2), points, (knots.clone(), knots)).unwrap(),
+        );
+        let plane = Surface::Plane(Plane::new(DVec3::Z, DVec3::Z));
+
+        let found = intersect_surfaces(&plane, &saddle, BOUNDS);
+        let found = curves(&found);
+        assert_eq!(found.len(), 1);
+        let Curve::Nurbs(nurbs) = found[0] else {
+            panic!("{found:?}");
+        };
+
+        // The curve runs from edge to edge of the patch
+        let ends = [nurbs.point_at(0.), nurbs.point_at(1.)];
+        for p in ends {
+            assert!((p.x * p.y - 1.).abs() < TOLERANCE.linear);
+            assert!((p.x.max(p.y) - 2.).abs() < 1e-2, "{p}");
+        }
+        assert_on_both(found[0], &plane, &saddle, TOLERANCE.linear);
+    }
+}
@@ -1,9 +1,11 @@
-use glam::Vec3;
+use std::fmt;
+
+use glam::DVec3;
 
 use crate::point::Point;
-use crate::Plane;
+use crate::{Plane, Tolerance};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Line {
     /// A line can be represented by a parametric equation in the form of `P(t)=P_0+t⋅v⃗`
     /// where `P_0` is a point on the line, `v⃗` is the direction vector of the line, and `t` is a
@@ -14,41 +16,74 @@ pub enum Line {
     // Representing a line by two distinct points `A` and `B` lying on the line.
     TwoPoint(TwoPointLine),
 
-    /// A line in 3D space can also be represented using an implicit equation of the form
-    /// `Ax+By+Cz+D=0`, where A,B,C are the direction coefficients and D
-    /// is a constant term defining the line.
+    /// A single equation `Ax+By+Cz+D=0` describes a plane in 3D space, so a line is
+    /// represented implicitly as the intersection of two non-parallel planes.
     Implicit(ImplicitLine),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineError {
+    /// The two points of a `TwoPointLine` coincide.
+    CoincidentPoints,
+    /// The direction vector of a `ParametricLine` has zero length.
+    ZeroDirection,
+    /// The planes of an `ImplicitLine` are parallel or one of them is degenerate.
+    ParallelPlanes,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::CoincidentPoints => write!(f, "line points coincide"),
+            LineError::ZeroDirection => write!(f, "line direction has zero length"),
+            LineError::ParallelPlanes => write!(f, "line planes do not intersect in a line"),
+        }
+    }
+}
+
+impl std::error::Error for LineError {}
+
 impl Line {
-    pub fn to_parametric(&self) -> &ParametricLine {
+    pub fn to_parametric(&self) -> Result<ParametricLine, LineError> {
         match self {
-            Line::Parametric(p) => p,
-            Line::TwoPoint(_) => todo!(),
-            Line::Implicit(_) => todo!(),
+            Line::Parametric(p) => p.validate().map(|_| p.clone()),
+            Line::TwoPoint(l) => l.to_parametric(),
+            Line::Implicit(l) => l.to_parametric(),
         }
     }
 
-    pub fn to_two_point_line(&self) -> &TwoPointLine {
+    pub fn to_two_point_line(&self) -> Result<TwoPointLine, LineError> {
         match self {
-            Line::TwoPoint(l) => l,
-            Line::Parametric(_) => todo!(),
-            Line::Implicit(_) => todo!(),
+            Line::TwoPoint(l) => l.to_parametric().map(|_| l.clone()),
+            Line::Parametric(p) => p.to_two_point_line(),
+            Line::Implicit(l) => l.to_parametric()?.to_two_point_line(),
         }
     }
 
-    pub fn project_to_plane(&self, plane: &Plane) -> Self {
-        let tpl = self.to_two_point_line();
+    pub fn to_implicit(&self) -> Result<ImplicitLine, LineError> {
+        match self {
+            Line::Implicit(l) => l.to_parametric().map(|_| l.clone()),
+            Line::Parametric(p) => p.to_implicit(),
+            Line::TwoPoint(l) => l.to_parametric()?.to_implicit(),
+        }
+    }
+
+    pub fn project_to_plane(&self, plane: &Plane) -> Result<Self, LineError> {
+        let tpl = self.to_two_point_line()?;
 
         let a = tpl.a.project_to_plane(plane);
         let b = tpl.b.project_to_plane(plane);
 
-        Self::TwoPoint(TwoPointLine::new(a.0, b.0))
+        Ok(Self::TwoPoint(TwoPointLine::new(a.0, b.0)))
     }
 
-    pub fn generate_projected_quad(&self, plane: &Plane, width: f32) -> [Vec3; 6] {
-        let projected_line = self.project_to_plane(plane);
-        let tpl = projected_line.to_two_point_line();
+    pub fn generate_projected_quad(
+        &self,
+        plane: &Plane,
+        width: f64,
+    ) -> Result<[DVec3; 6], LineError> {
+        let projected_line = self.project_to_plane(plane)?;
+        let tpl = projected_line.to_two_point_line()?;
 
         let line_dir = (tpl.b.0 - tpl.a.0).normalize();
         let line_up = line_dir.cross(plane.normal);
@@ -61,49 +96,237 @@ impl Line {
         let tr = tpl.b.0 + line_up * half_width;
         let br = tpl.b.0 - line_up * half_width;
 
-        [bl, tr, tl, bl, br, tr]
+        Ok([bl, tr, tl, bl, br, tr])
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParametricLine {
-    pub p: Vec3,
-    pub v: Vec3,
+    pub p: DVec3,
+    pub v: DVec3,
 }
 
 impl ParametricLine {
-    pub fn new(p: Vec3, v: Vec3) -> Self {
+    pub fn new(p: DVec3, v: DVec3) -> Self {
         Self { p, v }
     }
+
+    fn validate(&self) -> Result<(), LineError> {
+        if Tolerance::current().is_zero_length(self.v.length()) {
+            return Err(LineError::ZeroDirection);
+        }
+
+        Ok(())
+    }
+
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        self.p + t * self.v
+    }
+
+    /// The two points are `P(0)` and `P(1)`, so the parameterisation is preserved.
+    pub fn to_two_point_line(&self) -> Result<TwoPointLine, LineError> {
+        self.validate()?;
+
+        Ok(TwoPointLine::new(self.p, self.p + self.v))
+    }
+
+    /// Builds two perpendicular planes that both contain the line.
+    pub fn to_implicit(&self) -> Result<ImplicitLine, LineError> {
+        self.validate()?;
+
+        let dir = self.v.normalize();
+        let n1 = dir.any_orthonormal_vector();
+        let n2 = dir.cross(n1);
+
+        Ok(ImplicitLine::new(
+            PlaneEquation::from_normal_and_point(n1, self.p),
+            PlaneEquation::from_normal_and_point(n2, self.p),
+        ))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TwoPointLine {
     pub a: Point,
     pub b: Point,
 }
 
 impl TwoPointLine {
-    pub fn new(a: Vec3, b: Vec3) -> Self {
+    pub fn new(a: DVec3, b: DVec3) -> Self {
         Self {
             a: Point(a),
             b: Point(b),
         }
     }
 
-    pub fn normal(&self) -> Vec3 {
+    pub fn normal(&self) -> DVec3 {
         (self.b.0 - self.a.0).normalize()
     }
 
-    pub fn to_points(&self) -> [[f32; 3]; 2] {
+    pub fn to_points(&self) -> [[f64; 3]; 2] {
         [self.a.to_array(), self.b.to_array()]
     }
+
+    /// `P(0)` is `a` and `P(1)` is `b`.
+    pub fn to_parametric(&self) -> Result<ParametricLine, LineError> {
+        if Tolerance::current().coincident(self.a.0, self.b.0) {
+            return Err(LineError::CoincidentPoints);
+        }
+
+        Ok(ParametricLine::new(self.a.0, self.b.0 - self.a.0))
+    }
+}
+
+/// The plane `Ax+By+Cz+D=0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaneEquation {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+}
+
+impl PlaneEquation {
+    pub fn new(a: f64, b: f64, c: f64, d: f64) -> Self {
+        Self { a, b, c, d }
+    }
+
+    pub fn from_normal_and_point(normal: DVec3, point: DVec3) -> Self {
+        Self::new(normal.x, normal.y, normal.z, -normal.dot(point))
+    }
+
+    pub fn normal(&self) -> DVec3 {
+        DVec3::new(self.a, self.b, self.c)
+    }
+
+    pub fn evaluate(&self, p: DVec3) -> f64 {
+        self.normal().dot(p) + self.d
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ImplicitLine {
-    pub a: f32,
-    pub b: f32,
-    pub c: f32,
-    pub d: f32,
+    pub first: PlaneEquation,
+    pub second: PlaneEquation,
+}
+
+impl ImplicitLine {
+    pub fn new(first: PlaneEquation, second: PlaneEquation) -> Self {
+        Self { first, second }
+    }
+
+    /// The direction is `n₁ × n₂` and the point is the one on the line closest to the origin.
+    pub fn to_parametric(&self) -> Result<ParametricLine, LineError> {
+        let n1 = self.first.normal();
+        let n2 = self.second.normal();
+        let v = n1.cross(n2);
+
+        if Tolerance::current().parallel(n1, n2) {
+            return Err(LineError::ParallelPlanes);
+        }
+
+        let h1 = -self.first.d;
+        let h2 = -self.second.d;
+        let p = (h1 * n2.cross(v) + h2 * v.cross(n1)) / v.length_squared();
+
+        Ok(ParametricLine::new(p, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec_eq(a: DVec3, b: DVec3) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    fn assert_on_line(p: DVec3, line: &ParametricLine) {
+        let dist = (p - line.p).cross(line.v.normalize()).length();
+        assert!(dist < 1e-5, "{p} is {dist} away from the line");
+    }
+
+    #[test]
+    fn test_two_point_round_trip() {
+        let line = Line::TwoPoint(TwoPointLine::new(
+            DVec3::new(1., 2., 3.),
+            DVec3::new(4., -2., 3.),
+        ));
+
+        let parametric = line.to_parametric().unwrap();
+        assert_vec_eq(parametric.p, DVec3::new(1., 2., 3.));
+        assert_vec_eq(parametric.v, DVec3::new(3., -4., 0.));
+
+        let tpl = Line::Parametric(parametric).to_two_point_line().unwrap();
+        assert_vec_eq(tpl.a.0, DVec3::new(1., 2., 3.));
+        assert_vec_eq(tpl.b.0, DVec3::new(4., -2., 3.));
+    }
+
+    #[test]
+    fn test_implicit_round_trip() {
+        let parametric = ParametricLine::new(DVec3::new(1., 2., 3.), DVec3::new(0., 1., 1.));
+
+        let implicit = parametric.to_implicit().unwrap();
+        assert!(implicit.first.evaluate(parametric.p).abs() < 1e-5);
+        assert!(implicit.second.evaluate(parametric.point_at(2.)).abs() < 1e-5);
+
+        let back = Line::Implicit(implicit).to_parametric().unwrap();
+        assert!(back.v.normalize().cross(parametric.v.normalize()).length() < 1e-5);
+        assert_on_line(back.p, &parametric);
+        assert_on_line(back.point_at(1.), &parametric);
+    }
+
+    #[test]
+    fn test_implicit_to_two_point() {
+        // x = 1 and y = 2 intersect in a line parallel to the z axis
+        let implicit = ImplicitLine::new(
+            PlaneEquation::new(1., 0., 0., -1.),
+            PlaneEquation::new(0., 1., 0., -2.),
+        );
+
+        let tpl = Line::Implicit(implicit).to_two_point_line().unwrap();
+        assert_vec_eq(tpl.a.0, DVec3::new(1., 2., 0.));
+        assert_vec_eq(tpl.normal(), DVec3::Z);
+
+        let implicit = Line::TwoPoint(tpl).to_implicit().unwrap();
+        assert!(implicit.first.evaluate(DVec3::new(1., 2., 7.)).abs() < 1e-5);
+        assert!(implicit.second.evaluate(DVec3::new(1., 2., -3.)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_degenerate_lines() {
+        let line = Line::TwoPoint(TwoPointLine::new(DVec3::ONE, DVec3::ONE));
+        assert_eq!(
+            line.to_parametric().unwrap_err(),
+            LineError::CoincidentPoints
+        );
+        assert_eq!(line.to_implicit().unwrap_err(), LineError::CoincidentPoints);
+
+        let line = Line::Parametric(ParametricLine::new(DVec3::ONE, DVec3::ZERO));
+        assert_eq!(
+            line.to_two_point_line().unwrap_err(),
+            LineError::ZeroDirection
+        );
+
+        let line = Line::Implicit(ImplicitLine::new(
+            PlaneEquation::new(0., 0., 1., 0.),
+            PlaneEquation::new(0., 0., 2., -1.),
+        ));
+        assert_eq!(line.to_parametric().unwrap_err(), LineError::ParallelPlanes);
+        assert_eq!(
+            line.project_to_plane(&Plane::XY).unwrap_err(),
+            LineError::ParallelPlanes
+        );
+    }
+
+    #[test]
+    fn test_project_parametric_line_to_plane() {
+        let line = Line::Parametric(ParametricLine::new(DVec3::new(0., 0., 5.), DVec3::X));
+
+        let projected = line.project_to_plane(&Plane::XY).unwrap();
+        let tpl = projected.to_two_point_line().unwrap();
+
+        assert_vec_eq(tpl.a.0, DVec3::ZERO);
+        assert_vec_eq(tpl.b.0, DVec3::X);
+    }
 }
@@ -1,50 +1,238 @@
-use glam::Vec3;
+use std::cell::Cell;
 
-use self::line::Line;
+use glam::{DVec2, DVec3};
 
 pub mod arc;
+pub mod circle;
+pub mod curve;
+pub mod ellipse;
+pub mod intersection;
 pub mod line;
+pub mod nurbs;
 pub mod point;
+pub mod surface;
+pub mod transform;
 
+/// How far apart things may be and still count as the same. Every geometric predicate and
+/// operation in the kernel asks the current context, see [`Tolerance::current`], rather than
+/// taking a tolerance of its own. Use [`Tolerance::with`] to run an operation under another
+/// one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Points closer than this coincide and lengths shorter than this are zero.
+    pub linear: f64,
+    /// Directions within this many radians of each other are parallel.
+    pub angular: f64,
+    /// Curve and surface parameters closer than this are equal.
+    pub parametric: f64,
+}
+
+thread_local! {
+    static CURRENT: Cell<Tolerance> = const { Cell::new(Tolerance::DEFAULT) };
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Tolerance {
+    /// A tenth of a micron and a tenth of a nanoradian, for models in metres.
+    pub const DEFAULT: Self = Self {
+        linear: 1e-7,
+        angular: 1e-10,
+        parametric: 1e-9,
+    };
+
+    pub fn new(linear: f64, angular: f64, parametric: f64) -> Self {
+        Self {
+            linear,
+            angular,
+            parametric,
+        }
+    }
+
+    /// The context in effect on this thread, [`Tolerance::DEFAULT`] unless replaced.
+    pub fn current() -> Self {
+        CURRENT.with(Cell::get)
+    }
+
+    /// Makes `self` the context for this thread, returning the one it replaces.
+    pub fn set_current(self) -> Self {
+        CURRENT.with(|current| current.replace(self))
+    }
+
+    /// Runs `f` with `self` as the context for this thread, restoring the previous one
+    /// afterwards, even if `f` panics.
+    pub fn with<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(Tolerance);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                self.0.set_current();
+            }
+        }
+
+        let _restore = Restore(self.set_current());
+        f()
+    }
+
+    pub fn is_zero_length(&self, length: f64) -> bool {
+        length.abs() <= self.linear
+    }
+
+    pub fn is_zero_angle(&self, angle: f64) -> bool {
+        angle.abs() <= self.angular
+    }
+
+    pub fn coincident(&self, a: DVec3, b: DVec3) -> bool {
+        a.distance(b) <= self.linear
+    }
+
+    pub fn same_parameter(&self, s: f64, t: f64) -> bool {
+        (s - t).abs() <= self.parametric
+    }
+
+    /// Whether `a` and `b` point along the same line, in either sense. A zero vector is
+    /// parallel to everything.
+    pub fn parallel(&self, a: DVec3, b: DVec3) -> bool {
+        a.cross(b).length() <= self.angular * a.length() * b.length()
+    }
+
+    /// Whether `a` and `b` are at right angles. A zero vector is perpendicular to everything.
+    pub fn perpendicular(&self, a: DVec3, b: DVec3) -> bool {
+        a.dot(b).abs() <= self.angular * a.length() * b.length()
+    }
+}
+
+/// A plane with a local coordinate system: the origin `center`, the unit in-plane `x_axis` and
+/// the y axis `normal × x_axis`.
+#[derive(Debug, Clone)]
 pub struct Plane {
-    pub normal: Vec3,
-    pub center: Vec3,
+    pub normal: DVec3,
+    pub center: DVec3,
+    pub x_axis: DVec3,
 }
 
 impl Plane {
     pub const XY: Self = Self {
-        normal: Vec3::Z,
-        center: Vec3::ZERO,
+        normal: DVec3::Z,
+        center: DVec3::ZERO,
+        x_axis: DVec3::X,
     };
     pub const XZ: Self = Self {
-        normal: Vec3::Y,
-        center: Vec3::ZERO,
+        normal: DVec3::Y,
+        center: DVec3::ZERO,
+        x_axis: DVec3::X,
     };
     pub const YZ: Self = Self {
-        normal: Vec3::X,
-        center: Vec3::ZERO,
+        normal: DVec3::X,
+        center: DVec3::ZERO,
+        x_axis: DVec3::Y,
     };
 
-    pub fn new(normal: Vec3, center: Vec3) -> Self {
+    /// A plane through `center` whose x axis is the global X axis projected onto the plane, or
+    /// the global Y axis when the plane is perpendicular to X.
+    pub fn new(normal: DVec3, center: DVec3) -> Self {
+        let normal = normal.normalize();
+        // `(n × X) × n` is the projection of X, computed without cancellation however close to
+        // X the normal is
+        let axis = if Tolerance::current().parallel(normal, DVec3::X) {
+            DVec3::Y
+        } else {
+            DVec3::X
+        };
+        let x_axis = normal.cross(axis).normalize().cross(normal);
+
         Self {
-            normal: normal.normalize(),
+            normal,
             center,
+            x_axis,
         }
     }
-}
 
-pub struct Circle {
-    center: Vec3,
-    radius: f32,
-}
+    /// A plane through `origin` with its x axis along `x_axis` and its y axis along the part of
+    /// `y_axis` perpendicular to it.
+    pub fn from_axes(origin: DVec3, x_axis: DVec3, y_axis: DVec3) -> Self {
+        let x_axis = x_axis.normalize();
+        Self {
+            normal: x_axis.cross(y_axis).normalize(),
+            center: origin,
+            x_axis,
+        }
+    }
 
-impl Circle {
-    pub fn new(center: Vec3, radius: f32) -> Self {
-        Self { center, radius }
+    pub fn y_axis(&self) -> DVec3 {
+        self.normal.cross(self.x_axis)
+    }
+
+    /// The same plane seen from the other side: the normal and the y axis are reversed.
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            center: self.center,
+            x_axis: self.x_axis,
+        }
+    }
+
+    /// The coordinates of the projection of `p` onto the plane.
+    pub fn to_local(&self, p: DVec3) -> DVec2 {
+        let v = p - self.center;
+        DVec2::new(v.dot(self.x_axis), v.dot(self.y_axis()))
+    }
+
+    /// The point with local coordinates `p`.
+    pub fn to_world(&self, p: DVec2) -> DVec3 {
+        self.center + p.x * self.x_axis + p.y * self.y_axis()
     }
 }
 
-#[derive(Debug)]
-pub struct PolyLine {
-    lines: Vec<Line>,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::{Line, TwoPointLine};
+
+    #[test]
+    fn test_tolerance_predicates() {
+        let tolerance = Tolerance::DEFAULT;
+
+        assert!(tolerance.coincident(DVec3::ONE, DVec3::ONE + DVec3::X * 5e-8));
+        assert!(!tolerance.coincident(DVec3::ONE, DVec3::ONE + DVec3::X * 1e-6));
+        assert!(tolerance.parallel(DVec3::X, -2. * DVec3::X));
+        assert!(!tolerance.parallel(DVec3::X, DVec3::new(1., 1e-6, 0.)));
+        assert!(tolerance.perpendicular(DVec3::X, DVec3::new(1e-12, 3., 0.)));
+        assert!(!tolerance.perpendicular(DVec3::X, DVec3::new(1e-6, 1., 0.)));
+        assert!(tolerance.same_parameter(0.5, 0.5 + 1e-10));
+    }
+
+    #[test]
+    fn test_current_tolerance() {
+        // Micron features on a part a metre across stay distinct
+        let line = Line::TwoPoint(TwoPointLine::new(DVec3::ONE, DVec3::ONE + DVec3::X * 1e-6));
+        assert!(line.to_parametric().is_ok());
+
+        let previous = Tolerance::new(1e-5, 1e-8, 1e-8).set_current();
+        assert_eq!(previous, Tolerance::DEFAULT);
+        assert!(line.to_parametric().is_err());
+
+        Tolerance::DEFAULT.set_current();
+        assert_eq!(Tolerance::current(), Tolerance::default());
+
+        let loose = Tolerance::new(1e-5, 1e-8, 1e-8);
+        assert!(loose.with(|| line.to_parametric().is_err()));
+        assert_eq!(Tolerance::current(), Tolerance::DEFAULT);
+    }
+
+    #[test]
+    fn test_plane_axes() {
+        let plane = Plane::new(DVec3::new(1., 1e-9, 0.), DVec3::ZERO);
+        assert!(plane.x_axis.dot(plane.normal).abs() < 1e-15);
+        assert!((plane.x_axis.length() - 1.).abs() < 1e-15);
+
+        let plane = Plane::new(DVec3::X, DVec3::ZERO);
+        assert_eq!(plane.x_axis, DVec3::Y);
+        let plane = Plane::new(DVec3::new(1., 2., 2.), DVec3::ZERO);
+        let projected = DVec3::X - DVec3::X.dot(plane.normal) * plane.normal;
+        assert!(plane.x_axis.abs_diff_eq(projected.normalize(), 1e-15));
+    }
 }
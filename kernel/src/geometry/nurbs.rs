@@ -0,0 +1,1120 @@
+use std::f64::consts::FRAC_PI_2;
+use std::fmt;
+
+use glam::{DVec3, DVec4, Vec4Swizzles};
+
+use crate::linalg::{self, Matrix};
+use crate::line::{Line, TwoPointLine};
+use crate::{TesselationTolerance, Tolerance};
+
+/// The positive nodes and the weights of eight point Gauss–Legendre quadrature over `[-1, 1]`,
+/// exact for the polynomial pieces of curves up to degree eight.
+const GAUSS_LEGENDRE: [(f64, f64); 4] = [
+    (0.183_434_642_495_649_8, 0.362_683_783_378_362),
+    (0.525_532_409_916_329, 0.313_706_645_877_887_3),
+    (0.796_666_477_413_626_7, 0.222_381_034_453_374_5),
+    (0.960_289_856_497_536_3, 0.101_228_536_290_376_26),
+];
+
+/// A non-uniform rational B-spline curve.
+///
+/// The knot vector is clamped: its first and last knots repeat `degree + 1` times, so the curve
+/// starts at its first control point and ends at its last one. The curve is defined for
+/// parameters between those end knots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NurbsCurve {
+    pub degree: usize,
+    pub control_points: Vec<DVec3>,
+    pub weights: Vec<f64>,
+    pub knots: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NurbsError {
+    /// The degree is zero or there are not more control points than the degree.
+    InvalidDegree,
+    /// There is not one weight for every control point.
+    WeightCount,
+    /// There are not `control points + degree + 1` knots.
+    KnotCount,
+    /// A knot is smaller than the one before it.
+    DecreasingKnots,
+    /// The end knots do not repeat `degree + 1` times.
+    UnclampedKnots,
+    /// A weight is zero or negative.
+    NonPositiveWeight,
+    /// A parameter lies outside the domain of the curve, or on one of its ends where an
+    /// interior parameter is needed.
+    ParameterOutOfRange,
+    /// A knot repeats, or would repeat once inserted, more often than the degree allows:
+    /// `degree` times inside the domain and `degree + 1` times at its ends.
+    KnotMultiplicity,
+    /// The rows of a control net differ in length.
+    IrregularNet,
+    /// Fit points coincide, so no curve passes through them in order.
+    DegenerateFitPoints,
+}
+
+impl fmt::Display for NurbsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NurbsError::InvalidDegree => write!(f, "degree does not fit the control points"),
+            NurbsError::WeightCount => write!(f, "weight count does not match control points"),
+            NurbsError::KnotCount => write!(f, "knot count does not match control points"),
+            NurbsError::DecreasingKnots => write!(f, "knots are not in increasing order"),
+            NurbsError::UnclampedKnots => write!(f, "knot vector is not clamped"),
+            NurbsError::NonPositiveWeight => write!(f, "weights must be positive"),
+            NurbsError::ParameterOutOfRange => write!(f, "parameter is outside the curve"),
+            NurbsError::KnotMultiplicity => write!(f, "knot repeats more than the degree allows"),
+            NurbsError::IrregularNet => write!(f, "control net rows differ in length"),
+            NurbsError::DegenerateFitPoints => write!(f, "fit points coincide"),
+        }
+    }
+}
+
+impl std::error::Error for NurbsError {}
+
+impl NurbsCurve {
+    pub fn new(
+        degree: usize,
+        control_points: Vec<DVec3>,
+        weights: Vec<f64>,
+        knots: Vec<f64>,
+    ) -> Result<Self, NurbsError> {
+        validate_knots(degree, control_points.len(), &knots)?;
+        if weights.len() != control_points.len() {
+            return Err(NurbsError::WeightCount);
+        }
+        if weights.iter().any(|&w| w <= 0.) {
+            return Err(NurbsError::NonPositiveWeight);
+        }
+
+        Ok(Self {
+            degree,
+            control_points,
+            weights,
+            knots,
+        })
+    }
+
+    /// A polynomial B-spline, with every weight `1`.
+    pub fn non_rational(
+        degree: usize,
+        control_points: Vec<DVec3>,
+        knots: Vec<f64>,
+    ) -> Result<Self, NurbsError> {
+        let weights = vec![1.; control_points.len()];
+        Self::new(degree, control_points, weights, knots)
+    }
+
+    /// A polynomial B-spline over `[0, 1]` with evenly spaced interior knots.
+    pub fn uniform(degree: usize, control_points: Vec<DVec3>) -> Result<Self, NurbsError> {
+        let n = control_points.len();
+        if degree == 0 || n <= degree {
+            return Err(NurbsError::InvalidDegree);
+        }
+
+        let spans = n - degree;
+        let knots = (0..n + degree + 1)
+            .map(|i| (i.saturating_sub(degree).min(spans)) as f64 / spans as f64)
+            .collect();
+        Self::non_rational(degree, control_points, knots)
+    }
+
+    /// The cubic B-spline over `[0, 1]` through `points`, in order, or one of lower degree when
+    /// there are too few points for a cubic. The points are reached at parameters spaced by the
+    /// chord lengths between them.
+    ///
+    /// The curve leaves the first point along `start_tangent` and arrives at the last one along
+    /// `end_tangent` when they are given. Tangents are derivatives with respect to the
+    /// parameter divided by the total chord length, so unit tangents give curves about as full
+    /// as those without.
+    pub fn interpolate(
+        points: &[DVec3],
+        start_tangent: Option<DVec3>,
+        end_tangent: Option<DVec3>,
+    ) -> Result<Self, NurbsError> {
+        if points.len() < 2 {
+            return Err(NurbsError::InvalidDegree);
+        }
+
+        let chords: Vec<f64> = points.windows(2).map(|p| p[0].distance(p[1])).collect();
+        let total: f64 = chords.iter().sum();
+        if chords.iter().any(|&c| c <= f64::EPSILON * total) || total <= f64::EPSILON {
+            return Err(NurbsError::DegenerateFitPoints);
+        }
+        let mut parameters = vec![0.];
+        for chord in &chords[..chords.len() - 1] {
+            parameters.push(parameters[parameters.len() - 1] + chord / total);
+        }
+        parameters.push(1.);
+
+        // Every tangent adds a control point, and repeats its end parameter in the averages
+        // the interior knots are taken from
+        let mut averaged = parameters.clone();
+        if start_tangent.is_some() {
+            averaged.insert(0, 0.);
+        }
+        if end_tangent.is_some() {
+            averaged.push(1.);
+        }
+        let n = averaged.len();
+        let degree = (n - 1).min(3);
+
+        let mut knots = vec![0.; degree + 1];
+        for j in 1..n - degree {
+            knots.push(averaged[j..j + degree].iter().sum::<f64>() / degree as f64);
+        }
+        knots.extend(std::iter::repeat_n(1., degree + 1));
+
+        // One row for every fit point and every tangent, over the control point coordinates
+        let mut rows: Vec<(f64, usize, DVec3)> = Vec::with_capacity(n);
+        if let Some(tangent) = start_tangent {
+            rows.push((0., 1, tangent * total));
+        }
+        rows.extend(parameters.iter().zip(points).map(|(&t, &p)| (t, 0, p)));
+        if let Some(tangent) = end_tangent {
+            rows.push((1., 1, tangent * total));
+        }
+
+        let mut a = Matrix::zeros(n, n);
+        for (row, &(t, derivative, _)) in rows.iter().enumerate() {
+            let k = find_span(degree, &knots, t);
+            let basis = basis_derivatives(degree, &knots, k, t, derivative);
+            for (i, value) in basis[derivative].iter().enumerate() {
+                a.set(row, k - degree + i, *value);
+            }
+        }
+
+        let mut coordinates = Vec::with_capacity(3);
+        for axis in 0..3 {
+            let b: Vec<f64> = rows.iter().map(|(_, _, p)| p[axis]).collect();
+            coordinates.push(linalg::solve(&a, &b).ok_or(NurbsError::DegenerateFitPoints)?);
+        }
+        let control_points = (0..n)
+            .map(|i| DVec3::new(coordinates[0][i], coordinates[1][i], coordinates[2][i]))
+            .collect();
+
+        Self::non_rational(degree, control_points, knots)
+    }
+
+    /// The circular arc around `center` from `center + radius * x` turning through `sweep`
+    /// radians towards `y`, made of quadratic pieces of at most a quarter turn each. The
+    /// parameter runs over `[0, 1]`.
+    pub(crate) fn circular(center: DVec3, x: DVec3, y: DVec3, radius: f64, sweep: f64) -> Self {
+        let pieces = ((sweep.abs() / FRAC_PI_2).ceil() as usize).max(1);
+        let step = sweep / pieces as f64;
+        let middle_weight = (step / 2.).cos();
+
+        let point = |angle: f64| center + radius * (angle.cos() * x + angle.sin() * y);
+        let mut control_points = vec![point(0.)];
+        let mut weights = vec![1.];
+        for i in 0..pieces {
+            let a = i as f64 * step;
+            // The tangents at both ends of a piece meet above its middle
+            let middle = a + step / 2.;
+            control_points
+                .push(center + radius / middle_weight * (middle.cos() * x + middle.sin() * y));
+            control_points.push(point(a + step));
+            weights.extend([middle_weight, 1.]);
+        }
+
+        let mut knots = vec![0.; 3];
+        for i in 1..pieces {
+            let k = i as f64 / pieces as f64;
+            knots.extend([k, k]);
+        }
+        knots.extend([1.; 3]);
+
+        Self {
+            degree: 2,
+            control_points,
+            weights,
+            knots,
+        }
+    }
+
+    /// The first and last parameter of the curve.
+    pub fn domain(&self) -> (f64, f64) {
+        (
+            self.knots[self.degree],
+            self.knots[self.knots.len() - 1 - self.degree],
+        )
+    }
+
+    pub fn is_rational(&self) -> bool {
+        self.weights.iter().any(|&w| w != self.weights[0])
+    }
+
+    pub fn is_closed(&self) -> bool {
+        let first = self.control_points[0];
+        let last = self.control_points[self.control_points.len() - 1];
+        Tolerance::current().coincident(first, last)
+    }
+
+    /// The control points multiplied by their weights, with the weight as the fourth
+    /// coordinate.
+    fn homogeneous(&self) -> Vec<DVec4> {
+        self.control_points
+            .iter()
+            .zip(&self.weights)
+            .map(|(&p, &w)| (p * w).extend(w))
+            .collect()
+    }
+
+    fn from_homogeneous(degree: usize, points: &[DVec4], knots: Vec<f64>) -> Self {
+        Self {
+            degree,
+            control_points: points.iter().map(|p| p.xyz() / p.w).collect(),
+            weights: points.iter().map(|p| p.w).collect(),
+            knots,
+        }
+    }
+
+    fn span(&self, t: f64) -> usize {
+        find_span(self.degree, &self.knots, t)
+    }
+
+    /// The point at parameter `t`, evaluated with de Boor's algorithm. Parameters outside the
+    /// domain are clamped to it.
+    pub fn point_at(&self, t: f64) -> DVec3 {
+        let (start, end) = self.domain();
+        let t = t.clamp(start, end);
+        let p = self.degree;
+        let k = self.span(t);
+        let points = self.homogeneous();
+
+        let mut d: Vec<DVec4> = (0..=p).map(|j| points[j + k - p]).collect();
+        for r in 1..=p {
+            for j in (r..=p).rev() {
+                let i = j + k - p;
+                let denominator = self.knots[i + p + 1 - r] - self.knots[i];
+                let alpha = if denominator == 0. {
+                    0.
+                } else {
+                    (t - self.knots[i]) / denominator
+                };
+                d[j] = d[j - 1].lerp(d[j], alpha);
+            }
+        }
+
+        d[p].xyz() / d[p].w
+    }
+
+    /// The point and its first `n` derivatives with respect to the parameter at `t`, starting
+    /// with the point itself.
+    pub fn derivatives(&self, t: f64, n: usize) -> Vec<DVec3> {
+        let (start, end) = self.domain();
+        let t = t.clamp(start, end);
+        let p = self.degree;
+        let k = self.span(t);
+        let points = self.homogeneous();
+        let basis = basis_derivatives(p, &self.knots, k, t, n);
+
+        // Derivatives of the weighted point and of the weight
+        let homogeneous: Vec<DVec4> = basis
+            .iter()
+            .map(|row| (0..=p).map(|j| row[j] * points[k - p + j]).sum())
+            .collect();
+
+        let mut out: Vec<DVec3> = Vec::with_capacity(n + 1);
+        for i in 0..=n {
+            let mut v = homogeneous[i].xyz();
+            for j in 1..=i {
+                v -= binomial(i, j) as f64 * homogeneous[j].w * out[i - j];
+            }
+            out.push(v / homogeneous[0].w);
+        }
+        out
+    }
+
+    /// The first derivative with respect to the parameter.
+    pub fn derivative_at(&self, t: f64) -> DVec3 {
+        self.derivatives(t, 1)[1]
+    }
+
+    /// The parameter of the point on the curve closest to `p`, found by Newton iteration from
+    /// the closest of a few points spread over every knot span.
+    pub fn parameter_of(&self, p: DVec3) -> f64 {
+        let (start, end) = self.domain();
+        let samples = 8 * (self.control_points.len() - self.degree);
+
+        let mut t = (0..=samples)
+            .map(|i| start + (end - start) * i as f64 / samples as f64)
+            .min_by(|&a, &b| {
+                let da = self.point_at(a).distance_squared(p);
+                let db = self.point_at(b).distance_squared(p);
+                da.total_cmp(&db)
+            })
+            .unwrap();
+
+        let tolerance = Tolerance::current();
+        for _ in 0..16 {
+            let d = self.derivatives(t, 2);
+            let offset = d[0] - p;
+            let slope = d[2].dot(offset) + d[1].length_squared();
+            if slope.abs() <= f64::EPSILON {
+                break;
+            }
+            let next = (t - d[1].dot(offset) / slope).clamp(start, end);
+            let converged = tolerance.same_parameter(next, t);
+            t = next;
+            if converged {
+                break;
+            }
+        }
+
+        t
+    }
+
+    pub fn closest_point(&self, p: DVec3) -> DVec3 {
+        self.point_at(self.parameter_of(p))
+    }
+
+    /// How often `t` appears in the knot vector.
+    fn multiplicity(&self, t: f64) -> usize {
+        self.knots.iter().filter(|&&k| k == t).count()
+    }
+
+    /// Inserts the knot `t` `times` times without changing the shape of the curve.
+    pub fn insert_knot(&mut self, t: f64, times: usize) -> Result<(), NurbsError> {
+        let (start, end) = self.domain();
+        if t <= start || t >= end {
+            return Err(NurbsError::ParameterOutOfRange);
+        }
+        if self.multiplicity(t) + times > self.degree {
+            return Err(NurbsError::KnotMultiplicity);
+        }
+
+        let p = self.degree;
+        for _ in 0..times {
+            let k = self.span(t);
+            let points = self.homogeneous();
+            let mut inserted = Vec::with_capacity(points.len() + 1);
+            inserted.extend_from_slice(&points[..=k - p]);
+            for i in k - p + 1..=k {
+                let alpha = (t - self.knots[i]) / (self.knots[i + p] - self.knots[i]);
+                inserted.push(points[i - 1].lerp(points[i], alpha));
+            }
+            inserted.extend_from_slice(&points[k..]);
+
+            let mut knots = std::mem::take(&mut self.knots);
+            knots.insert(k + 1, t);
+            *self = Self::from_homogeneous(p, &inserted, knots);
+        }
+
+        Ok(())
+    }
+
+    /// Raises the degree by one without changing the shape of the curve. The curve is split
+    /// into Bézier pieces which are elevated one by one, so interior knots end up repeated
+    /// `degree` times.
+    pub fn elevate_degree(&mut self) {
+        let p = self.degree;
+
+        let (start, end) = self.domain();
+        let mut interior: Vec<f64> = self.knots[p + 1..self.knots.len() - p - 1].to_vec();
+        interior.dedup();
+        for t in interior.iter().copied().filter(|&t| t > start && t < end) {
+            let times = p - self.multiplicity(t);
+            if times > 0 {
+                // Cannot fail: the knot is interior and its multiplicity stays within `p`
+                let _ = self.insert_knot(t, times);
+            }
+        }
+
+        let points = self.homogeneous();
+        let pieces = (points.len() - 1) / p;
+        let mut elevated = vec![points[0]];
+        for s in 0..pieces {
+            let bezier = &points[s * p..=s * p + p];
+            for i in 1..=p {
+                let a = i as f64 / (p + 1) as f64;
+                elevated.push(a * bezier[i - 1] + (1. - a) * bezier[i]);
+            }
+            elevated.push(bezier[p]);
+        }
+
+        let mut knots = vec![start; p + 2];
+        for t in interior.iter().copied().filter(|&t| t > start && t < end) {
+            knots.extend(std::iter::repeat_n(t, p + 1));
+        }
+        knots.extend(std::iter::repeat_n(end, p + 2));
+
+        *self = Self::from_homogeneous(p + 1, &elevated, knots);
+    }
+
+    /// Splits the curve at the interior parameter `t` into the part before and the part
+    /// after it. Both keep the parameters they had on this curve.
+    pub fn split(&self, t: f64) -> Result<(Self, Self), NurbsError> {
+        let (start, end) = self.domain();
+        if t <= start || t >= end {
+            return Err(NurbsError::ParameterOutOfRange);
+        }
+
+        let p = self.degree;
+        let mut curve = self.clone();
+        let times = p.saturating_sub(curve.multiplicity(t));
+        if times > 0 {
+            curve.insert_knot(t, times)?;
+        }
+
+        // The control point shared by both halves is the one at the end of the repeated knots
+        let first = curve.knots.iter().position(|&k| k == t).unwrap();
+        let shared = first - 1;
+
+        let points = curve.homogeneous();
+        let mut before_knots = curve.knots[..first].to_vec();
+        before_knots.extend(std::iter::repeat_n(t, p + 1));
+        let mut after_knots = vec![t; p + 1];
+        after_knots.extend_from_slice(&curve.knots[first + p..]);
+
+        Ok((
+            Self::from_homogeneous(p, &points[..=shared], before_knots),
+            Self::from_homogeneous(p, &points[shared..], after_knots),
+        ))
+    }
+
+    /// The piece of the curve from `start` to `end`, with `start < end` inside the domain. It
+    /// keeps the parameters it had on this curve.
+    pub fn trimmed(&self, start: f64, end: f64) -> Result<Self, NurbsError> {
+        let (low, high) = self.domain();
+        if !(low <= start && start < end && end <= high) {
+            return Err(NurbsError::ParameterOutOfRange);
+        }
+
+        let mut curve = self.clone();
+        if start > low {
+            curve = curve.split(start)?.1;
+        }
+        if end < high {
+            curve = curve.split(end)?.0;
+        }
+        Ok(curve)
+    }
+
+    /// The same curve running the other way over the same domain.
+    pub fn reversed(&self) -> Self {
+        let (start, end) = self.domain();
+        Self {
+            degree: self.degree,
+            control_points: self.control_points.iter().rev().copied().collect(),
+            weights: self.weights.iter().rev().copied().collect(),
+            knots: self.knots.iter().rev().map(|k| start + end - k).collect(),
+        }
+    }
+
+    /// The curve with every control point moved by the affine map `f`, which moves every point
+    /// of the curve the same way.
+    pub fn map(&self, f: impl Fn(DVec3) -> DVec3) -> Self {
+        Self {
+            control_points: self.control_points.iter().map(|&p| f(p)).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// The integral `½∫ p × dp` from `start` to `end`, which may run backwards. Summed around
+    /// a closed loop, its component along the normal of the loop's plane is the signed area
+    /// the loop encloses. Integrated by Gauss–Legendre quadrature over every knot span.
+    pub fn swept_area(&self, start: f64, end: f64) -> DVec3 {
+        let (low, high) = (start.min(end), start.max(end));
+        let mut breaks: Vec<f64> = self
+            .knots
+            .iter()
+            .copied()
+            .filter(|&k| k > low && k < high)
+            .collect();
+        breaks.insert(0, low);
+        breaks.push(high);
+        breaks.dedup();
+
+        let mut area = DVec3::ZERO;
+        for span in breaks.windows(2) {
+            let (middle, half) = ((span[0] + span[1]) / 2., (span[1] - span[0]) / 2.);
+            for (x, w) in GAUSS_LEGENDRE {
+                for x in [-x, x] {
+                    let d = self.derivatives(middle + half * x, 1);
+                    area += w * half * d[0].cross(d[1]) / 2.;
+                }
+            }
+        }
+
+        if end < start {
+            -area
+        } else {
+            area
+        }
+    }
+
+    /// The curve as straight lines within `tolerance`, from its start to its end.
+    pub fn to_lines(&self, tolerance: &TesselationTolerance) -> Vec<Line> {
+        let (start, end) = self.domain();
+        let points: Vec<DVec3> = tolerance
+            .curve_parameters(start, end, |t| self.point_at(t))
+            .into_iter()
+            .map(|t| self.point_at(t))
+            .collect();
+
+        points
+            .windows(2)
+            .map(|pair| Line::TwoPoint(TwoPointLine::new(pair[0], pair[1])))
+            .collect()
+    }
+}
+
+/// A non-uniform rational B-spline surface. `control_points[i][j]` is the `i`th control point
+/// along u and the `j`th along v. Both knot vectors are clamped as for [`NurbsCurve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NurbsSurface {
+    pub degree_u: usize,
+    pub degree_v: usize,
+    pub control_points: Vec<Vec<DVec3>>,
+    pub weights: Vec<Vec<f64>>,
+    pub knots_u: Vec<f64>,
+    pub knots_v: Vec<f64>,
+}
+
+impl NurbsSurface {
+    pub fn new(
+        (degree_u, degree_v): (usize, usize),
+        control_points: Vec<Vec<DVec3>>,
+        weights: Vec<Vec<f64>>,
+        (knots_u, knots_v): (Vec<f64>, Vec<f64>),
+    ) -> Result<Self, NurbsError> {
+        let columns = control_points.first().map_or(0, Vec::len);
+        if control_points.iter().any(|row| row.len() != columns) {
+            return Err(NurbsError::IrregularNet);
+        }
+        validate_knots(degree_u, control_points.len(), &knots_u)?;
+        validate_knots(degree_v, columns, &knots_v)?;
+        if weights.len() != control_points.len() || weights.iter().any(|row| row.len() != columns) {
+            return Err(NurbsError::WeightCount);
+        }
+        if weights.iter().flatten().any(|&w| w <= 0.) {
+            return Err(NurbsError::NonPositiveWeight);
+        }
+
+        Ok(Self {
+            degree_u,
+            degree_v,
+            control_points,
+            weights,
+            knots_u,
+            knots_v,
+        })
+    }
+
+    /// A polynomial B-spline surface, with every weight `1`.
+    pub fn non_rational(
+        degrees: (usize, usize),
+        control_points: Vec<Vec<DVec3>>,
+        knots: (Vec<f64>, Vec<f64>),
+    ) -> Result<Self, NurbsError> {
+        let weights = control_points
+            .iter()
+            .map(|row| vec![1.; row.len()])
+            .collect();
+        Self::new(degrees, control_points, weights, knots)
+    }
+
+    /// The first and last parameters along u and along v.
+    pub fn domain(&self) -> ((f64, f64), (f64, f64)) {
+        let end = |degree: usize, knots: &[f64]| (knots[degree], knots[knots.len() - 1 - degree]);
+        (
+            end(self.degree_u, &self.knots_u),
+            end(self.degree_v, &self.knots_v),
+        )
+    }
+
+    /// The point at `(u, v)` and its partial derivatives along u and along v. Parameters
+    /// outside the domain are clamped to it.
+    pub fn derivatives_at(&self, u: f64, v: f64) -> (DVec3, DVec3, DVec3) {
+        let ((u0, u1), (v0, v1)) = self.domain();
+        let (u, v) = (u.clamp(u0, u1), v.clamp(v0, v1));
+        let (p, q) = (self.degree_u, self.degree_v);
+
+        let ku = find_span(p, &self.knots_u, u);
+        let kv = find_span(q, &self.knots_v, v);
+        let nu = basis_derivatives(p, &self.knots_u, ku, u, 1);
+        let nv = basis_derivatives(q, &self.knots_v, kv, v, 1);
+
+        let (mut a, mut a_u, mut a_v) = (DVec4::ZERO, DVec4::ZERO, DVec4::ZERO);
+        for (i, (n, dn)) in nu[0].iter().zip(&nu[1]).enumerate() {
+            for (j, (m, dm)) in nv[0].iter().zip(&nv[1]).enumerate() {
+                let (row, column) = (ku - p + i, kv - q + j);
+                let w = self.weights[row][column];
+                let point = (self.control_points[row][column] * w).extend(w);
+                a += n * m * point;
+                a_u += dn * m * point;
+                a_v += n * dm * point;
+            }
+        }
+
+        let s = a.xyz() / a.w;
+        (
+            s,
+            (a_u.xyz() - a_u.w * s) / a.w,
+            (a_v.xyz() - a_v.w * s) / a.w,
+        )
+    }
+
+    pub fn point_at(&self, u: f64, v: f64) -> DVec3 {
+        self.derivatives_at(u, v).0
+    }
+
+    /// The unit normal at `(u, v)`, along the cross product of the partial derivatives along u
+    /// and along v.
+    pub fn normal_at(&self, u: f64, v: f64) -> DVec3 {
+        let (_, du, dv) = self.derivatives_at(u, v);
+        du.cross(dv).normalize_or_zero()
+    }
+
+    /// The parameters of the point on the surface closest to `p`, found by Gauss–Newton
+    /// iteration from the closest point of a grid spread over every knot span.
+    pub fn parameters_of(&self, p: DVec3) -> (f64, f64) {
+        let ((u0, u1), (v0, v1)) = self.domain();
+        let samples_u = 4 * (self.control_points.len() - self.degree_u);
+        let samples_v = 4 * (self.control_points[0].len() - self.degree_v);
+
+        let grid = (0..=samples_u).flat_map(|i| {
+            (0..=samples_v).map(move |j| {
+                (
+                    u0 + (u1 - u0) * i as f64 / samples_u as f64,
+                    v0 + (v1 - v0) * j as f64 / samples_v as f64,
+                )
+            })
+        });
+        let (mut u, mut v) = grid
+            .min_by(|a, b| {
+                let da = self.point_at(a.0, a.1).distance_squared(p);
+                let db = self.point_at(b.0, b.1).distance_squared(p);
+                da.total_cmp(&db)
+            })
+            .unwrap();
+
+        let tolerance = Tolerance::current();
+        for _ in 0..32 {
+            let (s, du, dv) = self.derivatives_at(u, v);
+            let r = s - p;
+            let (a, b, c) = (du.dot(du), du.dot(dv), dv.dot(dv));
+            let det = a * c - b * b;
+            if det.abs() <= f64::EPSILON {
+                break;
+            }
+            let (gu, gv) = (du.dot(r), dv.dot(r));
+            let step_u = (c * gu - b * gv) / det;
+            let step_v = (a * gv - b * gu) / det;
+
+            let next = ((u - step_u).clamp(u0, u1), (v - step_v).clamp(v0, v1));
+            let converged =
+                tolerance.same_parameter(next.0, u) && tolerance.same_parameter(next.1, v);
+            (u, v) = next;
+            if converged {
+                break;
+            }
+        }
+
+        (u, v)
+    }
+}
+
+/// Checks that a clamped knot vector fits `n` control points of a curve of `degree`.
+fn validate_knots(degree: usize, n: usize, knots: &[f64]) -> Result<(), NurbsError> {
+    if degree == 0 || n <= degree {
+        return Err(NurbsError::InvalidDegree);
+    }
+    if knots.len() != n + degree + 1 {
+        return Err(NurbsError::KnotCount);
+    }
+    if knots.windows(2).any(|k| k[1] < k[0]) {
+        return Err(NurbsError::DecreasingKnots);
+    }
+    let last = knots.len() - 1;
+    if knots[degree] != knots[0] || knots[last - degree] != knots[last] || knots[0] == knots[last] {
+        return Err(NurbsError::UnclampedKnots);
+    }
+    let interior = &knots[degree + 1..last - degree];
+    if knots[degree + 1] == knots[0]
+        || knots[last - degree - 1] == knots[last]
+        || interior.windows(degree + 1).any(|w| w[0] == w[degree])
+    {
+        return Err(NurbsError::KnotMultiplicity);
+    }
+    Ok(())
+}
+
+/// The index of the knot span containing `t`: the `i` with `knots[i] <= t < knots[i + 1]`,
+/// clamped so the end of the domain belongs to the last span.
+fn find_span(degree: usize, knots: &[f64], t: f64) -> usize {
+    let n = knots.len() - degree - 2;
+    let (start, end) = (knots[degree], knots[n + 1]);
+    if t >= end {
+        return (degree..=n)
+            .rev()
+            .find(|&i| knots[i] < knots[i + 1])
+            .unwrap_or(n);
+    }
+    if t <= start {
+        return degree;
+    }
+
+    let (mut low, mut high) = (degree, n + 1);
+    let mut mid = (low + high) / 2;
+    while t < knots[mid] || t >= knots[mid + 1] {
+        if t < knots[mid] {
+            high = mid;
+        } else {
+            low = mid;
+        }
+        mid = (low + high) / 2;
+    }
+    mid
+}
+
+/// The values and first `n` derivatives of the basis functions that are non-zero in span
+/// `k` at `t`, as `[derivative][function]`.
+fn basis_derivatives(p: usize, u: &[f64], k: usize, t: f64, n: usize) -> Vec<Vec<f64>> {
+    // Basis functions and knot differences, as in The NURBS Book algorithm A2.3
+    let mut ndu = vec![vec![0f64; p + 1]; p + 1];
+    let mut left = vec![0f64; p + 1];
+    let mut right = vec![0f64; p + 1];
+    ndu[0][0] = 1.;
+    for j in 1..=p {
+        left[j] = t - u[k + 1 - j];
+        right[j] = u[k + j] - t;
+        let mut saved = 0.;
+        for r in 0..j {
+            ndu[j][r] = right[r + 1] + left[j - r];
+            let temp = ndu[r][j - 1] / ndu[j][r];
+            ndu[r][j] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        ndu[j][j] = saved;
+    }
+
+    let mut ders = vec![vec![0f64; p + 1]; n + 1];
+    for j in 0..=p {
+        ders[0][j] = ndu[j][p];
+    }
+
+    let mut a = vec![vec![0f64; p + 1]; 2];
+    for r in 0..=p {
+        let (mut s1, mut s2) = (0, 1);
+        a[0][0] = 1.;
+        for k in 1..=n.min(p) {
+            let mut d = 0.;
+            let rk = r as isize - k as isize;
+            let pk = p - k;
+            if r >= k {
+                a[s2][0] = a[s1][0] / ndu[pk + 1][rk as usize];
+                d = a[s2][0] * ndu[rk as usize][pk];
+            }
+            let j1 = if rk >= -1 { 1 } else { (-rk) as usize };
+            let j2 = if r as isize - 1 <= pk as isize {
+                k - 1
+            } else {
+                p - r
+            };
+            for j in j1..=j2 {
+                let idx = (rk + j as isize) as usize;
+                a[s2][j] = (a[s1][j] - a[s1][j - 1]) / ndu[pk + 1][idx];
+                d += a[s2][j] * ndu[idx][pk];
+            }
+            if r <= pk {
+                a[s2][k] = -a[s1][k - 1] / ndu[pk + 1][r];
+                d += a[s2][k] * ndu[r][pk];
+            }
+            ders[k][r] = d;
+            std::mem::swap(&mut s1, &mut s2);
+        }
+    }
+
+    let mut factor = p as f64;
+    for (k, row) in ders.iter_mut().enumerate().take(n.min(p) + 1).skip(1) {
+        for value in row {
+            *value *= factor;
+        }
+        factor *= (p - k) as f64;
+    }
+
+    ders
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, TAU};
+
+    use super::*;
+    use crate::arc::Arc;
+    use crate::circle::Circle;
+
+    fn cubic() -> NurbsCurve {
+        NurbsCurve::new(
+            3,
+            vec![
+                DVec3::new(0., 0., 0.),
+                DVec3::new(1., 2., 0.),
+                DVec3::new(3., 2., 1.),
+                DVec3::new(4., 0., 1.),
+                DVec3::new(6., -1., 0.),
+                DVec3::new(7., 1., 2.),
+            ],
+            vec![1., 0.5, 2., 1., 1.5, 1.],
+            vec![0., 0., 0., 0., 1., 2.5, 4., 4., 4., 4.],
+        )
+        .unwrap()
+    }
+
+    fn assert_same_shape(a: &NurbsCurve, b: &NurbsCurve) {
+        let (start, end) = a.domain();
+        for i in 0..=20 {
+            let t = start + (end - start) * i as f64 / 20.;
+            assert!(a.point_at(t).abs_diff_eq(b.point_at(t), 1e-4), "at {t}");
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        let points = vec![DVec3::ZERO, DVec3::X, DVec3::Y];
+        let knots = vec![0., 0., 0., 1., 1., 1.];
+
+        assert!(NurbsCurve::non_rational(2, points.clone(), knots.clone()).is_ok());
+        assert_eq!(
+            NurbsCurve::non_rational(3, points.clone(), knots.clone()),
+            Err(NurbsError::InvalidDegree)
+        );
+        assert_eq!(
+            NurbsCurve::new(2, points.clone(), vec![1., 0., 1.], knots),
+            Err(NurbsError::NonPositiveWeight)
+        );
+        assert_eq!(
+            NurbsCurve::non_rational(2, points.clone(), vec![0., 0., 1., 0.5, 1., 1.]),
+            Err(NurbsError::DecreasingKnots)
+        );
+        assert_eq!(
+            NurbsCurve::non_rational(2, points, vec![0., 0., 0.5, 0.5, 1., 1.]),
+            Err(NurbsError::UnclampedKnots)
+        );
+
+        // Interior knots may repeat at most `degree` times, end knots `degree + 1` times
+        let points: Vec<DVec3> = (0..6).map(|i| DVec3::new(i as f64, 0., 0.)).collect();
+        assert_eq!(
+            NurbsCurve::non_rational(2, points.clone(), vec![0., 0., 0., 1., 1., 1., 2., 2., 2.]),
+            Err(NurbsError::KnotMultiplicity)
+        );
+        assert_eq!(
+            NurbsCurve::non_rational(2, points.clone(), vec![0., 0., 0., 0., 1., 1., 2., 2., 2.]),
+            Err(NurbsError::KnotMultiplicity)
+        );
+        assert!(
+            NurbsCurve::non_rational(2, points, vec![0., 0., 0., 1., 1., 1.5, 2., 2., 2.]).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_evaluation_and_derivatives() {
+        let curve = cubic();
+
+        assert_eq!(curve.domain(), (0., 4.));
+        assert!(curve.point_at(0.).abs_diff_eq(DVec3::ZERO, 1e-6));
+        assert!(curve.point_at(4.).abs_diff_eq(DVec3::new(7., 1., 2.), 1e-5));
+
+        let h = 1e-2;
+        for t in [0.3, 1., 1.7, 3.2] {
+            let d = curve.derivatives(t, 2);
+            // de Boor and the basis functions agree
+            assert!(d[0].abs_diff_eq(curve.point_at(t), 1e-4));
+
+            let first = (curve.point_at(t + h) - curve.point_at(t - h)) / (2. * h);
+            assert!(d[1].abs_diff_eq(first, 1e-2), "{:?} {first:?}", d[1]);
+            let second = (curve.derivative_at(t + h) - curve.derivative_at(t - h)) / (2. * h);
+            assert!(d[2].abs_diff_eq(second, 5e-2), "{:?} {second:?}", d[2]);
+        }
+    }
+
+    #[test]
+    fn test_knot_insertion_and_split() {
+        let curve = cubic();
+
+        let mut refined = curve.clone();
+        refined.insert_knot(1.7, 2).unwrap();
+        assert_eq!(refined.control_points.len(), 8);
+        assert_same_shape(&curve, &refined);
+        assert_eq!(
+            refined.insert_knot(1.7, 2),
+            Err(NurbsError::KnotMultiplicity)
+        );
+
+        for t in [1., 2.] {
+            let (before, after) = curve.split(t).unwrap();
+            assert_eq!(before.domain(), (0., t));
+            assert_eq!(after.domain(), (t, 4.));
+            assert!(before.point_at(t).abs_diff_eq(after.point_at(t), 1e-5));
+            for s in [0., 0.25, 0.5, 0.75, 1.] {
+                let u = t * s;
+                assert!(before.point_at(u).abs_diff_eq(curve.point_at(u), 1e-4));
+                let u = t + (4. - t) * s;
+                assert!(after.point_at(u).abs_diff_eq(curve.point_at(u), 1e-4));
+            }
+        }
+        assert_eq!(curve.split(4.), Err(NurbsError::ParameterOutOfRange));
+
+        let piece = curve.trimmed(1., 2.5).unwrap();
+        assert_eq!(piece.domain(), (1., 2.5));
+        for u in [1., 1.3, 2., 2.5] {
+            assert!(piece.point_at(u).abs_diff_eq(curve.point_at(u), 1e-4));
+        }
+        assert_eq!(curve.trimmed(0., 4.), Ok(curve.clone()));
+        assert_eq!(curve.trimmed(2., 1.), Err(NurbsError::ParameterOutOfRange));
+
+        let reversed = curve.reversed();
+        assert_eq!(reversed.domain(), (0., 4.));
+        for u in [0., 0.5, 1.7, 4.] {
+            assert!(reversed
+                .point_at(4. - u)
+                .abs_diff_eq(curve.point_at(u), 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_degree_elevation() {
+        let curve = cubic();
+
+        let mut elevated = curve.clone();
+        elevated.elevate_degree();
+
+        assert_eq!(elevated.degree, 4);
+        assert_eq!(
+            elevated.knots.len(),
+            elevated.control_points.len() + elevated.degree + 1
+        );
+        assert_same_shape(&curve, &elevated);
+    }
+
+    #[test]
+    fn test_exact_circles() {
+        let center = DVec3::new(1., 2., 3.);
+        let arc = Arc::new(
+            center,
+            -DVec3::Z,
+            center + DVec3::X * 2.,
+            center + DVec3::Y * 2.,
+        )
+        .unwrap();
+        let curve = arc.to_nurbs();
+
+        // Three quarters of a turn take three quadratic pieces
+        assert_eq!(curve.control_points.len(), 7);
+        assert!(curve.point_at(0.).abs_diff_eq(arc.start, 1e-5));
+        assert!(curve.point_at(1.).abs_diff_eq(arc.end, 1e-5));
+        for i in 0..=30 {
+            let p = curve.point_at(i as f64 / 30.);
+            assert!((p.distance(center) - 2.).abs() < 1e-5);
+            assert!(p.y <= center.y + 1e-5 || p.x <= center.x + 1e-5);
+        }
+        assert!(curve.point_at(0.5).abs_diff_eq(arc.midpoint(), 1e-5));
+
+        let circle = Circle::new(center, DVec3::Y, 0.5).unwrap().to_nurbs();
+        assert!(circle.is_closed());
+        assert_eq!(circle.control_points.len(), 9);
+        let mut elevated = circle.clone();
+        elevated.elevate_degree();
+        assert_same_shape(&circle, &elevated);
+
+        let p = center + DVec3::new(3., 1., 3.);
+        let closest = circle.closest_point(p);
+        assert!(closest.abs_diff_eq(center + DVec3::new(0.5, 0., 0.5) / 2f64.sqrt(), 1e-4));
+
+        let lines = circle.to_lines(&TesselationTolerance::new(1., PI / 8.));
+        let length: f64 = lines
+            .iter()
+            .map(|l| {
+                let l = l.to_two_point_line().unwrap();
+                l.a.0.distance(l.b.0)
+            })
+            .sum();
+        assert!((length - TAU * 0.5).abs() < 0.05);
+
+        // The area inside the circle, and a quarter of it swept about its center
+        let circle = Circle::new(DVec3::ZERO, DVec3::Z, 2.).unwrap().to_nurbs();
+        assert!(circle
+            .swept_area(0., 1.)
+            .abs_diff_eq(DVec3::Z * 4. * PI, 1e-9));
+        assert!(circle
+            .swept_area(0.25, 0.)
+            .abs_diff_eq(-DVec3::Z * PI, 1e-9));
+    }
+
+    #[test]
+    fn test_surface() {
+        // A bilinear patch z = xy over [0, 2] x [0, 2], lifted to a degree 2 rational patch
+        let corners = |i: usize, j: usize| DVec3::new(i as f64, j as f64, (i * j) as f64);
+        let points = (0..3)
+            .map(|i| (0..3).map(|j| corners(i, j)).collect())
+            .collect();
+        let knots = vec![0., 0., 0., 1., 1., 1.];
+        let patch = NurbsSurface::non_rational((2, 2), points, (knots.clone(), knots)).unwrap();
+
+        assert_eq!(patch.domain(), ((0., 1.), (0., 1.)));
+        let (s, du, dv) = patch.derivatives_at(0.5, 0.25);
+        assert!(s.abs_diff_eq(DVec3::new(1., 0.5, 0.5), 1e-5));
+        assert!(du.abs_diff_eq(DVec3::new(2., 0., 1.), 1e-5));
+        assert!(dv.abs_diff_eq(DVec3::new(0., 2., 2.), 1e-5));
+        assert!(patch
+            .normal_at(0.5, 0.25)
+            .abs_diff_eq(du.cross(dv).normalize(), 1e-6));
+
+        let target = patch.point_at(0.3, 0.8);
+        let p = target + 0.1 * patch.normal_at(0.3, 0.8);
+        let (u, v) = patch.parameters_of(p);
+        assert!((u - 0.3).abs() < 1e-3 && (v - 0.8).abs() < 1e-3, "{u} {v}");
+
+        let irregular = vec![vec![DVec3::ZERO; 3], vec![DVec3::ZERO; 2]];
+        assert_eq!(
+            NurbsSurface::non_rational((1, 1), irregular, (vec![], vec![])),
+            Err(NurbsError::IrregularNet)
+        );
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let points = [
+            DVec3::new(0., 0., 0.),
+            DVec3::new(1., 2., 0.),
+            DVec3::new(3., 2., 1.),
+            DVec3::new(4., 0., 1.),
+            DVec3::new(6., -1., 0.),
+        ];
+
+        let curve = NurbsCurve::interpolate(&points, None, None).unwrap();
+        assert_eq!(curve.degree, 3);
+        assert_eq!(curve.control_points.len(), points.len());
+        for p in points {
+            assert!(curve.closest_point(p).distance(p) < 1e-4);
+        }
+
+        let total: f64 = points.windows(2).map(|p| p[0].distance(p[1])).sum();
+        let curve = NurbsCurve::interpolate(&points, Some(DVec3::X), Some(-DVec3::Y)).unwrap();
+        assert_eq!(curve.control_points.len(), points.len() + 2);
+        assert!(curve.point_at(0.).abs_diff_eq(points[0], 1e-5));
+        assert!(curve.point_at(1.).abs_diff_eq(points[4], 1e-4));
+        assert!(curve.derivative_at(0.).abs_diff_eq(DVec3::X * total, 1e-3));
+        assert!(curve.derivative_at(1.).abs_diff_eq(-DVec3::Y * total, 1e-3));
+        for p in points {
+            assert!(curve.closest_point(p).distance(p) < 1e-4);
+        }
+
+        // Two points and a tangent make a parabola
+        let curve = NurbsCurve::interpolate(&points[..2], Some(DVec3::Y), None).unwrap();
+        assert_eq!(curve.degree, 2);
+
+        assert_eq!(
+            NurbsCurve::interpolate(&[DVec3::ONE, DVec3::ONE], None, None),
+            Err(NurbsError::DegenerateFitPoints)
+        );
+    }
+}
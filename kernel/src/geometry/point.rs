@@ -1,14 +1,14 @@
 use std::ops::Deref;
 
-use glam::Vec3;
+use glam::DVec3;
 
 use crate::Plane;
 
-#[derive(Debug)]
-pub struct Point(pub Vec3);
+#[derive(Debug, Clone, Copy)]
+pub struct Point(pub DVec3);
 
 impl Deref for Point {
-    type Target = Vec3;
+    type Target = DVec3;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -16,7 +16,7 @@ impl Deref for Point {
 }
 
 impl Point {
-    pub fn new(p: Vec3) -> Self {
+    pub fn new(p: DVec3) -> Self {
         Self(p)
     }
 
@@ -35,17 +35,17 @@ mod tests {
 
     #[test]
     fn test_project_point_onto_plane() {
-        let plane = Plane::new(Vec3::Y, Vec3::ZERO);
-        let point = Point::new(Vec3::new(5., 7., 2.));
+        let plane = Plane::new(DVec3::Y, DVec3::ZERO);
+        let point = Point::new(DVec3::new(5., 7., 2.));
 
         let projected = point.project_to_plane(&plane);
 
-        assert_eq!(projected.0, Vec3::new(5., 0., 2.));
+        assert_eq!(projected.0, DVec3::new(5., 0., 2.));
 
-        let plane = Plane::new(Vec3::new(0., 1., 1.), Vec3::ZERO);
+        let plane = Plane::new(DVec3::new(0., 1., 1.), DVec3::ZERO);
 
         let projected = point.project_to_plane(&plane);
 
-        assert_eq!(projected.0, Vec3::new(5., 2.5, -2.5));
+        assert!(projected.0.abs_diff_eq(DVec3::new(5., 2.5, -2.5), 1e-12));
     }
 }
@@ -0,0 +1,595 @@
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use glam::DVec3;
+
+use crate::nurbs::{NurbsCurve, NurbsSurface};
+use crate::transform::Transform;
+use crate::{Plane, Tolerance};
+
+/// The number of Newton steps taken to find the closest point on a transformed surface.
+const CLOSEST_POINT_STEPS: usize = 16;
+
+/// The unbounded geometry of a B-rep face. Faces are trimmed by their loops.
+///
+/// Every surface is also parametrised by `(u, v)` over [`Surface::domain`]:
+///
+/// - a plane by the distances along its x and y axes (see [`Plane::x_axis`]);
+/// - a cylinder or cone by the angle `u` around its axis from the x axis of the plane
+///   perpendicular to it, and the signed distance `v` along the axis from its origin or apex;
+/// - a sphere by its longitude `u` around the global Z axis from X and its latitude `v`;
+/// - a torus by the angle `u` around its axis and the angle `v` around its tube, from the
+///   outer equator towards the axis direction;
+/// - a transformed surface by the parameters of the surface it is the image of.
+///
+/// The partial derivatives along u and along v cross in the direction of the normal.
+#[derive(Debug, Clone)]
+pub enum Surface {
+    Plane(Plane),
+    /// An infinite cylinder around the line through `origin` along `axis`. Its normal points
+    /// away from the axis.
+    Cylinder {
+        origin: DVec3,
+        axis: DVec3,
+        radius: f64,
+    },
+    /// A double cone with its tip at `apex`, opening along both directions of `axis` at
+    /// `half_angle` from it. Its normal points away from the axis.
+    Cone {
+        apex: DVec3,
+        axis: DVec3,
+        half_angle: f64,
+    },
+    /// Its normal points away from the center.
+    Sphere {
+        center: DVec3,
+        radius: f64,
+    },
+    /// The surface swept by a circle of `minor_radius` whose center runs around `axis` at
+    /// `major_radius` from `center`. Its normal points away from the swept circle's center.
+    Torus {
+        center: DVec3,
+        axis: DVec3,
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    Nurbs(NurbsSurface),
+    /// The image of a cylinder, cone, sphere or torus under a transform other than a
+    /// similarity, made by [`Surface::transformed`]. The transform never mirrors, so the
+    /// normal points away from the axis or center like that of `surface`.
+    Transformed {
+        surface: Box<Surface>,
+        transform: Transform,
+    },
+}
+
+impl Surface {
+    pub fn cylinder(origin: DVec3, axis: DVec3, radius: f64) -> Self {
+        Surface::Cylinder {
+            origin,
+            axis: axis.normalize(),
+            radius,
+        }
+    }
+
+    pub fn cone(apex: DVec3, axis: DVec3, half_angle: f64) -> Self {
+        Surface::Cone {
+            apex,
+            axis: axis.normalize(),
+            half_angle,
+        }
+    }
+
+    pub fn torus(center: DVec3, axis: DVec3, major_radius: f64, minor_radius: f64) -> Self {
+        Surface::Torus {
+            center,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    /// The normal of the surface at a point on it, before the face orientation is applied.
+    pub fn normal_at(&self, p: DVec3) -> DVec3 {
+        match self {
+            Surface::Plane(plane) => plane.normal,
+            Surface::Cylinder { origin, axis, .. } => {
+                let d = p - *origin;
+                (d - d.dot(*axis) * *axis).normalize()
+            }
+            Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let d = p - *apex;
+                let height = d.dot(*axis);
+                let radial = (d - height * *axis).normalize();
+                radial * half_angle.cos() - *axis * height.signum() * half_angle.sin()
+            }
+            Surface::Sphere { center, .. } => (p - *center).normalize(),
+            Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                ..
+            } => (p - torus_spine(*center, *axis, *major_radius, p)).normalize(),
+            Surface::Nurbs(nurbs) => {
+                let (u, v) = nurbs.parameters_of(p);
+                nurbs.normal_at(u, v)
+            }
+            Surface::Transformed { .. } => {
+                let (u, v) = self.parameters_of(p);
+                self.normal_at_parameters(u, v)
+            }
+        }
+    }
+
+    /// The signed distance of `p` from the surface along its normal.
+    pub fn distance(&self, p: DVec3) -> f64 {
+        match self {
+            Surface::Plane(plane) => (p - plane.center).dot(plane.normal),
+            Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            } => {
+                let d = p - *origin;
+                (d - d.dot(*axis) * *axis).length() - radius
+            }
+            Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let d = p - *apex;
+                let height = d.dot(*axis);
+                let radial = (d - height * *axis).length();
+                radial * half_angle.cos() - height.abs() * half_angle.sin()
+            }
+            Surface::Sphere { center, radius } => (p - *center).length() - radius,
+            Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => (p - torus_spine(*center, *axis, *major_radius, p)).length() - minor_radius,
+            Surface::Nurbs(nurbs) => {
+                let (u, v) = nurbs.parameters_of(p);
+                (p - nurbs.point_at(u, v)).dot(nurbs.normal_at(u, v))
+            }
+            Surface::Transformed { .. } => {
+                let (u, v) = self.parameters_of(p);
+                (p - self.point_at(u, v)).dot(self.normal_at_parameters(u, v))
+            }
+        }
+    }
+
+    /// The ranges of `u` and of `v`. Unbounded directions run to infinity.
+    pub fn domain(&self) -> ((f64, f64), (f64, f64)) {
+        let all = (f64::NEG_INFINITY, f64::INFINITY);
+        match self {
+            Surface::Plane(_) => (all, all),
+            Surface::Cylinder { .. } | Surface::Cone { .. } => ((0., TAU), all),
+            Surface::Sphere { .. } => ((0., TAU), (-FRAC_PI_2, FRAC_PI_2)),
+            Surface::Torus { .. } => ((0., TAU), (0., TAU)),
+            Surface::Nurbs(nurbs) => nurbs.domain(),
+            Surface::Transformed { surface, .. } => surface.domain(),
+        }
+    }
+
+    /// The point at `(u, v)` and its partial derivatives along u and along v.
+    pub fn derivatives_at(&self, u: f64, v: f64) -> (DVec3, DVec3, DVec3) {
+        match self {
+            Surface::Plane(plane) => {
+                let (x, y) = (plane.x_axis, plane.y_axis());
+                (plane.center + u * x + v * y, x, y)
+            }
+            Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            } => {
+                let (ring, turn) = ring(*axis, u);
+                (*origin + v * *axis + *radius * ring, *radius * turn, *axis)
+            }
+            Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let (ring, turn) = ring(*axis, u);
+                let tan = half_angle.tan();
+                (
+                    *apex + v * *axis + v.abs() * tan * ring,
+                    v.abs() * tan * turn,
+                    *axis + v.signum() * tan * ring,
+                )
+            }
+            Surface::Sphere { center, radius } => {
+                let (ring, turn) = ring(DVec3::Z, u);
+                let (sin, cos) = v.sin_cos();
+                (
+                    *center + *radius * (cos * ring + sin * DVec3::Z),
+                    *radius * cos * turn,
+                    *radius * (cos * DVec3::Z - sin * ring),
+                )
+            }
+            Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => {
+                let (ring, turn) = ring(*axis, u);
+                let (sin, cos) = v.sin_cos();
+                let r = major_radius + minor_radius * cos;
+                (
+                    *center + r * ring + minor_radius * sin * *axis,
+                    r * turn,
+                    *minor_radius * (cos * *axis - sin * ring),
+                )
+            }
+            Surface::Nurbs(nurbs) => nurbs.derivatives_at(u, v),
+            Surface::Transformed { surface, transform } => {
+                let (p, du, dv) = surface.derivatives_at(u, v);
+                (
+                    transform.point(p),
+                    transform.vector(du),
+                    transform.vector(dv),
+                )
+            }
+        }
+    }
+
+    pub fn point_at(&self, u: f64, v: f64) -> DVec3 {
+        self.derivatives_at(u, v).0
+    }
+
+    /// The normal at `(u, v)`, before the face orientation is applied.
+    pub fn normal_at_parameters(&self, u: f64, v: f64) -> DVec3 {
+        match self {
+            Surface::Nurbs(nurbs) => nurbs.normal_at(u, v),
+            Surface::Transformed { surface, transform } => {
+                transform.normal(surface.normal_at_parameters(u, v))
+            }
+            _ => self.normal_at(self.point_at(u, v)),
+        }
+    }
+
+    /// The parameters of the point on the surface closest to `p`. Angles around an axis are in
+    /// `[0, 2π)`, with points on the axis giving `0`.
+    pub fn parameters_of(&self, p: DVec3) -> (f64, f64) {
+        match self {
+            Surface::Plane(plane) => {
+                let d = p - plane.center;
+                (d.dot(plane.x_axis), d.dot(plane.y_axis()))
+            }
+            Surface::Cylinder { origin, axis, .. } => {
+                let d = p - *origin;
+                (angle_around(*axis, d), d.dot(*axis))
+            }
+            Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let d = p - *apex;
+                let height = d.dot(*axis);
+                let radial = (d - height * *axis).length();
+
+                // The distances along the generators on either side of the apex to the
+                // foot of the perpendicular from `p`
+                let (sin, cos) = half_angle.sin_cos();
+                let above = radial * sin + height * cos;
+                let below = radial * sin - height * cos;
+                let v = if above >= below {
+                    above.max(0.) * cos
+                } else {
+                    -below.max(0.) * cos
+                };
+
+                (angle_around(*axis, d), v)
+            }
+            Surface::Sphere { center, .. } => {
+                let d = p - *center;
+                let latitude = d.z.atan2(d.truncate().length());
+                (angle_around(DVec3::Z, d), latitude)
+            }
+            Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                ..
+            } => {
+                let d = p - *center;
+                let u = angle_around(*axis, d);
+                let (ring, _) = ring(*axis, u);
+                let e = d - *major_radius * ring;
+                (u, e.dot(*axis).atan2(e.dot(ring)).rem_euclid(TAU))
+            }
+            Surface::Nurbs(nurbs) => nurbs.parameters_of(p),
+            Surface::Transformed { surface, transform } => {
+                let start = surface.parameters_of(transform.inverse().point(p));
+                self.refine_parameters(p, start)
+            }
+        }
+    }
+
+    /// Gauss-Newton steps from `(u, v)` towards the parameters of the point closest to `p`,
+    /// kept within the domain.
+    fn refine_parameters(&self, p: DVec3, (mut u, mut v): (f64, f64)) -> (f64, f64) {
+        let ((u_low, u_high), (v_low, v_high)) = self.domain();
+        let keep = |t: f64, low: f64, high: f64| {
+            if low == 0. && high == TAU {
+                t.rem_euclid(TAU)
+            } else {
+                t.clamp(low, high)
+            }
+        };
+
+        for _ in 0..CLOSEST_POINT_STEPS {
+            let (q, du, dv) = self.derivatives_at(u, v);
+            let r = p - q;
+            let (a, b, c) = (du.dot(du), du.dot(dv), dv.dot(dv));
+            let det = a * c - b * b;
+            // At a pole or an apex the parameters no longer tell points apart
+            if det.abs() <= f64::EPSILON * a.max(c) * a.max(c) {
+                break;
+            }
+            let (x, y) = (du.dot(r), dv.dot(r));
+            let (step_u, step_v) = ((c * x - b * y) / det, (a * y - b * x) / det);
+            u = keep(u + step_u, u_low, u_high);
+            v = keep(v + step_v, v_low, v_high);
+            if (step_u * step_u * a + step_v * step_v * c).sqrt()
+                <= Tolerance::current().linear / 16.
+            {
+                break;
+            }
+        }
+        (u, v)
+    }
+
+    pub fn closest_point(&self, p: DVec3) -> DVec3 {
+        let (u, v) = self.parameters_of(p);
+        self.point_at(u, v)
+    }
+
+    /// The exact NURBS form of the part of the surface once around its axis with `v` in
+    /// `range`, with parameters from `0` to `1` along both. `None` for planes, which have no
+    /// axis.
+    pub fn to_nurbs(&self, (v0, v1): (f64, f64)) -> Option<NurbsSurface> {
+        // The section through the axis, as distances from the axis along x and along the
+        // axis along y, turned around the axis
+        let (origin, axis, section) = match self {
+            Surface::Plane(_) => return None,
+            Surface::Nurbs(nurbs) => return Some(nurbs.clone()),
+            Surface::Transformed { surface, transform } => {
+                return Some(surface.to_nurbs((v0, v1))?.transformed(transform))
+            }
+            &Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            } => {
+                let section = [DVec3::new(radius, v0, 0.), DVec3::new(radius, v1, 0.)];
+                (
+                    origin,
+                    axis,
+                    NurbsCurve::non_rational(1, section.to_vec(), vec![0., 0., 1., 1.]).ok()?,
+                )
+            }
+            &Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let point = |v: f64| DVec3::new(v.abs() * half_angle.tan(), v, 0.);
+                let section = if v0 < 0. && v1 > 0. {
+                    // Across the apex, from one nappe to the other
+                    let apex = -v0 / (v1 - v0);
+                    NurbsCurve::non_rational(
+                        1,
+                        vec![point(v0), DVec3::ZERO, point(v1)],
+                        vec![0., 0., apex, 1., 1.],
+                    )
+                } else {
+                    NurbsCurve::non_rational(1, vec![point(v0), point(v1)], vec![0., 0., 1., 1.])
+                };
+                (apex, axis, section.ok()?)
+            }
+            &Surface::Sphere { center, radius } => {
+                (center, DVec3::Z, meridian(DVec3::ZERO, radius, v0, v1))
+            }
+            &Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => (
+                center,
+                axis,
+                meridian(DVec3::X * major_radius, minor_radius, v0, v1),
+            ),
+        };
+
+        let plane = Plane::new(axis, DVec3::ZERO);
+        let turn = NurbsCurve::circular(DVec3::ZERO, plane.x_axis, plane.y_axis(), 1., TAU);
+        let control_points = turn
+            .control_points
+            .iter()
+            .map(|&ring| {
+                section
+                    .control_points
+                    .iter()
+                    .map(|p| origin + p.y * axis + p.x * ring)
+                    .collect()
+            })
+            .collect();
+        let weights = turn
+            .weights
+            .iter()
+            .map(|&w| section.weights.iter().map(|&s| w * s).collect())
+            .collect();
+
+        NurbsSurface::new(
+            (turn.degree, section.degree),
+            control_points,
+            weights,
+            (turn.knots, section.knots),
+        )
+        .ok()
+    }
+}
+
+/// The arc of the circle around `center` in the xy plane from angle `v0` to `v1`.
+fn meridian(center: DVec3, radius: f64, v0: f64, v1: f64) -> NurbsCurve {
+    let (sin, cos) = v0.sin_cos();
+    NurbsCurve::circular(
+        center,
+        DVec3::new(cos, sin, 0.),
+        DVec3::new(-sin, cos, 0.),
+        radius,
+        v1 - v0,
+    )
+}
+
+/// The unit direction at angle `u` around `axis` from the x axis of the plane perpendicular to
+/// it, and its derivative.
+fn ring(axis: DVec3, u: f64) -> (DVec3, DVec3) {
+    let plane = Plane::new(axis, DVec3::ZERO);
+    let (x, y) = (plane.x_axis, plane.y_axis());
+    let (sin, cos) = u.sin_cos();
+    (cos * x + sin * y, cos * y - sin * x)
+}
+
+/// The angle of `d` around `axis` from the x axis of the plane perpendicular to it, in
+/// `[0, 2π)`.
+fn angle_around(axis: DVec3, d: DVec3) -> f64 {
+    let plane = Plane::new(axis, DVec3::ZERO);
+    d.dot(plane.y_axis())
+        .atan2(d.dot(plane.x_axis))
+        .rem_euclid(TAU)
+}
+
+/// The point on the circle traced by the center of a torus tube that is closest to `p`.
+fn torus_spine(center: DVec3, axis: DVec3, major_radius: f64, p: DVec3) -> DVec3 {
+    let d = p - center;
+    let radial = d - d.dot(axis) * axis;
+    center + radial.normalize_or_zero() * major_radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cylinder() {
+        let cylinder = Surface::cylinder(DVec3::new(1., 0., 0.), DVec3::Z * 2., 2.);
+        let p = DVec3::new(1., 2., 5.);
+
+        assert!(cylinder.distance(p).abs() < 1e-6);
+        assert!(cylinder.normal_at(p).abs_diff_eq(DVec3::Y, 1e-6));
+        assert!((cylinder.distance(DVec3::new(1., 0., -3.)) + 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cone() {
+        let cone = Surface::cone(DVec3::ZERO, DVec3::Z, std::f64::consts::FRAC_PI_4);
+
+        for p in [DVec3::new(1., 0., 1.), DVec3::new(0., -2., -2.)] {
+            assert!(cone.distance(p).abs() < 1e-6);
+        }
+        let normal = cone.normal_at(DVec3::new(1., 0., 1.));
+        assert!(normal.abs_diff_eq(DVec3::new(1., 0., -1.).normalize(), 1e-6));
+        assert!(cone.distance(DVec3::new(3., 0., 1.)) > 0.);
+    }
+
+    #[test]
+    fn test_sphere_and_torus() {
+        let sphere = Surface::Sphere {
+            center: DVec3::ONE,
+            radius: 2.,
+        };
+        assert!(sphere.distance(DVec3::new(1., 3., 1.)).abs() < 1e-6);
+        assert!(sphere
+            .normal_at(DVec3::new(1., 3., 1.))
+            .abs_diff_eq(DVec3::Y, 1e-6));
+
+        let torus = Surface::torus(DVec3::ZERO, DVec3::Z, 3., 1.);
+        assert!(torus.distance(DVec3::new(0., 4., 0.)).abs() < 1e-6);
+        assert!(torus.distance(DVec3::new(3., 0., 1.)).abs() < 1e-6);
+        assert!(torus
+            .normal_at(DVec3::new(0., 2., 0.))
+            .abs_diff_eq(-DVec3::Y, 1e-6));
+        assert!((torus.distance(DVec3::new(3., 0., 0.)) + 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parametrisation() {
+        let points = (0..3)
+            .map(|i| {
+                (0..3)
+                    .map(|j| DVec3::new(i as f64, j as f64, ((i + j) % 2) as f64))
+                    .collect()
+            })
+            .collect();
+        let knots = vec![0., 0., 0., 1., 1., 1.];
+        let nurbs = NurbsSurface::non_rational((2, 2), points, (knots.clone(), knots)).unwrap();
+
+        let surfaces = [
+            Surface::Plane(Plane::new(DVec3::new(1., 1., 0.), DVec3::Z)),
+            Surface::cylinder(DVec3::ONE, DVec3::new(0., 1., 1.), 2.),
+            Surface::cone(DVec3::X, DVec3::Y, 0.4),
+            Surface::Sphere {
+                center: DVec3::ONE,
+                radius: 2.,
+            },
+            Surface::torus(DVec3::ZERO, DVec3::X, 3., 1.),
+            Surface::Nurbs(nurbs),
+        ];
+
+        let h = 1e-3;
+        for surface in &surfaces {
+            let ((u0, u1), (v0, v1)) = surface.domain();
+            // Away from the seams of closed directions and inside bounded NURBS domains
+            let (u, v) = if u1 - u0 < 2. {
+                (u0 + 0.3 * (u1 - u0), v0 + 0.6 * (v1 - v0))
+            } else {
+                (0.8, 0.5)
+            };
+
+            let (s, du, dv) = surface.derivatives_at(u, v);
+            let fu = (surface.point_at(u + h, v) - surface.point_at(u - h, v)) / (2. * h);
+            let fv = (surface.point_at(u, v + h) - surface.point_at(u, v - h)) / (2. * h);
+            assert!(du.abs_diff_eq(fu, 1e-2), "{surface:?}");
+            assert!(dv.abs_diff_eq(fv, 1e-2), "{surface:?}");
+
+            let normal = surface.normal_at_parameters(u, v);
+            assert!(
+                normal.abs_diff_eq(du.cross(dv).normalize(), 1e-4),
+                "{surface:?}"
+            );
+
+            // Points off the surface along the normal come back to where they started
+            let (pu, pv) = surface.parameters_of(s + 0.1 * normal);
+            assert!(
+                (pu - u).abs() < 1e-3 && (pv - v).abs() < 1e-3,
+                "{surface:?}"
+            );
+            assert!(surface.closest_point(s - 0.1 * normal).abs_diff_eq(s, 1e-3));
+            assert!((surface.distance(s + 0.1 * normal) - 0.1).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_cone_below_apex() {
+        let cone = Surface::cone(DVec3::ZERO, DVec3::Z, std::f64::consts::FRAC_PI_4);
+
+        let p = cone.point_at(0., -2.);
+        assert!(p.abs_diff_eq(DVec3::new(2., 0., -2.), 1e-6));
+        let (u, v) = cone.parameters_of(DVec3::new(3., 0., -1.));
+        assert!(u == 0. && (v + 2.).abs() < 1e-5);
+        // Points beyond the apex come to the nearer nappe
+        assert!((cone.parameters_of(DVec3::new(0.1, 0., 2.)).1 - 1.05).abs() < 1e-5);
+    }
+}
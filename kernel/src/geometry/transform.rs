@@ -0,0 +1,649 @@
+use glam::{DAffine3, DMat3, DVec3};
+
+use crate::arc::Arc;
+use crate::circle::Circle;
+use crate::curve::Curve;
+use crate::ellipse::Ellipse;
+use crate::line::{ImplicitLine, Line, ParametricLine, PlaneEquation, TwoPointLine};
+use crate::nurbs::{NurbsCurve, NurbsSurface};
+use crate::point::Point;
+use crate::surface::Surface;
+use crate::{Plane, Tolerance};
+
+/// An affine transform: any sequence of translations, rotations, mirrors, scales, stretches
+/// and shears. Lines stay lines, planes stay planes and ellipses stay ellipses.
+///
+/// Similarities, which keep angles and the ratios of lengths, map every piece of geometry
+/// onto one of the same kind. Other transforms turn circles into ellipses, arcs into splines
+/// and cylinders, cones, spheres and tori into [`Surface::Transformed`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    affine: DAffine3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        affine: DAffine3::IDENTITY,
+    };
+
+    /// Any affine map of space, or `None` if it flattens space onto a plane, a line or a
+    /// point.
+    pub fn from_affine(affine: DAffine3) -> Option<Self> {
+        let volume = affine.matrix3.determinant().abs();
+        (affine.is_finite() && volume.cbrt() > Tolerance::current().angular)
+            .then_some(Self { affine })
+    }
+
+    pub fn translation(offset: DVec3) -> Self {
+        Self {
+            affine: DAffine3::from_translation(offset),
+        }
+    }
+
+    /// A rotation by `angle` radians counter-clockwise around the line through `origin` along
+    /// `axis`.
+    pub fn rotation(origin: DVec3, axis: DVec3, angle: f64) -> Self {
+        let rotation = DMat3::from_axis_angle(axis.normalize(), angle);
+        Self::about(origin, rotation)
+    }
+
+    /// The reflection in a plane.
+    pub fn mirror(plane: &Plane) -> Self {
+        let n = plane.normal;
+        let reflection = DMat3::from_cols(
+            DVec3::X - 2. * n.x * n,
+            DVec3::Y - 2. * n.y * n,
+            DVec3::Z - 2. * n.z * n,
+        );
+        Self::about(plane.center, reflection)
+    }
+
+    /// Scaling by `factor` away from `center`. A negative factor also turns everything through
+    /// the center.
+    pub fn scale(center: DVec3, factor: f64) -> Self {
+        Self::about(center, DMat3::from_diagonal(DVec3::splat(factor)))
+    }
+
+    /// Stretching by `factor` along `axis`, away from the plane through `center`
+    /// perpendicular to it.
+    pub fn stretch(center: DVec3, axis: DVec3, factor: f64) -> Self {
+        let a = axis.normalize();
+        let outer = DMat3::from_cols(a * a.x, a * a.y, a * a.z);
+        Self::about(center, DMat3::IDENTITY + outer * (factor - 1.))
+    }
+
+    /// A shear along `direction`, which is projected into `plane`: every point moves by
+    /// `factor` times its signed distance from the plane.
+    pub fn shear(plane: &Plane, direction: DVec3, factor: f64) -> Self {
+        let n = plane.normal;
+        let d = (direction - direction.dot(n) * n).normalize();
+        let outer = DMat3::from_cols(d * n.x, d * n.y, d * n.z);
+        Self::about(plane.center, DMat3::IDENTITY + outer * factor)
+    }
+
+    /// The rigid motion carrying the coordinate system of `from` onto that of `to`, so points
+    /// keep their local coordinates.
+    pub fn between(from: &Plane, to: &Plane) -> Self {
+        let frame = |p: &Plane| DMat3::from_cols(p.x_axis, p.y_axis(), p.normal);
+        let matrix = frame(to) * frame(from).transpose();
+        Self {
+            affine: DAffine3::from_mat3_translation(matrix, to.center - matrix * from.center),
+        }
+    }
+
+    /// The linear map `matrix` with `origin` held in place.
+    fn about(origin: DVec3, matrix: DMat3) -> Self {
+        Self {
+            affine: DAffine3::from_mat3_translation(matrix, origin - matrix * origin),
+        }
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self {
+            affine: next.affine * self.affine,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            affine: self.affine.inverse(),
+        }
+    }
+
+    pub fn to_affine(&self) -> DAffine3 {
+        self.affine
+    }
+
+    /// Whether the transform keeps angles, multiplying every length by the same factor.
+    pub fn is_similarity(&self) -> bool {
+        let m = self.affine.matrix3;
+        let gram = m.transpose() * m;
+        let scale = (gram.x_axis.x + gram.y_axis.y + gram.z_axis.z) / 3.;
+        (gram * (1. / scale)).abs_diff_eq(DMat3::IDENTITY, Tolerance::current().angular)
+    }
+
+    /// Whether circles in planes perpendicular to `normal` stay circles.
+    pub fn keeps_circles(&self, normal: DVec3) -> bool {
+        let plane = Plane::new(normal, DVec3::ZERO);
+        let (x, y) = (self.vector(plane.x_axis), self.vector(plane.y_axis()));
+        let scale = (x.length_squared() + y.length_squared()) / 2.;
+        let tolerance = Tolerance::current().angular;
+
+        ((x.length_squared() - y.length_squared()) / scale).abs() <= tolerance
+            && (x.dot(y) / scale).abs() <= tolerance
+    }
+
+    /// The factor every length is multiplied by. Other than similarities stretch lengths
+    /// differently in different directions, and give the cube root of the factor volumes are
+    /// multiplied by.
+    pub fn scale_factor(&self) -> f64 {
+        if self.is_similarity() {
+            self.affine.matrix3.x_axis.length()
+        } else {
+            self.affine.matrix3.determinant().abs().cbrt()
+        }
+    }
+
+    /// Whether the transform turns space inside out, as a mirror does.
+    pub fn is_mirror(&self) -> bool {
+        self.affine.matrix3.determinant() < 0.
+    }
+
+    pub fn point(&self, p: DVec3) -> DVec3 {
+        self.affine.transform_point3(p)
+    }
+
+    /// A displacement, scaled along with the geometry.
+    pub fn vector(&self, v: DVec3) -> DVec3 {
+        self.affine.transform_vector3(v)
+    }
+
+    /// A direction, kept at its length.
+    pub fn direction(&self, d: DVec3) -> DVec3 {
+        self.vector(d).normalize() * d.length()
+    }
+
+    /// An axis of rotation, such as the normal of a circle or a plane, kept at its length. It
+    /// stays perpendicular to the mapped plane, and is reversed by a mirror so that it still
+    /// turns the same way relative to the mapped geometry.
+    pub fn normal(&self, n: DVec3) -> DVec3 {
+        // The matrix of cofactors maps the cross products of vectors to those of their images
+        let m = self.affine.matrix3;
+        let cofactors = DMat3::from_cols(
+            m.y_axis.cross(m.z_axis),
+            m.z_axis.cross(m.x_axis),
+            m.x_axis.cross(m.y_axis),
+        );
+        (cofactors * n).normalize() * n.length()
+    }
+
+    pub fn length(&self, l: f64) -> f64 {
+        l * self.scale_factor()
+    }
+}
+
+impl Point {
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Point(transform.point(self.0))
+    }
+}
+
+impl ParametricLine {
+    /// The image of the line, with every parameter mapping to the image of its point.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self::new(transform.point(self.p), transform.vector(self.v))
+    }
+}
+
+impl TwoPointLine {
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self {
+            a: self.a.transformed(transform),
+            b: self.b.transformed(transform),
+        }
+    }
+}
+
+impl PlaneEquation {
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let normal = self.normal();
+        let foot = -self.d * normal / normal.length_squared();
+        Self::from_normal_and_point(transform.normal(normal), transform.point(foot))
+    }
+}
+
+impl ImplicitLine {
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self::new(
+            self.first.transformed(transform),
+            self.second.transformed(transform),
+        )
+    }
+}
+
+impl Line {
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        match self {
+            Line::Parametric(line) => Line::Parametric(line.transformed(transform)),
+            Line::TwoPoint(line) => Line::TwoPoint(line.transformed(transform)),
+            Line::Implicit(line) => Line::Implicit(line.transformed(transform)),
+        }
+    }
+}
+
+impl Plane {
+    /// The image of the plane with its x axis turned to the image of the old one. Under
+    /// similarities points keep their local coordinates up to the scale. A mirror reverses the
+    /// normal along with the handedness.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self {
+            normal: transform.normal(self.normal),
+            center: transform.point(self.center),
+            x_axis: transform.direction(self.x_axis),
+        }
+    }
+}
+
+impl Arc {
+    /// The image of the arc, or `None` if the transform does not keep it round. Its exact
+    /// NURBS form, see [`Arc::to_nurbs`], maps onto the image in either case.
+    pub fn transformed(&self, transform: &Transform) -> Option<Self> {
+        let radial = (self.start - self.center).normalize();
+        transform.keeps_circles(self.normal).then(|| Self {
+            center: transform.point(self.center),
+            normal: transform.normal(self.normal),
+            radius: self.radius * transform.vector(radial).length(),
+            start: transform.point(self.start),
+            end: transform.point(self.end),
+        })
+    }
+}
+
+impl Circle {
+    /// The image of the circle, with every parameter mapping to the image of its point, or
+    /// `None` if the transform does not keep it round. [`Curve::transformed`] turns it into an
+    /// ellipse then.
+    pub fn transformed(&self, transform: &Transform) -> Option<Self> {
+        transform.keeps_circles(self.normal).then(|| Self {
+            center: transform.point(self.center),
+            normal: transform.normal(self.normal),
+            x_axis: transform.direction(self.x_axis),
+            radius: self.radius * transform.vector(self.x_axis).length(),
+        })
+    }
+
+    /// The circle as an ellipse with equal radii and the same parameters.
+    pub fn to_ellipse(&self) -> Ellipse {
+        Ellipse {
+            center: self.center,
+            normal: self.normal,
+            x_axis: self.x_axis,
+            major_radius: self.radius,
+            minor_radius: self.radius,
+        }
+    }
+}
+
+impl Ellipse {
+    /// The image of the ellipse. Similarities map every parameter to the image of its point;
+    /// other transforms shift the parameters, so that the image starts at the end of one of
+    /// its axes.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let center = transform.point(self.center);
+        let normal = transform.normal(self.normal);
+        if transform.is_similarity() {
+            return Self {
+                center,
+                normal,
+                x_axis: transform.direction(self.x_axis),
+                major_radius: transform.length(self.major_radius),
+                minor_radius: transform.length(self.minor_radius),
+            };
+        }
+
+        // The images of the radii are conjugate: the point at `t` is `p cos t + q sin t` from
+        // the center, furthest from and closest to it a quarter turn apart from `t0`
+        let p = transform.vector(self.major_radius * self.x_axis);
+        let q = transform.vector(self.minor_radius * self.y_axis());
+        let t0 = (2. * p.dot(q)).atan2(p.length_squared() - q.length_squared()) / 2.;
+        let (sin, cos) = t0.sin_cos();
+        let major = p * cos + q * sin;
+        let minor = q * cos - p * sin;
+
+        Self {
+            center,
+            normal,
+            x_axis: major.normalize(),
+            major_radius: major.length(),
+            minor_radius: minor.length(),
+        }
+    }
+}
+
+impl NurbsCurve {
+    /// The image of the curve, found by mapping the control points.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self {
+            control_points: self
+                .control_points
+                .iter()
+                .map(|&p| transform.point(p))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl NurbsSurface {
+    /// The image of the surface, found by mapping the control points.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        Self {
+            control_points: self
+                .control_points
+                .iter()
+                .map(|row| row.iter().map(|&p| transform.point(p)).collect())
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Curve {
+    /// The image of the curve. Circles the transform does not keep round become ellipses, and
+    /// ellipses it makes round become circles. Parameters map to the images of their points,
+    /// except where [`Ellipse::transformed`] shifts them.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let ellipse = |ellipse: &Ellipse| {
+            let image = ellipse.transformed(transform);
+            if Tolerance::current().is_zero_length(image.major_radius - image.minor_radius) {
+                Curve::Circle(Circle {
+                    center: image.center,
+                    normal: image.normal,
+                    x_axis: image.x_axis,
+                    radius: image.major_radius,
+                })
+            } else {
+                Curve::Ellipse(image)
+            }
+        };
+
+        match self {
+            Curve::Line(line) => Curve::Line(line.transformed(transform)),
+            Curve::Circle(circle) => match circle.transformed(transform) {
+                Some(image) => Curve::Circle(image),
+                None => ellipse(&circle.to_ellipse()),
+            },
+            Curve::Ellipse(e) => ellipse(e),
+            Curve::Nurbs(nurbs) => Curve::Nurbs(nurbs.transformed(transform)),
+        }
+    }
+}
+
+impl Surface {
+    /// The image of the surface. A mirror reverses the normals of planes and NURBS surfaces,
+    /// which follow their parametrisation, while the other surfaces keep pointing away from
+    /// their axis or center.
+    ///
+    /// Cylinders, cones, spheres and tori keep their kind under similarities only, and
+    /// otherwise become a [`Surface::Transformed`]. Transforming one of those again composes
+    /// the transforms, giving back the analytic surface once they add up to a similarity.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let (base, total) = match self {
+            Surface::Plane(_) | Surface::Nurbs(_) => return self.similar(transform),
+            Surface::Transformed {
+                surface,
+                transform: first,
+            } => (surface.as_ref(), first.then(transform)),
+            _ => (self, *transform),
+        };
+        if total.is_similarity() {
+            return base.similar(&total);
+        }
+
+        // A mirror is taken on by the surface itself, which keeps its normal pointing outwards
+        if total.is_mirror() {
+            let flip = Transform::mirror(&Plane::new(DVec3::Z, DVec3::ZERO));
+            return Surface::Transformed {
+                surface: Box::new(base.similar(&flip)),
+                transform: flip.then(&total),
+            };
+        }
+        Surface::Transformed {
+            surface: Box::new(base.clone()),
+            transform: total,
+        }
+    }
+
+    /// The image of a surface under a similarity, or of a plane or a NURBS surface under any
+    /// transform.
+    fn similar(&self, transform: &Transform) -> Self {
+        match self {
+            Surface::Plane(plane) => Surface::Plane(plane.transformed(transform)),
+            Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            } => Surface::Cylinder {
+                origin: transform.point(*origin),
+                axis: transform.direction(*axis),
+                radius: transform.length(*radius),
+            },
+            Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => Surface::Cone {
+                apex: transform.point(*apex),
+                axis: transform.direction(*axis),
+                half_angle: *half_angle,
+            },
+            Surface::Sphere { center, radius } => Surface::Sphere {
+                center: transform.point(*center),
+                radius: transform.length(*radius),
+            },
+            Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => Surface::Torus {
+                center: transform.point(*center),
+                axis: transform.direction(*axis),
+                major_radius: transform.length(*major_radius),
+                minor_radius: transform.length(*minor_radius),
+            },
+            Surface::Nurbs(nurbs) => Surface::Nurbs(nurbs.transformed(transform)),
+            Surface::Transformed { .. } => self.transformed(transform),
+        }
+    }
+
+    /// Whether [`Surface::transformed`] reverses the normal relative to the mapped geometry.
+    pub fn normal_reversed_by(&self, transform: &Transform) -> bool {
+        transform.is_mirror() && matches!(self, Surface::Plane(_) | Surface::Nurbs(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_rotation_and_translation() {
+        let rotation = Transform::rotation(DVec3::new(1., 0., 0.), DVec3::Z, FRAC_PI_2);
+        assert!(rotation
+            .point(DVec3::new(2., 0., 5.))
+            .abs_diff_eq(DVec3::new(1., 1., 5.), 1e-12));
+
+        let moved = rotation.then(&Transform::translation(DVec3::new(0., 0., -5.)));
+        assert!(moved
+            .point(DVec3::new(2., 0., 5.))
+            .abs_diff_eq(DVec3::new(1., 1., 0.), 1e-12));
+        assert!(moved
+            .inverse()
+            .point(DVec3::new(1., 1., 0.))
+            .abs_diff_eq(DVec3::new(2., 0., 5.), 1e-12));
+        assert!(!moved.is_mirror());
+        assert!((moved.scale_factor() - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mirror_keeps_curve_parameters() {
+        let mirror = Transform::mirror(&Plane::new(DVec3::X, DVec3::new(1., 0., 0.)));
+        assert!(mirror.is_mirror());
+
+        let circle = Circle::new(DVec3::new(3., 1., 0.), DVec3::Z, 1.).unwrap();
+        let image = circle.transformed(&mirror).unwrap();
+        for t in [0., 0.5, 2., 4.] {
+            assert!(image
+                .point_at(t)
+                .abs_diff_eq(mirror.point(circle.point_at(t)), 1e-12));
+        }
+
+        // The arc still runs from the image of its start to the image of its end
+        let arc = Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X, DVec3::Y).unwrap();
+        let image = arc.transformed(&mirror).unwrap();
+        assert!((image.sweep() - arc.sweep()).abs() < 1e-12);
+        assert!(image.start.abs_diff_eq(DVec3::new(1., 0., 0.), 1e-12));
+        assert!(image.end.abs_diff_eq(DVec3::new(2., 1., 0.), 1e-12));
+    }
+
+    #[test]
+    fn test_scale() {
+        let scale = Transform::scale(DVec3::ONE, 2.);
+        assert!((scale.scale_factor() - 2.).abs() < 1e-12);
+
+        let arc = Arc::new(
+            DVec3::ONE,
+            DVec3::Z,
+            DVec3::new(2., 1., 1.),
+            DVec3::new(1., 2., 1.),
+        )
+        .unwrap();
+        let image = arc.transformed(&scale).unwrap();
+        assert_eq!(image.radius, 2.);
+        assert!(image.end.abs_diff_eq(DVec3::new(1., 3., 1.), 1e-12));
+
+        let Surface::Sphere { center, radius } = Surface::Sphere {
+            center: DVec3::ZERO,
+            radius: 1.,
+        }
+        .transformed(&scale) else {
+            unreachable!()
+        };
+        assert!(center.abs_diff_eq(-DVec3::ONE, 1e-12));
+        assert_eq!(radius, 2.);
+    }
+
+    #[test]
+    fn test_plane_between() {
+        let from = Plane::XY;
+        let to = Plane::from_axes(DVec3::new(0., 0., 3.), DVec3::Y, DVec3::Z);
+        assert!(to.normal.abs_diff_eq(DVec3::X, 1e-12));
+
+        let placement = Transform::between(&from, &to);
+        let p = DVec3::new(1., 2., 0.);
+        assert!(placement
+            .point(p)
+            .abs_diff_eq(to.to_world(from.to_local(p)), 1e-12));
+
+        let placed = from.transformed(&placement);
+        assert!(placed.center.abs_diff_eq(to.center, 1e-12));
+        assert!(placed.x_axis.abs_diff_eq(to.x_axis, 1e-12));
+        assert!(placed.normal.abs_diff_eq(to.normal, 1e-12));
+    }
+
+    #[test]
+    fn test_stretch_circle_and_arc() {
+        let stretch = Transform::stretch(DVec3::ZERO, DVec3::X, 3.);
+        assert!(!stretch.is_similarity());
+        assert!((stretch.scale_factor() - 3f64.cbrt()).abs() < 1e-12);
+
+        // A circle across the stretch becomes an ellipse through the images of its points
+        let circle = Curve::circle(DVec3::new(1., 0., 0.), DVec3::Z, 2.).unwrap();
+        let Curve::Ellipse(ellipse) = circle.transformed(&stretch) else {
+            panic!("the circle stayed round");
+        };
+        assert!((ellipse.major_radius - 6.).abs() < 1e-12);
+        assert!((ellipse.minor_radius - 2.).abs() < 1e-12);
+        assert!(ellipse.center.abs_diff_eq(DVec3::new(3., 0., 0.), 1e-12));
+        for t in [0., 1., 2.5, 4.] {
+            let p = stretch.point(circle.point_at(t));
+            assert!(ellipse.closest_point(p).abs_diff_eq(p, 1e-9));
+        }
+
+        // One in a plane along the stretch stays round
+        let side = Circle::new(DVec3::ZERO, DVec3::X, 1.).unwrap();
+        assert_eq!(side.transformed(&stretch).unwrap().radius, 1.);
+
+        // An arc keeps its exact NURBS form instead
+        let arc = Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X, DVec3::Y).unwrap();
+        assert!(arc.transformed(&stretch).is_none());
+        let image = arc.to_nurbs().transformed(&stretch);
+        for i in 0..=8 {
+            let p = image.point_at(i as f64 / 8.);
+            assert!((p.x * p.x / 9. + p.y * p.y - 1.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_shear_ellipse_and_plane() {
+        let shear = Transform::shear(&Plane::XY, DVec3::X, 1.);
+        let ellipse = Ellipse::new(DVec3::ZERO, DVec3::Y, DVec3::X, 2., 1.).unwrap();
+        let image = ellipse.transformed(&shear);
+
+        // The axes of the image are perpendicular and the image runs through the images of
+        // the points of the ellipse
+        assert!(image.x_axis.dot(image.normal).abs() < 1e-12);
+        assert!(image.major_radius >= image.minor_radius);
+        assert!((image.major_radius * image.minor_radius - 2.).abs() < 1e-9);
+        for t in [0., 0.7, 2., 3.5, 5.] {
+            let p = shear.point(ellipse.point_at(t));
+            assert!(image.closest_point(p).abs_diff_eq(p, 1e-9));
+        }
+
+        // The normal of a sheared plane stays perpendicular to it
+        let plane = Plane::new(DVec3::new(1., 0., 1.), DVec3::ZERO);
+        let sheared = plane.transformed(&shear);
+        for d in [plane.x_axis, plane.y_axis()] {
+            assert!(shear.vector(d).dot(sheared.normal).abs() < 1e-12);
+        }
+        assert!((sheared.normal.length() - 1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_transformed_surface() {
+        let cylinder = Surface::cylinder(DVec3::ZERO, DVec3::Z, 1.);
+        let stretch = Transform::stretch(DVec3::ZERO, DVec3::Y, 2.);
+        let image = cylinder.transformed(&stretch);
+        assert!(matches!(image, Surface::Transformed { .. }));
+
+        // Its points, normals and distances are those of an elliptic cylinder
+        let p = DVec3::new(0., 2., 5.);
+        assert!(image.distance(p).abs() < 1e-9);
+        assert!(image.normal_at(p).abs_diff_eq(DVec3::Y, 1e-9));
+        assert!((image.distance(DVec3::new(3., 0., 1.)) - 2.).abs() < 1e-9);
+        let (u, v) = image.parameters_of(DVec3::new(0.6, 1.6, -1.));
+        assert!(image
+            .point_at(u, v)
+            .abs_diff_eq(DVec3::new(0.6, 1.6, -1.), 1e-9));
+
+        // Stretching back gives the cylinder again, and a mirror keeps the normal outwards
+        let back = image.transformed(&stretch.inverse());
+        assert!(matches!(back, Surface::Cylinder { .. }));
+        let mirror = Transform::mirror(&Plane::new(DVec3::X, DVec3::ZERO));
+        let mirrored = image.transformed(&mirror);
+        assert!(!image.normal_reversed_by(&mirror));
+        assert!(mirrored
+            .normal_at(DVec3::new(-1., 0., 0.))
+            .abs_diff_eq(-DVec3::X, 1e-9));
+    }
+}
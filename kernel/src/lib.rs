@@ -1,7 +1,26 @@
+mod boolean;
 mod boundary_geometry;
+mod brep;
+mod dxf;
+mod features;
 mod geometry;
+mod linalg;
 mod sketch;
+mod step;
+mod stl;
+mod svg;
 mod tesselation;
+#[cfg(test)]
+mod test_util;
 
+pub use boolean::*;
+pub use boundary_geometry::*;
+pub use brep::*;
+pub use dxf::*;
+pub use features::*;
 pub use geometry::*;
 pub use sketch::*;
+pub use step::*;
+pub use stl::*;
+pub use svg::*;
+pub use tesselation::*;
@@ -0,0 +1,246 @@
+//! Small dense linear algebra used by the numeric solvers.
+
+/// A dense, row-major matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![0.; rows * cols],
+        }
+    }
+
+    /// Stacks equally long rows into a matrix with `cols` columns.
+    pub fn from_rows(rows: &[Vec<f64>], cols: usize) -> Self {
+        Self {
+            rows: rows.len(),
+            cols,
+            data: rows.concat(),
+        }
+    }
+
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.data[row * self.cols + col] = value;
+    }
+
+    /// Computes `Aᵀ·A`.
+    pub fn transpose_mul_self(&self) -> Matrix {
+        let mut out = Matrix::zeros(self.cols, self.cols);
+
+        for i in 0..self.cols {
+            for j in i..self.cols {
+                let mut sum = 0.;
+                for k in 0..self.rows {
+                    sum += self.get(k, i) * self.get(k, j);
+                }
+                out.set(i, j, sum);
+                out.set(j, i, sum);
+            }
+        }
+
+        out
+    }
+
+    /// Computes `Aᵀ·v`.
+    pub fn transpose_mul_vec(&self, v: &[f64]) -> Vec<f64> {
+        let mut out = vec![0.; self.cols];
+
+        for (k, vk) in v.iter().enumerate() {
+            for (i, o) in out.iter_mut().enumerate() {
+                *o += self.get(k, i) * vk;
+            }
+        }
+
+        out
+    }
+}
+
+/// Solves the square system `A·x = b` with Gaussian elimination and partial pivoting.
+/// Returns `None` if the matrix is singular.
+pub fn solve(a: &Matrix, b: &[f64]) -> Option<Vec<f64>> {
+    let n = a.rows;
+    let mut m = a.clone();
+    let mut x = b.to_vec();
+
+    let scale = m.data.iter().fold(0f64, |acc, v| acc.max(v.abs())).max(1.);
+
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&i, &j| m.get(i, col).abs().total_cmp(&m.get(j, col).abs()))?;
+
+        if m.get(pivot, col).abs() <= 1e-14 * scale {
+            return None;
+        }
+
+        if pivot != col {
+            for k in 0..n {
+                m.data.swap(pivot * n + k, col * n + k);
+            }
+            x.swap(pivot, col);
+        }
+
+        for row in col + 1..n {
+            let factor = m.get(row, col) / m.get(col, col);
+            if factor == 0. {
+                continue;
+            }
+            for k in col..n {
+                let v = m.get(row, k) - factor * m.get(col, k);
+                m.set(row, k, v);
+            }
+            x[row] -= factor * x[col];
+        }
+    }
+
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| m.get(row, k) * x[k]).sum();
+        x[row] = (x[row] - sum) / m.get(row, row);
+    }
+
+    Some(x)
+}
+
+/// Reduces `a` to reduced row echelon form in place and returns the pivot column of every
+/// non-zero row. Entries below `tolerance` times the largest entry are treated as zero.
+pub fn row_reduce(a: &mut Matrix, tolerance: f64) -> Vec<usize> {
+    let scale = a.data.iter().fold(0f64, |acc, v| acc.max(v.abs()));
+    let threshold = tolerance * scale.max(1e-300);
+
+    let mut pivots = Vec::new();
+    let mut row = 0;
+
+    for col in 0..a.cols {
+        if row == a.rows {
+            break;
+        }
+
+        let Some(pivot) =
+            (row..a.rows).max_by(|&i, &j| a.get(i, col).abs().total_cmp(&a.get(j, col).abs()))
+        else {
+            break;
+        };
+
+        if a.get(pivot, col).abs() <= threshold {
+            for r in row..a.rows {
+                a.set(r, col, 0.);
+            }
+            continue;
+        }
+
+        for k in 0..a.cols {
+            a.data.swap(pivot * a.cols + k, row * a.cols + k);
+        }
+
+        let p = a.get(row, col);
+        for k in 0..a.cols {
+            let v = a.get(row, k) / p;
+            a.set(row, k, v);
+        }
+
+        for r in 0..a.rows {
+            if r == row {
+                continue;
+            }
+            let factor = a.get(r, col);
+            if factor == 0. {
+                continue;
+            }
+            for k in 0..a.cols {
+                let v = a.get(r, k) - factor * a.get(row, k);
+                a.set(r, k, v);
+            }
+        }
+
+        pivots.push(col);
+        row += 1;
+    }
+
+    pivots
+}
+
+pub fn rank(a: &Matrix, tolerance: f64) -> usize {
+    row_reduce(&mut a.clone(), tolerance).len()
+}
+
+/// A basis of the vectors `x` with `A·x = 0`.
+pub fn null_space(a: &Matrix, tolerance: f64) -> Vec<Vec<f64>> {
+    let mut reduced = a.clone();
+    let pivots = row_reduce(&mut reduced, tolerance);
+
+    let mut basis = Vec::new();
+    for free in (0..a.cols).filter(|c| !pivots.contains(c)) {
+        let mut v = vec![0.; a.cols];
+        v[free] = 1.;
+        for (row, &pivot) in pivots.iter().enumerate() {
+            v[pivot] = -reduced.get(row, free);
+        }
+        basis.push(v);
+    }
+
+    basis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let a = Matrix {
+            rows: 3,
+            cols: 3,
+            data: vec![0., 2., 1., 1., 1., 1., 2., 1., 0.],
+        };
+
+        let x = solve(&a, &[7., 6., 4.]).unwrap();
+
+        for (x, expected) in x.iter().zip([1., 2., 3.]) {
+            assert!((x - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_solve_singular() {
+        let a = Matrix {
+            rows: 2,
+            cols: 2,
+            data: vec![1., 2., 2., 4.],
+        };
+
+        assert!(solve(&a, &[1., 2.]).is_none());
+    }
+
+    #[test]
+    fn test_rank_and_null_space() {
+        let a = Matrix {
+            rows: 3,
+            cols: 3,
+            data: vec![1., 2., 3., 2., 4., 6., 0., 1., 1.],
+        };
+
+        assert_eq!(rank(&a, 1e-12), 2);
+
+        let basis = null_space(&a, 1e-12);
+        assert_eq!(basis.len(), 1);
+
+        for row in 0..a.rows {
+            let dot: f64 = (0..a.cols).map(|c| a.get(row, c) * basis[0][c]).sum();
+            assert!(dot.abs() < 1e-12);
+        }
+    }
+}
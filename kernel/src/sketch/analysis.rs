@@ -0,0 +1,327 @@
+use crate::linalg::{self, Matrix};
+
+use super::{Sketch, SolveError, SolverSettings, System};
+
+/// Relative size below which a Jacobian row is treated as a combination of other rows.
+const RANK_TOLERANCE: f64 = 1e-6;
+
+/// Identifies a relation or a dimension of a [`Sketch`] by its index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstraintRef {
+    Relation(usize),
+    Dimension(usize),
+}
+
+/// A constraint whose equations depend on constraints that came before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overconstraint {
+    pub constraint: ConstraintRef,
+    /// A minimal set of constraints, including `constraint`, whose equations are dependent.
+    /// Removing any one of them resolves the dependency.
+    pub set: Vec<ConstraintRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintStatus {
+    UnderConstrained,
+    FullyConstrained,
+    OverConstrained,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintAnalysis {
+    /// The degrees of freedom left in the whole sketch.
+    pub degrees_of_freedom: usize,
+    /// The degrees of freedom left in every element, in the order of [`Sketch::elements`].
+    /// Freedoms that move several elements together count towards each of them.
+    pub element_freedom: Vec<usize>,
+    /// Constraints that are implied by others and are satisfied.
+    pub redundant: Vec<Overconstraint>,
+    /// Constraints that contradict others and cannot be satisfied.
+    pub conflicting: Vec<Overconstraint>,
+}
+
+impl ConstraintAnalysis {
+    pub fn status(&self) -> ConstraintStatus {
+        if !self.conflicting.is_empty() {
+            ConstraintStatus::OverConstrained
+        } else if self.degrees_of_freedom > 0 {
+            ConstraintStatus::UnderConstrained
+        } else {
+            ConstraintStatus::FullyConstrained
+        }
+    }
+}
+
+impl Sketch {
+    /// Analyses the relations and driving dimensions without modifying the sketch.
+    ///
+    /// Constraints are added one at a time, relations first and dimensions after, and the
+    /// sketch is re-solved after each. A constraint that cannot be solved together with the
+    /// ones before it is conflicting and is left out of the rest of the analysis. One that can,
+    /// but whose equations depend on the earlier ones at the solved state, is redundant.
+    pub fn analyze(&self) -> Result<ConstraintAnalysis, SolveError> {
+        let settings = SolverSettings::default();
+        let system = System::new(self)?;
+        let n = system.initial.len();
+
+        let constraint = |i: usize| {
+            if i < self.relations.len() {
+                ConstraintRef::Relation(i)
+            } else {
+                ConstraintRef::Dimension(i - self.relations.len())
+            }
+        };
+
+        let solvable = |members: &[usize], start: &[f64]| {
+            let mut subsystem = system.clone();
+            subsystem.active = (0..system.active.len())
+                .map(|k| members.contains(&k))
+                .collect();
+            subsystem
+                .solve_from(start, &settings)
+                .map(|(x, report)| (x, report.converged))
+        };
+
+        let mut x = system.initial.clone();
+        let mut rows = equation_rows(&system, &x)?;
+        let mut active: Vec<usize> = Vec::new();
+        let mut redundant = Vec::new();
+        let mut conflicting = Vec::new();
+
+        for i in 0..system.active.len() {
+            // Driven dimensions have no equations
+            if rows[i].is_empty() {
+                continue;
+            }
+
+            let mut trial = active.clone();
+            trial.push(i);
+            let (solved, converged) = solvable(&trial, &x)?;
+
+            if !converged {
+                // Deletion filter: drop every constraint the conflict survives without
+                let mut members = active.clone();
+                for &candidate in &active {
+                    let rest: Vec<usize> = members
+                        .iter()
+                        .copied()
+                        .filter(|&k| k != candidate)
+                        .collect();
+                    let mut trial = rest.clone();
+                    trial.push(i);
+                    if !solvable(&trial, &x)?.1 {
+                        members = rest;
+                    }
+                }
+                members.push(i);
+
+                conflicting.push(Overconstraint {
+                    constraint: constraint(i),
+                    set: members.into_iter().map(constraint).collect(),
+                });
+                continue;
+            }
+
+            x = solved;
+            rows = equation_rows(&system, &x)?;
+
+            let rank = |members: &[usize]| {
+                let stacked: Vec<Vec<f64>> =
+                    members.iter().flat_map(|&k| rows[k].clone()).collect();
+                linalg::rank(&Matrix::from_rows(&stacked, n), RANK_TOLERANCE)
+            };
+            let dependent = |members: &[usize]| {
+                let mut with = members.to_vec();
+                with.push(i);
+                rank(&with) < rank(members) + rows[i].len()
+            };
+
+            if dependent(&active) {
+                let mut members = active.clone();
+                for &candidate in &active {
+                    let rest: Vec<usize> = members
+                        .iter()
+                        .copied()
+                        .filter(|&k| k != candidate)
+                        .collect();
+                    if dependent(&rest) {
+                        members = rest;
+                    }
+                }
+                members.push(i);
+
+                redundant.push(Overconstraint {
+                    constraint: constraint(i),
+                    set: members.into_iter().map(constraint).collect(),
+                });
+            }
+
+            active.push(i);
+        }
+
+        let active_rows: Vec<Vec<f64>> = active.iter().flat_map(|&k| rows[k].clone()).collect();
+        let null_space = linalg::null_space(&Matrix::from_rows(&active_rows, n), RANK_TOLERANCE);
+
+        let element_freedom = system
+            .layouts
+            .iter()
+            .map(|layout| {
+                let range = layout.range();
+                let restricted: Vec<Vec<f64>> = null_space
+                    .iter()
+                    .map(|v| v[range.clone()].to_vec())
+                    .collect();
+                linalg::rank(&Matrix::from_rows(&restricted, range.len()), RANK_TOLERANCE)
+            })
+            .collect();
+
+        Ok(ConstraintAnalysis {
+            degrees_of_freedom: null_space.len(),
+            element_freedom,
+            redundant,
+            conflicting,
+        })
+    }
+}
+
+/// The normalised Jacobian rows of every equation at `x`, grouped by equation.
+fn equation_rows(system: &System, x: &[f64]) -> Result<Vec<Vec<Vec<f64>>>, SolveError> {
+    let residuals = system.equation_residuals(x)?;
+    let jacobian = system.jacobian(x)?;
+
+    let mut out = Vec::with_capacity(residuals.len());
+    let mut row = 0;
+    for equation in residuals {
+        out.push(
+            (row..row + equation.len())
+                .map(|r| normalized(jacobian.row(r)))
+                .collect(),
+        );
+        row += equation.len();
+    }
+
+    Ok(out)
+}
+
+fn normalized(row: &[f64]) -> Vec<f64> {
+    let norm = row.iter().map(|v| v * v).sum::<f64>().sqrt();
+
+    if norm > 0. {
+        row.iter().map(|v| v / norm).collect()
+    } else {
+        row.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::*;
+    use crate::test_util::{line, rectangle};
+
+    /// A 4 by 2 rectangle whose sides are joined and kept horizontal and vertical.
+    fn constrained_rectangle(sketch: &mut Sketch) -> [ElementId; 4] {
+        let lines = rectangle(sketch, (0., 0.), (4., 2.));
+        let [bottom, right, top, left] = lines;
+        for i in 0..4 {
+            sketch.add_relation(Relation::Coincident(Coincident {
+                point: PointRef::End(lines[i]),
+                other: CoincidentOther::Point(PointRef::Start(lines[(i + 1) % 4])),
+            }));
+        }
+        sketch.add_relation(Relation::Horizontal(bottom));
+        sketch.add_relation(Relation::Horizontal(top));
+        sketch.add_relation(Relation::Vertical(left));
+        sketch.add_relation(Relation::Vertical(right));
+
+        lines
+    }
+
+    #[test]
+    fn test_under_constrained_rectangle() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let [bottom, ..] = constrained_rectangle(&mut sketch);
+
+        let analysis = sketch.analyze().unwrap();
+
+        assert_eq!(analysis.status(), ConstraintStatus::UnderConstrained);
+        // Position, width and height
+        assert_eq!(analysis.degrees_of_freedom, 4);
+        // A horizontal line can still move and stretch, but not tilt
+        assert_eq!(analysis.element_freedom[bottom.0], 3);
+        assert!(analysis.redundant.is_empty());
+        assert!(analysis.conflicting.is_empty());
+    }
+
+    #[test]
+    fn test_fully_constrained_with_redundancy() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let [bottom, right, ..] = constrained_rectangle(&mut sketch);
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(right), 2.));
+        sketch.add_relation(Relation::Fixed(bottom));
+
+        let analysis = sketch.analyze().unwrap();
+
+        assert_eq!(analysis.status(), ConstraintStatus::FullyConstrained);
+        assert_eq!(analysis.element_freedom, vec![0; 4]);
+        assert!(analysis.conflicting.is_empty());
+
+        // Fixing the bottom line also makes it horizontal
+        assert_eq!(analysis.redundant.len(), 1);
+        let redundant = &analysis.redundant[0];
+        assert_eq!(redundant.constraint, ConstraintRef::Relation(8));
+        assert_eq!(
+            redundant.set,
+            vec![ConstraintRef::Relation(4), ConstraintRef::Relation(8)]
+        );
+    }
+
+    #[test]
+    fn test_horizontal_and_vertical_conflict() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (0., 0.), (1., 1.));
+        sketch.add_relation(Relation::Horizontal(l));
+        sketch.add_relation(Relation::Vertical(l));
+
+        let analysis = sketch.analyze().unwrap();
+
+        assert_eq!(analysis.status(), ConstraintStatus::OverConstrained);
+        assert_eq!(analysis.conflicting.len(), 1);
+        assert_eq!(
+            analysis.conflicting[0].set,
+            vec![ConstraintRef::Relation(0), ConstraintRef::Relation(1)]
+        );
+    }
+
+    #[test]
+    fn test_conflicting_dimensions() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let [bottom, right, top, _] = constrained_rectangle(&mut sketch);
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(right), 2.));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(bottom), 4.));
+        sketch.add_relation(Relation::Equal(Equal {
+            element: bottom,
+            other: top,
+        }));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(top), 5.));
+
+        let analysis = sketch.analyze().unwrap();
+
+        assert_eq!(analysis.conflicting.len(), 1);
+        let conflict = &analysis.conflicting[0];
+        assert_eq!(conflict.constraint, ConstraintRef::Dimension(2));
+        assert_eq!(
+            conflict.set,
+            vec![
+                ConstraintRef::Relation(8),
+                ConstraintRef::Dimension(1),
+                ConstraintRef::Dimension(2)
+            ]
+        );
+
+        // The rectangle already makes the top and bottom lines equal
+        assert_eq!(analysis.redundant.len(), 1);
+        assert_eq!(analysis.redundant[0].constraint, ConstraintRef::Relation(8));
+    }
+}
@@ -0,0 +1,287 @@
+use std::f64::consts::PI;
+
+use super::{ElementId, PointRef, Sketch, SolveError, SolveReport, System};
+
+/// A measurement between sketch elements. Lengths are in sketch units and angles in radians.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension {
+    pub kind: DimensionKind,
+    pub value: f64,
+    pub mode: DimensionMode,
+}
+
+impl Dimension {
+    pub fn driving(kind: DimensionKind, value: f64) -> Self {
+        Self {
+            kind,
+            value,
+            mode: DimensionMode::Driving,
+        }
+    }
+
+    /// A reference dimension. Its value is measured from the sketch when it is added and
+    /// after every successful solve.
+    pub fn driven(kind: DimensionKind) -> Self {
+        Self {
+            kind,
+            value: 0.,
+            mode: DimensionMode::Driven,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DimensionMode {
+    /// The solver moves the geometry to match the value.
+    Driving,
+    /// The value follows the geometry.
+    Driven,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DimensionKind {
+    /// The distance between two points.
+    Distance(PointRef, PointRef),
+    /// The perpendicular distance of a point from a line.
+    PointLineDistance(PointRef, ElementId),
+    /// The length of a line.
+    Length(ElementId),
+    /// The angle measured counter-clockwise from the direction of the first line to the
+    /// direction of the second.
+    Angle(ElementId, ElementId),
+    Radius(ElementId),
+    Diameter(ElementId),
+    /// The distance between two points along the x axis of the sketch plane.
+    HorizontalDistance(PointRef, PointRef),
+    /// The distance between two points along the y axis of the sketch plane.
+    VerticalDistance(PointRef, PointRef),
+}
+
+/// Identifies a dimension by its index in [`Sketch::dimensions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DimensionId(pub usize);
+
+impl Sketch {
+    pub fn add_dimension(&mut self, mut dimension: Dimension) -> DimensionId {
+        if dimension.mode == DimensionMode::Driven {
+            if let Some(value) = self.measure(&dimension.kind) {
+                dimension.value = value;
+            }
+        }
+
+        self.dimensions.push(dimension);
+        DimensionId(self.dimensions.len() - 1)
+    }
+
+    /// Changes the value of a driving dimension and re-solves the sketch. If the new value
+    /// cannot be satisfied, the dimension keeps its old value and the sketch is left as it was.
+    pub fn set_dimension_value(
+        &mut self,
+        id: DimensionId,
+        value: f64,
+    ) -> Result<SolveReport, SolveError> {
+        let dimension = self
+            .dimensions
+            .get_mut(id.0)
+            .ok_or(SolveError::InvalidDimension(id.0))?;
+
+        if dimension.mode == DimensionMode::Driven {
+            return Err(SolveError::DrivenDimension(id.0));
+        }
+
+        let old = std::mem::replace(&mut dimension.value, value);
+        let report = self.solve();
+        if !matches!(
+            report,
+            Ok(SolveReport {
+                converged: true,
+                ..
+            })
+        ) {
+            self.dimensions[id.0].value = old;
+        }
+        report
+    }
+
+    /// Measures the current geometry, or `None` if the dimension references invalid elements.
+    pub fn measure(&self, kind: &DimensionKind) -> Option<f64> {
+        let system = System::new(self).ok()?;
+        system.measure(&system.initial, kind)
+    }
+}
+
+impl<'a> System<'a> {
+    pub(crate) fn measure(&self, x: &[f64], kind: &DimensionKind) -> Option<f64> {
+        let value = match kind {
+            DimensionKind::Distance(a, b) => (self.point(x, *a)? - self.point(x, *b)?).length(),
+            DimensionKind::PointLineDistance(p, line) => {
+                self.line(x, *line)?;
+                self.distance_to_curve(x, self.point(x, *p)?, *line)?.abs()
+            }
+            DimensionKind::Length(line) => {
+                let (a, b) = self.line(x, *line)?;
+                (b - a).length()
+            }
+            DimensionKind::Angle(first, second) => {
+                let u = self.direction(x, *first)?;
+                let v = self.direction(x, *second)?;
+                u.perp_dot(v).atan2(u.dot(v)).rem_euclid(2. * PI)
+            }
+            DimensionKind::Radius(arc) => self.arc(x, *arc)?.1.abs(),
+            DimensionKind::Diameter(arc) => 2. * self.arc(x, *arc)?.1.abs(),
+            DimensionKind::HorizontalDistance(a, b) => {
+                (self.point(x, *a)? - self.point(x, *b)?).x.abs()
+            }
+            DimensionKind::VerticalDistance(a, b) => {
+                (self.point(x, *a)? - self.point(x, *b)?).y.abs()
+            }
+        };
+
+        Some(value)
+    }
+
+    /// Driving dimensions contribute one equation each; driven dimensions contribute none.
+    pub(crate) fn dimension_residuals(
+        &self,
+        x: &[f64],
+        dimension: &Dimension,
+        out: &mut Vec<f64>,
+    ) -> Option<()> {
+        let measured = self.measure(x, &dimension.kind)?;
+
+        if dimension.mode == DimensionMode::Driven {
+            return Some(());
+        }
+
+        let target = dimension.value;
+        let residual = match dimension.kind {
+            // Wrap into (-π, π] so angles close to 0 and 2π are treated as neighbours
+            DimensionKind::Angle(..) => (measured - target + PI).rem_euclid(2. * PI) - PI,
+            _ => measured - target,
+        };
+        out.push(residual);
+
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::super::*;
+    use crate::arc::Arc;
+    use crate::test_util::line;
+
+    fn coincident(a: PointRef, b: PointRef) -> Relation {
+        Relation::Coincident(Coincident {
+            point: a,
+            other: CoincidentOther::Point(b),
+        })
+    }
+
+    #[test]
+    fn test_dimensioned_rectangle() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let bottom = line(&mut sketch, (0., 0.), (4., 0.));
+        let right = line(&mut sketch, (4., 0.), (4., 2.));
+        let top = line(&mut sketch, (4., 2.), (0., 2.));
+        let left = line(&mut sketch, (0., 2.), (0., 0.));
+
+        let lines = [bottom, right, top, left];
+        for i in 0..4 {
+            let next = lines[(i + 1) % 4];
+            sketch.add_relation(coincident(PointRef::End(lines[i]), PointRef::Start(next)));
+        }
+        sketch.add_relation(Relation::Horizontal(bottom));
+        sketch.add_relation(Relation::Horizontal(top));
+        sketch.add_relation(Relation::Vertical(left));
+        sketch.add_relation(Relation::Vertical(right));
+
+        let width = sketch.add_dimension(Dimension::driving(DimensionKind::Length(bottom), 4.));
+        sketch.add_dimension(Dimension::driving(
+            DimensionKind::VerticalDistance(PointRef::Start(bottom), PointRef::Start(top)),
+            2.,
+        ));
+        let diagonal = sketch.add_dimension(Dimension::driven(DimensionKind::Distance(
+            PointRef::Start(bottom),
+            PointRef::Start(top),
+        )));
+        assert!((sketch.dimensions[diagonal.0].value - 20f64.sqrt()).abs() < 1e-5);
+
+        let report = sketch.set_dimension_value(width, 6.).unwrap();
+        assert!(report.converged, "{report:?}");
+        assert_eq!(report.dimension_residuals.len(), 3);
+
+        let length = sketch.measure(&DimensionKind::Length(top)).unwrap();
+        assert!((length - 6.).abs() < 1e-4);
+        assert!((sketch.dimensions[diagonal.0].value - 40f64.sqrt()).abs() < 1e-4);
+
+        assert_eq!(
+            sketch.set_dimension_value(diagonal, 1.).unwrap_err(),
+            SolveError::DrivenDimension(diagonal.0)
+        );
+    }
+
+    #[test]
+    fn test_angle_and_radius_dimensions() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let base = line(&mut sketch, (0., 0.), (2., 0.));
+        let arm = line(&mut sketch, (0., 0.), (1., 1.5));
+        let arc = sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::new(3., 0., 0.),
+                DVec3::Z,
+                DVec3::new(4., 0., 0.),
+                DVec3::new(2., 0., 0.),
+            )
+            .unwrap(),
+        )));
+
+        sketch.add_relation(Relation::Fixed(base));
+        sketch.add_relation(coincident(PointRef::Start(arm), PointRef::Start(base)));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(arm), 2.));
+        sketch.add_dimension(Dimension::driving(
+            DimensionKind::Angle(base, arm),
+            std::f64::consts::FRAC_PI_6,
+        ));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Diameter(arc), 3.));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        let Some(SketchElement::Line(l)) = sketch.element(arm) else {
+            panic!("not a line");
+        };
+        let tpl = l.0.to_two_point_line().unwrap();
+        let expected = DVec3::new(3f64.sqrt(), 1., 0.);
+        assert!(tpl.b.0.abs_diff_eq(expected, 1e-4), "{}", tpl.b.0);
+
+        let radius = sketch.measure(&DimensionKind::Radius(arc)).unwrap();
+        assert!((radius - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_invalid_dimension() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (0., 0.), (1., 0.));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Radius(l), 1.));
+
+        assert_eq!(sketch.measure(&DimensionKind::Radius(l)), None);
+        assert_eq!(sketch.solve().unwrap_err(), SolveError::InvalidDimension(0));
+    }
+
+    #[test]
+    fn test_unsatisfiable_dimension_value() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (0., 0.), (1., 0.));
+        sketch.add_relation(Relation::Fixed(l));
+        let length = sketch.add_dimension(Dimension::driving(DimensionKind::Length(l), 1.));
+        let before = format!("{:?}", sketch.elements);
+
+        let report = sketch.set_dimension_value(length, 2.).unwrap();
+        assert!(!report.converged);
+        assert_eq!(sketch.dimensions[length.0].value, 1.);
+        assert_eq!(format!("{:?}", sketch.elements), before);
+    }
+}
@@ -1,49 +1,506 @@
+mod analysis;
+mod dimensions;
+mod profile;
 mod relations;
+mod solver;
+
+use std::collections::BTreeSet;
+
+use glam::{DVec2, DVec3};
 
 use crate::arc::Arc;
+use crate::circle::Circle;
+use crate::curve::Curve;
+use crate::ellipse::Ellipse;
+use crate::intersection::{self, CurveIntersection, TrimmedCurve};
 use crate::line::Line;
+use crate::nurbs::{NurbsCurve, NurbsError};
 use crate::point::Point;
-use crate::Plane;
+use crate::surface::Surface;
+use crate::transform::Transform;
+use crate::{Brep, FaceId, Plane, TesselationTolerance};
 
+pub use analysis::*;
+pub use dimensions::*;
+pub use relations::*;
+pub use solver::*;
+
+#[derive(Debug, Clone)]
 pub struct SketchPlane(pub Plane);
 
 impl SketchPlane {
     pub const XY: Self = Self(Plane::XY);
     pub const XZ: Self = Self(Plane::XZ);
     pub const YZ: Self = Self(Plane::YZ);
+
+    /// The plane of a planar face, with its normal pointing out of the face. Gives `None` for
+    /// faces on other surfaces.
+    pub fn on_face(brep: &Brep, face: FaceId) -> Option<Self> {
+        let face = brep.face(face);
+        let Surface::Plane(plane) = &face.surface else {
+            return None;
+        };
+
+        Some(Self(if face.reversed {
+            plane.flipped()
+        } else {
+            plane.clone()
+        }))
+    }
+
+    pub fn x_axis(&self) -> DVec3 {
+        self.0.x_axis
+    }
+
+    pub fn y_axis(&self) -> DVec3 {
+        self.0.y_axis()
+    }
+
+    /// Maps a point in 3D onto the 2D coordinates of the sketch.
+    pub fn to_local(&self, p: DVec3) -> DVec2 {
+        self.0.to_local(p)
+    }
+
+    /// Maps 2D sketch coordinates back into 3D.
+    pub fn to_world(&self, p: DVec2) -> DVec3 {
+        self.0.to_world(p)
+    }
 }
 
+#[derive(Debug, Clone)]
 pub struct SketchLine(pub Line);
 
+#[derive(Debug, Clone)]
 pub struct SketchPoint(pub Point);
 
+/// The normal of an arc in a sketch points along the sketch plane normal for arcs running
+/// counter-clockwise when looking down onto the plane, and against it for clockwise ones.
+#[derive(Debug, Clone)]
 pub struct SketchArc(pub Arc);
 
+/// A full circle in a sketch, with its normal along the sketch plane normal.
+#[derive(Debug, Clone)]
+pub struct SketchCircle(pub Circle);
+
+/// An ellipse in a sketch, with its normal along the sketch plane normal.
+#[derive(Debug, Clone)]
+pub struct SketchEllipse(pub Ellipse);
+
+/// A spline in a sketch, kept in the form it was drawn in.
+#[derive(Debug, Clone)]
+pub enum SketchSpline {
+    /// A curve drawn by its control points, such as one from [`NurbsCurve::uniform`]. The
+    /// solver moves its control points within the sketch plane and keeps its weights and knots.
+    Control(NurbsCurve),
+    /// A curve through fit points, see [`FitSpline`].
+    Fit(FitSpline),
+}
+
+impl SketchSpline {
+    pub fn curve(&self) -> &NurbsCurve {
+        match self {
+            SketchSpline::Control(curve) => curve,
+            SketchSpline::Fit(fit) => fit.curve(),
+        }
+    }
+
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        match self {
+            SketchSpline::Control(curve) => SketchSpline::Control(curve.transformed(transform)),
+            SketchSpline::Fit(fit) => SketchSpline::Fit(fit.transformed(transform)),
+        }
+    }
+}
+
+/// A spline through fit points, leaving the first along an optional start tangent and arriving
+/// at the last along an optional end tangent, as for [`NurbsCurve::interpolate`]. The solver
+/// moves the fit points and the tangents within the sketch plane and interpolates the curve
+/// again.
+#[derive(Debug, Clone)]
+pub struct FitSpline {
+    points: Vec<DVec3>,
+    start_tangent: Option<DVec3>,
+    end_tangent: Option<DVec3>,
+    curve: NurbsCurve,
+}
+
+impl FitSpline {
+    pub fn new(
+        points: Vec<DVec3>,
+        start_tangent: Option<DVec3>,
+        end_tangent: Option<DVec3>,
+    ) -> Result<Self, NurbsError> {
+        let curve = NurbsCurve::interpolate(&points, start_tangent, end_tangent)?;
+        Ok(Self {
+            points,
+            start_tangent,
+            end_tangent,
+            curve,
+        })
+    }
+
+    pub fn points(&self) -> &[DVec3] {
+        &self.points
+    }
+
+    pub fn start_tangent(&self) -> Option<DVec3> {
+        self.start_tangent
+    }
+
+    pub fn end_tangent(&self) -> Option<DVec3> {
+        self.end_tangent
+    }
+
+    /// The interpolated curve.
+    pub fn curve(&self) -> &NurbsCurve {
+        &self.curve
+    }
+
+    /// Moves the fit points and maps the tangents, scaled by how much the chords between the
+    /// points grow. Similarities keep the proportions of the chords, so the moved curve is the
+    /// one through the moved points; other transforms interpolate the curve again.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let points: Vec<DVec3> = self.points.iter().map(|&p| transform.point(p)).collect();
+        if transform.is_similarity() {
+            return Self {
+                points,
+                start_tangent: self.start_tangent.map(|t| transform.direction(t)),
+                end_tangent: self.end_tangent.map(|t| transform.direction(t)),
+                curve: self.curve.transformed(transform),
+            };
+        }
+
+        let chords =
+            |points: &[DVec3]| -> f64 { points.windows(2).map(|p| p[0].distance(p[1])).sum() };
+        let growth = chords(&self.points) / chords(&points);
+        let tangent = |t: DVec3| transform.vector(t) * growth;
+        let (start_tangent, end_tangent) = (
+            self.start_tangent.map(tangent),
+            self.end_tangent.map(tangent),
+        );
+        let curve = NurbsCurve::interpolate(&points, start_tangent, end_tangent)
+            .unwrap_or_else(|_| self.curve.transformed(transform));
+
+        Self {
+            points,
+            start_tangent,
+            end_tangent,
+            curve,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum SketchElement {
     Line(SketchLine),
     Point(SketchPoint),
     Arc(SketchArc),
+    Circle(SketchCircle),
+    Ellipse(SketchEllipse),
+    Spline(SketchSpline),
 }
 
+impl SketchElement {
+    /// The image of the element. Arcs and circles that the transform does not keep round
+    /// become splines and ellipses.
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        match self {
+            SketchElement::Line(l) => SketchElement::Line(SketchLine(l.0.transformed(transform))),
+            SketchElement::Point(p) => {
+                SketchElement::Point(SketchPoint(p.0.transformed(transform)))
+            }
+            SketchElement::Arc(a) => match a.0.transformed(transform) {
+                Some(arc) => SketchElement::Arc(SketchArc(arc)),
+                None => SketchElement::Spline(SketchSpline::Control(
+                    a.0.to_nurbs().transformed(transform),
+                )),
+            },
+            SketchElement::Circle(c) => match c.0.transformed(transform) {
+                Some(circle) => SketchElement::Circle(SketchCircle(circle)),
+                None => {
+                    SketchElement::Ellipse(SketchEllipse(c.0.to_ellipse().transformed(transform)))
+                }
+            },
+            SketchElement::Ellipse(e) => {
+                SketchElement::Ellipse(SketchEllipse(e.0.transformed(transform)))
+            }
+            SketchElement::Spline(s) => SketchElement::Spline(s.transformed(transform)),
+        }
+    }
+}
+
+/// Identifies an element of a [`Sketch`] by its index in [`Sketch::elements`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(pub usize);
+
 pub struct Sketch {
     pub plane: SketchPlane,
     pub elements: Vec<SketchElement>,
+    pub relations: Vec<Relation>,
+    pub dimensions: Vec<Dimension>,
+    /// Elements that only guide the rest of the geometry, such as center lines. The solver
+    /// treats them like any other element, but they bound no regions.
+    pub construction: BTreeSet<ElementId>,
 }
 
 impl Sketch {
-    pub fn to_lines(&self) -> Vec<Line> {
+    pub fn new(plane: SketchPlane) -> Self {
+        Self {
+            plane,
+            elements: Vec::new(),
+            relations: Vec::new(),
+            dimensions: Vec::new(),
+            construction: BTreeSet::new(),
+        }
+    }
+
+    /// Moves the sketch together with its plane. Similarities keep the coordinates of its
+    /// geometry in the plane up to the scale and its relations, and scale the length
+    /// dimensions too.
+    ///
+    /// Other transforms, such as stretches and shears, change the shape of the geometry: the
+    /// relations that no longer hold are dropped and the dimensions measure the new geometry,
+    /// or are dropped if they no longer apply to it.
+    pub fn transform(&mut self, transform: &Transform) {
+        self.plane = SketchPlane(self.plane.0.transformed(transform));
+        for element in &mut self.elements {
+            *element = element.transformed(transform);
+        }
+
+        if transform.is_similarity() {
+            for dimension in &mut self.dimensions {
+                if !matches!(dimension.kind, DimensionKind::Angle(..)) {
+                    dimension.value = transform.length(dimension.value);
+                }
+            }
+            return;
+        }
+
+        let Ok(system) = System::new(self) else {
+            self.relations.clear();
+            self.dimensions.clear();
+            return;
+        };
+        let tolerance = SolverSettings::default().tolerance;
+        let holds: Vec<bool> = self
+            .relations
+            .iter()
+            .map(|relation| system.relation_holds(&system.initial, relation, tolerance))
+            .collect();
+        let measured: Vec<Option<f64>> = self
+            .dimensions
+            .iter()
+            .map(|dimension| system.measure(&system.initial, &dimension.kind))
+            .collect();
+
+        let mut holds = holds.into_iter();
+        self.relations.retain(|_| holds.next().unwrap_or(false));
+        let mut measured = measured.into_iter();
+        self.dimensions
+            .retain_mut(|dimension| match measured.next().flatten() {
+                Some(value) => {
+                    dimension.value = value;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Moves the sketch onto `plane`, keeping the coordinates of its geometry in the plane.
+    pub fn place(&mut self, plane: Plane) {
+        let placement = Transform::between(&self.plane.0, &plane);
+        self.transform(&placement);
+        self.plane = SketchPlane(plane);
+    }
+
+    pub fn add_element(&mut self, element: SketchElement) -> ElementId {
+        self.elements.push(element);
+        ElementId(self.elements.len() - 1)
+    }
+
+    pub fn add_relation(&mut self, relation: Relation) {
+        self.relations.push(relation);
+    }
+
+    /// Marks an element as construction geometry, or makes it regular geometry again.
+    pub fn set_construction(&mut self, id: ElementId, construction: bool) {
+        if construction {
+            self.construction.insert(id);
+        } else {
+            self.construction.remove(&id);
+        }
+    }
+
+    pub fn is_construction(&self, id: ElementId) -> bool {
+        self.construction.contains(&id)
+    }
+
+    pub fn element(&self, id: ElementId) -> Option<&SketchElement> {
+        self.elements.get(id.0)
+    }
+
+    /// The geometry of a line, arc, circle, ellipse or spline, trimmed to the element. Lines
+    /// run from `0` at their start to `1` at their end and arcs by the angle from their start.
+    pub fn curve(&self, id: ElementId) -> Option<TrimmedCurve> {
+        match self.element(id)? {
+            SketchElement::Line(l) => {
+                let tpl = l.0.to_two_point_line().ok()?;
+                Some(TrimmedCurve::segment(tpl.a.0, tpl.b.0))
+            }
+            SketchElement::Arc(arc) => Some(TrimmedCurve::arc(&arc.0)),
+            SketchElement::Circle(circle) => {
+                Some(TrimmedCurve::full(Curve::Circle(circle.0.clone())))
+            }
+            SketchElement::Ellipse(ellipse) => {
+                Some(TrimmedCurve::full(Curve::Ellipse(ellipse.0.clone())))
+            }
+            SketchElement::Spline(spline) => {
+                Some(TrimmedCurve::full(Curve::Nurbs(spline.curve().clone())))
+            }
+            SketchElement::Point(_) => None,
+        }
+    }
+
+    /// Where two elements of the sketch cross, touch or overlap within the linear tolerance,
+    /// by their parameters as for [`Sketch::curve`]. Gives `None` if either element is not a
+    /// curve.
+    pub fn intersections(&self, a: ElementId, b: ElementId) -> Option<Vec<CurveIntersection>> {
+        Some(intersection::intersect(&self.curve(a)?, &self.curve(b)?))
+    }
+
+    /// The arcs, circles, ellipses and splines of the sketch as straight lines within
+    /// `tolerance`.
+    pub fn to_lines(&self, tolerance: &TesselationTolerance) -> Vec<Line> {
         let mut out = Vec::new();
 
         for element in &self.elements {
             match element {
-                SketchElement::Arc(arc) => {
-                    let mut lines = arc.0.to_lines(16);
-                    out.append(&mut lines);
+                SketchElement::Arc(arc) => out.append(&mut arc.0.to_lines(tolerance)),
+                SketchElement::Circle(circle) => out.append(&mut circle.0.to_lines(tolerance)),
+                SketchElement::Ellipse(ellipse) => out.append(&mut ellipse.0.to_lines(tolerance)),
+                SketchElement::Spline(spline) => {
+                    out.append(&mut spline.curve().to_lines(tolerance))
                 }
-                _ => (),
+                SketchElement::Line(_) | SketchElement::Point(_) => (),
             }
         }
 
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::line;
+
+    #[test]
+    fn test_sketch_plane_local_coordinates() {
+        let plane = SketchPlane(Plane::new(DVec3::new(0., 1., 1.), DVec3::new(1., 2., 3.)));
+
+        assert!(plane.x_axis().dot(plane.0.normal).abs() < 1e-6);
+        assert!(plane.y_axis().dot(plane.0.normal).abs() < 1e-6);
+
+        let local = DVec2::new(3., -2.);
+        let world = plane.to_world(local);
+        assert!(plane.to_local(world).abs_diff_eq(local, 1e-5));
+
+        assert_eq!(
+            SketchPlane::XY.to_world(DVec2::new(1., 2.)),
+            DVec3::new(1., 2., 0.)
+        );
+        assert_eq!(
+            SketchPlane::YZ.to_world(DVec2::new(1., 2.)),
+            DVec3::new(0., 1., 2.)
+        );
+    }
+
+    #[test]
+    fn test_intersections() {
+        let plane = SketchPlane(Plane::new(DVec3::X, DVec3::new(2., 0., 0.)));
+        let mut sketch = Sketch::new(plane.clone());
+        let world = |x: f64, y: f64| plane.to_world(DVec2::new(x, y));
+
+        let line = sketch.add_element(SketchElement::Line(SketchLine(Line::TwoPoint(
+            crate::line::TwoPointLine::new(world(-2., 0.5), world(2., 0.5)),
+        ))));
+        let circle = sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(world(0., 0.), plane.0.normal, 1.).unwrap(),
+        )));
+        let point = sketch.add_element(SketchElement::Point(SketchPoint(Point(world(0., 0.)))));
+
+        let found = sketch.intersections(line, circle).unwrap();
+        assert_eq!(found.len(), 2);
+        for i in found {
+            let CurveIntersection::Point { point, .. } = i else {
+                panic!("unexpected overlap");
+            };
+            let local = plane.to_local(point);
+            assert!((local.y - 0.5).abs() < 1e-5 && (local.length() - 1.).abs() < 1e-5);
+        }
+        assert!(sketch.intersections(line, point).is_none());
+    }
+
+    #[test]
+    fn test_place_and_transform() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let line = line(&mut sketch, (1., 0.), (3., 0.));
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::new(3., 1., 0.),
+                DVec3::Z,
+                DVec3::new(3., 0., 0.),
+                DVec3::new(4., 1., 0.),
+            )
+            .unwrap(),
+        )));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(line), 2.));
+
+        // Onto the top of a box, keeping the 2D coordinates
+        let (brep, _) = crate::test_util::cuboid(DVec3::ONE);
+        let top = SketchPlane::on_face(&brep, FaceId(1)).unwrap();
+        assert!(top.0.normal.abs_diff_eq(DVec3::Z, 1e-12));
+        let bottom = SketchPlane::on_face(&brep, FaceId(0)).unwrap();
+        assert!(bottom.0.normal.abs_diff_eq(-DVec3::Z, 1e-12));
+
+        let tilted = Plane::from_axes(DVec3::new(0., 0., 5.), DVec3::Y, DVec3::new(-1., 0., 1.));
+        sketch.place(tilted);
+        let Some(SketchElement::Arc(arc)) = sketch.element(ElementId(1)) else {
+            unreachable!()
+        };
+        assert!(sketch
+            .plane
+            .to_local(arc.0.end)
+            .abs_diff_eq(DVec2::new(4., 1.), 1e-12));
+        assert!(arc.0.normal.abs_diff_eq(sketch.plane.0.normal, 1e-12));
+
+        // Scaling scales the lengths the solver keeps
+        sketch.transform(&Transform::scale(DVec3::ZERO, 2.));
+        assert_eq!(sketch.dimensions[0].value, 4.);
+        assert!(sketch.solve().unwrap().converged);
+    }
+
+    #[test]
+    fn test_shear_sketch() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let base = line(&mut sketch, (0., 0.), (2., 0.));
+        let side = line(&mut sketch, (2., 0.), (2., 2.));
+        sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(DVec3::new(1., 1., 0.), DVec3::Z, 0.5).unwrap(),
+        )));
+        sketch.add_relation(Relation::Horizontal(base));
+        sketch.add_relation(Relation::Vertical(side));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(side), 2.));
+
+        // Shearing along the base keeps it horizontal, leans the side over and turns the
+        // circle into an ellipse
+        sketch.transform(&Transform::shear(&Plane::XZ, DVec3::X, 1.));
+        assert!(matches!(sketch.relations[..], [Relation::Horizontal(_)]));
+        assert!((sketch.dimensions[0].value - 8f64.sqrt()).abs() < 1e-12);
+        assert!(matches!(
+            sketch.element(ElementId(2)),
+            Some(SketchElement::Ellipse(_))
+        ));
+        assert!(sketch.solve().unwrap().converged);
+    }
+}
@@ -0,0 +1,1024 @@
+use std::cmp::Ordering;
+use std::f64::consts::TAU;
+use std::rc::Rc;
+
+use glam::{DVec2, DVec3};
+
+use crate::circle::Circle;
+use crate::curve;
+use crate::intersection::{self, CurveIntersection, TrimmedCurve};
+use crate::nurbs::NurbsCurve;
+use crate::{
+    BoundaryArc, BoundaryCurve, BoundaryElement, BoundaryLine, BoundaryLoop, BoundarySurface,
+    Direction, Tolerance,
+};
+
+/// The straight pieces a piece of an ellipse or a spline is measured and drawn with.
+const CURVE_SAMPLES: usize = 64;
+
+use super::{ElementId, Sketch, SketchElement, SketchPlane};
+
+impl Sketch {
+    /// Finds every closed region bounded by the lines, arcs, circles, ellipses and splines of
+    /// the sketch. Construction geometry does not bound regions.
+    ///
+    /// Curves are split where they cross or touch, endpoints within the linear tolerance are
+    /// joined and open chains are ignored. Every bounded face of the resulting planar
+    /// arrangement becomes a [`BoundarySurface`] with its outer loop running counter-clockwise
+    /// and any islands inside it as clockwise holes.
+    pub fn find_regions(&self) -> Vec<BoundarySurface> {
+        let tolerance = &Tolerance::current();
+        let curves = self.profile_curves();
+        let curves = split_at_intersections(&curves, tolerance);
+        let graph = Graph::new(curves, tolerance);
+        let cycles = graph.cycles();
+
+        let faces: Vec<&Cycle> = cycles.iter().filter(|c| c.area > 0.).collect();
+
+        let mut holes: Vec<Vec<&Cycle>> = vec![Vec::new(); faces.len()];
+        for hole in cycles.iter().filter(|c| c.area < 0.) {
+            let sample = hole.polyline(tolerance)[0];
+
+            let container = faces
+                .iter()
+                .enumerate()
+                .filter(|(_, face)| face.component != hole.component)
+                .filter(|(_, face)| contains(&face.polyline(tolerance), sample))
+                .min_by(|(_, a), (_, b)| a.area.total_cmp(&b.area));
+
+            if let Some((i, _)) = container {
+                holes[i].push(hole);
+            }
+        }
+
+        faces
+            .iter()
+            .zip(holes)
+            .map(|(face, holes)| {
+                BoundarySurface::new(
+                    self.plane.0.clone(),
+                    face.to_boundary_loop(&self.plane),
+                    holes
+                        .iter()
+                        .map(|hole| hole.to_boundary_loop(&self.plane))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// The lines, arcs, circles, ellipses and splines of the sketch in the 2D coordinates of
+    /// the sketch plane, leaving out construction geometry. Circles become arcs sweeping a full
+    /// turn and ellipses their exact NURBS form.
+    fn profile_curves(&self) -> Vec<Curve> {
+        let local = |p: DVec3| self.plane.to_local(p);
+
+        self.elements
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.is_construction(ElementId(i)))
+            .filter_map(|(_, element)| match element {
+                SketchElement::Line(l) => {
+                    let tpl = l.0.to_two_point_line().ok()?;
+                    Some(Curve::Line {
+                        a: local(tpl.a.0),
+                        b: local(tpl.b.0),
+                    })
+                }
+                SketchElement::Arc(arc) => {
+                    let center = local(arc.0.center);
+                    let s = local(arc.0.start) - center;
+                    let e = local(arc.0.end) - center;
+                    let start = s.y.atan2(s.x);
+                    let end = e.y.atan2(e.x);
+
+                    // Coinciding start and end points describe a full circle
+                    let sweep = if arc.0.normal.dot(self.plane.0.normal) >= 0. {
+                        full_turn((end - start).rem_euclid(TAU))
+                    } else {
+                        -full_turn((start - end).rem_euclid(TAU))
+                    };
+
+                    Some(Curve::Arc {
+                        center,
+                        radius: arc.0.radius,
+                        start,
+                        sweep,
+                    })
+                }
+                SketchElement::Circle(circle) => {
+                    let center = local(circle.0.center);
+                    let x = local(circle.0.center + circle.0.x_axis) - center;
+                    Some(Curve::Arc {
+                        center,
+                        radius: circle.0.radius,
+                        start: x.y.atan2(x.x),
+                        sweep: TAU,
+                    })
+                }
+                SketchElement::Ellipse(ellipse) => {
+                    let curve = ellipse.0.to_nurbs().map(|p| local(p).extend(0.));
+                    Some(Curve::nurbs(curve))
+                }
+                SketchElement::Spline(spline) => {
+                    let curve = spline.curve().map(|p| local(p).extend(0.));
+                    Some(Curve::nurbs(curve))
+                }
+                SketchElement::Point(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn full_turn(angle: f64) -> f64 {
+    if Tolerance::current().is_zero_angle(angle) {
+        TAU
+    } else {
+        angle
+    }
+}
+
+/// A line, a circular arc or a piece of a NURBS curve in sketch coordinates, parameterised
+/// over `[0, 1]`.
+#[derive(Debug, Clone)]
+enum Curve {
+    Line {
+        a: DVec2,
+        b: DVec2,
+    },
+    /// A positive sweep runs counter-clockwise.
+    Arc {
+        center: DVec2,
+        radius: f64,
+        start: f64,
+        sweep: f64,
+    },
+    /// The curve, which lies in the plane `z = 0`, from parameter `start` to `end`. It runs
+    /// backwards when `end < start`.
+    Nurbs {
+        curve: Rc<NurbsCurve>,
+        start: f64,
+        end: f64,
+    },
+}
+
+impl Curve {
+    /// The whole of a NURBS curve.
+    fn nurbs(curve: NurbsCurve) -> Self {
+        let (start, end) = curve.domain();
+        Curve::Nurbs {
+            curve: Rc::new(curve),
+            start,
+            end,
+        }
+    }
+
+    fn point(&self, t: f64) -> DVec2 {
+        match *self {
+            Curve::Line { a, b } => a + t * (b - a),
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => curve.point_at(start + t * (end - start)).truncate(),
+            Curve::Arc {
+                center,
+                radius,
+                start,
+                sweep,
+            } => center + radius * DVec2::from_angle(start + t * sweep),
+        }
+    }
+
+    /// The derivative with respect to `t`.
+    fn tangent(&self, t: f64) -> DVec2 {
+        match *self {
+            Curve::Line { a, b } => b - a,
+            Curve::Arc {
+                radius,
+                start,
+                sweep,
+                ..
+            } => radius * sweep * DVec2::from_angle(start + t * sweep).perp(),
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => curve.derivative_at(start + t * (end - start)).truncate() * (end - start),
+        }
+    }
+
+    /// Positive when the curve turns left at its start.
+    fn curvature(&self) -> f64 {
+        match *self {
+            Curve::Line { .. } => 0.,
+            Curve::Arc { radius, sweep, .. } => sweep.signum() / radius,
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => {
+                let d = curve.derivatives(start, 2);
+                let first = d[1].truncate() * (end - start);
+                let second = d[2].truncate() * (end - start) * (end - start);
+                first.perp_dot(second) / first.length().powi(3)
+            }
+        }
+    }
+
+    fn length(&self) -> f64 {
+        match *self {
+            Curve::Line { a, b } => (b - a).length(),
+            Curve::Arc { radius, sweep, .. } => radius * sweep.abs(),
+            Curve::Nurbs { .. } => (0..CURVE_SAMPLES)
+                .map(|i| {
+                    let t = |i: usize| i as f64 / CURVE_SAMPLES as f64;
+                    self.point(t(i)).distance(self.point(t(i + 1)))
+                })
+                .sum(),
+        }
+    }
+
+    fn sub(&self, t0: f64, t1: f64) -> Curve {
+        match *self {
+            Curve::Line { .. } => Curve::Line {
+                a: self.point(t0),
+                b: self.point(t1),
+            },
+            Curve::Arc {
+                center,
+                radius,
+                start,
+                sweep,
+            } => Curve::Arc {
+                center,
+                radius,
+                start: start + t0 * sweep,
+                sweep: (t1 - t0) * sweep,
+            },
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => Curve::Nurbs {
+                curve: curve.clone(),
+                start: start + t0 * (end - start),
+                end: start + t1 * (end - start),
+            },
+        }
+    }
+
+    fn reversed(&self) -> Curve {
+        match *self {
+            Curve::Line { a, b } => Curve::Line { a: b, b: a },
+            Curve::Arc {
+                center,
+                radius,
+                start,
+                sweep,
+            } => Curve::Arc {
+                center,
+                radius,
+                start: start + sweep,
+                sweep: -sweep,
+            },
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => Curve::Nurbs {
+                curve: curve.clone(),
+                start: end,
+                end: start,
+            },
+        }
+    }
+
+    /// The contribution `½∫ p × dp` of the curve to the signed area of a closed loop.
+    fn area(&self) -> f64 {
+        match *self {
+            Curve::Line { a, b } => a.perp_dot(b) / 2.,
+            Curve::Arc {
+                center,
+                radius,
+                sweep,
+                ..
+            } => (center.perp_dot(self.point(1.) - self.point(0.)) + radius * radius * sweep) / 2.,
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => curve.swept_area(start, end).z,
+        }
+    }
+
+    /// The curve in the plane `z = 0` of 3D space, with its own parameters.
+    fn trimmed(&self) -> TrimmedCurve {
+        match *self {
+            Curve::Line { a, b } => TrimmedCurve::segment(a.extend(0.), b.extend(0.)),
+            Curve::Arc {
+                center,
+                radius,
+                start,
+                sweep,
+            } => {
+                let circle = Circle {
+                    center: center.extend(0.),
+                    normal: DVec3::Z,
+                    x_axis: DVec3::X,
+                    radius,
+                };
+                let (from, to) = (start.min(start + sweep), start.max(start + sweep));
+                TrimmedCurve::new(curve::Curve::Circle(circle), from, to)
+            }
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => TrimmedCurve::new(
+                curve::Curve::Nurbs(curve.as_ref().clone()),
+                start.min(end),
+                start.max(end),
+            ),
+        }
+    }
+
+    /// The parameter of a point on the curve, or `None` if it is further than the linear tolerance
+    /// beyond the ends.
+    fn parameter_of(&self, p: DVec2, tolerance: &Tolerance) -> Option<f64> {
+        let t = match *self {
+            Curve::Line { a, b } => (p - a).dot(b - a) / (b - a).length_squared(),
+            Curve::Arc {
+                center,
+                start,
+                sweep,
+                ..
+            } => {
+                let d = p - center;
+                let angle = (d.y.atan2(d.x) - start) * sweep.signum();
+                let mut angle = angle.rem_euclid(TAU);
+                // Points just before the start wrap around to the end of the circle
+                if angle > sweep.abs() && TAU - angle < angle - sweep.abs() {
+                    angle -= TAU;
+                }
+                angle / sweep.abs()
+            }
+            Curve::Nurbs {
+                ref curve,
+                start,
+                end,
+            } => {
+                let s = curve.parameter_of(p.extend(0.));
+                let t = |s: f64| (s - start) / (end - start);
+                let outside = |t: f64| (-t).max(t - 1.).max(0.);
+                // The point where a closed curve starts and ends may be found at either end
+                let (low, high) = curve.domain();
+                let period = high - low;
+                let shifts = if curve.is_closed() {
+                    vec![0., -period, period]
+                } else {
+                    vec![0.]
+                };
+                shifts
+                    .into_iter()
+                    .map(|shift| t(s + shift))
+                    .min_by(|a, b| outside(*a).total_cmp(&outside(*b)))
+                    .unwrap()
+            }
+        };
+
+        let slack = tolerance.linear / self.length();
+        (t >= -slack && t <= 1. + slack).then_some(t)
+    }
+
+    fn polyline(&self, tolerance: &Tolerance, out: &mut Vec<DVec2>) {
+        let segments = match *self {
+            Curve::Line { .. } => 1,
+            Curve::Arc { radius, sweep, .. } => {
+                let max_angle = 2. * (1. - (tolerance.linear / radius).min(1.)).acos();
+                ((sweep.abs() / max_angle.max(1e-3)).ceil() as usize).clamp(4, 4096)
+            }
+            Curve::Nurbs { .. } => CURVE_SAMPLES,
+        };
+
+        for i in 0..segments {
+            out.push(self.point(i as f64 / segments as f64));
+        }
+    }
+}
+
+/// The points where two curves cross or touch, as parameters on each curve.
+fn intersect(c1: &Curve, c2: &Curve, tolerance: &Tolerance) -> Vec<(f64, f64)> {
+    let candidates: Vec<DVec2> = match (c1, c2) {
+        (&Curve::Line { a, b }, &Curve::Line { a: c, b: d }) => {
+            let r = b - a;
+            let s = d - c;
+            let denom = r.perp_dot(s);
+
+            if tolerance.parallel(r.extend(0.), s.extend(0.)) {
+                // Parallel lines only meet where one ends on top of the other
+                vec![a, b, c, d]
+            } else {
+                vec![a + r * (c - a).perp_dot(s) / denom]
+            }
+        }
+        (&Curve::Line { a, b }, &Curve::Arc { center, radius, .. })
+        | (&Curve::Arc { center, radius, .. }, &Curve::Line { a, b }) => {
+            let d = b - a;
+            let f = a - center;
+            let qa = d.dot(d);
+            let qb = 2. * f.dot(d);
+            let qc = f.dot(f) - radius * radius;
+            let closest = (-qb / (2. * qa)).clamp(f64::MIN, f64::MAX);
+            let distance = (a + closest * d - center).length();
+
+            if (distance - radius).abs() <= tolerance.linear {
+                // Tangent
+                vec![a + closest * d]
+            } else {
+                let disc = qb * qb - 4. * qa * qc;
+                if disc < 0. {
+                    vec![]
+                } else {
+                    let sq = disc.sqrt();
+                    vec![
+                        a + (-qb - sq) / (2. * qa) * d,
+                        a + (-qb + sq) / (2. * qa) * d,
+                    ]
+                }
+            }
+        }
+        (
+            &Curve::Arc {
+                center: c1c,
+                radius: r1,
+                ..
+            },
+            &Curve::Arc {
+                center: c2c,
+                radius: r2,
+                ..
+            },
+        ) => {
+            let delta = c2c - c1c;
+            let d = delta.length();
+
+            if d <= tolerance.linear {
+                // Concentric arcs only meet where one ends on top of the other
+                vec![c1.point(0.), c1.point(1.), c2.point(0.), c2.point(1.)]
+            } else if d > r1 + r2 + tolerance.linear || d < (r1 - r2).abs() - tolerance.linear {
+                vec![]
+            } else {
+                let a = (d * d + r1 * r1 - r2 * r2) / (2. * d);
+                let h = (r1 * r1 - a * a).max(0.).sqrt();
+                let e = delta / d;
+                let base = c1c + a * e;
+                if h <= tolerance.linear {
+                    vec![base]
+                } else {
+                    vec![base + h * e.perp(), base - h * e.perp()]
+                }
+            }
+        }
+        // Pieces of NURBS curves meet other curves where the general intersection finds them,
+        // and overlap them from end to end of the stretch they share
+        _ => {
+            let first = c1.trimmed();
+            tolerance
+                .with(|| intersection::intersect(&first, &c2.trimmed()))
+                .into_iter()
+                .flat_map(|found| match found {
+                    CurveIntersection::Point { point, .. } => vec![point.truncate()],
+                    CurveIntersection::Overlap { t, .. } => vec![
+                        first.point_at(t.0).truncate(),
+                        first.point_at(t.1).truncate(),
+                    ],
+                })
+                .collect()
+        }
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|p| {
+            let t1 = c1.parameter_of(p, tolerance)?;
+            let t2 = c2.parameter_of(p, tolerance)?;
+            let on_both = (c1.point(t1) - p).length() <= tolerance.linear
+                && (c2.point(t2) - p).length() <= tolerance.linear;
+            on_both.then_some((t1, t2))
+        })
+        .collect()
+}
+
+/// Splits every curve at the points where it meets another one.
+fn split_at_intersections(curves: &[Curve], tolerance: &Tolerance) -> Vec<Curve> {
+    let mut splits: Vec<Vec<f64>> = vec![Vec::new(); curves.len()];
+
+    for i in 0..curves.len() {
+        for j in i + 1..curves.len() {
+            for (t1, t2) in intersect(&curves[i], &curves[j], tolerance) {
+                splits[i].push(t1);
+                splits[j].push(t2);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    for (curve, mut params) in curves.iter().zip(splits) {
+        let slack = tolerance.linear / curve.length();
+        params.retain(|&t| t > slack && t < 1. - slack);
+        params.sort_by(f64::total_cmp);
+        params.dedup_by(|a, b| (*a - *b).abs() <= slack);
+
+        let mut t0 = 0.;
+        for t in params.into_iter().chain([1.]) {
+            out.push(curve.sub(t0, t));
+            t0 = t;
+        }
+    }
+
+    out
+}
+
+struct HalfEdge {
+    curve: Curve,
+    from: usize,
+    to: usize,
+}
+
+/// The planar graph of the split curves. Half-edge `2k` runs along edge `k` and `2k + 1` is
+/// its twin running the other way.
+struct Graph {
+    half_edges: Vec<HalfEdge>,
+    /// The outgoing half-edges of every vertex, sorted counter-clockwise.
+    outgoing: Vec<Vec<usize>>,
+    component: Vec<usize>,
+}
+
+impl Graph {
+    fn new(curves: Vec<Curve>, tolerance: &Tolerance) -> Self {
+        let mut vertices: Vec<DVec2> = Vec::new();
+        let mut vertex = |p: DVec2| match vertices
+            .iter()
+            .position(|v| (*v - p).length() <= tolerance.linear)
+        {
+            Some(i) => i,
+            None => {
+                vertices.push(p);
+                vertices.len() - 1
+            }
+        };
+
+        let mut edges: Vec<(Curve, usize, usize)> = Vec::new();
+        for curve in curves {
+            if curve.length() <= tolerance.linear {
+                continue;
+            }
+
+            let from = vertex(curve.point(0.));
+            let to = vertex(curve.point(1.));
+
+            // Overlapping curves have been split into identical pieces
+            let mid = curve.point(0.5);
+            let duplicate = edges.iter().any(|(other, a, b)| {
+                ((*a, *b) == (from, to) || (*a, *b) == (to, from))
+                    && (other.point(0.5) - mid).length() <= tolerance.linear
+            });
+
+            if !duplicate {
+                edges.push((curve, from, to));
+            }
+        }
+        let vertex_count = vertices.len();
+
+        // Repeatedly drop edges that end in a vertex nothing else is connected to
+        let mut alive = vec![true; edges.len()];
+        loop {
+            let mut degree = vec![0; vertex_count];
+            for (k, (_, from, to)) in edges.iter().enumerate() {
+                if alive[k] {
+                    degree[*from] += 1;
+                    degree[*to] += 1;
+                }
+            }
+
+            let mut changed = false;
+            for (k, (_, from, to)) in edges.iter().enumerate() {
+                if alive[k] && (degree[*from] == 1 || degree[*to] == 1) {
+                    alive[k] = false;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut half_edges = Vec::new();
+        let mut parent: Vec<usize> = (0..vertex_count).collect();
+        for (curve, from, to) in edges
+            .into_iter()
+            .zip(alive)
+            .filter(|(_, a)| *a)
+            .map(|(e, _)| e)
+        {
+            let reversed = curve.reversed();
+            half_edges.push(HalfEdge { curve, from, to });
+            half_edges.push(HalfEdge {
+                curve: reversed,
+                from: to,
+                to: from,
+            });
+
+            let (a, b) = (find(&mut parent, from), find(&mut parent, to));
+            parent[a] = b;
+        }
+
+        let component = (0..vertex_count).map(|v| find(&mut parent, v)).collect();
+
+        let mut outgoing: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+        for (h, half_edge) in half_edges.iter().enumerate() {
+            outgoing[half_edge.from].push(h);
+        }
+
+        for list in &mut outgoing {
+            let key = |h: usize| {
+                let curve = &half_edges[h].curve;
+                let t = curve.tangent(0.);
+                (t.y.atan2(t.x), curve.curvature())
+            };
+
+            list.sort_by(|&a, &b| {
+                let (angle_a, curvature_a) = key(a);
+                let (angle_b, curvature_b) = key(b);
+
+                if !tolerance.is_zero_angle(angle_a - angle_b) {
+                    angle_a.total_cmp(&angle_b)
+                } else {
+                    // Of two curves leaving in the same direction, the one turning left
+                    // more sharply is further counter-clockwise
+                    curvature_a
+                        .partial_cmp(&curvature_b)
+                        .unwrap_or(Ordering::Equal)
+                }
+            });
+        }
+
+        Self {
+            half_edges,
+            outgoing,
+            component,
+        }
+    }
+
+    /// The half-edge that follows `h` around the face on its left.
+    fn next(&self, h: usize) -> usize {
+        let twin = h ^ 1;
+        let list = &self.outgoing[self.half_edges[h].to];
+        let i = list.iter().position(|&o| o == twin).unwrap();
+
+        list[(i + list.len() - 1) % list.len()]
+    }
+
+    fn cycles(&self) -> Vec<Cycle> {
+        let mut visited = vec![false; self.half_edges.len()];
+        let mut out = Vec::new();
+
+        for start in 0..self.half_edges.len() {
+            if visited[start] {
+                continue;
+            }
+
+            let mut curves = Vec::new();
+            let mut h = start;
+            while !visited[h] {
+                visited[h] = true;
+                curves.push(self.half_edges[h].curve.clone());
+                h = self.next(h);
+            }
+
+            let area = curves.iter().map(Curve::area).sum();
+            out.push(Cycle {
+                curves,
+                area,
+                component: self.component[self.half_edges[start].from],
+            });
+        }
+
+        out
+    }
+}
+
+fn find(parent: &mut [usize], v: usize) -> usize {
+    let mut root = v;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[v] = root;
+    root
+}
+
+/// A closed walk around a face of the graph. Bounded faces have a positive area, the outer
+/// boundaries of connected components a negative one.
+struct Cycle {
+    curves: Vec<Curve>,
+    area: f64,
+    component: usize,
+}
+
+impl Cycle {
+    fn polyline(&self, tolerance: &Tolerance) -> Vec<DVec2> {
+        let mut out = Vec::new();
+        for curve in &self.curves {
+            curve.polyline(tolerance, &mut out);
+        }
+        out
+    }
+
+    fn to_boundary_loop(&self, plane: &SketchPlane) -> BoundaryLoop {
+        let world = |p: DVec2| plane.to_world(DVec2::new(p.x, p.y));
+
+        let elements = self
+            .curves
+            .iter()
+            .map(|curve| match *curve {
+                Curve::Line { a, b } => {
+                    BoundaryElement::BoundaryLine(BoundaryLine::new(world(a), world(b)))
+                }
+                Curve::Arc {
+                    center,
+                    radius,
+                    sweep,
+                    ..
+                } => BoundaryElement::BoundaryArc(
+                    BoundaryArc::new(
+                        world(center),
+                        plane.0.normal,
+                        radius,
+                        world(curve.point(0.)),
+                        world(curve.point(1.)),
+                        if sweep > 0. {
+                            Direction::CCW
+                        } else {
+                            Direction::CW
+                        },
+                    )
+                    .expect("sketch arcs have a radius"),
+                ),
+                Curve::Nurbs {
+                    ref curve,
+                    start,
+                    end,
+                } => {
+                    // Cannot fail: pieces lie within the domain and are never empty
+                    let (low, high) = curve.domain();
+                    let piece = curve
+                        .trimmed(start.min(end).max(low), start.max(end).min(high))
+                        .unwrap();
+                    let piece = if end < start { piece.reversed() } else { piece };
+                    BoundaryElement::BoundaryCurve(BoundaryCurve::new(
+                        piece.map(|p| world(p.truncate())),
+                    ))
+                }
+            })
+            .collect();
+
+        BoundaryLoop { elements }
+    }
+}
+
+/// Even–odd point in polygon test.
+fn contains(polygon: &[DVec2], p: DVec2) -> bool {
+    let mut inside = false;
+
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use crate::arc::Arc;
+    use crate::circle::Circle;
+    use crate::ellipse::Ellipse;
+    use crate::test_util::{circle, line, polygon};
+    use crate::{BoundaryElement, BoundaryLoop, Direction, Tolerance};
+    use glam::DVec3;
+
+    /// The signed area of a loop in the XY plane, following arcs exactly.
+    fn area(boundary: &BoundaryLoop) -> f64 {
+        boundary
+            .elements
+            .iter()
+            .map(|element| match element {
+                BoundaryElement::BoundaryLine(l) => {
+                    l.a.0.truncate().perp_dot(l.b.0.truncate()) / 2.
+                }
+                BoundaryElement::BoundaryArc(arc) => {
+                    let c = arc.circle.center.truncate();
+                    let s = arc.start.0.truncate() - c;
+                    let e = arc.end.0.truncate() - c;
+                    let mut sweep = s.perp_dot(e).atan2(s.dot(e));
+                    match arc.direction {
+                        Direction::CCW if sweep <= 0. => sweep += std::f64::consts::TAU,
+                        Direction::CW if sweep >= 0. => sweep -= std::f64::consts::TAU,
+                        _ => (),
+                    }
+                    (c.perp_dot(e - s) + arc.circle.radius * arc.circle.radius * sweep) / 2.
+                }
+                BoundaryElement::BoundaryCurve(c) => {
+                    let (start, end) = c.curve.domain();
+                    c.curve.swept_area(start, end).z
+                }
+                BoundaryElement::BoundaryPolygon(_) => unreachable!(),
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_square_region() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        polygon(&mut sketch, &[(0., 0.), (2., 0.), (2., 2.), (0., 2.)]);
+        // A dangling line is not part of any region
+        line(&mut sketch, (2., 2.), (3., 3.));
+        // Nor does a construction line split it
+        line(&mut sketch, (0., 0.), (2., 2.));
+        sketch.set_construction(ElementId(5), true);
+
+        let regions = sketch.find_regions();
+
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].boundary.elements.len(), 4);
+        assert!(regions[0].holes.is_empty());
+        assert!((area(&regions[0].boundary) - 4.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_plate_with_holes() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        // Drawn clockwise, with a gap smaller than the tolerance
+        polygon(&mut sketch, &[(0., 0.), (0., 4.), (6., 4.), (6., 0.)]);
+        line(&mut sketch, (0., 0.), (0., 0.00001));
+        circle(&mut sketch, (1.5, 2.), 1.);
+        polygon(&mut sketch, &[(3.5, 1.), (5., 1.), (5., 3.), (3.5, 3.)]);
+        // An island inside the square hole
+        circle(&mut sketch, (4.25, 2.), 0.5);
+
+        let loose = Tolerance {
+            linear: 1e-4,
+            ..Tolerance::DEFAULT
+        };
+        let regions = loose.with(|| sketch.find_regions());
+        // The plate, the disc filling the round hole, the square hole and the island
+        assert_eq!(regions.len(), 4);
+
+        let plate = regions.iter().find(|r| r.holes.len() == 2).unwrap();
+        let pi = std::f64::consts::PI;
+        assert!((area(&plate.boundary) - 24.).abs() < 1e-4);
+        let hole_area: f64 = plate.holes.iter().map(area).sum();
+        assert!((hole_area + pi + 3.).abs() < 1e-4);
+
+        // The square hole is also a region, with the island as its hole
+        let square = regions
+            .iter()
+            .find(|r| (area(&r.boundary) - 3.).abs() < 1e-4)
+            .unwrap();
+        assert_eq!(square.holes.len(), 1);
+        assert!((area(&square.holes[0]) + pi * 0.25).abs() < 1e-4);
+
+        assert!(regions
+            .iter()
+            .any(|r| (area(&r.boundary) - pi * 0.25).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_overlapping_shapes_are_split() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        polygon(&mut sketch, &[(0., 0.), (2., 0.), (2., 2.), (0., 2.)]);
+        // A circle centred on a corner cuts the square into two faces and adds a third outside
+        circle(&mut sketch, (2., 2.), 1.);
+
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 3);
+
+        let pi = std::f64::consts::PI;
+        let mut areas: Vec<f64> = regions.iter().map(|r| area(&r.boundary)).collect();
+        areas.sort_by(f64::total_cmp);
+
+        assert!((areas[0] - pi / 4.).abs() < 1e-4);
+        assert!((areas[1] - 3. * pi / 4.).abs() < 1e-4);
+        assert!((areas[2] - (4. - pi / 4.)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_slot_with_circle() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        line(&mut sketch, (0., 0.), (4., 0.));
+        line(&mut sketch, (4., 2.), (0., 2.));
+        for (center, start, end) in [
+            ((4., 1.), (4., 0.), (4., 2.)),
+            ((0., 1.), (0., 2.), (0., 0.)),
+        ] {
+            let v = |p: (f64, f64)| DVec3::new(p.0, p.1, 0.);
+            sketch.add_element(SketchElement::Arc(SketchArc(
+                Arc::new(v(center), DVec3::Z, v(start), v(end)).unwrap(),
+            )));
+        }
+        sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(DVec3::new(2., 1., 0.), DVec3::Z, 0.5).unwrap(),
+        )));
+
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 2);
+
+        let pi = std::f64::consts::PI;
+        let slot = regions.iter().find(|r| r.holes.len() == 1).unwrap();
+        assert!((area(&slot.boundary) - (8. + pi)).abs() < 1e-4);
+        assert!((area(&slot.holes[0]) + pi * 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_elliptical_hole() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        polygon(&mut sketch, &[(0., 0.), (6., 0.), (6., 4.), (0., 4.)]);
+        sketch.add_element(SketchElement::Ellipse(SketchEllipse(
+            Ellipse::new(
+                DVec3::new(3., 2., 0.),
+                DVec3::Z,
+                DVec3::new(1., 1., 0.),
+                2.,
+                1.,
+            )
+            .unwrap(),
+        )));
+        // An ellipse across the right edge is cut in two by it
+        sketch.add_element(SketchElement::Ellipse(SketchEllipse(
+            Ellipse::new(DVec3::new(6., 2., 0.), -DVec3::Z, DVec3::Y, 1., 0.5).unwrap(),
+        )));
+
+        let regions = sketch.find_regions();
+        // The plate, the disc filling the hole and both halves of the other ellipse
+        assert_eq!(regions.len(), 4);
+
+        let pi = std::f64::consts::PI;
+        let plate = regions.iter().find(|r| r.holes.len() == 1).unwrap();
+        assert!((area(&plate.boundary) - (24. - pi / 4.)).abs() < 1e-4);
+        assert!((area(&plate.holes[0]) + 2. * pi).abs() < 1e-6);
+
+        let mut areas: Vec<f64> = regions.iter().map(|r| area(&r.boundary)).collect();
+        areas.sort_by(f64::total_cmp);
+        assert!((areas[0] - pi / 4.).abs() < 1e-4);
+        assert!((areas[1] - pi / 4.).abs() < 1e-4);
+        assert!((areas[2] - 2. * pi).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_spline_edged_region() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        line(&mut sketch, (0., 0.), (4., 0.));
+        let fit = FitSpline::new(
+            vec![
+                DVec3::new(4., 0., 0.),
+                DVec3::new(3.5, 2., 0.),
+                DVec3::new(1., 2.5, 0.),
+                DVec3::ZERO,
+            ],
+            Some(DVec3::Y),
+            None,
+        )
+        .unwrap();
+        let curve = fit.curve().clone();
+        sketch.add_element(SketchElement::Spline(SketchSpline::Fit(fit)));
+
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].boundary.elements.len(), 2);
+
+        // The area under the curve, by the shoelace formula over a fine polyline
+        let points: Vec<DVec3> = (0..=1000)
+            .map(|i| curve.point_at(i as f64 / 1000.))
+            .collect();
+        let expected: f64 = points
+            .windows(2)
+            .map(|p| p[0].truncate().perp_dot(p[1].truncate()) / 2.)
+            .sum();
+        assert!((area(&regions[0].boundary) - expected).abs() < 1e-4);
+
+        // A line across cuts it in two
+        line(&mut sketch, (2., -1.), (2., 4.));
+        let regions = sketch.find_regions();
+        assert_eq!(regions.len(), 2);
+        let total: f64 = regions.iter().map(|r| area(&r.boundary)).sum();
+        assert!((total - expected).abs() < 1e-4);
+        assert!(regions.iter().all(|r| area(&r.boundary) > 1.));
+    }
+}
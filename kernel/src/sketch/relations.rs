@@ -1,42 +1,132 @@
-use super::{SketchArc, SketchElement, SketchLine, SketchPoint};
+use super::ElementId;
 
+/// A geometric relation between elements of a [`super::Sketch`]. Elements are referenced by
+/// their [`ElementId`] so the solver can move the geometry they describe.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Relation {
-    Horizontal(SketchLine),
-    Vertical(SketchLine),
+    Horizontal(ElementId),
+    Vertical(ElementId),
     Coincident(Coincident),
     Perpendicular(Perpendicular),
-    Tangent(),
-    Fixed,
-    Colinear,
-    Coradial,
-    Parallel,
-    Concentric,
-    Midpoint,
-    Intersection,
-    Equal,
+    Tangent(Tangent),
+    /// Keeps every parameter of the element at its current value.
+    Fixed(ElementId),
+    Colinear(Colinear),
+    /// Two arcs share their center and radius.
+    Coradial(Coradial),
+    Parallel(Parallel),
+    Concentric(Concentric),
+    Midpoint(Midpoint),
+    /// A point lies on two curves at once.
+    Intersection(Intersection),
+    /// Two lines of equal length or two arcs of equal radius.
+    Equal(Equal),
+    /// The start or end of a spline runs along a line, or along an arc where it meets the
+    /// arc's circle.
+    EndTangent(EndTangent),
 }
 
+/// A point of a sketch element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointRef {
+    /// A standalone [`super::SketchPoint`].
+    Point(ElementId),
+    /// The start of a line, an arc or a spline.
+    Start(ElementId),
+    /// The end of a line, an arc or a spline.
+    End(ElementId),
+    /// The center of an arc.
+    Center(ElementId),
+}
+
+impl PointRef {
+    pub fn element(&self) -> ElementId {
+        match self {
+            PointRef::Point(id)
+            | PointRef::Start(id)
+            | PointRef::End(id)
+            | PointRef::Center(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Coincident {
-    point: SketchPoint,
-    other: CoincidentOther,
+    pub point: PointRef,
+    pub other: CoincidentOther,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum CoincidentOther {
-    Line(SketchLine),
-    Arc(SketchArc),
+    Point(PointRef),
+    Line(ElementId),
+    Arc(ElementId),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Perpendicular {
-    line: SketchLine,
-    perp: SketchLine,
+    pub line: ElementId,
+    pub perp: ElementId,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Tangent {
-    arc: SketchArc,
-    other: TangentOther,
+    pub arc: ElementId,
+    pub other: TangentOther,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum TangentOther {
-    Line(SketchLine),
-    Arc(SketchArc),
+    Line(ElementId),
+    Arc(ElementId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colinear {
+    pub line: ElementId,
+    pub other: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coradial {
+    pub arc: ElementId,
+    pub other: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parallel {
+    pub line: ElementId,
+    pub other: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Concentric {
+    pub arc: ElementId,
+    pub other: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Midpoint {
+    pub point: PointRef,
+    pub line: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intersection {
+    pub point: PointRef,
+    pub first: ElementId,
+    pub second: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equal {
+    pub element: ElementId,
+    pub other: ElementId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndTangent {
+    /// The start or end of a spline.
+    pub point: PointRef,
+    pub other: TangentOther,
 }
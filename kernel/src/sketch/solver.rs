@@ -0,0 +1,955 @@
+use std::fmt;
+
+use glam::{DVec2, DVec3};
+
+use crate::linalg::{self, Matrix};
+use crate::line::{Line, TwoPointLine};
+use crate::nurbs::NurbsCurve;
+use crate::point::Point;
+
+use super::{
+    CoincidentOther, DimensionMode, ElementId, FitSpline, PointRef, Relation, Sketch,
+    SketchElement, SketchPlane, SketchSpline, TangentOther,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SolverSettings {
+    pub max_iterations: usize,
+    /// The solve has converged once no residual is larger than this.
+    pub tolerance: f64,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self {
+            max_iterations: 100,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveReport {
+    pub converged: bool,
+    pub iterations: usize,
+    /// The largest absolute residual of any relation.
+    pub max_residual: f64,
+    /// The residual norm of every relation, in the order of [`Sketch::relations`].
+    pub residuals: Vec<f64>,
+    /// The residual of every dimension, in the order of [`Sketch::dimensions`]. Driven
+    /// dimensions always have a residual of zero.
+    pub dimension_residuals: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveError {
+    /// The relation at this index references a missing element or an element of the wrong kind.
+    InvalidRelation(usize),
+    /// The dimension at this index references a missing element or an element of the wrong kind.
+    InvalidDimension(usize),
+    /// The dimension at this index is driven by the geometry and cannot be edited.
+    DrivenDimension(usize),
+    /// A line whose points coincide.
+    DegenerateElement(ElementId),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::InvalidRelation(i) => write!(f, "relation {i} references invalid elements"),
+            SolveError::InvalidDimension(i) => {
+                write!(f, "dimension {i} references invalid elements")
+            }
+            SolveError::DrivenDimension(i) => write!(f, "dimension {i} is a driven dimension"),
+            SolveError::DegenerateElement(id) => write!(f, "element {} is degenerate", id.0),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl Sketch {
+    pub fn solve(&mut self) -> Result<SolveReport, SolveError> {
+        self.solve_with(&SolverSettings::default())
+    }
+
+    /// Moves the sketch geometry until every relation and driving dimension is satisfied and
+    /// updates the driven dimensions. The sketch is only modified if the solve converges.
+    pub fn solve_with(&mut self, settings: &SolverSettings) -> Result<SolveReport, SolveError> {
+        let system = System::new(self)?;
+        let (x, report) = system.solve(settings)?;
+
+        let driven: Vec<Option<f64>> = self
+            .dimensions
+            .iter()
+            .map(|d| match d.mode {
+                DimensionMode::Driven => system.measure(&x, &d.kind),
+                DimensionMode::Driving => None,
+            })
+            .collect();
+        let layouts = system.layouts;
+
+        if report.converged {
+            write_back(self, &layouts, &x);
+
+            for (dimension, value) in self.dimensions.iter_mut().zip(driven) {
+                if let Some(value) = value {
+                    dimension.value = value;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Where the parameters of an element start in the parameter vector. Points are stored as
+/// `[x, y]`, lines as `[x0, y0, x1, y1]`, arcs as `[cx, cy, r, start_angle, end_angle]`,
+/// circles as `[cx, cy, r]`, ellipses as `[cx, cy, major, minor, major_angle]`, splines as
+/// `[x0, y0, x1, y1, ...]` over their control points and fit splines as
+/// `[x0, y0, x1, y1, ..., start_x, start_y, end_x, end_y]` over their fit points and end
+/// tangents, all in the 2D coordinates of the sketch plane.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Layout {
+    Point(usize),
+    Line(usize),
+    Arc(usize),
+    Circle(usize),
+    Ellipse(usize),
+    /// The offset and the number of control points.
+    Spline(usize, usize),
+    /// The offset and the number of fit points.
+    FitSpline(usize, usize),
+}
+
+impl Layout {
+    pub(crate) fn range(&self) -> std::ops::Range<usize> {
+        match *self {
+            Layout::Point(o) => o..o + 2,
+            Layout::Line(o) => o..o + 4,
+            Layout::Arc(o) => o..o + 5,
+            Layout::Circle(o) => o..o + 3,
+            Layout::Ellipse(o) => o..o + 5,
+            Layout::Spline(o, n) => o..o + 2 * n,
+            Layout::FitSpline(o, n) => o..o + 2 * n + 4,
+        }
+    }
+}
+
+/// The sketch as a system of nonlinear equations over the element parameters.
+#[derive(Clone)]
+pub(crate) struct System<'a> {
+    pub(crate) sketch: &'a Sketch,
+    pub(crate) layouts: Vec<Layout>,
+    pub(crate) initial: Vec<f64>,
+    /// Whether each equation takes part in the solve. Inactive equations have no residuals.
+    pub(crate) active: Vec<bool>,
+}
+
+impl<'a> System<'a> {
+    pub(crate) fn new(sketch: &'a Sketch) -> Result<Self, SolveError> {
+        let plane = &sketch.plane;
+        let local = |p: glam::DVec3| plane.to_local(p);
+
+        let mut layouts = Vec::with_capacity(sketch.elements.len());
+        let mut initial = Vec::new();
+
+        for (i, element) in sketch.elements.iter().enumerate() {
+            let offset = initial.len();
+
+            match element {
+                SketchElement::Point(p) => {
+                    let p = local(p.0 .0);
+                    initial.extend([p.x, p.y]);
+                    layouts.push(Layout::Point(offset));
+                }
+                SketchElement::Line(l) => {
+                    let tpl =
+                        l.0.to_two_point_line()
+                            .map_err(|_| SolveError::DegenerateElement(ElementId(i)))?;
+                    let a = local(tpl.a.0);
+                    let b = local(tpl.b.0);
+                    initial.extend([a.x, a.y, b.x, b.y]);
+                    layouts.push(Layout::Line(offset));
+                }
+                SketchElement::Arc(a) => {
+                    let c = local(a.0.center);
+                    let s = local(a.0.start) - c;
+                    let e = local(a.0.end) - c;
+                    initial.extend([c.x, c.y, a.0.radius, s.y.atan2(s.x), e.y.atan2(e.x)]);
+                    layouts.push(Layout::Arc(offset));
+                }
+                SketchElement::Circle(circle) => {
+                    let c = local(circle.0.center);
+                    initial.extend([c.x, c.y, circle.0.radius]);
+                    layouts.push(Layout::Circle(offset));
+                }
+                SketchElement::Ellipse(ellipse) => {
+                    let e = &ellipse.0;
+                    let c = local(e.center);
+                    let major = local(e.center + e.x_axis) - c;
+                    initial.extend([
+                        c.x,
+                        c.y,
+                        e.major_radius,
+                        e.minor_radius,
+                        major.y.atan2(major.x),
+                    ]);
+                    layouts.push(Layout::Ellipse(offset));
+                }
+                SketchElement::Spline(SketchSpline::Control(curve)) => {
+                    let points = &curve.control_points;
+                    initial.extend(points.iter().flat_map(|&p| {
+                        let p = local(p);
+                        [p.x, p.y]
+                    }));
+                    layouts.push(Layout::Spline(offset, points.len()));
+                }
+                SketchElement::Spline(SketchSpline::Fit(fit)) => {
+                    let points = fit.points();
+                    initial.extend(points.iter().flat_map(|&p| {
+                        let p = local(p);
+                        [p.x, p.y]
+                    }));
+
+                    // Ends without a tangent start from the direction the curve has there,
+                    // scaled like the tangents of NurbsCurve::interpolate
+                    let curve = fit.curve();
+                    let chords: f64 = points.windows(2).map(|p| p[0].distance(p[1])).sum();
+                    let (start, end) = curve.domain();
+                    let tangent = |given: Option<DVec3>, t: f64, p: DVec3| {
+                        let t = given.unwrap_or_else(|| curve.derivative_at(t) / chords);
+                        local(p + t) - local(p)
+                    };
+                    let s = tangent(fit.start_tangent(), start, points[0]);
+                    let e = tangent(fit.end_tangent(), end, points[points.len() - 1]);
+                    initial.extend([s.x, s.y, e.x, e.y]);
+                    layouts.push(Layout::FitSpline(offset, points.len()));
+                }
+            }
+        }
+
+        Ok(Self {
+            sketch,
+            layouts,
+            initial,
+            active: vec![true; sketch.relations.len() + sketch.dimensions.len()],
+        })
+    }
+
+    fn layout(&self, id: ElementId) -> Option<Layout> {
+        self.layouts.get(id.0).copied()
+    }
+
+    pub(crate) fn point(&self, x: &[f64], point: PointRef) -> Option<DVec2> {
+        let arc_point = |o: usize, angle: f64| {
+            DVec2::new(x[o], x[o + 1]) + x[o + 2] * DVec2::new(angle.cos(), angle.sin())
+        };
+
+        match (point, self.layout(point.element())?) {
+            (PointRef::Point(_), Layout::Point(o)) | (PointRef::Start(_), Layout::Line(o)) => {
+                Some(DVec2::new(x[o], x[o + 1]))
+            }
+            (PointRef::End(_), Layout::Line(o)) => Some(DVec2::new(x[o + 2], x[o + 3])),
+            (PointRef::Start(_), Layout::Arc(o)) => Some(arc_point(o, x[o + 3])),
+            (PointRef::End(_), Layout::Arc(o)) => Some(arc_point(o, x[o + 4])),
+            (PointRef::Start(_), Layout::Spline(o, _) | Layout::FitSpline(o, _)) => {
+                Some(DVec2::new(x[o], x[o + 1]))
+            }
+            (PointRef::End(_), Layout::Spline(o, n) | Layout::FitSpline(o, n)) => {
+                Some(DVec2::new(x[o + 2 * n - 2], x[o + 2 * n - 1]))
+            }
+            (PointRef::Center(_), Layout::Arc(o) | Layout::Circle(o) | Layout::Ellipse(o)) => {
+                Some(DVec2::new(x[o], x[o + 1]))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn line(&self, x: &[f64], id: ElementId) -> Option<(DVec2, DVec2)> {
+        match self.layout(id)? {
+            Layout::Line(o) => Some((DVec2::new(x[o], x[o + 1]), DVec2::new(x[o + 2], x[o + 3]))),
+            _ => None,
+        }
+    }
+
+    /// The center and radius of an arc or a circle.
+    pub(crate) fn arc(&self, x: &[f64], id: ElementId) -> Option<(DVec2, f64)> {
+        match self.layout(id)? {
+            Layout::Arc(o) | Layout::Circle(o) => Some((DVec2::new(x[o], x[o + 1]), x[o + 2])),
+            _ => None,
+        }
+    }
+
+    /// The start or end of a spline and the unit direction the spline runs in there. Clamped
+    /// splines leave their first control point towards the second and arrive at their last one
+    /// from the one before. Fit splines run along their end tangents.
+    pub(crate) fn spline_end(&self, x: &[f64], point: PointRef) -> Option<(DVec2, DVec2)> {
+        let at = |i: usize| DVec2::new(x[i], x[i + 1]);
+
+        let (end, d) = match (point, self.layout(point.element())?) {
+            (PointRef::Start(_), Layout::Spline(o, _)) => (at(o), at(o + 2) - at(o)),
+            (PointRef::End(_), Layout::Spline(o, n)) => {
+                (at(o + 2 * n - 2), at(o + 2 * n - 2) - at(o + 2 * n - 4))
+            }
+            (PointRef::Start(_), Layout::FitSpline(o, n)) => (at(o), at(o + 2 * n)),
+            (PointRef::End(_), Layout::FitSpline(o, n)) => (at(o + 2 * n - 2), at(o + 2 * n + 2)),
+            _ => return None,
+        };
+        Some((end, d / d.length().max(1e-12)))
+    }
+
+    /// The unit direction of a line.
+    pub(crate) fn direction(&self, x: &[f64], id: ElementId) -> Option<DVec2> {
+        let (a, b) = self.line(x, id)?;
+        let d = b - a;
+        Some(d / d.length().max(1e-12))
+    }
+
+    /// The signed distance of a point from a line, an arc or a circle. For ellipses it is the
+    /// distance scaled to the unit circle, which is zero on the ellipse just the same.
+    pub(crate) fn distance_to_curve(&self, x: &[f64], p: DVec2, id: ElementId) -> Option<f64> {
+        match self.layout(id)? {
+            Layout::Line(_) => {
+                let (a, _) = self.line(x, id)?;
+                Some(self.direction(x, id)?.perp_dot(p - a))
+            }
+            Layout::Arc(_) | Layout::Circle(_) => {
+                let (c, r) = self.arc(x, id)?;
+                Some((p - c).length() - r.abs())
+            }
+            Layout::Ellipse(o) => {
+                let d = DVec2::from_angle(-x[o + 4]).rotate(p - DVec2::new(x[o], x[o + 1]));
+                let (a, b) = (x[o + 2].abs().max(1e-12), x[o + 3].abs().max(1e-12));
+                Some(DVec2::new(d.x / a, d.y / b).length() - 1.)
+            }
+            Layout::Point(_) | Layout::Spline(..) | Layout::FitSpline(..) => None,
+        }
+    }
+
+    fn relation_residuals(&self, x: &[f64], relation: &Relation, out: &mut Vec<f64>) -> Option<()> {
+        match relation {
+            Relation::Horizontal(line) => out.push(self.direction(x, *line)?.y),
+            Relation::Vertical(line) => out.push(self.direction(x, *line)?.x),
+            Relation::Coincident(c) => {
+                let p = self.point(x, c.point)?;
+                match c.other {
+                    CoincidentOther::Point(other) => {
+                        let d = p - self.point(x, other)?;
+                        out.extend([d.x, d.y]);
+                    }
+                    CoincidentOther::Line(id) | CoincidentOther::Arc(id) => {
+                        out.push(self.distance_to_curve(x, p, id)?)
+                    }
+                }
+            }
+            Relation::Perpendicular(p) => {
+                out.push(self.direction(x, p.line)?.dot(self.direction(x, p.perp)?))
+            }
+            Relation::Parallel(p) => out.push(
+                self.direction(x, p.line)?
+                    .perp_dot(self.direction(x, p.other)?),
+            ),
+            Relation::Colinear(c) => {
+                let u = self.direction(x, c.line)?;
+                let (a, _) = self.line(x, c.line)?;
+                let (b, _) = self.line(x, c.other)?;
+                out.extend([u.perp_dot(self.direction(x, c.other)?), u.perp_dot(b - a)]);
+            }
+            Relation::Tangent(t) => {
+                let (c, r) = self.arc(x, t.arc)?;
+                match t.other {
+                    TangentOther::Line(line) => {
+                        out.push(self.distance_to_curve(x, c, line)?.abs() - r.abs())
+                    }
+                    TangentOther::Arc(arc) => {
+                        let (c2, r2) = self.arc(x, arc)?;
+                        let dist = (c - c2).length();
+                        let external = dist - (r.abs() + r2.abs());
+                        let internal = dist - (r.abs() - r2.abs()).abs();
+                        out.push(if external.abs() < internal.abs() {
+                            external
+                        } else {
+                            internal
+                        });
+                    }
+                }
+            }
+            Relation::Fixed(id) => {
+                out.extend(self.layout(*id)?.range().map(|k| x[k] - self.initial[k]));
+            }
+            Relation::Coradial(c) => {
+                let (c1, r1) = self.arc(x, c.arc)?;
+                let (c2, r2) = self.arc(x, c.other)?;
+                out.extend([c1.x - c2.x, c1.y - c2.y, r1.abs() - r2.abs()]);
+            }
+            Relation::Concentric(c) => {
+                let (c1, _) = self.arc(x, c.arc)?;
+                let (c2, _) = self.arc(x, c.other)?;
+                out.extend([c1.x - c2.x, c1.y - c2.y]);
+            }
+            Relation::Midpoint(m) => {
+                let p = self.point(x, m.point)?;
+                let (a, b) = self.line(x, m.line)?;
+                let d = p - (a + b) / 2.;
+                out.extend([d.x, d.y]);
+            }
+            Relation::Intersection(i) => {
+                let p = self.point(x, i.point)?;
+                out.push(self.distance_to_curve(x, p, i.first)?);
+                out.push(self.distance_to_curve(x, p, i.second)?);
+            }
+            Relation::Equal(e) => match (self.layout(e.element)?, self.layout(e.other)?) {
+                (Layout::Line(_), Layout::Line(_)) => {
+                    let (a1, b1) = self.line(x, e.element)?;
+                    let (a2, b2) = self.line(x, e.other)?;
+                    out.push((b1 - a1).length() - (b2 - a2).length());
+                }
+                (Layout::Arc(_) | Layout::Circle(_), Layout::Arc(_) | Layout::Circle(_)) => {
+                    let (_, r1) = self.arc(x, e.element)?;
+                    let (_, r2) = self.arc(x, e.other)?;
+                    out.push(r1.abs() - r2.abs());
+                }
+                _ => return None,
+            },
+            Relation::EndTangent(t) => {
+                let (end, tangent) = self.spline_end(x, t.point)?;
+                match t.other {
+                    TangentOther::Line(line) => {
+                        out.push(tangent.perp_dot(self.direction(x, line)?))
+                    }
+                    TangentOther::Arc(arc) => {
+                        let (c, _) = self.arc(x, arc)?;
+                        let radial = end - c;
+                        out.push(tangent.dot(radial / radial.length().max(1e-12)));
+                    }
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    /// Whether the geometry at `x` satisfies `relation` to within `tolerance`. Relations
+    /// between elements they do not apply to never hold.
+    pub(crate) fn relation_holds(&self, x: &[f64], relation: &Relation, tolerance: f64) -> bool {
+        let mut residuals = Vec::new();
+        self.relation_residuals(x, relation, &mut residuals)
+            .is_some()
+            && max_abs(&residuals) <= tolerance
+    }
+
+    /// The residuals of every equation of the system, grouped by equation. The relations come
+    /// first, followed by the dimensions.
+    pub(crate) fn equation_residuals(&self, x: &[f64]) -> Result<Vec<Vec<f64>>, SolveError> {
+        let mut out =
+            Vec::with_capacity(self.sketch.relations.len() + self.sketch.dimensions.len());
+
+        for (i, relation) in self.sketch.relations.iter().enumerate() {
+            let mut rows = Vec::new();
+            self.relation_residuals(x, relation, &mut rows)
+                .ok_or(SolveError::InvalidRelation(i))?;
+            if !self.active[i] {
+                rows.clear();
+            }
+            out.push(rows);
+        }
+
+        for (i, dimension) in self.sketch.dimensions.iter().enumerate() {
+            let mut rows = Vec::new();
+            self.dimension_residuals(x, dimension, &mut rows)
+                .ok_or(SolveError::InvalidDimension(i))?;
+            if !self.active[self.sketch.relations.len() + i] {
+                rows.clear();
+            }
+            out.push(rows);
+        }
+
+        Ok(out)
+    }
+
+    pub(crate) fn residuals(&self, x: &[f64]) -> Result<Vec<f64>, SolveError> {
+        Ok(self.equation_residuals(x)?.concat())
+    }
+
+    /// The Jacobian of [`Self::residuals`], approximated with central differences.
+    pub(crate) fn jacobian(&self, x: &[f64]) -> Result<Matrix, SolveError> {
+        let rows = self.residuals(x)?.len();
+        let mut j = Matrix::zeros(rows, x.len());
+        let mut probe = x.to_vec();
+
+        for k in 0..x.len() {
+            let h = 1e-7 * x[k].abs().max(1.);
+
+            probe[k] = x[k] + h;
+            let forward = self.residuals(&probe)?;
+            probe[k] = x[k] - h;
+            let backward = self.residuals(&probe)?;
+            probe[k] = x[k];
+
+            for row in 0..rows {
+                j.set(row, k, (forward[row] - backward[row]) / (2. * h));
+            }
+        }
+
+        Ok(j)
+    }
+
+    /// Levenberg–Marquardt iteration starting from the current geometry.
+    pub(crate) fn solve(
+        &self,
+        settings: &SolverSettings,
+    ) -> Result<(Vec<f64>, SolveReport), SolveError> {
+        self.solve_from(&self.initial, settings)
+    }
+
+    /// Levenberg–Marquardt iteration starting from the parameters `start`.
+    pub(crate) fn solve_from(
+        &self,
+        start: &[f64],
+        settings: &SolverSettings,
+    ) -> Result<(Vec<f64>, SolveReport), SolveError> {
+        let tolerance = settings.tolerance;
+        let n = start.len();
+
+        let mut x = start.to_vec();
+        let mut r = self.residuals(&x)?;
+        let mut cost = sum_squares(&r);
+        let mut lambda = 1e-3;
+        let mut iterations = 0;
+
+        while iterations < settings.max_iterations && max_abs(&r) > tolerance {
+            iterations += 1;
+
+            let j = self.jacobian(&x)?;
+            let a = j.transpose_mul_self();
+            let g: Vec<f64> = j.transpose_mul_vec(&r).iter().map(|v| -v).collect();
+
+            let mut improved = false;
+            while lambda < 1e12 {
+                let mut damped = a.clone();
+                for i in 0..n {
+                    let d = a.get(i, i);
+                    damped.set(i, i, d + lambda * (1. + d));
+                }
+
+                if let Some(step) = linalg::solve(&damped, &g) {
+                    let candidate: Vec<f64> = x.iter().zip(&step).map(|(x, s)| x + s).collect();
+                    let candidate_r = self.residuals(&candidate)?;
+                    let candidate_cost = sum_squares(&candidate_r);
+
+                    if candidate_cost < cost {
+                        x = candidate;
+                        r = candidate_r;
+                        cost = candidate_cost;
+                        lambda = (lambda / 10.).max(1e-12);
+                        improved = true;
+                        break;
+                    }
+                }
+
+                lambda *= 10.;
+            }
+
+            if !improved {
+                break;
+            }
+        }
+
+        let mut residuals: Vec<f64> = self
+            .equation_residuals(&x)?
+            .iter()
+            .map(|rows| sum_squares(rows).sqrt())
+            .collect();
+        let dimension_residuals = residuals.split_off(self.sketch.relations.len());
+
+        let report = SolveReport {
+            converged: max_abs(&r) <= tolerance,
+            iterations,
+            max_residual: max_abs(&r),
+            residuals,
+            dimension_residuals,
+        };
+
+        Ok((x, report))
+    }
+}
+
+fn sum_squares(v: &[f64]) -> f64 {
+    v.iter().map(|v| v * v).sum()
+}
+
+fn max_abs(v: &[f64]) -> f64 {
+    v.iter().fold(0., |acc: f64, v| acc.max(v.abs()))
+}
+
+/// Writes the solved parameters back into the sketch elements.
+pub(crate) fn write_back(sketch: &mut Sketch, layouts: &[Layout], x: &[f64]) {
+    let plane: &SketchPlane = &sketch.plane;
+    let world = |x: f64, y: f64| plane.to_world(DVec2::new(x, y));
+
+    let mut updated = Vec::with_capacity(layouts.len());
+    for (i, (element, layout)) in sketch.elements.iter().zip(layouts).enumerate() {
+        let element = match (element, *layout) {
+            (SketchElement::Point(_), Layout::Point(o)) => {
+                SketchElement::Point(super::SketchPoint(Point(world(x[o], x[o + 1]))))
+            }
+            (SketchElement::Line(_), Layout::Line(o)) => {
+                SketchElement::Line(super::SketchLine(Line::TwoPoint(TwoPointLine::new(
+                    world(x[o], x[o + 1]),
+                    world(x[o + 2], x[o + 3]),
+                ))))
+            }
+            (SketchElement::Arc(arc), Layout::Arc(o)) => {
+                let (cx, cy, r) = (x[o], x[o + 1], x[o + 2]);
+                let mut arc = arc.clone();
+                arc.0.center = world(cx, cy);
+                arc.0.radius = r.abs();
+                arc.0.start = world(cx + r * x[o + 3].cos(), cy + r * x[o + 3].sin());
+                arc.0.end = world(cx + r * x[o + 4].cos(), cy + r * x[o + 4].sin());
+                SketchElement::Arc(arc)
+            }
+            (SketchElement::Circle(circle), Layout::Circle(o)) => {
+                let mut circle = circle.clone();
+                circle.0.center = world(x[o], x[o + 1]);
+                circle.0.radius = x[o + 2].abs();
+                SketchElement::Circle(circle)
+            }
+            (SketchElement::Ellipse(ellipse), Layout::Ellipse(o)) => {
+                let (cx, cy, angle) = (x[o], x[o + 1], x[o + 4]);
+                let mut ellipse = ellipse.clone();
+                ellipse.0.center = world(cx, cy);
+                ellipse.0.x_axis =
+                    (world(cx + angle.cos(), cy + angle.sin()) - ellipse.0.center).normalize();
+                ellipse.0.major_radius = x[o + 2].abs();
+                ellipse.0.minor_radius = x[o + 3].abs();
+                SketchElement::Ellipse(ellipse)
+            }
+            (SketchElement::Spline(SketchSpline::Control(curve)), Layout::Spline(o, n)) => {
+                let control_points = (0..n)
+                    .map(|i| world(x[o + 2 * i], x[o + 2 * i + 1]))
+                    .collect();
+                SketchElement::Spline(SketchSpline::Control(NurbsCurve {
+                    control_points,
+                    ..curve.clone()
+                }))
+            }
+            (SketchElement::Spline(SketchSpline::Fit(fit)), Layout::FitSpline(o, n)) => {
+                let points = (0..n)
+                    .map(|i| world(x[o + 2 * i], x[o + 2 * i + 1]))
+                    .collect();
+
+                // Ends keep a tangent they had or one a relation has given them
+                let id = ElementId(i);
+                let tangent = |given: Option<DVec3>, point: PointRef, k: usize| {
+                    let held = sketch
+                        .relations
+                        .iter()
+                        .any(|r| matches!(r, Relation::EndTangent(t) if t.point == point));
+                    (given.is_some() || held).then(|| world(x[k], x[k + 1]) - world(0., 0.))
+                };
+                let start = tangent(fit.start_tangent(), PointRef::Start(id), o + 2 * n);
+                let end = tangent(fit.end_tangent(), PointRef::End(id), o + 2 * n + 2);
+
+                // Fit points moved onto each other leave the spline as it was
+                FitSpline::new(points, start, end).map_or_else(
+                    |_| element.clone(),
+                    |fit| SketchElement::Spline(SketchSpline::Fit(fit)),
+                )
+            }
+            (element, _) => element.clone(),
+        };
+        updated.push(element);
+    }
+
+    sketch.elements = updated;
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::super::*;
+    use crate::arc::Arc;
+    use crate::circle::Circle;
+    use crate::ellipse::Ellipse;
+    use crate::nurbs::NurbsCurve;
+    use crate::test_util::line;
+
+    fn endpoints(sketch: &Sketch, id: ElementId) -> (DVec3, DVec3) {
+        match sketch.element(id) {
+            Some(SketchElement::Line(l)) => {
+                let tpl = l.0.to_two_point_line().unwrap();
+                (tpl.a.0, tpl.b.0)
+            }
+            _ => panic!("not a line"),
+        }
+    }
+
+    fn coincident(a: PointRef, b: PointRef) -> Relation {
+        Relation::Coincident(Coincident {
+            point: a,
+            other: CoincidentOther::Point(b),
+        })
+    }
+
+    #[test]
+    fn test_solve_rectangle() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let bottom = line(&mut sketch, (0., 0.), (4., 0.3));
+        let right = line(&mut sketch, (4.1, 0.2), (3.8, 2.));
+        let top = line(&mut sketch, (4., 2.2), (0.1, 1.9));
+        let left = line(&mut sketch, (-0.2, 2.), (0.1, 0.1));
+
+        let lines = [bottom, right, top, left];
+        for i in 0..4 {
+            let next = lines[(i + 1) % 4];
+            sketch.add_relation(coincident(PointRef::End(lines[i]), PointRef::Start(next)));
+        }
+        sketch.add_relation(Relation::Horizontal(bottom));
+        sketch.add_relation(Relation::Horizontal(top));
+        sketch.add_relation(Relation::Vertical(left));
+        sketch.add_relation(Relation::Vertical(right));
+
+        let report = sketch.solve().unwrap();
+
+        assert!(report.converged, "{report:?}");
+        assert!(report.iterations > 0);
+        assert_eq!(report.residuals.len(), 8);
+
+        let (a, b) = endpoints(&sketch, bottom);
+        assert!((a.y - b.y).abs() < 1e-4);
+        let (c, d) = endpoints(&sketch, right);
+        assert!((c.x - d.x).abs() < 1e-4);
+        assert!(b.abs_diff_eq(c, 1e-4));
+    }
+
+    #[test]
+    fn test_solve_tangent_line_and_arc() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (-2., 1.2), (2., 1.1));
+        let arc = sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::ZERO,
+                DVec3::Z,
+                DVec3::new(1., 0., 0.),
+                DVec3::new(-1., 0., 0.),
+            )
+            .unwrap(),
+        )));
+
+        sketch.add_relation(Relation::Fixed(arc));
+        sketch.add_relation(Relation::Horizontal(l));
+        sketch.add_relation(Relation::Tangent(Tangent {
+            arc,
+            other: TangentOther::Line(l),
+        }));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        let (a, b) = endpoints(&sketch, l);
+        assert!((a.y - 1.).abs() < 1e-4 && (b.y - 1.).abs() < 1e-4);
+
+        let Some(SketchElement::Arc(arc)) = sketch.element(arc) else {
+            panic!("not an arc");
+        };
+        assert!(arc.0.center.abs_diff_eq(DVec3::ZERO, 1e-5));
+    }
+
+    #[test]
+    fn test_solve_circle_and_ellipse() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (-3., 0.), (3., 0.));
+        let circle = sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(DVec3::new(0.2, 1.3, 0.), DVec3::Z, 0.8).unwrap(),
+        )));
+        let ellipse = sketch.add_element(SketchElement::Ellipse(SketchEllipse(
+            Ellipse::new(DVec3::new(2., 2., 0.), DVec3::Z, DVec3::Y, 2., 1.).unwrap(),
+        )));
+
+        sketch.add_relation(Relation::Fixed(l));
+        sketch.add_relation(Relation::Tangent(Tangent {
+            arc: circle,
+            other: TangentOther::Line(l),
+        }));
+        sketch.add_relation(coincident(
+            PointRef::Center(ellipse),
+            PointRef::Center(circle),
+        ));
+        // The start of the line lies on the ellipse
+        sketch.add_relation(Relation::Coincident(Coincident {
+            point: PointRef::Start(l),
+            other: CoincidentOther::Arc(ellipse),
+        }));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Radius(circle), 1.));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        let Some(SketchElement::Circle(c)) = sketch.element(circle) else {
+            panic!("not a circle");
+        };
+        assert!((c.0.radius - 1.).abs() < 1e-4);
+        assert!((c.0.center.y.abs() - 1.).abs() < 1e-4);
+
+        let Some(SketchElement::Ellipse(e)) = sketch.element(ellipse) else {
+            panic!("not an ellipse");
+        };
+        assert!(e.0.center.abs_diff_eq(c.0.center, 1e-4));
+        assert!(e.0.x_axis.dot(DVec3::Z).abs() < 1e-6);
+        let start = DVec3::new(-3., 0., 0.);
+        assert!(e.0.closest_point(start).distance(start) < 1e-3);
+    }
+
+    #[test]
+    fn test_solve_spline_ends() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (-3., 0.), (0., 0.));
+        let arc = sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::new(5., 2., 0.),
+                DVec3::Z,
+                DVec3::new(5., 0., 0.),
+                DVec3::new(7., 2., 0.),
+            )
+            .unwrap(),
+        )));
+        let fit = [
+            DVec3::new(0.2, 0.3, 0.),
+            DVec3::new(2., 1.5, 0.),
+            DVec3::new(4.8, 0.3, 0.),
+        ];
+        let spline = sketch.add_element(SketchElement::Spline(SketchSpline::Control(
+            NurbsCurve::interpolate(&fit, None, None).unwrap(),
+        )));
+
+        sketch.add_relation(Relation::Fixed(l));
+        sketch.add_relation(Relation::Fixed(arc));
+        sketch.add_relation(coincident(PointRef::Start(spline), PointRef::End(l)));
+        sketch.add_relation(coincident(PointRef::End(spline), PointRef::Start(arc)));
+        sketch.add_relation(Relation::EndTangent(EndTangent {
+            point: PointRef::Start(spline),
+            other: TangentOther::Line(l),
+        }));
+        sketch.add_relation(Relation::EndTangent(EndTangent {
+            point: PointRef::End(spline),
+            other: TangentOther::Arc(arc),
+        }));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        let Some(SketchElement::Spline(s)) = sketch.element(spline) else {
+            panic!("not a spline");
+        };
+        let curve = s.curve();
+        assert!(curve.point_at(0.).abs_diff_eq(DVec3::ZERO, 1e-4));
+        assert!(curve.point_at(1.).abs_diff_eq(DVec3::new(5., 0., 0.), 1e-4));
+        assert!(curve.derivative_at(0.).normalize().y.abs() < 1e-4);
+        assert!(curve.derivative_at(1.).normalize().y.abs() < 1e-4);
+        assert!(curve.control_points.iter().all(|p| p.z == 0.));
+
+        assert!(!sketch.to_lines(&TesselationTolerance::default()).is_empty());
+    }
+
+    #[test]
+    fn test_solve_fit_spline() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (-3., 0.), (0., 0.));
+        let fit = FitSpline::new(
+            vec![
+                DVec3::new(0.2, 0.3, 0.),
+                DVec3::new(2., 1.5, 0.),
+                DVec3::new(4.8, 0.3, 0.),
+            ],
+            None,
+            Some(DVec3::new(1., -1., 0.)),
+        )
+        .unwrap();
+        let spline = sketch.add_element(SketchElement::Spline(SketchSpline::Fit(fit)));
+
+        sketch.add_relation(Relation::Fixed(l));
+        sketch.add_relation(coincident(PointRef::Start(spline), PointRef::End(l)));
+        sketch.add_relation(Relation::EndTangent(EndTangent {
+            point: PointRef::Start(spline),
+            other: TangentOther::Line(l),
+        }));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        // The spline keeps its fit points and end tangent and runs through them
+        let Some(SketchElement::Spline(SketchSpline::Fit(fit))) = sketch.element(spline) else {
+            panic!("not a fit spline");
+        };
+        assert_eq!(fit.points().len(), 3);
+        assert!(fit.points()[0].abs_diff_eq(DVec3::ZERO, 1e-6));
+        let curve = fit.curve();
+        for &p in fit.points() {
+            assert!(curve.closest_point(p).abs_diff_eq(p, 1e-6));
+        }
+
+        // The start has taken the tangent of the line, and the end kept its own
+        let start = fit.start_tangent().unwrap();
+        assert!(start.y.abs() < 1e-6 && start.x > 0.);
+        assert!(curve
+            .derivative_at(0.)
+            .normalize()
+            .abs_diff_eq(DVec3::X, 1e-6));
+        let end = fit.end_tangent().unwrap();
+        assert!(end
+            .normalize()
+            .abs_diff_eq(DVec3::new(1., -1., 0.).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn test_solve_perpendicular_and_equal() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let first = line(&mut sketch, (0., 0.), (2., 0.));
+        let second = line(&mut sketch, (0., 0.), (0.5, 1.));
+
+        sketch.add_relation(Relation::Fixed(first));
+        sketch.add_relation(coincident(PointRef::Start(second), PointRef::Start(first)));
+        sketch.add_relation(Relation::Perpendicular(Perpendicular {
+            line: first,
+            perp: second,
+        }));
+        sketch.add_relation(Relation::Equal(Equal {
+            element: first,
+            other: second,
+        }));
+
+        let report = sketch.solve().unwrap();
+        assert!(report.converged, "{report:?}");
+
+        let (a, b) = endpoints(&sketch, first);
+        let (c, d) = endpoints(&sketch, second);
+        assert!((b - a).dot(d - c).abs() < 1e-4);
+        assert!(((b - a).length() - (d - c).length()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_solve_invalid_relation() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let point = sketch.add_element(SketchElement::Point(SketchPoint(Point(DVec3::ZERO))));
+        sketch.add_relation(Relation::Horizontal(point));
+
+        assert_eq!(sketch.solve().unwrap_err(), SolveError::InvalidRelation(0));
+    }
+
+    #[test]
+    fn test_unsatisfiable_sketch_is_untouched() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let l = line(&mut sketch, (0., 0.), (1., 1.));
+        sketch.add_relation(Relation::Horizontal(l));
+        sketch.add_relation(Relation::Vertical(l));
+
+        let report = sketch.solve().unwrap();
+        assert!(!report.converged);
+
+        let (a, b) = endpoints(&sketch, l);
+        assert_eq!(a, DVec3::ZERO);
+        assert_eq!(b, DVec3::new(1., 1., 0.));
+    }
+}
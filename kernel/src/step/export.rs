@@ -0,0 +1,988 @@
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glam::DVec3;
+
+use crate::curve::Curve;
+use crate::nurbs::{NurbsCurve, NurbsSurface};
+use crate::surface::Surface;
+use crate::{
+    Brep, BrepError, EdgeId, FaceId, LoopId, Plane, ShellId, SolidId, TesselationTolerance,
+    Tolerance, VertexId,
+};
+
+use super::part21::{EntityRecord, ExchangeFile, Parameter, Record};
+use super::StepSchema;
+
+impl Brep {
+    /// Writes solids to a STEP file as a single part, each solid an advanced B-rep. Lengths are
+    /// in metres, angles in radians, and the uncertainty is the linear tolerance in effect.
+    /// Fails if one of the solids has no shells.
+    pub fn to_step(&self, solids: &[SolidId], schema: StepSchema) -> Result<String, BrepError> {
+        if let Some(&empty) = solids.iter().find(|&&s| self.solid(s).shells.is_empty()) {
+            return Err(BrepError::EmptySolid(empty));
+        }
+
+        let mut writer = StepWriter::new(self);
+        let items = solids.iter().map(|&s| writer.solid(s)).collect();
+        writer.product(items, schema);
+
+        writer.file.header = vec![
+            EntityRecord::new(
+                "FILE_DESCRIPTION",
+                vec![
+                    Parameter::List(vec![Parameter::string("")]),
+                    Parameter::string("2;1"),
+                ],
+            ),
+            EntityRecord::new(
+                "FILE_NAME",
+                vec![
+                    unnamed(),
+                    Parameter::String(timestamp()),
+                    Parameter::List(vec![unnamed()]),
+                    Parameter::List(vec![unnamed()]),
+                    Parameter::string("cad-kernel"),
+                    Parameter::string("cad-kernel"),
+                    unnamed(),
+                ],
+            ),
+            EntityRecord::new(
+                "FILE_SCHEMA",
+                vec![Parameter::List(vec![Parameter::string(
+                    schema.schema_name(),
+                )])],
+            ),
+        ];
+
+        Ok(writer.file.to_string())
+    }
+}
+
+/// The empty name most entities are written with.
+fn unnamed() -> Parameter {
+    Parameter::string("")
+}
+
+/// Builds the data section, numbering instances in the order they are added and writing every
+/// vertex and edge once however many faces share it.
+struct StepWriter<'a> {
+    brep: &'a Brep,
+    file: ExchangeFile,
+    vertices: HashMap<VertexId, usize>,
+    edges: HashMap<EdgeId, usize>,
+}
+
+impl<'a> StepWriter<'a> {
+    fn new(brep: &'a Brep) -> Self {
+        Self {
+            brep,
+            file: ExchangeFile::default(),
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, name: &str, parameters: Vec<Parameter>) -> usize {
+        self.insert(Record::Simple(EntityRecord::new(name, parameters)))
+    }
+
+    /// Adds an instance of several entities at once, given in alphabetical order.
+    fn add_complex(&mut self, records: Vec<EntityRecord>) -> usize {
+        self.insert(Record::Complex(records))
+    }
+
+    fn insert(&mut self, record: Record) -> usize {
+        let id = self.file.data.len() + 1;
+        self.file.data.insert(id, record);
+        id
+    }
+
+    /// The product, its definition and the shape representation holding `items`.
+    fn product(&mut self, mut items: Vec<usize>, schema: StepSchema) {
+        let (application, protocol, year) = match schema {
+            StepSchema::Ap203 => (
+                "configuration controlled 3D designs of mechanical parts and assemblies",
+                "config_control_design",
+                1994,
+            ),
+            StepSchema::Ap214 => (
+                "core data for automotive mechanical design processes",
+                "automotive_design",
+                2000,
+            ),
+        };
+
+        let application = self.add("APPLICATION_CONTEXT", vec![Parameter::string(application)]);
+        self.add(
+            "APPLICATION_PROTOCOL_DEFINITION",
+            vec![
+                Parameter::string("international standard"),
+                Parameter::string(protocol),
+                Parameter::Integer(year),
+                Parameter::Reference(application),
+            ],
+        );
+        let product_context = self.add(
+            match schema {
+                StepSchema::Ap203 => "MECHANICAL_CONTEXT",
+                StepSchema::Ap214 => "PRODUCT_CONTEXT",
+            },
+            vec![
+                unnamed(),
+                Parameter::Reference(application),
+                Parameter::string("mechanical"),
+            ],
+        );
+        let product = self.add(
+            "PRODUCT",
+            vec![
+                Parameter::string("part"),
+                Parameter::string("part"),
+                unnamed(),
+                Parameter::references([product_context]),
+            ],
+        );
+        self.add(
+            "PRODUCT_RELATED_PRODUCT_CATEGORY",
+            vec![
+                Parameter::string("part"),
+                Parameter::Unset,
+                Parameter::references([product]),
+            ],
+        );
+        let formation = match schema {
+            StepSchema::Ap203 => self.add(
+                "PRODUCT_DEFINITION_FORMATION_WITH_SPECIFIED_SOURCE",
+                vec![
+                    unnamed(),
+                    unnamed(),
+                    Parameter::Reference(product),
+                    Parameter::enumeration("NOT_KNOWN"),
+                ],
+            ),
+            StepSchema::Ap214 => self.add(
+                "PRODUCT_DEFINITION_FORMATION",
+                vec![unnamed(), unnamed(), Parameter::Reference(product)],
+            ),
+        };
+        let definition_context = self.add(
+            "PRODUCT_DEFINITION_CONTEXT",
+            vec![
+                Parameter::string("part definition"),
+                Parameter::Reference(application),
+                Parameter::string("design"),
+            ],
+        );
+        let definition = self.add(
+            "PRODUCT_DEFINITION",
+            vec![
+                Parameter::string("design"),
+                unnamed(),
+                Parameter::Reference(formation),
+                Parameter::Reference(definition_context),
+            ],
+        );
+        let shape = self.add(
+            "PRODUCT_DEFINITION_SHAPE",
+            vec![unnamed(), unnamed(), Parameter::Reference(definition)],
+        );
+
+        let context = self.representation_context();
+        items.push(self.placement(DVec3::ZERO, DVec3::Z, DVec3::X));
+        let representation = self.add(
+            "ADVANCED_BREP_SHAPE_REPRESENTATION",
+            vec![
+                unnamed(),
+                Parameter::references(items),
+                Parameter::Reference(context),
+            ],
+        );
+        self.add(
+            "SHAPE_DEFINITION_REPRESENTATION",
+            vec![
+                Parameter::Reference(shape),
+                Parameter::Reference(representation),
+            ],
+        );
+    }
+
+    /// A 3D context in metres and radians with the current linear tolerance as its
+    /// uncertainty.
+    fn representation_context(&mut self) -> usize {
+        let unit = |records: &[(&str, Vec<Parameter>)]| {
+            records
+                .iter()
+                .map(|(name, parameters)| EntityRecord::new(name, parameters.clone()))
+                .collect()
+        };
+
+        let length = self.add_complex(unit(&[
+            ("LENGTH_UNIT", vec![]),
+            ("NAMED_UNIT", vec![Parameter::Derived]),
+            (
+                "SI_UNIT",
+                vec![Parameter::Unset, Parameter::enumeration("METRE")],
+            ),
+        ]));
+        let angle = self.add_complex(unit(&[
+            ("NAMED_UNIT", vec![Parameter::Derived]),
+            ("PLANE_ANGLE_UNIT", vec![]),
+            (
+                "SI_UNIT",
+                vec![Parameter::Unset, Parameter::enumeration("RADIAN")],
+            ),
+        ]));
+        let solid_angle = self.add_complex(unit(&[
+            ("NAMED_UNIT", vec![Parameter::Derived]),
+            (
+                "SI_UNIT",
+                vec![Parameter::Unset, Parameter::enumeration("STERADIAN")],
+            ),
+            ("SOLID_ANGLE_UNIT", vec![]),
+        ]));
+        let uncertainty = self.add(
+            "UNCERTAINTY_MEASURE_WITH_UNIT",
+            vec![
+                Parameter::Typed(
+                    "LENGTH_MEASURE".to_string(),
+                    Box::new(Parameter::Real(Tolerance::current().linear)),
+                ),
+                Parameter::Reference(length),
+                Parameter::string("distance_accuracy_value"),
+                Parameter::string("maximum distance between coincident points"),
+            ],
+        );
+
+        self.add_complex(unit(&[
+            (
+                "GEOMETRIC_REPRESENTATION_CONTEXT",
+                vec![Parameter::Integer(3)],
+            ),
+            (
+                "GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT",
+                vec![Parameter::references([uncertainty])],
+            ),
+            (
+                "GLOBAL_UNIT_ASSIGNED_CONTEXT",
+                vec![Parameter::references([length, angle, solid_angle])],
+            ),
+            (
+                "REPRESENTATION_CONTEXT",
+                vec![unnamed(), Parameter::string("3D")],
+            ),
+        ]))
+    }
+
+    /// A solid without voids is a manifold solid B-rep, one with voids a B-rep with voids.
+    /// A solid with at least one shell.
+    fn solid(&mut self, id: SolidId) -> usize {
+        let shells = &self.brep.solid(id).shells;
+        let outer = self.shell(shells[0], false);
+        if shells.len() == 1 {
+            return self.add(
+                "MANIFOLD_SOLID_BREP",
+                vec![unnamed(), Parameter::Reference(outer)],
+            );
+        }
+
+        // The void shells are written facing into the void and used reversed, so that after
+        // the reversal they face out of the material as in the B-rep
+        let voids: Vec<usize> = shells[1..]
+            .iter()
+            .map(|&s| {
+                let shell = self.shell(s, true);
+                self.add(
+                    "ORIENTED_CLOSED_SHELL",
+                    vec![
+                        unnamed(),
+                        Parameter::Derived,
+                        Parameter::Reference(shell),
+                        Parameter::boolean(false),
+                    ],
+                )
+            })
+            .collect();
+        self.add(
+            "BREP_WITH_VOIDS",
+            vec![
+                unnamed(),
+                Parameter::Reference(outer),
+                Parameter::references(voids),
+            ],
+        )
+    }
+
+    fn shell(&mut self, id: ShellId, flip: bool) -> usize {
+        let faces: Vec<usize> = self
+            .brep
+            .shell(id)
+            .faces
+            .iter()
+            .map(|&f| self.face(f, flip))
+            .collect();
+        self.add(
+            "CLOSED_SHELL",
+            vec![unnamed(), Parameter::references(faces)],
+        )
+    }
+
+    /// Flipping a face reverses its sense and uses its bounds backwards.
+    fn face(&mut self, id: FaceId, flip: bool) -> usize {
+        let brep = self.brep;
+        let face = brep.face(id);
+
+        let bounds: Vec<usize> = brep
+            .face_loops(id)
+            .into_iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let edge_loop = self.edge_loop(l);
+                self.add(
+                    if i == 0 {
+                        "FACE_OUTER_BOUND"
+                    } else {
+                        "FACE_BOUND"
+                    },
+                    vec![
+                        unnamed(),
+                        Parameter::Reference(edge_loop),
+                        Parameter::boolean(!flip),
+                    ],
+                )
+            })
+            .collect();
+        let surface = self.surface(id);
+
+        self.add(
+            "ADVANCED_FACE",
+            vec![
+                unnamed(),
+                Parameter::references(bounds),
+                Parameter::Reference(surface),
+                Parameter::boolean(face.reversed == flip),
+            ],
+        )
+    }
+
+    fn edge_loop(&mut self, id: LoopId) -> usize {
+        let brep = self.brep;
+        let edges: Vec<usize> = brep
+            .loop_coedges(id)
+            .into_iter()
+            .map(|c| {
+                let coedge = brep.coedge(c);
+                let edge = self.edge(coedge.edge);
+                self.add(
+                    "ORIENTED_EDGE",
+                    vec![
+                        unnamed(),
+                        Parameter::Derived,
+                        Parameter::Derived,
+                        Parameter::Reference(edge),
+                        Parameter::boolean(!coedge.reversed),
+                    ],
+                )
+            })
+            .collect();
+        self.add("EDGE_LOOP", vec![unnamed(), Parameter::references(edges)])
+    }
+
+    fn edge(&mut self, id: EdgeId) -> usize {
+        if let Some(&edge) = self.edges.get(&id) {
+            return edge;
+        }
+
+        let edge = self.brep.edge(id);
+        let start = self.vertex(edge.start);
+        let end = self.vertex(edge.end);
+        let curve = self.curve(&edge.curve);
+        let step_edge = self.add(
+            "EDGE_CURVE",
+            vec![
+                unnamed(),
+                Parameter::Reference(start),
+                Parameter::Reference(end),
+                Parameter::Reference(curve),
+                Parameter::boolean(true),
+            ],
+        );
+
+        self.edges.insert(id, step_edge);
+        step_edge
+    }
+
+    fn vertex(&mut self, id: VertexId) -> usize {
+        if let Some(&vertex) = self.vertices.get(&id) {
+            return vertex;
+        }
+
+        let point = self.point(self.brep.vertex(id).point.0);
+        let vertex = self.add("VERTEX_POINT", vec![unnamed(), Parameter::Reference(point)]);
+
+        self.vertices.insert(id, vertex);
+        vertex
+    }
+
+    fn curve(&mut self, curve: &Curve) -> usize {
+        match curve {
+            Curve::Line(line) => {
+                let point = self.point(line.p);
+                let direction = self.direction(line.v.normalize());
+                let vector = self.add(
+                    "VECTOR",
+                    vec![
+                        unnamed(),
+                        Parameter::Reference(direction),
+                        Parameter::Real(line.v.length()),
+                    ],
+                );
+                self.add(
+                    "LINE",
+                    vec![
+                        unnamed(),
+                        Parameter::Reference(point),
+                        Parameter::Reference(vector),
+                    ],
+                )
+            }
+            Curve::Circle(circle) => {
+                let placement = self.placement(circle.center, circle.normal, circle.x_axis);
+                self.add(
+                    "CIRCLE",
+                    vec![
+                        unnamed(),
+                        Parameter::Reference(placement),
+                        Parameter::Real(circle.radius),
+                    ],
+                )
+            }
+            Curve::Ellipse(ellipse) => {
+                let placement = self.placement(ellipse.center, ellipse.normal, ellipse.x_axis);
+                self.add(
+                    "ELLIPSE",
+                    vec![
+                        unnamed(),
+                        Parameter::Reference(placement),
+                        Parameter::Real(ellipse.major_radius),
+                        Parameter::Real(ellipse.minor_radius),
+                    ],
+                )
+            }
+            Curve::Nurbs(nurbs) => self.nurbs_curve(nurbs),
+        }
+    }
+
+    /// A B-spline with knots, combined with a rational B-spline when the weights differ.
+    fn nurbs_curve(&mut self, curve: &NurbsCurve) -> usize {
+        let points: Vec<usize> = curve
+            .control_points
+            .iter()
+            .map(|&p| self.point(p))
+            .collect();
+        let (multiplicities, knots) = distinct_knots(&curve.knots);
+
+        let spline = vec![
+            Parameter::Integer(curve.degree as i64),
+            Parameter::references(points),
+            Parameter::enumeration("UNSPECIFIED"),
+            Parameter::boolean(curve.is_closed()),
+            Parameter::boolean(false),
+        ];
+        let with_knots = vec![
+            Parameter::integers(multiplicities),
+            Parameter::reals(knots),
+            Parameter::enumeration("UNSPECIFIED"),
+        ];
+
+        if !curve.is_rational() {
+            let parameters = std::iter::once(unnamed())
+                .chain(spline)
+                .chain(with_knots)
+                .collect();
+            return self.add("B_SPLINE_CURVE_WITH_KNOTS", parameters);
+        }
+
+        self.add_complex(vec![
+            EntityRecord::new("BOUNDED_CURVE", vec![]),
+            EntityRecord::new("B_SPLINE_CURVE", spline),
+            EntityRecord::new("B_SPLINE_CURVE_WITH_KNOTS", with_knots),
+            EntityRecord::new("CURVE", vec![]),
+            EntityRecord::new("GEOMETRIC_REPRESENTATION_ITEM", vec![]),
+            EntityRecord::new(
+                "RATIONAL_B_SPLINE_CURVE",
+                vec![Parameter::reals(curve.weights.iter().copied())],
+            ),
+            EntityRecord::new("REPRESENTATION_ITEM", vec![unnamed()]),
+        ])
+    }
+
+    /// The surface of a face. Only cones and transformed surfaces need the face itself: STEP
+    /// cones have a single nappe, so the axis is turned towards the one the face lies on, and
+    /// transformed surfaces are written as NURBS surfaces covering the face.
+    fn surface(&mut self, id: FaceId) -> usize {
+        let brep = self.brep;
+        let surface = &brep.face(id).surface;
+
+        let (name, placement, parameters) = match surface {
+            Surface::Plane(plane) => ("PLANE", (plane.center, plane.normal, plane.x_axis), vec![]),
+            &Surface::Cylinder {
+                origin,
+                axis,
+                radius,
+            } => (
+                "CYLINDRICAL_SURFACE",
+                (origin, axis, Plane::new(axis, origin).x_axis),
+                vec![Parameter::Real(radius)],
+            ),
+            &Surface::Cone {
+                apex,
+                axis,
+                half_angle,
+            } => {
+                let height = brep
+                    .loop_coedges(brep.face(id).outer)
+                    .into_iter()
+                    .map(|c| (brep.vertex(brep.coedge_start(c)).point.0 - apex).dot(axis))
+                    .fold(0., |a: f64, h| if h.abs() > a.abs() { h } else { a });
+                let axis = if height < 0. { -axis } else { axis };
+                let location = apex + height.abs() * axis;
+
+                (
+                    "CONICAL_SURFACE",
+                    (location, axis, Plane::new(axis, location).x_axis),
+                    vec![
+                        Parameter::Real(height.abs() * half_angle.tan()),
+                        Parameter::Real(half_angle),
+                    ],
+                )
+            }
+            &Surface::Sphere { center, radius } => (
+                "SPHERICAL_SURFACE",
+                (center, DVec3::Z, DVec3::X),
+                vec![Parameter::Real(radius)],
+            ),
+            &Surface::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => (
+                "TOROIDAL_SURFACE",
+                (center, axis, Plane::new(axis, center).x_axis),
+                vec![Parameter::Real(major_radius), Parameter::Real(minor_radius)],
+            ),
+            Surface::Nurbs(nurbs) => return self.nurbs_surface(nurbs),
+            Surface::Transformed { surface: base, .. } => {
+                let range = match base.as_ref() {
+                    Surface::Sphere { .. } => (-FRAC_PI_2, FRAC_PI_2),
+                    Surface::Torus { .. } => (0., TAU),
+                    _ => self.face_range(id),
+                };
+                let nurbs = surface
+                    .to_nurbs(range)
+                    .expect("transformed surfaces turn around an axis");
+                return self.nurbs_surface(&nurbs);
+            }
+        };
+
+        let placement = self.placement(placement.0, placement.1, placement.2);
+        let parameters = [unnamed(), Parameter::Reference(placement)]
+            .into_iter()
+            .chain(parameters)
+            .collect();
+        self.add(name, parameters)
+    }
+
+    /// The range of v a face on a transformed cylinder or cone spans, with a margin.
+    fn face_range(&self, id: FaceId) -> (f64, f64) {
+        let brep = self.brep;
+        let surface = &brep.face(id).surface;
+        let tolerance = TesselationTolerance::default();
+        let (low, high) = brep
+            .face_edges(id)
+            .into_iter()
+            .flat_map(|e| brep.edge_points(e, &tolerance))
+            .map(|p| surface.parameters_of(p).1)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), v| {
+                (l.min(v), h.max(v))
+            });
+        let margin = (high - low) / 16. + tolerance.chord;
+        (low - margin, high + margin)
+    }
+
+    fn nurbs_surface(&mut self, surface: &NurbsSurface) -> usize {
+        let tolerance = Tolerance::current();
+        let rows = &surface.control_points;
+        let points = Parameter::List(
+            rows.iter()
+                .map(|row| Parameter::references(row.iter().map(|&p| self.point(p))))
+                .collect(),
+        );
+        let u_closed = rows[0]
+            .iter()
+            .zip(&rows[rows.len() - 1])
+            .all(|(&a, &b)| tolerance.coincident(a, b));
+        let v_closed = rows
+            .iter()
+            .all(|row| tolerance.coincident(row[0], row[row.len() - 1]));
+        let (u_multiplicities, u_knots) = distinct_knots(&surface.knots_u);
+        let (v_multiplicities, v_knots) = distinct_knots(&surface.knots_v);
+
+        let spline = vec![
+            Parameter::Integer(surface.degree_u as i64),
+            Parameter::Integer(surface.degree_v as i64),
+            points,
+            Parameter::enumeration("UNSPECIFIED"),
+            Parameter::boolean(u_closed),
+            Parameter::boolean(v_closed),
+            Parameter::boolean(false),
+        ];
+        let with_knots = vec![
+            Parameter::integers(u_multiplicities),
+            Parameter::integers(v_multiplicities),
+            Parameter::reals(u_knots),
+            Parameter::reals(v_knots),
+            Parameter::enumeration("UNSPECIFIED"),
+        ];
+
+        let weights = surface.weights.iter().flatten();
+        if weights.clone().all(|&w| w == surface.weights[0][0]) {
+            let parameters = std::iter::once(unnamed())
+                .chain(spline)
+                .chain(with_knots)
+                .collect();
+            return self.add("B_SPLINE_SURFACE_WITH_KNOTS", parameters);
+        }
+
+        let weights = surface
+            .weights
+            .iter()
+            .map(|row| Parameter::reals(row.iter().copied()))
+            .collect();
+        self.add_complex(vec![
+            EntityRecord::new("BOUNDED_SURFACE", vec![]),
+            EntityRecord::new("B_SPLINE_SURFACE", spline),
+            EntityRecord::new("B_SPLINE_SURFACE_WITH_KNOTS", with_knots),
+            EntityRecord::new("GEOMETRIC_REPRESENTATION_ITEM", vec![]),
+            EntityRecord::new("RATIONAL_B_SPLINE_SURFACE", vec![Parameter::List(weights)]),
+            EntityRecord::new("REPRESENTATION_ITEM", vec![unnamed()]),
+            EntityRecord::new("SURFACE", vec![]),
+        ])
+    }
+
+    fn placement(&mut self, origin: DVec3, axis: DVec3, reference: DVec3) -> usize {
+        let origin = self.point(origin);
+        let axis = self.direction(axis);
+        let reference = self.direction(reference);
+        self.add(
+            "AXIS2_PLACEMENT_3D",
+            vec![
+                unnamed(),
+                Parameter::Reference(origin),
+                Parameter::Reference(axis),
+                Parameter::Reference(reference),
+            ],
+        )
+    }
+
+    fn point(&mut self, p: DVec3) -> usize {
+        self.add(
+            "CARTESIAN_POINT",
+            vec![unnamed(), Parameter::reals(p.to_array())],
+        )
+    }
+
+    fn direction(&mut self, d: DVec3) -> usize {
+        self.add("DIRECTION", vec![unnamed(), Parameter::reals(d.to_array())])
+    }
+}
+
+/// Splits a knot vector into its distinct values and how often each repeats.
+fn distinct_knots(knots: &[f64]) -> (Vec<i64>, Vec<f64>) {
+    let tolerance = Tolerance::current();
+    let mut multiplicities = Vec::new();
+    let mut values: Vec<f64> = Vec::new();
+
+    for &knot in knots {
+        match values.last() {
+            Some(&last) if tolerance.same_parameter(last, knot) => {
+                *multiplicities.last_mut().unwrap() += 1;
+            }
+            _ => {
+                values.push(knot);
+                multiplicities.push(1);
+            }
+        }
+    }
+
+    (multiplicities, values)
+}
+
+/// The current UTC time as an ISO 8601 timestamp, as `FILE_NAME` expects.
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_date((seconds / 86400) as i64);
+    let time = seconds % 86400;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// The Gregorian year, month and day a number of days after 1970-01-01.
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    // Count from 0000-03-01 so that leap days fall at the end of the year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use glam::DVec3;
+
+    use crate::arc::Arc;
+    use crate::nurbs::{NurbsCurve, NurbsSurface};
+    use crate::step::part21::ExchangeFile;
+    use crate::test_util::{block, cuboid};
+    use crate::*;
+
+    use super::*;
+
+    /// Reads a written file back, checking that it is stable and that every reference
+    /// resolves, and counts its entities.
+    fn read_back(text: &str) -> (ExchangeFile, BTreeMap<String, usize>) {
+        let file = ExchangeFile::parse(text).unwrap();
+        assert_eq!(file.to_string(), text);
+
+        let mut counts = BTreeMap::new();
+        for record in file.data.values() {
+            for id in record.references() {
+                assert!(file.data.contains_key(&id), "#{id} is not defined");
+            }
+            for name in record.names() {
+                *counts.entry(name.to_string()).or_default() += 1;
+            }
+        }
+        (file, counts)
+    }
+
+    #[test]
+    fn test_export_cuboid() {
+        let (brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+        let (file, counts) = read_back(&brep.to_step(&[solid], StepSchema::Ap214).unwrap());
+
+        assert_eq!(
+            file.header[2].parameters[0],
+            Parameter::List(vec![Parameter::string(
+                "AUTOMOTIVE_DESIGN { 1 0 10303 214 1 1 1 1 }"
+            )])
+        );
+        for (name, count) in [
+            ("MANIFOLD_SOLID_BREP", 1),
+            ("CLOSED_SHELL", 1),
+            ("ADVANCED_FACE", 6),
+            ("PLANE", 6),
+            ("FACE_OUTER_BOUND", 6),
+            ("EDGE_LOOP", 6),
+            ("ORIENTED_EDGE", 24),
+            ("EDGE_CURVE", 12),
+            ("LINE", 12),
+            ("VERTEX_POINT", 8),
+            ("ADVANCED_BREP_SHAPE_REPRESENTATION", 1),
+            ("SHAPE_DEFINITION_REPRESENTATION", 1),
+        ] {
+            assert_eq!(counts.get(name), Some(&count), "{name}");
+        }
+
+        // Every edge is used once in each direction
+        let mut senses = HashMap::new();
+        for record in file.data.values() {
+            if let Some(edge) = record.entity("ORIENTED_EDGE") {
+                let id = edge.parameters[3].as_reference().unwrap();
+                let sense = edge.parameters[4].as_boolean().unwrap();
+                senses.entry(id).or_insert_with(Vec::new).push(sense);
+            }
+        }
+        assert!(senses.values().all(|s| s.len() == 2 && s[0] != s[1]));
+
+        let mut brep = brep;
+        let empty = brep.add_solid(vec![]);
+        assert_eq!(
+            brep.to_step(&[solid, empty], StepSchema::Ap214),
+            Err(BrepError::EmptySolid(empty))
+        );
+    }
+
+    #[test]
+    fn test_export_curved_faces() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let start = DVec3::new(1., 0., 0.);
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(DVec3::ZERO, DVec3::Z, start, start).unwrap(),
+        )));
+        let region = &sketch.find_regions()[0];
+
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(region, DVec3::Z, ExtrudeExtent::OneSided(2.))
+            .unwrap();
+        let (file, counts) = read_back(&brep.to_step(&[solid], StepSchema::Ap203).unwrap());
+
+        assert_eq!(
+            file.header[2].parameters[0],
+            Parameter::List(vec![Parameter::string("CONFIG_CONTROL_DESIGN")])
+        );
+        assert_eq!(counts.get("MECHANICAL_CONTEXT"), Some(&1));
+        assert_eq!(counts.get("CYLINDRICAL_SURFACE"), Some(&1));
+        assert_eq!(counts.get("PLANE"), Some(&2));
+        // The two rims and the seam joining them
+        assert_eq!(counts.get("EDGE_CURVE"), Some(&3));
+        assert_eq!(counts.get("CIRCLE"), Some(&2));
+
+        let cylinder = file
+            .data
+            .values()
+            .find_map(|r| r.entity("CYLINDRICAL_SURFACE"))
+            .unwrap();
+        assert_eq!(cylinder.parameters[2].as_real(), Some(1.));
+    }
+
+    #[test]
+    fn test_export_cone_nappe() {
+        let mut brep = Brep::new();
+        let bottom = brep.add_vertex(DVec3::new(2., 0., -2.));
+        let top = brep.add_vertex(DVec3::new(1., 0., -1.));
+        let circle = |z: f64| Curve::circle(DVec3::new(0., 0., z), DVec3::Z, -z).unwrap();
+        let lower = brep.add_edge(circle(-2.), bottom, bottom);
+        let upper = brep.add_edge(circle(-1.), top, top);
+        let seam = brep.add_line_edge(bottom, top);
+
+        // The lower nappe of a cone with its apex at the origin
+        let face = brep
+            .add_face(
+                Surface::cone(DVec3::ZERO, DVec3::Z, std::f64::consts::FRAC_PI_4),
+                false,
+                &[(lower, false), (seam, false), (upper, true), (seam, true)],
+                &[],
+            )
+            .unwrap();
+
+        let mut writer = StepWriter::new(&brep);
+        let cone = writer.surface(face);
+        let Record::Simple(cone) = &writer.file.data[&cone] else {
+            panic!("not a simple record");
+        };
+        let placement = writer.file.data[&cone.parameters[1].as_reference().unwrap()]
+            .entity("AXIS2_PLACEMENT_3D")
+            .unwrap();
+        let axis = writer.file.data[&placement.parameters[2].as_reference().unwrap()]
+            .entity("DIRECTION")
+            .unwrap();
+
+        assert_eq!(axis.parameters[1], Parameter::reals([0., 0., -1.]));
+        assert!((cone.parameters[2].as_real().unwrap() - 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_export_splines() {
+        let brep = Brep::new();
+        let mut writer = StepWriter::new(&brep);
+
+        let curve = NurbsCurve::non_rational(
+            2,
+            vec![DVec3::ZERO, DVec3::X, DVec3::ONE, DVec3::Z],
+            vec![0., 0., 0., 0.5, 1., 1., 1.],
+        )
+        .unwrap();
+        let id = writer.curve(&Curve::Nurbs(curve));
+        let record = writer.file.data[&id]
+            .entity("B_SPLINE_CURVE_WITH_KNOTS")
+            .unwrap();
+        assert_eq!(record.parameters[6], Parameter::integers([3, 1, 3]));
+        assert_eq!(record.parameters[7], Parameter::reals([0., 0.5, 1.]));
+
+        let circle = Curve::circle(DVec3::ZERO, DVec3::Z, 1.).unwrap();
+        let Curve::Circle(circle) = circle else {
+            unreachable!()
+        };
+        let id = writer.curve(&Curve::Nurbs(circle.to_nurbs()));
+        assert_eq!(
+            writer.file.data[&id].names(),
+            [
+                "BOUNDED_CURVE",
+                "B_SPLINE_CURVE",
+                "B_SPLINE_CURVE_WITH_KNOTS",
+                "CURVE",
+                "GEOMETRIC_REPRESENTATION_ITEM",
+                "RATIONAL_B_SPLINE_CURVE",
+                "REPRESENTATION_ITEM"
+            ]
+        );
+        let spline = writer.file.data[&id].entity("B_SPLINE_CURVE").unwrap();
+        assert_eq!(spline.parameters[3].as_boolean(), Some(true));
+
+        let surface = NurbsSurface::new(
+            (1, 1),
+            vec![vec![DVec3::ZERO, DVec3::Y], vec![DVec3::X, DVec3::ONE]],
+            vec![vec![1., 2.], vec![1., 1.]],
+            (vec![0., 0., 1., 1.], vec![0., 0., 1., 1.]),
+        )
+        .unwrap();
+        let id = writer.nurbs_surface(&surface);
+        let record = &writer.file.data[&id];
+        let weights = record.entity("RATIONAL_B_SPLINE_SURFACE").unwrap();
+        assert_eq!(
+            weights.parameters[0],
+            Parameter::List(vec![Parameter::reals([1., 2.]), Parameter::reals([1., 1.])])
+        );
+        let spline = record.entity("B_SPLINE_SURFACE").unwrap();
+        assert_eq!(spline.parameters[2].as_list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_export_void() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::splat(3.));
+        let b = block(&mut brep, DVec3::ONE, DVec3::splat(2.));
+        let result = brep.subtract(a, b).unwrap();
+
+        let (file, counts) = read_back(&brep.to_step(&result, StepSchema::Ap214).unwrap());
+        assert_eq!(counts.get("BREP_WITH_VOIDS"), Some(&1));
+        assert_eq!(counts.get("CLOSED_SHELL"), Some(&2));
+        assert_eq!(counts.get("ADVANCED_FACE"), Some(&12));
+
+        let void = file
+            .data
+            .values()
+            .find_map(|r| r.entity("ORIENTED_CLOSED_SHELL"))
+            .unwrap();
+        assert_eq!(void.parameters[3].as_boolean(), Some(false));
+    }
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_723), (2024, 1, 1));
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+    }
+}
@@ -0,0 +1,1085 @@
+use std::collections::{BTreeSet, HashMap};
+
+use glam::{DVec2, DVec3};
+
+use crate::circle::Circle;
+use crate::curve::Curve;
+use crate::ellipse::Ellipse;
+use crate::nurbs::{NurbsCurve, NurbsSurface};
+use crate::surface::Surface;
+use crate::{
+    Brep, Coedge, CoedgeId, Edge, EdgeId, Face, FaceId, Loop, LoopId, Plane, Shell, ShellId, Solid,
+    SolidId, VertexId,
+};
+
+use super::part21::{EntityRecord, ExchangeFile, Parameter, Record};
+use super::{StepError, StepWarning};
+
+/// The outcome of reading a STEP file: the solids added to the B-rep, and what had to be left
+/// out on the way.
+#[derive(Debug, Clone, Default)]
+pub struct StepImport {
+    pub solids: Vec<SolidId>,
+    pub warnings: Vec<StepWarning>,
+}
+
+type Read<T> = Result<T, StepWarning>;
+
+impl Brep {
+    /// Reads every manifold solid B-rep of a STEP file into the B-rep, converting lengths to
+    /// metres and angles to radians. Only a file that breaks the exchange structure fails as a
+    /// whole; a solid using entities the kernel cannot represent is skipped with a warning.
+    pub fn import_step(&mut self, text: &str) -> Result<StepImport, StepError> {
+        let file = ExchangeFile::parse(text)?;
+        let mut import = StepImport::default();
+
+        // The context, and so the units, of every item of a shape representation
+        let mut contexts = HashMap::new();
+        let mut unsupported = BTreeSet::new();
+        for record in file.data.values() {
+            let Record::Simple(record) = record else {
+                continue;
+            };
+            if !record.name.ends_with("SHAPE_REPRESENTATION") {
+                continue;
+            }
+            let (Some(items), Some(context)) = (
+                record.parameters.get(1).and_then(Parameter::as_list),
+                record.parameters.get(2).and_then(Parameter::as_reference),
+            ) else {
+                continue;
+            };
+
+            for item in items.iter().filter_map(Parameter::as_reference) {
+                contexts.insert(item, context);
+                match file.data.get(&item) {
+                    Some(record) if is_solid(record) => (),
+                    Some(record) if record.entity("AXIS2_PLACEMENT_3D").is_some() => (),
+                    Some(_) => {
+                        unsupported.insert(item);
+                    }
+                    None => import.warnings.push(StepWarning::UndefinedInstance(item)),
+                }
+            }
+        }
+        for id in unsupported {
+            import
+                .warnings
+                .push(unsupported_entity(id, &file.data[&id]));
+        }
+
+        for (&id, record) in &file.data {
+            if !is_solid(record) {
+                continue;
+            }
+
+            let mut reader = StepReader::new(&file);
+            let solid = contexts
+                .get(&id)
+                .map_or(Ok(()), |&context| reader.read_units(context))
+                .and_then(|()| reader.solid(id));
+
+            match solid {
+                Ok(()) => match reader.brep.validate() {
+                    Ok(()) => import.solids.extend(self.absorb(reader.brep)),
+                    Err(error) => import.warnings.push(StepWarning::InvalidSolid {
+                        instance: id,
+                        error,
+                    }),
+                },
+                Err(warning) => import.warnings.push(warning),
+            }
+        }
+
+        Ok(import)
+    }
+
+    /// Moves the entities of another B-rep into this one, returning the ids of its solids.
+    fn absorb(&mut self, other: Brep) -> Vec<SolidId> {
+        let vertices = self.vertices.len();
+        let edges = self.edges.len();
+        let coedges = self.coedges.len();
+        let loops = self.loops.len();
+        let faces = self.faces.len();
+        let shells = self.shells.len();
+        let solids = self.solids.len();
+
+        self.vertices.extend(other.vertices);
+        self.edges.extend(other.edges.into_iter().map(|e| Edge {
+            start: VertexId(e.start.0 + vertices),
+            end: VertexId(e.end.0 + vertices),
+            coedges: e.coedges.iter().map(|c| CoedgeId(c.0 + coedges)).collect(),
+            ..e
+        }));
+        self.coedges
+            .extend(other.coedges.into_iter().map(|c| Coedge {
+                edge: EdgeId(c.edge.0 + edges),
+                parent: LoopId(c.parent.0 + loops),
+                next: CoedgeId(c.next.0 + coedges),
+                previous: CoedgeId(c.previous.0 + coedges),
+                ..c
+            }));
+        self.loops.extend(other.loops.into_iter().map(|l| Loop {
+            face: FaceId(l.face.0 + faces),
+            first: CoedgeId(l.first.0 + coedges),
+        }));
+        self.faces.extend(other.faces.into_iter().map(|f| Face {
+            outer: LoopId(f.outer.0 + loops),
+            inner: f.inner.iter().map(|l| LoopId(l.0 + loops)).collect(),
+            shell: f.shell.map(|s| ShellId(s.0 + shells)),
+            ..f
+        }));
+        self.shells.extend(other.shells.into_iter().map(|s| Shell {
+            faces: s.faces.iter().map(|f| FaceId(f.0 + faces)).collect(),
+            solid: s.solid.map(|s| SolidId(s.0 + solids)),
+        }));
+        self.solids.extend(other.solids.into_iter().map(|s| Solid {
+            shells: s.shells.iter().map(|s| ShellId(s.0 + shells)).collect(),
+        }));
+
+        (solids..self.solids.len()).map(SolidId).collect()
+    }
+}
+
+fn is_solid(record: &Record) -> bool {
+    record.entity("MANIFOLD_SOLID_BREP").is_some() || record.entity("BREP_WITH_VOIDS").is_some()
+}
+
+fn unsupported_entity(id: usize, record: &Record) -> StepWarning {
+    StepWarning::UnsupportedEntity {
+        instance: id,
+        entity: record.names().join(" "),
+    }
+}
+
+/// The attributes of an instance, read with the instance id at hand for warnings.
+#[derive(Clone, Copy)]
+struct Attributes<'f> {
+    id: usize,
+    values: &'f [Parameter],
+}
+
+impl<'f> Attributes<'f> {
+    fn new(id: usize, record: &'f EntityRecord) -> Self {
+        Self {
+            id,
+            values: &record.parameters,
+        }
+    }
+
+    fn get(&self, i: usize) -> Read<&'f Parameter> {
+        self.values
+            .get(i)
+            .ok_or(StepWarning::MalformedInstance(self.id))
+    }
+
+    fn malformed<T>(&self, value: Option<T>) -> Read<T> {
+        value.ok_or(StepWarning::MalformedInstance(self.id))
+    }
+
+    /// A real, which may be wrapped in a measure type such as `LENGTH_MEASURE`.
+    fn real(&self, i: usize) -> Read<f64> {
+        self.malformed(match self.get(i)? {
+            Parameter::Typed(_, value) => value.as_real(),
+            value => value.as_real(),
+        })
+    }
+
+    fn integer(&self, i: usize) -> Read<i64> {
+        self.malformed(self.get(i)?.as_integer())
+    }
+
+    fn boolean(&self, i: usize) -> Read<bool> {
+        self.malformed(self.get(i)?.as_boolean())
+    }
+
+    fn reference(&self, i: usize) -> Read<usize> {
+        self.malformed(self.get(i)?.as_reference())
+    }
+
+    /// A reference that may be left unset.
+    fn optional_reference(&self, i: usize) -> Read<Option<usize>> {
+        match self.get(i)? {
+            Parameter::Unset => Ok(None),
+            value => self.malformed(value.as_reference()).map(Some),
+        }
+    }
+
+    fn list(&self, i: usize) -> Read<&'f [Parameter]> {
+        self.malformed(self.get(i)?.as_list())
+    }
+
+    fn references(&self, i: usize) -> Read<Vec<usize>> {
+        self.list(i)?
+            .iter()
+            .map(|p| self.malformed(p.as_reference()))
+            .collect()
+    }
+
+    fn reals(&self, i: usize) -> Read<Vec<f64>> {
+        self.list(i)?
+            .iter()
+            .map(|p| self.malformed(p.as_real()))
+            .collect()
+    }
+
+    fn integers(&self, i: usize) -> Read<Vec<i64>> {
+        self.list(i)?
+            .iter()
+            .map(|p| self.malformed(p.as_integer()))
+            .collect()
+    }
+
+    /// The attributes from `i` on, as for the supertype part of a simple instance.
+    fn from(&self, i: usize) -> Read<Self> {
+        Ok(Self {
+            id: self.id,
+            values: self.malformed(self.values.get(i..))?,
+        })
+    }
+}
+
+/// Reads one solid into a B-rep of its own, so that a solid that turns out to be unreadable or
+/// invalid leaves nothing behind.
+struct StepReader<'f> {
+    file: &'f ExchangeFile,
+    /// Metres per length unit of the file.
+    length: f64,
+    /// Radians per angle unit of the file.
+    angle: f64,
+    brep: Brep,
+    vertices: HashMap<usize, VertexId>,
+    /// The kernel edge of every edge curve, and whether it runs against it.
+    edges: HashMap<usize, (EdgeId, bool)>,
+}
+
+impl<'f> StepReader<'f> {
+    fn new(file: &'f ExchangeFile) -> Self {
+        Self {
+            file,
+            length: 1.,
+            angle: 1.,
+            brep: Brep::new(),
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+        }
+    }
+
+    fn record(&self, id: usize) -> Read<&'f Record> {
+        self.file
+            .data
+            .get(&id)
+            .ok_or(StepWarning::UndefinedInstance(id))
+    }
+
+    /// The attributes of an instance of `name`, or of the first of `names` it is an instance
+    /// of.
+    fn entity(&self, id: usize, names: &[&str]) -> Read<Attributes<'f>> {
+        let record = self.record(id)?;
+        names
+            .iter()
+            .find_map(|name| record.entity(name))
+            .map(|r| Attributes::new(id, r))
+            .ok_or_else(|| unsupported_entity(id, record))
+    }
+
+    /// Takes the length and angle units from a representation context.
+    fn read_units(&mut self, context: usize) -> Read<()> {
+        let record = self.record(context)?;
+        let Some(units) = record.entity("GLOBAL_UNIT_ASSIGNED_CONTEXT") else {
+            return Ok(());
+        };
+
+        for unit in Attributes::new(context, units).references(0)? {
+            let record = self.record(unit)?;
+            if record.entity("LENGTH_UNIT").is_some() {
+                self.length = self.unit_factor(unit)?;
+            } else if record.entity("PLANE_ANGLE_UNIT").is_some() {
+                self.angle = self.unit_factor(unit)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The size of a unit in metres or radians, following conversion based units such as
+    /// inches or degrees to the SI unit they are defined by.
+    fn unit_factor(&self, id: usize) -> Read<f64> {
+        let mut id = id;
+        let mut factor = 1.;
+        let mut converted = BTreeSet::new();
+
+        loop {
+            let record = self.record(id)?;
+
+            if let Some(si) = record.entity("SI_UNIT") {
+                let prefix = match Attributes::new(id, si).get(0)? {
+                    Parameter::Unset => return Ok(factor),
+                    prefix => prefix.as_enumeration(),
+                };
+                let exponent = match prefix.unwrap_or_default() {
+                    "EXA" => 18,
+                    "PETA" => 15,
+                    "TERA" => 12,
+                    "GIGA" => 9,
+                    "MEGA" => 6,
+                    "KILO" => 3,
+                    "HECTO" => 2,
+                    "DECA" => 1,
+                    "DECI" => -1,
+                    "CENTI" => -2,
+                    "MILLI" => -3,
+                    "MICRO" => -6,
+                    "NANO" => -9,
+                    "PICO" => -12,
+                    "FEMTO" => -15,
+                    "ATTO" => -18,
+                    _ => return Err(StepWarning::MalformedInstance(id)),
+                };
+                return Ok(factor * 10f64.powi(exponent));
+            }
+
+            let Some(conversion) = record.entity("CONVERSION_BASED_UNIT") else {
+                return Err(unsupported_entity(id, record));
+            };
+            // A unit defined in terms of itself has no size
+            if !converted.insert(id) {
+                return Err(StepWarning::MalformedInstance(id));
+            }
+            let measure = Attributes::new(id, conversion).reference(1)?;
+            let measure = match self.record(measure)? {
+                Record::Simple(record) => Attributes::new(measure, record),
+                _ => self.entity(measure, &["MEASURE_WITH_UNIT"])?,
+            };
+            factor *= measure.real(0)?;
+            id = measure.reference(1)?;
+        }
+    }
+
+    fn solid(&mut self, id: usize) -> Read<()> {
+        let solid = self.entity(id, &["MANIFOLD_SOLID_BREP", "BREP_WITH_VOIDS"])?;
+
+        let mut shells = vec![self.shell(solid.reference(1)?, false)?];
+        if self.record(id)?.entity("BREP_WITH_VOIDS").is_some() {
+            for void in solid.references(2)? {
+                shells.push(self.shell(void, false)?);
+            }
+        }
+
+        self.brep.add_solid(shells);
+        Ok(())
+    }
+
+    /// A closed shell, with every face flipped if `flip` is set. Oriented shells flip their
+    /// underlying shell when used against it, as the voids of a solid are.
+    fn shell(&mut self, id: usize, flip: bool) -> Read<ShellId> {
+        let (mut id, mut flip) = (id, flip);
+        let mut oriented = BTreeSet::new();
+        let shell = loop {
+            let shell = self.entity(id, &["CLOSED_SHELL", "ORIENTED_CLOSED_SHELL"])?;
+            if self.record(id)?.entity("ORIENTED_CLOSED_SHELL").is_none() {
+                break shell;
+            }
+            // An oriented shell that ends up orienting itself has no faces
+            if !oriented.insert(id) {
+                return Err(StepWarning::MalformedInstance(id));
+            }
+            flip = flip != !shell.boolean(3)?;
+            id = shell.reference(2)?;
+        };
+
+        let faces = shell
+            .references(1)?
+            .into_iter()
+            .map(|f| self.face(f, flip))
+            .collect::<Read<Vec<_>>>()?;
+        Ok(self.brep.add_shell(faces))
+    }
+
+    fn face(&mut self, id: usize, flip: bool) -> Read<FaceId> {
+        let face = self.entity(id, &["ADVANCED_FACE", "FACE_SURFACE"])?;
+        let surface = self.surface(face.reference(2)?)?;
+        let mut reversed = face.boolean(3)? == flip;
+
+        let mut loops = Vec::new();
+        let mut outer = None;
+        for bound in face.references(1)? {
+            let attributes = self.entity(bound, &["FACE_OUTER_BOUND", "FACE_BOUND"])?;
+            if self.record(bound)?.entity("FACE_OUTER_BOUND").is_some() {
+                outer = Some(loops.len());
+            }
+            let reverse = attributes.boolean(2)? == flip;
+            loops.push(self.edge_loop(attributes.reference(1)?, reverse)?);
+        }
+        if loops.is_empty() {
+            return Err(StepWarning::MalformedInstance(id));
+        }
+
+        // Without a marked outer bound, the outer loop of a planar face is the one enclosing
+        // the largest area
+        let outer = match (outer, &surface) {
+            (Some(outer), _) => outer,
+            (None, Surface::Plane(plane)) => {
+                let area = |edges: &[(EdgeId, bool)]| {
+                    let points: Vec<DVec2> = edges
+                        .iter()
+                        .map(|&(e, reversed)| plane.to_local(self.edge_start(e, reversed)))
+                        .collect();
+                    (0..points.len())
+                        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+                        .sum::<f64>()
+                        .abs()
+                };
+                (0..loops.len())
+                    .max_by(|&a, &b| area(&loops[a]).total_cmp(&area(&loops[b])))
+                    .unwrap_or(0)
+            }
+            (None, _) => 0,
+        };
+        let outer_loop = loops.remove(outer);
+
+        // The kernel's cones are double, with their normal away from the axis on both nappes,
+        // while a STEP cone's normal points towards the axis beyond its apex
+        if let Surface::Cone { apex, axis, .. } = surface {
+            let height = outer_loop
+                .iter()
+                .map(|&(e, _)| (self.brep.vertex(self.brep.edge(e).start).point.0 - apex).dot(axis))
+                .fold(0., |a: f64, h| if h.abs() > a.abs() { h } else { a });
+            if height < 0. {
+                reversed = !reversed;
+            }
+        }
+
+        self.brep
+            .add_face(surface, reversed, &outer_loop, &loops)
+            .map_err(|_| StepWarning::MalformedInstance(id))
+    }
+
+    /// Where an edge starts when run in the given direction.
+    fn edge_start(&self, id: EdgeId, reversed: bool) -> DVec3 {
+        let edge = self.brep.edge(id);
+        self.brep
+            .vertex(if reversed { edge.end } else { edge.start })
+            .point
+            .0
+    }
+
+    /// The edges of a loop, each paired with whether the loop runs against it, reversed as a
+    /// whole if `reverse` is set.
+    fn edge_loop(&mut self, id: usize, reverse: bool) -> Read<Vec<(EdgeId, bool)>> {
+        let edge_loop = self.entity(id, &["EDGE_LOOP"])?;
+
+        let mut edges = edge_loop
+            .references(1)?
+            .into_iter()
+            .map(|o| {
+                let oriented = self.entity(o, &["ORIENTED_EDGE"])?;
+                let (edge, against) = self.edge(oriented.reference(3)?)?;
+                Ok((edge, (!oriented.boolean(4)? != against) != reverse))
+            })
+            .collect::<Read<Vec<_>>>()?;
+
+        if reverse {
+            edges.reverse();
+        }
+        Ok(edges)
+    }
+
+    /// An edge curve as a kernel edge, which always runs along its curve, and whether it runs
+    /// against the edge curve to do so. Lines are remade through the edge's vertices.
+    fn edge(&mut self, id: usize) -> Read<(EdgeId, bool)> {
+        if let Some(&edge) = self.edges.get(&id) {
+            return Ok(edge);
+        }
+
+        let edge = self.entity(id, &["EDGE_CURVE"])?;
+        let start = self.vertex(edge.reference(1)?)?;
+        let end = self.vertex(edge.reference(2)?)?;
+        let same_sense = edge.boolean(4)?;
+
+        let kernel_edge = match self.curve(edge.reference(3)?)? {
+            Curve::Line(_) if start == end => return Err(StepWarning::MalformedInstance(id)),
+            Curve::Line(_) => (self.brep.add_line_edge(start, end), false),
+            curve if same_sense => (self.brep.add_edge(curve, start, end), false),
+            curve => (self.brep.add_edge(curve, end, start), true),
+        };
+
+        self.edges.insert(id, kernel_edge);
+        Ok(kernel_edge)
+    }
+
+    fn vertex(&mut self, id: usize) -> Read<VertexId> {
+        if let Some(&vertex) = self.vertices.get(&id) {
+            return Ok(vertex);
+        }
+
+        let point = self.point(self.entity(id, &["VERTEX_POINT"])?.reference(1)?)?;
+        let vertex = self.brep.add_vertex(point);
+
+        self.vertices.insert(id, vertex);
+        Ok(vertex)
+    }
+
+    fn curve(&self, id: usize) -> Read<Curve> {
+        let mut id = id;
+        let mut based = BTreeSet::new();
+
+        loop {
+            let record = self.record(id)?;
+            if record.entity("B_SPLINE_CURVE_WITH_KNOTS").is_some() {
+                return self.nurbs_curve(id).map(Curve::Nurbs);
+            }
+            let Record::Simple(entity) = record else {
+                return Err(unsupported_entity(id, record));
+            };
+            let curve = Attributes::new(id, entity);
+
+            return match entity.name.as_str() {
+                "LINE" => {
+                    let point = self.point(curve.reference(1)?)?;
+                    let vector = self.entity(curve.reference(2)?, &["VECTOR"])?;
+                    let direction = self.direction(vector.reference(1)?)?;
+                    Ok(Curve::line(point, point + direction))
+                }
+                "CIRCLE" => {
+                    let (center, normal, x_axis) = self.placement(curve.reference(1)?)?;
+                    let radius = curve.real(2)? * self.length;
+                    Circle::with_axis(center, normal, x_axis, radius)
+                        .map(Curve::Circle)
+                        .map_err(|_| StepWarning::MalformedInstance(id))
+                }
+                "ELLIPSE" => {
+                    let (center, normal, x_axis) = self.placement(curve.reference(1)?)?;
+                    Ellipse::new(
+                        center,
+                        normal,
+                        x_axis,
+                        curve.real(2)? * self.length,
+                        curve.real(3)? * self.length,
+                    )
+                    .map(Curve::Ellipse)
+                    .map_err(|_| StepWarning::MalformedInstance(id))
+                }
+                // Curves on surfaces carry their 3D curve first, and a trimmed curve is trimmed
+                // again by the vertices of its edge. A curve based on itself has no geometry.
+                "SURFACE_CURVE" | "SEAM_CURVE" | "INTERSECTION_CURVE" | "TRIMMED_CURVE" => {
+                    if !based.insert(id) {
+                        return Err(StepWarning::MalformedInstance(id));
+                    }
+                    id = curve.reference(1)?;
+                    continue;
+                }
+                _ => Err(unsupported_entity(id, record)),
+            };
+        }
+    }
+
+    /// A B-spline curve, which in a simple instance carries the B-spline attributes after its
+    /// name and in a complex one, together with rational weights, in separate parts.
+    fn nurbs_curve(&self, id: usize) -> Read<NurbsCurve> {
+        let record = self.record(id)?;
+        let (spline, knots, weights) = match record {
+            Record::Simple(entity) => {
+                let attributes = Attributes::new(id, entity);
+                (attributes.from(1)?, attributes.from(6)?, None)
+            }
+            Record::Complex(_) => (
+                self.entity(id, &["B_SPLINE_CURVE"])?,
+                self.entity(id, &["B_SPLINE_CURVE_WITH_KNOTS"])?,
+                record
+                    .entity("RATIONAL_B_SPLINE_CURVE")
+                    .map(|r| Attributes::new(id, r).reals(0))
+                    .transpose()?,
+            ),
+        };
+
+        let degree = spline.integer(0)?;
+        let points = spline
+            .references(1)?
+            .into_iter()
+            .map(|p| self.point(p))
+            .collect::<Read<Vec<_>>>()?;
+        let knots = expand_knots(knots.integers(0)?, knots.reals(1)?);
+        let weights = weights.unwrap_or_else(|| vec![1.; points.len()]);
+
+        NurbsCurve::new(degree.max(0) as usize, points, weights, knots)
+            .map_err(|_| StepWarning::MalformedInstance(id))
+    }
+
+    fn surface(&self, id: usize) -> Read<Surface> {
+        let record = self.record(id)?;
+        if record.entity("B_SPLINE_SURFACE_WITH_KNOTS").is_some() {
+            return self.nurbs_surface(id).map(Surface::Nurbs);
+        }
+        let Record::Simple(entity) = record else {
+            return Err(unsupported_entity(id, record));
+        };
+        let surface = Attributes::new(id, entity);
+
+        let (origin, axis, x_axis) = match entity.name.as_str() {
+            "PLANE"
+            | "CYLINDRICAL_SURFACE"
+            | "CONICAL_SURFACE"
+            | "SPHERICAL_SURFACE"
+            | "TOROIDAL_SURFACE" => self.placement(surface.reference(1)?)?,
+            _ => return Err(unsupported_entity(id, record)),
+        };
+
+        Ok(match entity.name.as_str() {
+            "PLANE" => Surface::Plane(Plane::from_axes(origin, x_axis, axis.cross(x_axis))),
+            "CYLINDRICAL_SURFACE" => {
+                Surface::cylinder(origin, axis, surface.real(2)? * self.length)
+            }
+            "CONICAL_SURFACE" => {
+                // The radius is given at the placement, from which the apex lies back along the
+                // axis
+                let radius = surface.real(2)? * self.length;
+                let half_angle = surface.real(3)? * self.angle;
+                if half_angle <= 0. || half_angle >= std::f64::consts::FRAC_PI_2 {
+                    return Err(StepWarning::MalformedInstance(id));
+                }
+                Surface::cone(origin - radius / half_angle.tan() * axis, axis, half_angle)
+            }
+            "SPHERICAL_SURFACE" => Surface::Sphere {
+                center: origin,
+                radius: surface.real(2)? * self.length,
+            },
+            _ => Surface::torus(
+                origin,
+                axis,
+                surface.real(2)? * self.length,
+                surface.real(3)? * self.length,
+            ),
+        })
+    }
+
+    fn nurbs_surface(&self, id: usize) -> Read<NurbsSurface> {
+        let record = self.record(id)?;
+        let (spline, knots, weights) = match record {
+            Record::Simple(entity) => {
+                let attributes = Attributes::new(id, entity);
+                (attributes.from(1)?, attributes.from(8)?, None)
+            }
+            Record::Complex(_) => (
+                self.entity(id, &["B_SPLINE_SURFACE"])?,
+                self.entity(id, &["B_SPLINE_SURFACE_WITH_KNOTS"])?,
+                record
+                    .entity("RATIONAL_B_SPLINE_SURFACE")
+                    .map(|r| {
+                        let weights = Attributes::new(id, r);
+                        weights
+                            .list(0)?
+                            .iter()
+                            .map(|row| {
+                                let row = weights.malformed(row.as_list())?;
+                                row.iter().map(|w| weights.malformed(w.as_real())).collect()
+                            })
+                            .collect::<Read<Vec<Vec<f64>>>>()
+                    })
+                    .transpose()?,
+            ),
+        };
+
+        let degrees = (spline.integer(0)?, spline.integer(1)?);
+        let points = spline
+            .list(2)?
+            .iter()
+            .map(|row| {
+                spline
+                    .malformed(row.as_list())?
+                    .iter()
+                    .map(|p| self.point(spline.malformed(p.as_reference())?))
+                    .collect()
+            })
+            .collect::<Read<Vec<Vec<DVec3>>>>()?;
+        let knots_u = expand_knots(knots.integers(0)?, knots.reals(2)?);
+        let knots_v = expand_knots(knots.integers(1)?, knots.reals(3)?);
+        let weights =
+            weights.unwrap_or_else(|| points.iter().map(|row| vec![1.; row.len()]).collect());
+
+        NurbsSurface::new(
+            (degrees.0.max(0) as usize, degrees.1.max(0) as usize),
+            points,
+            weights,
+            (knots_u, knots_v),
+        )
+        .map_err(|_| StepWarning::MalformedInstance(id))
+    }
+
+    /// The origin, axis and reference direction of a placement. The reference direction is
+    /// made perpendicular to the axis, and both default as in ISO 10303-42.
+    fn placement(&self, id: usize) -> Read<(DVec3, DVec3, DVec3)> {
+        let placement = self.entity(id, &["AXIS2_PLACEMENT_3D"])?;
+        let origin = self.point(placement.reference(1)?)?;
+        let axis = match placement.optional_reference(2)? {
+            Some(axis) => self.direction(axis)?,
+            None => DVec3::Z,
+        };
+        let x_axis = match placement.optional_reference(3)? {
+            Some(x_axis) => self.direction(x_axis)?,
+            None => Plane::new(axis, origin).x_axis,
+        };
+
+        let x_axis = x_axis - x_axis.dot(axis) * axis;
+        if x_axis.length_squared() == 0. {
+            return Err(StepWarning::MalformedInstance(id));
+        }
+        Ok((origin, axis, x_axis.normalize()))
+    }
+
+    fn point(&self, id: usize) -> Read<DVec3> {
+        let point = self.entity(id, &["CARTESIAN_POINT"])?;
+        match point.reals(1)?[..] {
+            [x, y, z] => Ok(DVec3::new(x, y, z) * self.length),
+            _ => Err(StepWarning::MalformedInstance(id)),
+        }
+    }
+
+    fn direction(&self, id: usize) -> Read<DVec3> {
+        let direction = self.entity(id, &["DIRECTION"])?;
+        match direction.reals(1)?[..] {
+            [x, y, z] if DVec3::new(x, y, z) != DVec3::ZERO => Ok(DVec3::new(x, y, z).normalize()),
+            _ => Err(StepWarning::MalformedInstance(id)),
+        }
+    }
+}
+
+/// Repeats every distinct knot as often as its multiplicity says.
+fn expand_knots(multiplicities: Vec<i64>, knots: Vec<f64>) -> Vec<f64> {
+    knots
+        .into_iter()
+        .zip(multiplicities)
+        .flat_map(|(k, m)| std::iter::repeat_n(k, m.max(0) as usize))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, TAU};
+
+    use glam::DVec3;
+
+    use crate::arc::Arc;
+    use crate::line::{Line, ParametricLine, TwoPointLine};
+    use crate::test_util::{block, cuboid};
+    use crate::*;
+
+    use super::*;
+
+    fn kind(surface: &Surface) -> &'static str {
+        match surface {
+            Surface::Plane(_) => "plane",
+            Surface::Cylinder { .. } => "cylinder",
+            Surface::Cone { .. } => "cone",
+            Surface::Sphere { .. } => "sphere",
+            Surface::Torus { .. } => "torus",
+            Surface::Nurbs(_) => "nurbs",
+            Surface::Transformed { .. } => "transformed",
+        }
+    }
+
+    /// Writes solids and reads them back into a new B-rep, checking that faces keep their
+    /// surfaces, orientation and loops and that vertices keep their positions.
+    fn round_trip(brep: &Brep, solids: &[SolidId]) -> (Brep, Vec<SolidId>) {
+        let text = brep.to_step(solids, StepSchema::Ap214).unwrap();
+        let mut imported = Brep::new();
+        let import = imported.import_step(&text).unwrap();
+
+        assert_eq!(import.warnings, []);
+        assert_eq!(import.solids.len(), solids.len());
+        assert_eq!(imported.validate(), Ok(()));
+
+        for (&a, &b) in solids.iter().zip(&import.solids) {
+            assert_eq!(brep.genus(a), imported.genus(b));
+            assert_eq!(brep.solid(a).shells.len(), imported.solid(b).shells.len());
+
+            for (fa, fb) in brep.solid_faces(a).into_iter().zip(imported.solid_faces(b)) {
+                let (fa, fb) = (brep.face(fa), imported.face(fb));
+                assert_eq!(kind(&fa.surface), kind(&fb.surface));
+                assert_eq!(fa.reversed, fb.reversed);
+                assert_eq!(fa.inner.len(), fb.inner.len());
+            }
+
+            let vertices = imported.solid_vertices(b);
+            assert_eq!(brep.solid_vertices(a).len(), vertices.len());
+            for v in brep.solid_vertices(a) {
+                let p = brep.vertex(v).point.0;
+                assert!(vertices
+                    .iter()
+                    .any(|&w| imported.vertex(w).point.0.abs_diff_eq(p, 1e-12)));
+            }
+        }
+
+        (imported, import.solids)
+    }
+
+    #[test]
+    fn test_round_trip_planar() {
+        let (brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+        round_trip(&brep, &[solid]);
+
+        // Two solids, one of them with a void
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::splat(3.));
+        let b = block(&mut brep, DVec3::ONE, DVec3::splat(2.));
+        let c = block(&mut brep, DVec3::splat(5.), DVec3::splat(6.));
+        let mut solids = brep.subtract(a, b).unwrap();
+        solids.push(c);
+
+        let (imported, solids) = round_trip(&brep, &solids);
+        assert_eq!(imported.solid(solids[0]).shells.len(), 2);
+    }
+
+    #[test]
+    fn test_round_trip_curved() {
+        let arc = |center: DVec3, start: DVec3, end: DVec3| {
+            SketchElement::Arc(SketchArc(Arc::new(center, DVec3::Z, start, end).unwrap()))
+        };
+        let line = |a: DVec3, b: DVec3| {
+            SketchElement::Line(SketchLine(Line::TwoPoint(TwoPointLine::new(a, b))))
+        };
+        let y_axis = Line::Parametric(ParametricLine::new(DVec3::ZERO, DVec3::Y));
+        let mut brep = Brep::new();
+
+        // A plate with a bore
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let corners = [(0., 0.), (4., 0.), (4., 2.), (0., 2.)].map(|(x, y)| DVec3::new(x, y, 0.));
+        for i in 0..4 {
+            sketch.add_element(line(corners[i], corners[(i + 1) % 4]));
+        }
+        let start = DVec3::new(1.5, 1., 0.);
+        sketch.add_element(arc(DVec3::new(1., 1., 0.), start, start));
+        let region = sketch
+            .find_regions()
+            .into_iter()
+            .find(|r| !r.holes.is_empty())
+            .unwrap();
+        let plate = brep
+            .extrude(&region, DVec3::Z, ExtrudeExtent::OneSided(1.))
+            .unwrap();
+
+        // A cylinder with a conical tip, a sphere and a torus
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let points = [(0., 0.), (1., 0.), (1., 2.), (0., 3.)].map(|(x, y)| DVec3::new(x, y, 0.));
+        for i in 0..4 {
+            sketch.add_element(line(points[i], points[(i + 1) % 4]));
+        }
+        let region = &sketch.find_regions()[0];
+        let cone = brep.revolve(region, &y_axis, TAU).unwrap();
+
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(arc(DVec3::ZERO, -DVec3::Y, DVec3::Y));
+        sketch.add_element(line(DVec3::Y, -DVec3::Y));
+        let region = &sketch.find_regions()[0];
+        let sphere = brep.revolve(region, &y_axis, TAU).unwrap();
+
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let start = DVec3::new(4., 0., 0.);
+        sketch.add_element(arc(DVec3::new(3., 0., 0.), start, start));
+        let region = &sketch.find_regions()[0];
+        let torus = brep.revolve(region, &y_axis, FRAC_PI_2).unwrap();
+
+        let (imported, solids) = round_trip(&brep, &[plate, cone, sphere, torus]);
+
+        let Surface::Cone {
+            apex,
+            axis,
+            half_angle,
+        } = imported
+            .solid_faces(solids[1])
+            .into_iter()
+            .find_map(|f| match imported.face(f).surface {
+                Surface::Cone { .. } => Some(imported.face(f).surface.clone()),
+                _ => None,
+            })
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        assert!(apex.abs_diff_eq(DVec3::new(0., 3., 0.), 1e-12));
+        assert!(axis.cross(DVec3::Y).length() < 1e-12);
+        assert!((half_angle - std::f64::consts::FRAC_PI_4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_units() {
+        let (brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+        let text = brep.to_step(&[solid], StepSchema::Ap203).unwrap();
+
+        let millimetres = text.replace("SI_UNIT($,.METRE.)", "SI_UNIT(.MILLI.,.METRE.)");
+        let mut imported = Brep::new();
+        let import = imported.import_step(&millimetres).unwrap();
+        let far = imported
+            .vertices
+            .iter()
+            .map(|v| v.point.0)
+            .fold(DVec3::ZERO, DVec3::max);
+        assert!(far.abs_diff_eq(DVec3::new(1e-3, 2e-3, 3e-3), 1e-15));
+        assert_eq!(import.warnings, []);
+
+        // An inch defined through millimetres
+        let (length, _) = text.split_once("=(LENGTH_UNIT()").unwrap();
+        let length: usize = length.rsplit('#').next().unwrap().parse().unwrap();
+        let inches = text.replace(
+            &format!("#{length}=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT($,.METRE.));"),
+            &format!(
+                "#{length}=(CONVERSION_BASED_UNIT('INCH',#9001)LENGTH_UNIT()NAMED_UNIT(*));
+#9000=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.));
+#9001=LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(25.4),#9000);"
+            ),
+        );
+        let mut imported = Brep::new();
+        imported.import_step(&inches).unwrap();
+        let far = imported
+            .vertices
+            .iter()
+            .map(|v| v.point.0)
+            .fold(DVec3::ZERO, DVec3::max);
+        assert!(far.abs_diff_eq(DVec3::new(1., 2., 3.) * 0.0254, 1e-12));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut brep = Brep::new();
+        let a = block(&mut brep, DVec3::ZERO, DVec3::ONE);
+        let b = block(&mut brep, DVec3::splat(2.), DVec3::splat(3.));
+        let text = brep.to_step(&[a, b], StepSchema::Ap214).unwrap();
+
+        // A surface the kernel does not have spoils only the solid using it
+        let (before, after) = text.split_once("=PLANE(").unwrap();
+        let plane: usize = before.rsplit('#').next().unwrap().parse().unwrap();
+        let unsupported = format!("{before}=SURFACE_OF_REVOLUTION({after}");
+        let mut imported = Brep::new();
+        let import = imported.import_step(&unsupported).unwrap();
+        assert_eq!(import.solids.len(), 1);
+        assert_eq!(
+            import.warnings,
+            [StepWarning::UnsupportedEntity {
+                instance: plane,
+                entity: "SURFACE_OF_REVOLUTION".to_string()
+            }]
+        );
+        assert_eq!(imported.validate(), Ok(()));
+        assert_eq!(imported.faces.len(), 6);
+
+        // A dangling reference and a missing face
+        let (before, after) = text.split_once("CLOSED_SHELL('',(").unwrap();
+        let (_, after) = after.split_once(',').unwrap();
+        let dangling = format!("{before}CLOSED_SHELL('',(#99999,{after}");
+        let open = format!("{before}CLOSED_SHELL('',({after}");
+
+        let import = Brep::new().import_step(&dangling).unwrap();
+        assert_eq!(import.solids.len(), 1);
+        assert_eq!(import.warnings, [StepWarning::UndefinedInstance(99999)]);
+
+        let import = Brep::new().import_step(&open).unwrap();
+        assert_eq!(import.solids.len(), 1);
+        assert!(matches!(
+            import.warnings[..],
+            [StepWarning::InvalidSolid {
+                error: BrepError::OpenShell(_),
+                ..
+            }]
+        ));
+
+        // Unreadable representation items are reported too
+        let faceted = text.replacen("MANIFOLD_SOLID_BREP", "FACETED_BREP", 1);
+        let import = Brep::new().import_step(&faceted).unwrap();
+        assert_eq!(import.solids.len(), 1);
+        assert!(matches!(
+            &import.warnings[..],
+            [StepWarning::UnsupportedEntity { entity, .. }] if entity == "FACETED_BREP"
+        ));
+
+        assert_eq!(
+            Brep::new()
+                .import_step("ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=A(\n")
+                .err(),
+            Some(StepError::Syntax(5))
+        );
+    }
+
+    #[test]
+    fn test_reference_cycles() {
+        let file = |data: &str| {
+            format!("ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n{data}\nENDSEC;\nEND-ISO-10303-21;\n")
+        };
+
+        let import = Brep::new()
+            .import_step(&file(
+                "#1=MANIFOLD_SOLID_BREP('',#2);\n#2=ORIENTED_CLOSED_SHELL('',*,#2,.F.);",
+            ))
+            .unwrap();
+        assert_eq!(import.solids, []);
+        assert_eq!(import.warnings, [StepWarning::MalformedInstance(2)]);
+
+        let (brep, solid) = cuboid(DVec3::ONE);
+        let text = brep.to_step(&[solid], StepSchema::Ap214).unwrap();
+
+        // An edge whose curve lies on itself
+        let (_, after) = text.split_once("=EDGE_CURVE(").unwrap();
+        let curve = after.split(',').nth(3).unwrap().trim_start_matches('#');
+        let line = text
+            .lines()
+            .find(|l| l.starts_with(&format!("#{curve}=")))
+            .unwrap();
+        let cyclic = text.replace(
+            line,
+            &format!("#{curve}=SURFACE_CURVE('',#{curve},(),.CURVE_3D.);"),
+        );
+        let import = Brep::new().import_step(&cyclic).unwrap();
+        assert_eq!(import.solids, []);
+        assert_eq!(
+            import.warnings,
+            [StepWarning::MalformedInstance(curve.parse().unwrap())]
+        );
+
+        // A unit converted from itself
+        let (length, _) = text.split_once("=(LENGTH_UNIT()").unwrap();
+        let length: usize = length.rsplit('#').next().unwrap().parse().unwrap();
+        let cyclic = text.replace(
+            &format!("#{length}=(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT($,.METRE.));"),
+            &format!(
+                "#{length}=(CONVERSION_BASED_UNIT('INCH',#9000)LENGTH_UNIT()NAMED_UNIT(*));
+#9000=LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(25.4),#{length});"
+            ),
+        );
+        let import = Brep::new().import_step(&cyclic).unwrap();
+        assert_eq!(import.solids, []);
+        assert_eq!(import.warnings, [StepWarning::MalformedInstance(length)]);
+    }
+
+    #[test]
+    fn test_rational_spline() {
+        let text = "ISO-10303-21;
+HEADER;
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(1.,0.,0.));
+#2=CARTESIAN_POINT('',(1.,1.,0.));
+#3=CARTESIAN_POINT('',(0.,1.,0.));
+#4=(BOUNDED_CURVE()B_SPLINE_CURVE(2,(#1,#2,#3),.CIRCULAR_ARC.,.F.,.F.)
+B_SPLINE_CURVE_WITH_KNOTS((3,3),(0.,1.),.PIECEWISE_BEZIER_KNOTS.)CURVE()
+GEOMETRIC_REPRESENTATION_ITEM()RATIONAL_B_SPLINE_CURVE((1.,0.7071067811865476,1.))
+REPRESENTATION_ITEM(''));
+#5=B_SPLINE_SURFACE_WITH_KNOTS('',1,1,((#1,#2),(#3,#1)),.UNSPECIFIED.,.F.,.F.,.F.,
+(2,2),(2,2),(0.,1.),(0.,1.),.UNSPECIFIED.);
+ENDSEC;
+END-ISO-10303-21;
+";
+        let file = ExchangeFile::parse(text).unwrap();
+        let reader = StepReader::new(&file);
+
+        let Ok(Curve::Nurbs(curve)) = reader.curve(4) else {
+            panic!("not a spline");
+        };
+        assert!((curve.point_at(0.5).length() - 1.).abs() < 1e-12);
+
+        let Ok(Surface::Nurbs(surface)) = reader.surface(5) else {
+            panic!("not a spline");
+        };
+        assert_eq!(surface.knots_u, [0., 0., 1., 1.]);
+        assert!(surface
+            .point_at(1., 0.)
+            .abs_diff_eq(DVec3::new(0., 1., 0.), 1e-12));
+    }
+}
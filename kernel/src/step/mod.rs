@@ -0,0 +1,83 @@
+//! Exchange of solids through STEP (ISO 10303) files.
+//!
+//! [`part21`] reads and writes the clear text exchange structure without knowing any
+//! entities. On top of it, solids are written as advanced boundary representations, the
+//! shape representation shared by AP203 and AP214, and read back from any file that uses them.
+
+mod export;
+mod import;
+pub mod part21;
+
+use std::fmt;
+
+use crate::BrepError;
+
+pub use import::*;
+
+/// The application protocol a file declares in its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepSchema {
+    /// AP203, configuration controlled 3D design.
+    Ap203,
+    /// AP214, core data for automotive mechanical design processes.
+    Ap214,
+}
+
+impl StepSchema {
+    /// The schema name as written in `FILE_SCHEMA`.
+    pub fn schema_name(&self) -> &'static str {
+        match self {
+            StepSchema::Ap203 => "CONFIG_CONTROL_DESIGN",
+            StepSchema::Ap214 => "AUTOMOTIVE_DESIGN { 1 0 10303 214 1 1 1 1 }",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepError {
+    /// The text does not follow the exchange structure at this line.
+    Syntax(usize),
+    /// Two instances of the data section share this id.
+    DuplicateInstance(usize),
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepError::Syntax(line) => write!(f, "syntax error on line {line}"),
+            StepError::DuplicateInstance(id) => write!(f, "instance #{id} is defined twice"),
+        }
+    }
+}
+
+impl std::error::Error for StepError {}
+
+/// Something in a file that could not be read. The solid it belongs to is left out, and the
+/// rest of the file is read as usual.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepWarning {
+    /// An instance of an entity the kernel has no counterpart for. Complex instances list the
+    /// names of all their entities.
+    UnsupportedEntity { instance: usize, entity: String },
+    /// An instance whose attributes are missing, of the wrong kind or geometrically invalid.
+    MalformedInstance(usize),
+    /// A reference to an instance the file does not define.
+    UndefinedInstance(usize),
+    /// A solid was read but is not a closed, consistently oriented manifold.
+    InvalidSolid { instance: usize, error: BrepError },
+}
+
+impl fmt::Display for StepWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepWarning::UnsupportedEntity { instance, entity } => {
+                write!(f, "instance #{instance} is an unsupported {entity}")
+            }
+            StepWarning::MalformedInstance(id) => write!(f, "instance #{id} is malformed"),
+            StepWarning::UndefinedInstance(id) => write!(f, "instance #{id} is not defined"),
+            StepWarning::InvalidSolid { instance, error } => {
+                write!(f, "solid #{instance} is invalid: {error}")
+            }
+        }
+    }
+}
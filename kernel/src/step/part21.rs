@@ -0,0 +1,694 @@
+//! The exchange structure of ISO 10303-21, the clear text encoding STEP files are written in.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use super::StepError;
+
+/// A value in an entity record.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    Integer(i64),
+    Real(f64),
+    String(String),
+    /// An enumeration item or a boolean, such as `.T.`, without the dots.
+    Enumeration(String),
+    /// The instance with this id, written `#id`.
+    Reference(usize),
+    List(Vec<Parameter>),
+    /// A value of a named defined type, such as `LENGTH_MEASURE(1.E-07)`.
+    Typed(String, Box<Parameter>),
+    /// `$`: the value is left out.
+    Unset,
+    /// `*`: the value follows from other attributes.
+    Derived,
+}
+
+impl Parameter {
+    pub fn string(s: &str) -> Self {
+        Parameter::String(s.to_string())
+    }
+
+    pub fn boolean(b: bool) -> Self {
+        Parameter::Enumeration(if b { "T" } else { "F" }.to_string())
+    }
+
+    pub fn enumeration(item: &str) -> Self {
+        Parameter::Enumeration(item.to_string())
+    }
+
+    pub fn references(ids: impl IntoIterator<Item = usize>) -> Self {
+        Parameter::List(ids.into_iter().map(Parameter::Reference).collect())
+    }
+
+    pub fn reals(values: impl IntoIterator<Item = f64>) -> Self {
+        Parameter::List(values.into_iter().map(Parameter::Real).collect())
+    }
+
+    pub fn integers(values: impl IntoIterator<Item = i64>) -> Self {
+        Parameter::List(values.into_iter().map(Parameter::Integer).collect())
+    }
+
+    /// The value as a real, accepting integers too.
+    pub fn as_real(&self) -> Option<f64> {
+        match *self {
+            Parameter::Real(r) => Some(r),
+            Parameter::Integer(i) => Some(i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match *self {
+            Parameter::Integer(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_reference(&self) -> Option<usize> {
+        match *self {
+            Parameter::Reference(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Parameter]> {
+        match self {
+            Parameter::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_enumeration(&self) -> Option<&str> {
+        match self {
+            Parameter::Enumeration(item) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// `.T.` and `.F.`. The unknown logical `.U.` is neither.
+    pub fn as_boolean(&self) -> Option<bool> {
+        match self.as_enumeration()? {
+            "T" => Some(true),
+            "F" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parameter::Integer(i) => write!(f, "{i}"),
+            Parameter::Real(r) => f.write_str(&format_real(*r)),
+            Parameter::String(s) => f.write_str(&encode_string(s)),
+            Parameter::Enumeration(item) => write!(f, ".{item}."),
+            Parameter::Reference(id) => write!(f, "#{id}"),
+            Parameter::List(items) => {
+                f.write_char('(')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(')')
+            }
+            Parameter::Typed(name, value) => write!(f, "{name}({value})"),
+            Parameter::Unset => f.write_char('$'),
+            Parameter::Derived => f.write_char('*'),
+        }
+    }
+}
+
+/// Reals always carry a decimal point and an upper case exponent, as in `1.E-07`.
+fn format_real(r: f64) -> String {
+    let s = format!("{r:?}").replace('e', "E");
+    let (mantissa, exponent) = s.split_at(s.find('E').unwrap_or(s.len()));
+    if mantissa.contains('.') {
+        s
+    } else {
+        format!("{mantissa}.{exponent}")
+    }
+}
+
+/// Quotes a string, doubling apostrophes and backslashes and encoding everything outside
+/// printable ASCII as `\X2\` or `\X4\` hex.
+fn encode_string(s: &str) -> String {
+    let mut out = String::from("'");
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            c if (c as u32) <= 0xFFFF => {
+                let _ = write!(out, "\\X2\\{:04X}\\X0\\", c as u32);
+            }
+            c => {
+                let _ = write!(out, "\\X4\\{:08X}\\X0\\", c as u32);
+            }
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// One entity in a record: its name and its attribute values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityRecord {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+}
+
+impl EntityRecord {
+    pub fn new(name: &str, parameters: Vec<Parameter>) -> Self {
+        Self {
+            name: name.to_string(),
+            parameters,
+        }
+    }
+}
+
+impl fmt::Display for EntityRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            if i > 0 {
+                f.write_char(',')?;
+            }
+            write!(f, "{parameter}")?;
+        }
+        f.write_char(')')
+    }
+}
+
+/// The value of an instance: a single entity, or for an instance of several entities at once,
+/// one record per entity in alphabetical order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Simple(EntityRecord),
+    Complex(Vec<EntityRecord>),
+}
+
+impl Record {
+    /// The record of the entity `name`, which for a complex instance is one of its parts.
+    pub fn entity(&self, name: &str) -> Option<&EntityRecord> {
+        match self {
+            Record::Simple(record) => (record.name == name).then_some(record),
+            Record::Complex(records) => records.iter().find(|r| r.name == name),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Record::Simple(record) => vec![record.name.as_str()],
+            Record::Complex(records) => records.iter().map(|r| r.name.as_str()).collect(),
+        }
+    }
+
+    /// Every instance referred to from the record.
+    pub fn references(&self) -> Vec<usize> {
+        fn collect(parameter: &Parameter, out: &mut Vec<usize>) {
+            match parameter {
+                Parameter::Reference(id) => out.push(*id),
+                Parameter::List(items) => items.iter().for_each(|p| collect(p, out)),
+                Parameter::Typed(_, value) => collect(value, out),
+                _ => (),
+            }
+        }
+
+        let mut out = Vec::new();
+        let records = match self {
+            Record::Simple(record) => std::slice::from_ref(record),
+            Record::Complex(records) => records.as_slice(),
+        };
+        for parameter in records.iter().flat_map(|r| &r.parameters) {
+            collect(parameter, &mut out);
+        }
+        out
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Simple(record) => write!(f, "{record}"),
+            Record::Complex(records) => {
+                f.write_char('(')?;
+                for record in records {
+                    write!(f, "{record}")?;
+                }
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+/// The contents of a STEP file: the header entities and the instances of the data section by
+/// id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExchangeFile {
+    pub header: Vec<EntityRecord>,
+    pub data: BTreeMap<usize, Record>,
+}
+
+impl fmt::Display for ExchangeFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ISO-10303-21;")?;
+        writeln!(f, "HEADER;")?;
+        for record in &self.header {
+            writeln!(f, "{record};")?;
+        }
+        writeln!(f, "ENDSEC;")?;
+        writeln!(f, "DATA;")?;
+        for (id, record) in &self.data {
+            writeln!(f, "#{id}={record};")?;
+        }
+        writeln!(f, "ENDSEC;")?;
+        writeln!(f, "END-ISO-10303-21;")
+    }
+}
+
+impl ExchangeFile {
+    /// Reads the exchange structure of a STEP file without interpreting any entities.
+    pub fn parse(text: &str) -> Result<Self, StepError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let mut file = ExchangeFile::default();
+
+        parser.keyword("ISO-10303-21")?;
+        parser.punctuation(';')?;
+        parser.keyword("HEADER")?;
+        parser.punctuation(';')?;
+        while !parser.at_keyword("ENDSEC") {
+            file.header.push(parser.entity_record()?);
+            parser.punctuation(';')?;
+        }
+        parser.keyword("ENDSEC")?;
+        parser.punctuation(';')?;
+
+        while parser.at_keyword("DATA") {
+            parser.keyword("DATA")?;
+            if parser.at_punctuation('(') {
+                // The name and schema of a data section in later editions
+                parser.parameter()?;
+            }
+            parser.punctuation(';')?;
+
+            while !parser.at_keyword("ENDSEC") {
+                let line = parser.line();
+                let id = match parser.next() {
+                    Some(Token::Instance(id)) => id,
+                    _ => return Err(StepError::Syntax(line)),
+                };
+                parser.punctuation('=')?;
+                let record = if parser.at_punctuation('(') {
+                    parser.punctuation('(')?;
+                    let mut records = Vec::new();
+                    while !parser.at_punctuation(')') {
+                        records.push(parser.entity_record()?);
+                    }
+                    parser.punctuation(')')?;
+                    Record::Complex(records)
+                } else {
+                    Record::Simple(parser.entity_record()?)
+                };
+                parser.punctuation(';')?;
+
+                if file.data.insert(id, record).is_some() {
+                    return Err(StepError::DuplicateInstance(id));
+                }
+            }
+            parser.keyword("ENDSEC")?;
+            parser.punctuation(';')?;
+        }
+
+        parser.keyword("END-ISO-10303-21")?;
+        parser.punctuation(';')?;
+
+        Ok(file)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Keyword(String),
+    Instance(usize),
+    String(String),
+    Enumeration(String),
+    Integer(i64),
+    Real(f64),
+    Punctuation(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, StepError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = line;
+        let token = match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(StepError::Syntax(start));
+                }
+                i += 2;
+                continue;
+            }
+            '(' | ')' | ',' | '=' | ';' | '$' | '*' => {
+                i += 1;
+                Token::Punctuation(c)
+            }
+            '#' => {
+                let digits = take_while(&chars, i + 1, |c| c.is_ascii_digit());
+                i += 1 + digits.len();
+                Token::Instance(digits.parse().map_err(|_| StepError::Syntax(line))?)
+            }
+            '.' => {
+                let item = take_while(&chars, i + 1, |c| c.is_ascii_alphanumeric() || c == '_');
+                if chars.get(i + 1 + item.len()) != Some(&'.') {
+                    return Err(StepError::Syntax(line));
+                }
+                i += item.len() + 2;
+                Token::Enumeration(item)
+            }
+            '\'' => {
+                let mut raw = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(StepError::Syntax(start)),
+                        Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+                            raw.push('\'');
+                            i += 2;
+                        }
+                        Some('\'') => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&c) => {
+                            if c == '\n' {
+                                line += 1;
+                            } else if c != '\r' {
+                                raw.push(c);
+                            }
+                            i += 1;
+                        }
+                    }
+                }
+                Token::String(decode_string(&raw))
+            }
+            '"' => {
+                // Binary values are kept as their hex digits
+                let digits = take_while(&chars, i + 1, |c| c.is_ascii_hexdigit());
+                if chars.get(i + 1 + digits.len()) != Some(&'"') {
+                    return Err(StepError::Syntax(line));
+                }
+                i += digits.len() + 2;
+                Token::String(digits)
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' => {
+                let number = take_while(&chars, i, |c| {
+                    c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'E' | 'e')
+                });
+                i += number.len();
+                if number.contains(['.', 'E', 'e']) {
+                    Token::Real(number.parse().map_err(|_| StepError::Syntax(line))?)
+                } else {
+                    Token::Integer(number.parse().map_err(|_| StepError::Syntax(line))?)
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '!' => {
+                let word = take_while(&chars, i, |c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '!')
+                });
+                i += word.len();
+                Token::Keyword(word.to_ascii_uppercase())
+            }
+            _ => return Err(StepError::Syntax(line)),
+        };
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+fn take_while(chars: &[char], from: usize, predicate: impl Fn(char) -> bool) -> String {
+    chars[from.min(chars.len())..]
+        .iter()
+        .take_while(|&&c| predicate(c))
+        .collect()
+}
+
+/// Undoes the control directives of a string: `\\`, `\S\`, `\X\`, `\X2\` and `\X4\`. Code
+/// page switches with `\P` are dropped.
+fn decode_string(raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    let hex = |from: usize, count: usize| -> Option<u32> {
+        let digits: String = chars.get(from..from + count)?.iter().collect();
+        u32::from_str_radix(&digits, 16).ok()
+    };
+
+    while i < chars.len() {
+        if chars[i] != '\\' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let rest: String = chars[i..chars.len().min(i + 4)].iter().collect();
+        if rest.starts_with("\\\\") {
+            out.push('\\');
+            i += 2;
+        } else if rest.starts_with("\\S\\") && i + 3 < chars.len() {
+            out.extend(char::from_u32(chars[i + 3] as u32 + 128));
+            i += 4;
+        } else if rest.starts_with("\\X\\") {
+            out.extend(hex(i + 3, 2).and_then(char::from_u32));
+            i += 5;
+        } else if rest == "\\X2\\" || rest == "\\X4\\" {
+            let width = if rest == "\\X2\\" { 4 } else { 8 };
+            i += 4;
+            let mut units = Vec::new();
+            while let Some(unit) = hex(i, width) {
+                units.push(unit);
+                i += width;
+            }
+            if width == 4 {
+                let units: Vec<u16> = units.iter().map(|&u| u as u16).collect();
+                out.extend(char::decode_utf16(units).map(|c| c.unwrap_or('\u{FFFD}')));
+            } else {
+                out.extend(units.into_iter().filter_map(char::from_u32));
+            }
+            // The closing `\X0\`
+            i += 4;
+        } else if rest.starts_with("\\P") && rest.ends_with('\\') {
+            i += 4;
+        } else {
+            out.push('\\');
+            i += 1;
+        }
+    }
+
+    out
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(t, _)| t)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(t, _)| t.clone());
+        self.position += 1;
+        token
+    }
+
+    /// The line of the next token, or of the last one at the end.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(1, |&(_, line)| line)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if k == keyword)
+    }
+
+    fn at_punctuation(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punctuation(c))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), StepError> {
+        if !self.at_keyword(keyword) {
+            return Err(StepError::Syntax(self.line()));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn punctuation(&mut self, c: char) -> Result<(), StepError> {
+        if !self.at_punctuation(c) {
+            return Err(StepError::Syntax(self.line()));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn entity_record(&mut self) -> Result<EntityRecord, StepError> {
+        let line = self.line();
+        let Some(Token::Keyword(name)) = self.next() else {
+            return Err(StepError::Syntax(line));
+        };
+        let Parameter::List(parameters) = self.list()? else {
+            unreachable!()
+        };
+        Ok(EntityRecord { name, parameters })
+    }
+
+    fn list(&mut self) -> Result<Parameter, StepError> {
+        self.punctuation('(')?;
+        let mut items = Vec::new();
+        if !self.at_punctuation(')') {
+            items.push(self.parameter()?);
+            while self.at_punctuation(',') {
+                self.position += 1;
+                items.push(self.parameter()?);
+            }
+        }
+        self.punctuation(')')?;
+        Ok(Parameter::List(items))
+    }
+
+    fn parameter(&mut self) -> Result<Parameter, StepError> {
+        let line = self.line();
+        Ok(match self.peek() {
+            Some(Token::Punctuation('(')) => return self.list(),
+            Some(Token::Keyword(_)) => {
+                let Some(Token::Keyword(name)) = self.next() else {
+                    unreachable!()
+                };
+                self.punctuation('(')?;
+                let value = self.parameter()?;
+                self.punctuation(')')?;
+                Parameter::Typed(name, Box::new(value))
+            }
+            _ => match self.next() {
+                Some(Token::Instance(id)) => Parameter::Reference(id),
+                Some(Token::String(s)) => Parameter::String(s),
+                Some(Token::Enumeration(item)) => Parameter::Enumeration(item),
+                Some(Token::Integer(i)) => Parameter::Integer(i),
+                Some(Token::Real(r)) => Parameter::Real(r),
+                Some(Token::Punctuation('$')) => Parameter::Unset,
+                Some(Token::Punctuation('*')) => Parameter::Derived,
+                _ => return Err(StepError::Syntax(line)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reals() {
+        assert_eq!(format_real(1.), "1.0");
+        assert_eq!(format_real(1e-7), "1.E-7");
+        assert_eq!(format_real(-2.5e20), "-2.5E20");
+        assert_eq!(format_real(0.1), "0.1");
+    }
+
+    #[test]
+    fn test_strings() {
+        let s = "it's a \\ café ✓";
+        let encoded = encode_string(s);
+        assert_eq!(
+            encoded,
+            "'it''s a \\\\ caf\\X2\\00E9\\X0\\ \\X2\\2713\\X0\\'"
+        );
+        assert_eq!(
+            decode_string(&encoded[1..encoded.len() - 1].replace("''", "'")),
+            s
+        );
+        assert_eq!(decode_string("\\X\\E9t\\S\\i"), "\u{E9}t\u{E9}");
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "ISO-10303-21;
+HEADER;
+/* a comment */
+FILE_DESCRIPTION(('test'),'2;1');
+ENDSEC;
+DATA;
+#1=CARTESIAN_POINT('',(0.,1.5,-2.E-3));
+#2 = ( NAMED_UNIT(*) SI_UNIT($,.METRE.) );
+#10=UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-07),#2,'it''s',\"0F\");
+ENDSEC;
+END-ISO-10303-21;
+";
+        let file = ExchangeFile::parse(text).unwrap();
+        assert_eq!(file.header[0].name, "FILE_DESCRIPTION");
+        assert_eq!(file.data.len(), 3);
+
+        let point = file.data[&1].entity("CARTESIAN_POINT").unwrap();
+        assert_eq!(point.parameters[1], Parameter::reals([0., 1.5, -2e-3]));
+        assert_eq!(file.data[&2].names(), ["NAMED_UNIT", "SI_UNIT"]);
+        assert_eq!(
+            file.data[&10]
+                .entity("UNCERTAINTY_MEASURE_WITH_UNIT")
+                .unwrap()
+                .parameters[0],
+            Parameter::Typed("LENGTH_MEASURE".into(), Box::new(Parameter::Real(1e-7)))
+        );
+        assert_eq!(file.data[&10].references(), [2]);
+
+        // Writing and reading again gives the same file
+        assert_eq!(ExchangeFile::parse(&file.to_string()).unwrap(), file);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(
+            ExchangeFile::parse("ISO-10303-21;\nHEADER;\nENDSEC;\nDATA;\n#1=POINT(;\n"),
+            Err(StepError::Syntax(5))
+        );
+        assert_eq!(
+            ExchangeFile::parse(
+                "ISO-10303-21;HEADER;ENDSEC;DATA;#1=A();#1=B();ENDSEC;END-ISO-10303-21;"
+            ),
+            Err(StepError::DuplicateInstance(1))
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Export of tessellated solids as STL, in both the ASCII and the binary form.
+//!
+//! STL files carry no units; most slicers read them as millimetres, so the coordinates are
+//! scaled on the way out (see [`StlOptions::scale`]). Every solid is checked to tessellate into a
+//! closed mesh at the single precision it is written in, so an exported file slices without
+//! repair.
+
+use std::fmt::{self, Write};
+
+use glam::{DVec3, Vec3};
+
+use crate::{Brep, Mesh, SolidId, TesselationError, TesselationTolerance};
+
+/// The 80 bytes that start a binary file. They must not start with `solid`, or readers take
+/// the file for ASCII.
+const BINARY_HEADER: &[u8] = b"binary STL";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StlOptions {
+    /// How closely the triangles follow curved faces, in model units.
+    pub tolerance: TesselationTolerance,
+    /// What every coordinate is multiplied by as it is written. Models in metres are written in
+    /// millimetres with `1000.`. Has to be positive and finite.
+    pub scale: f64,
+}
+
+impl Default for StlOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: TesselationTolerance::default(),
+            scale: 1.,
+        }
+    }
+}
+
+impl StlOptions {
+    pub fn new(tolerance: TesselationTolerance, scale: f64) -> Self {
+        Self { tolerance, scale }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StlError {
+    Tesselation(TesselationError),
+    /// The scale is zero, negative or not finite, which would flatten or turn the solids inside
+    /// out.
+    InvalidScale,
+    /// The mesh of a solid has this many triangle edges not matched by exactly one running the
+    /// other way, so it has holes or overlaps a slicer would have to repair.
+    NotWatertight {
+        solid: SolidId,
+        open_edges: usize,
+    },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Tesselation(error) => write!(f, "{error}"),
+            StlError::InvalidScale => write!(f, "the scale has to be positive and finite"),
+            StlError::NotWatertight { solid, open_edges } => write!(
+                f,
+                "the mesh of solid {} has {open_edges} open edges",
+                solid.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+impl From<TesselationError> for StlError {
+    fn from(error: TesselationError) -> Self {
+        StlError::Tesselation(error)
+    }
+}
+
+impl Brep {
+    /// Writes solids as a single ASCII STL solid named `name`.
+    pub fn to_stl(
+        &self,
+        solids: &[SolidId],
+        name: &str,
+        options: &StlOptions,
+    ) -> Result<String, StlError> {
+        Ok(self.stl_mesh(solids, options)?.to_stl(name))
+    }
+
+    /// Writes solids as a binary STL file.
+    pub fn to_binary_stl(
+        &self,
+        solids: &[SolidId],
+        options: &StlOptions,
+    ) -> Result<Vec<u8>, StlError> {
+        Ok(self.stl_mesh(solids, options)?.to_binary_stl())
+    }
+
+    /// The scaled triangles of all the solids, each of which has to be watertight.
+    fn stl_mesh(&self, solids: &[SolidId], options: &StlOptions) -> Result<Mesh, StlError> {
+        if !(options.scale > 0. && options.scale.is_finite()) {
+            return Err(StlError::InvalidScale);
+        }
+
+        let mut mesh = Mesh::new();
+        for &solid in solids {
+            let mut part = self.tesselate_solid(solid, &options.tolerance)?;
+
+            // The mesh is checked as it will be read back: in single precision, where nearby
+            // corners may fall together and triangles lose their area. Corners that fall
+            // together are welded by the check, which matches edges by their end positions.
+            for p in &mut part.positions {
+                *p = (*p * options.scale).as_vec3().as_dvec3();
+            }
+            part.indices
+                .retain(|t| facet_normal(t.map(|i| part.positions[i as usize])) != Vec3::ZERO);
+
+            let open_edges = part.open_edges();
+            if open_edges > 0 {
+                return Err(StlError::NotWatertight { solid, open_edges });
+            }
+            mesh.append(&part);
+        }
+        Ok(mesh)
+    }
+}
+
+impl Mesh {
+    /// The triangles as an ASCII STL solid named `name`.
+    pub fn to_stl(&self, name: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "solid {name}").unwrap();
+        for t in &self.indices {
+            let corners = t.map(|i| self.positions[i as usize]);
+            let [nx, ny, nz] = facet_normal(corners).to_array();
+            writeln!(out, "  facet normal {nx:e} {ny:e} {nz:e}").unwrap();
+            writeln!(out, "    outer loop").unwrap();
+            for p in corners {
+                let [x, y, z] = p.as_vec3().to_array();
+                writeln!(out, "      vertex {x:e} {y:e} {z:e}").unwrap();
+            }
+            writeln!(out, "    endloop").unwrap();
+            writeln!(out, "  endfacet").unwrap();
+        }
+        writeln!(out, "endsolid {name}").unwrap();
+        out
+    }
+
+    /// The triangles as a binary STL file: a header, the number of triangles and then each
+    /// triangle's normal and corners as little-endian single precision floats.
+    pub fn to_binary_stl(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(84 + 50 * self.indices.len());
+        out.extend_from_slice(BINARY_HEADER);
+        out.resize(80, 0);
+        out.extend_from_slice(&(self.indices.len() as u32).to_le_bytes());
+
+        for t in &self.indices {
+            let corners = t.map(|i| self.positions[i as usize]);
+            let normal = facet_normal(corners);
+            for v in std::iter::once(normal).chain(corners.map(|p| p.as_vec3())) {
+                for c in v.to_array() {
+                    out.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            // The attribute byte count, unused
+            out.extend_from_slice(&0u16.to_le_bytes());
+        }
+        out
+    }
+}
+
+/// The unit normal of a counter-clockwise triangle, zero when it has no area.
+fn facet_normal([a, b, c]: [DVec3; 3]) -> Vec3 {
+    (b - a).cross(c - a).normalize_or_zero().as_vec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DVec3, Vec3};
+
+    use crate::test_util::{block, cuboid};
+    use crate::*;
+
+    fn read_f32(bytes: &[u8], at: usize) -> f32 {
+        f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// The triangles of a binary file, with corners at the same position shared.
+    fn read_binary(bytes: &[u8]) -> Mesh {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+        let mut mesh = Mesh::new();
+        for t in 0..count {
+            let at = 84 + 50 * t + 12;
+            let corner = |i: usize| {
+                let at = at + 12 * i;
+                Vec3::new(
+                    read_f32(bytes, at),
+                    read_f32(bytes, at + 4),
+                    read_f32(bytes, at + 8),
+                )
+                .as_dvec3()
+            };
+            let first = mesh.positions.len() as u32;
+            mesh.positions.extend((0..3).map(corner));
+            mesh.indices.push([first, first + 1, first + 2]);
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_ascii() {
+        let (brep, solid) = cuboid(DVec3::new(1., 2., 3.));
+        let options = StlOptions {
+            scale: 1000.,
+            ..Default::default()
+        };
+
+        let text = brep.to_stl(&[solid], "box", &options).unwrap();
+
+        assert!(text.starts_with("solid box\n"));
+        assert!(text.ends_with("endsolid box\n"));
+        assert_eq!(text.matches("facet normal").count(), 12);
+        assert_eq!(text.matches("endfacet").count(), 12);
+
+        let vertices: Vec<Vec3> = text
+            .lines()
+            .filter_map(|l| l.trim().strip_prefix("vertex "))
+            .map(|l| {
+                let c: Vec<f32> = l.split(' ').map(|c| c.parse().unwrap()).collect();
+                Vec3::new(c[0], c[1], c[2])
+            })
+            .collect();
+        assert_eq!(vertices.len(), 36);
+        // The box is scaled from metres to millimetres
+        let max = vertices.iter().fold(Vec3::ZERO, |m, &v| m.max(v));
+        assert_eq!(max, Vec3::new(1000., 2000., 3000.));
+    }
+
+    #[test]
+    fn test_binary() {
+        let (brep, solid) = cuboid(DVec3::ONE);
+
+        let bytes = brep
+            .to_binary_stl(&[solid], &StlOptions::default())
+            .unwrap();
+
+        assert_eq!(bytes.len(), 84 + 50 * 12);
+        assert!(!bytes.starts_with(b"solid"));
+        assert_eq!(u32::from_le_bytes(bytes[80..84].try_into().unwrap()), 12);
+
+        // Every normal points away from the middle of the box
+        for t in 0..12 {
+            let at = 84 + 50 * t;
+            let normal = Vec3::new(
+                read_f32(&bytes, at),
+                read_f32(&bytes, at + 4),
+                read_f32(&bytes, at + 8),
+            );
+            let corner = Vec3::new(
+                read_f32(&bytes, at + 12),
+                read_f32(&bytes, at + 16),
+                read_f32(&bytes, at + 20),
+            );
+            assert_eq!(normal.length(), 1.);
+            assert!(normal.dot(corner - Vec3::splat(0.5)) > 0.);
+        }
+    }
+
+    #[test]
+    fn test_curved_solid_is_watertight() {
+        let start = DVec3::X;
+        let profile = BoundarySurface::new(
+            Plane::XY,
+            BoundaryLoop {
+                elements: vec![BoundaryElement::BoundaryArc(
+                    BoundaryArc::new(DVec3::ZERO, DVec3::Z, 1., start, start, Direction::CCW)
+                        .unwrap(),
+                )],
+            },
+            Vec::new(),
+        );
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&profile, DVec3::Z, ExtrudeExtent::OneSided(1.))
+            .unwrap();
+
+        let coarse = brep
+            .to_binary_stl(
+                &[solid],
+                &StlOptions::new(TesselationTolerance::new(0.1, 1.), 1.),
+            )
+            .unwrap();
+        let fine = brep
+            .to_binary_stl(&[solid], &StlOptions::default())
+            .unwrap();
+        assert!(fine.len() > coarse.len());
+
+        // The file as read back closes up, with every triangle edge matched by one the other way
+        for bytes in [coarse, fine] {
+            let mesh = read_binary(&bytes);
+            assert!(!mesh.indices.is_empty());
+            assert_eq!(mesh.open_edges(), 0);
+        }
+    }
+
+    #[test]
+    fn test_single_precision() {
+        // Far from the origin the box is thinner than the spacing of single precision floats,
+        // so its sides lose their area and only its two ends are left, back to back
+        let mut brep = Brep::new();
+        let solid = block(
+            &mut brep,
+            DVec3::new(1e4, 0., 0.),
+            DVec3::new(1e4 + 1e-4, 1., 1.),
+        );
+
+        let bytes = brep
+            .to_binary_stl(&[solid], &StlOptions::default())
+            .unwrap();
+        let mesh = read_binary(&bytes);
+        assert_eq!(mesh.indices.len(), 4);
+        assert_eq!(mesh.open_edges(), 0);
+        for t in 0..4 {
+            assert_eq!(read_f32(&bytes, 84 + 50 * t).abs(), 1.);
+        }
+    }
+
+    #[test]
+    fn test_invalid_scale() {
+        let (brep, solid) = cuboid(DVec3::ONE);
+        for scale in [0., -1., f64::NAN, f64::INFINITY] {
+            let options = StlOptions {
+                scale,
+                ..Default::default()
+            };
+            assert_eq!(
+                brep.to_binary_stl(&[solid], &options),
+                Err(StlError::InvalidScale)
+            );
+        }
+    }
+
+    #[test]
+    fn test_open_solid() {
+        let (mut brep, solid) = cuboid(DVec3::ONE);
+        let shell = brep.solid(solid).shells[0];
+        brep.shells[shell.0].faces.pop();
+
+        assert_eq!(
+            brep.to_stl(&[solid], "open", &StlOptions::default()),
+            Err(StlError::NotWatertight {
+                solid,
+                open_edges: 4
+            })
+        );
+    }
+}
@@ -0,0 +1,863 @@
+//! Export of sketches and planar profiles as SVG drawings, for documentation and reviews.
+//!
+//! Drawings are in millimetres, seen from above the plane with its x axis to the right and its
+//! y axis up. Lines, arcs, circles and ellipses are written as the SVG shapes and path
+//! commands for them, and polynomial splines up to cubics as Bézier curves, so the drawing
+//! stays exact at any zoom. The view box is fitted around everything drawn.
+
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::fmt::Write;
+
+use glam::{DVec2, DVec3};
+
+use crate::nurbs::NurbsCurve;
+use crate::{
+    BoundaryElement, BoundaryLoop, BoundarySurface, DimensionKind, DimensionMode, Direction,
+    ElementId, Plane, Sketch, SketchElement, System, TesselationTolerance, Tolerance,
+};
+
+/// Millimetres per metre, the unit drawings are in.
+const SCALE: f64 = 1000.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    /// Whether the dimensions of sketches are drawn. Reference dimensions are shown in
+    /// parentheses.
+    pub dimensions: bool,
+    /// The space left around the content, in millimetres.
+    pub margin: f64,
+    /// The width of lines, in millimetres. Construction geometry and dimensions are drawn half
+    /// as wide.
+    pub stroke_width: f64,
+    /// The height of dimension text, in millimetres. Dimension lines are set off from the
+    /// geometry by twice as much.
+    pub text_height: f64,
+    /// How closely rational splines and splines above cubic, which SVG has no curves for, are
+    /// followed by straight segments.
+    pub tolerance: TesselationTolerance,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            dimensions: false,
+            margin: 5.,
+            stroke_width: 0.35,
+            text_height: 3.5,
+            tolerance: TesselationTolerance::new(1e-5, PI / 36.),
+        }
+    }
+}
+
+/// An SVG drawing built up from sketches and planar profiles. Everything is drawn in the 2D
+/// coordinates of the plane it lies on, so sketches on different planes overlap in the
+/// drawing.
+#[derive(Debug, Clone)]
+pub struct SvgDrawing {
+    options: SvgOptions,
+    body: String,
+    /// The corners of the content in drawing coordinates, with y pointing down.
+    min: DVec2,
+    max: DVec2,
+}
+
+impl SvgDrawing {
+    pub fn new(options: SvgOptions) -> Self {
+        Self {
+            options,
+            body: String::new(),
+            min: DVec2::INFINITY,
+            max: DVec2::NEG_INFINITY,
+        }
+    }
+
+    /// Adds every element of a sketch, construction geometry dashed, and its dimensions if the
+    /// options ask for them.
+    pub fn add_sketch(&mut self, sketch: &Sketch) {
+        let plane = &sketch.plane.0;
+        let local = |p: DVec3| plane.to_local(p);
+
+        for (i, element) in sketch.elements.iter().enumerate() {
+            let class = if sketch.is_construction(ElementId(i)) {
+                "construction"
+            } else {
+                "geometry"
+            };
+
+            match element {
+                SketchElement::Line(line) => {
+                    let Ok(line) = line.0.to_two_point_line() else {
+                        continue;
+                    };
+                    self.line(class, local(line.a.0), local(line.b.0), "");
+                }
+                SketchElement::Point(point) => {
+                    let c = self.map(local(point.0 .0));
+                    let r = self.options.stroke_width * 1.5;
+                    let _ = writeln!(
+                        self.body,
+                        r#"<circle class="point" cx="{}" cy="{}" r="{}"/>"#,
+                        num(c.x),
+                        num(c.y),
+                        num(r)
+                    );
+                    self.include(c);
+                }
+                SketchElement::Arc(arc) => {
+                    let center = local(arc.0.center);
+                    let start = local(arc.0.start);
+                    let sweep = if arc.0.normal.dot(plane.normal) < 0. {
+                        -arc.0.sweep()
+                    } else {
+                        arc.0.sweep()
+                    };
+                    let mut d = self.move_to(start);
+                    self.arc_to(&mut d, center, start, sweep);
+                    self.path(class, &d, "");
+                }
+                SketchElement::Circle(circle) => {
+                    self.circle(class, local(circle.0.center), circle.0.radius)
+                }
+                SketchElement::Ellipse(ellipse) => {
+                    let e = &ellipse.0;
+                    let center = local(e.center);
+                    let major = local(e.center + e.x_axis) - center;
+                    self.ellipse(class, center, major, e.major_radius, e.minor_radius);
+                }
+                SketchElement::Spline(spline) => {
+                    let mut curve = spline.curve().clone();
+                    for p in &mut curve.control_points {
+                        *p = local(*p).extend(0.);
+                    }
+                    self.spline(class, &curve);
+                }
+            }
+        }
+
+        if self.options.dimensions {
+            self.dimensions(sketch);
+        }
+    }
+
+    /// Adds a profile as a single filled path, its holes left open.
+    pub fn add_profile(&mut self, profile: &BoundarySurface) {
+        let mut d = String::new();
+        for boundary in std::iter::once(&profile.boundary).chain(&profile.holes) {
+            self.profile_loop(&mut d, &profile.plane, boundary);
+        }
+        self.path("profile", d.trim_start(), "");
+    }
+
+    /// Writes the drawing, sized in millimetres to fit its content and the margin.
+    pub fn to_svg(&self) -> String {
+        let (min, max) = if self.min.x <= self.max.x {
+            (self.min, self.max)
+        } else {
+            (DVec2::ZERO, DVec2::ZERO)
+        };
+        let min = min - self.options.margin;
+        let size = max + self.options.margin - min;
+
+        let w = self.options.stroke_width;
+        let h = self.options.text_height;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}mm" height="{}mm" viewBox="{} {} {} {}">"#,
+            num(size.x),
+            num(size.y),
+            num(min.x),
+            num(min.y),
+            num(size.x),
+            num(size.y)
+        );
+        let _ = writeln!(out, "<style>");
+        let _ = writeln!(
+            out,
+            ".geometry {{ fill: none; stroke: black; stroke-width: {} }}",
+            num(w)
+        );
+        let _ = writeln!(
+            out,
+            ".construction {{ fill: none; stroke: gray; stroke-width: {}; stroke-dasharray: {} {} }}",
+            num(w / 2.),
+            num(w * 8.),
+            num(w * 4.)
+        );
+        let _ = writeln!(
+            out,
+            ".profile {{ fill: #dde4ec; fill-rule: evenodd; stroke: black; stroke-width: {} }}",
+            num(w)
+        );
+        let _ = writeln!(out, ".point {{ fill: black }}");
+        let _ = writeln!(
+            out,
+            ".dimension {{ fill: none; stroke: #1f5fa8; stroke-width: {} }}",
+            num(w / 2.)
+        );
+        let _ = writeln!(
+            out,
+            "text.dimension {{ fill: #1f5fa8; stroke: none; font-family: sans-serif; font-size: {}px; text-anchor: middle }}",
+            num(h)
+        );
+        let _ = writeln!(out, "</style>");
+        let _ = writeln!(
+            out,
+            r##"<defs><marker id="arrow" viewBox="0 0 10 4" refX="10" refY="2" markerUnits="userSpaceOnUse" markerWidth="{}" markerHeight="{}" orient="auto-start-reverse"><path d="M 0 0 L 10 2 L 0 4 Z" fill="#1f5fa8"/></marker></defs>"##,
+            num(h),
+            num(h * 0.4)
+        );
+        out.push_str(&self.body);
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Drawing coordinates of a point in the plane, in millimetres with y pointing down.
+    fn map(&self, p: DVec2) -> DVec2 {
+        DVec2::new(p.x, -p.y) * SCALE
+    }
+
+    /// Grows the content to hold a point in drawing coordinates.
+    fn include(&mut self, p: DVec2) {
+        self.min = self.min.min(p);
+        self.max = self.max.max(p);
+    }
+
+    fn move_to(&mut self, p: DVec2) -> String {
+        let p = self.map(p);
+        self.include(p);
+        format!("M {} {}", num(p.x), num(p.y))
+    }
+
+    fn line_to(&mut self, d: &mut String, p: DVec2) {
+        let p = self.map(p);
+        self.include(p);
+        let _ = write!(d, " L {} {}", num(p.x), num(p.y));
+    }
+
+    /// Continues a path at `start` along the arc around `center` that turns through `sweep`
+    /// radians, counter-clockwise when positive. Arcs of a full turn are drawn in two halves,
+    /// since SVG cannot tell which circle to follow between coinciding points.
+    fn arc_to(&mut self, d: &mut String, center: DVec2, start: DVec2, sweep: f64) {
+        let radius = start.distance(center);
+        let from = angle(start - center);
+        let at = |a: f64| center + radius * DVec2::from_angle(a);
+
+        let halves = if sweep.abs() > PI * 1.5 { 2 } else { 1 };
+        for i in 1..=halves {
+            let end = at(from + sweep * i as f64 / halves as f64);
+            let p = self.map(end);
+            let r = num(radius * SCALE);
+            // Counter-clockwise in the plane is clockwise with y pointing down
+            let large = (sweep.abs() / halves as f64 > PI) as u8;
+            let clockwise = (sweep < 0.) as u8;
+            let _ = write!(
+                d,
+                " A {r} {r} 0 {large} {clockwise} {} {}",
+                num(p.x),
+                num(p.y)
+            );
+        }
+
+        // The arc reaches furthest out where it crosses the axes through its center
+        self.include(self.map(at(from + sweep)));
+        let (low, high) = if sweep > 0. {
+            (from, from + sweep)
+        } else {
+            (from + sweep, from)
+        };
+        let mut quarter = (low / FRAC_PI_2).ceil();
+        while quarter * FRAC_PI_2 <= high {
+            self.include(self.map(at(quarter * FRAC_PI_2)));
+            quarter += 1.;
+        }
+    }
+
+    fn line(&mut self, class: &str, a: DVec2, b: DVec2, extra: &str) {
+        let (a, b) = (self.map(a), self.map(b));
+        self.include(a);
+        self.include(b);
+        let _ = writeln!(
+            self.body,
+            r#"<line class="{class}" x1="{}" y1="{}" x2="{}" y2="{}"{extra}/>"#,
+            num(a.x),
+            num(a.y),
+            num(b.x),
+            num(b.y)
+        );
+    }
+
+    fn path(&mut self, class: &str, d: &str, extra: &str) {
+        let _ = writeln!(self.body, r#"<path class="{class}" d="{d}"{extra}/>"#);
+    }
+
+    fn circle(&mut self, class: &str, center: DVec2, radius: f64) {
+        let c = self.map(center);
+        let r = radius * SCALE;
+        self.include(c - r);
+        self.include(c + r);
+        let _ = writeln!(
+            self.body,
+            r#"<circle class="{class}" cx="{}" cy="{}" r="{}"/>"#,
+            num(c.x),
+            num(c.y),
+            num(r)
+        );
+    }
+
+    /// An ellipse with its major axis along `major`.
+    fn ellipse(&mut self, class: &str, center: DVec2, major: DVec2, a: f64, b: f64) {
+        let c = self.map(center);
+        let rotation = -major.y.atan2(major.x);
+        let (a, b) = (a * SCALE, b * SCALE);
+        let (sin, cos) = rotation.sin_cos();
+        let extent = DVec2::new(
+            (a * a * cos * cos + b * b * sin * sin).sqrt(),
+            (a * a * sin * sin + b * b * cos * cos).sqrt(),
+        );
+        self.include(c - extent);
+        self.include(c + extent);
+        let _ = writeln!(
+            self.body,
+            r#"<ellipse class="{class}" cx="{}" cy="{}" rx="{}" ry="{}" transform="rotate({} {} {})"/>"#,
+            num(c.x),
+            num(c.y),
+            num(a),
+            num(b),
+            num(rotation.to_degrees()),
+            num(c.x),
+            num(c.y)
+        );
+    }
+
+    /// A spline with control points in plane coordinates, as Bézier pieces where SVG has
+    /// curves of its degree and as straight segments otherwise.
+    fn spline(&mut self, class: &str, curve: &NurbsCurve) {
+        let mut d = self.move_to(curve.point_at(curve.domain().0).truncate());
+        self.curve_to(&mut d, curve);
+        self.path(class, &d, "");
+    }
+
+    /// Continues a path from the start of a curve with control points in plane coordinates to
+    /// its end.
+    fn curve_to(&mut self, d: &mut String, curve: &NurbsCurve) {
+        let (start, end) = curve.domain();
+        for i in 0..=64 {
+            let t = start + (end - start) * i as f64 / 64.;
+            self.include(self.map(curve.point_at(t).truncate()));
+        }
+
+        let p = curve.degree;
+        if curve.is_rational() || p > 3 {
+            for line in curve.to_lines(&self.options.tolerance) {
+                let Ok(line) = line.to_two_point_line() else {
+                    continue;
+                };
+                self.line_to(d, line.b.0.truncate());
+            }
+        } else {
+            let command = ["L", "Q", "C"][p - 1];
+            for piece in bezier_pieces(curve) {
+                d.push(' ');
+                d.push_str(command);
+                for &q in &piece[1..] {
+                    let q = self.map(q.truncate());
+                    let _ = write!(d, " {} {}", num(q.x), num(q.y));
+                }
+            }
+        }
+    }
+
+    /// One closed loop of a profile path, in the plane of the profile.
+    fn profile_loop(&mut self, d: &mut String, plane: &Plane, boundary: &BoundaryLoop) {
+        let local = |p: DVec3| plane.to_local(p);
+        let Some(first) = boundary.elements.first() else {
+            return;
+        };
+        let start = match first {
+            BoundaryElement::BoundaryLine(line) => line.a.0,
+            BoundaryElement::BoundaryArc(arc) => arc.start.0,
+            BoundaryElement::BoundaryCurve(curve) => curve.start.0,
+            BoundaryElement::BoundaryPolygon(polygon) => match polygon.lines.first() {
+                Some(line) => line.a.0,
+                None => return,
+            },
+        };
+        d.push(' ');
+        d.push_str(&self.move_to(local(start)));
+
+        for element in &boundary.elements {
+            match element {
+                BoundaryElement::BoundaryLine(line) => self.line_to(d, local(line.b.0)),
+                BoundaryElement::BoundaryPolygon(polygon) => {
+                    for line in &polygon.lines {
+                        self.line_to(d, local(line.b.0));
+                    }
+                }
+                BoundaryElement::BoundaryArc(arc) => {
+                    let center = local(arc.circle.center);
+                    let start = local(arc.start.0);
+                    let turn = |from: DVec2, to: DVec2| {
+                        let a = (angle(to - center) - angle(from - center)).rem_euclid(TAU);
+                        // Coinciding ends make a full turn
+                        if Tolerance::current().is_zero_length(a * arc.circle.radius) {
+                            TAU
+                        } else {
+                            a
+                        }
+                    };
+                    let end = local(arc.end.0);
+                    let sweep = match arc.direction {
+                        Direction::CCW => turn(start, end),
+                        Direction::CW => -turn(end, start),
+                    };
+                    self.arc_to(d, center, start, sweep);
+                }
+                BoundaryElement::BoundaryCurve(curve) => {
+                    let curve = curve.curve.map(|p| local(p).extend(0.));
+                    self.curve_to(d, &curve);
+                }
+            }
+        }
+        d.push_str(" Z");
+    }
+
+    fn dimensions(&mut self, sketch: &Sketch) {
+        let Ok(system) = System::new(sketch) else {
+            return;
+        };
+        let x = &system.initial;
+        // Dimensions are set off away from the middle of the sketch
+        let middle = if self.min.x <= self.max.x {
+            let m = (self.min + self.max) / 2. / SCALE;
+            DVec2::new(m.x, -m.y)
+        } else {
+            DVec2::ZERO
+        };
+
+        for dimension in &sketch.dimensions {
+            let text = |s: String| match dimension.mode {
+                DimensionMode::Driving => s,
+                DimensionMode::Driven => format!("({s})"),
+            };
+            let length = |v: f64| text(round(v * SCALE, 2));
+
+            match dimension.kind {
+                DimensionKind::Distance(a, b) => {
+                    let (Some(p), Some(q)) = (system.point(x, a), system.point(x, b)) else {
+                        continue;
+                    };
+                    self.linear(p, q, q - p, middle, &length(dimension.value));
+                }
+                DimensionKind::Length(line) => {
+                    let Some((p, q)) = system.line(x, line) else {
+                        continue;
+                    };
+                    self.linear(p, q, q - p, middle, &length(dimension.value));
+                }
+                DimensionKind::HorizontalDistance(a, b) => {
+                    let (Some(p), Some(q)) = (system.point(x, a), system.point(x, b)) else {
+                        continue;
+                    };
+                    self.linear(p, q, DVec2::X, middle, &length(dimension.value));
+                }
+                DimensionKind::VerticalDistance(a, b) => {
+                    let (Some(p), Some(q)) = (system.point(x, a), system.point(x, b)) else {
+                        continue;
+                    };
+                    self.linear(p, q, DVec2::Y, middle, &length(dimension.value));
+                }
+                DimensionKind::PointLineDistance(a, line) => {
+                    let (Some(p), Some((l0, _)), Some(u)) = (
+                        system.point(x, a),
+                        system.line(x, line),
+                        system.direction(x, line),
+                    ) else {
+                        continue;
+                    };
+                    let foot = l0 + u * (p - l0).dot(u);
+                    self.linear(p, foot, foot - p, middle, &length(dimension.value));
+                }
+                DimensionKind::Radius(arc) => {
+                    let Some((center, radius)) = system.arc(x, arc) else {
+                        continue;
+                    };
+                    let rim = center + radius.abs() * DVec2::from_angle(PI / 4.);
+                    self.line("dimension", center, rim, r#" marker-end="url(#arrow)""#);
+                    let label = text(format!("R{}", round(dimension.value * SCALE, 2)));
+                    self.label(rim, DVec2::from_angle(PI / 4.), 0., &label);
+                }
+                DimensionKind::Diameter(arc) => {
+                    let Some((center, radius)) = system.arc(x, arc) else {
+                        continue;
+                    };
+                    let u = DVec2::from_angle(PI / 4.);
+                    self.line(
+                        "dimension",
+                        center - radius.abs() * u,
+                        center + radius.abs() * u,
+                        r#" marker-start="url(#arrow)" marker-end="url(#arrow)""#,
+                    );
+                    let label = text(format!("⌀{}", round(dimension.value * SCALE, 2)));
+                    self.label(center + radius.abs() * u, u, 0., &label);
+                }
+                DimensionKind::Angle(first, second) => {
+                    let (Some((a0, _)), Some((b0, _)), Some(u), Some(v)) = (
+                        system.line(x, first),
+                        system.line(x, second),
+                        system.direction(x, first),
+                        system.direction(x, second),
+                    ) else {
+                        continue;
+                    };
+                    let label = text(format!("{}°", round(dimension.value.to_degrees(), 2)));
+                    self.angular(a0, u, b0, v, dimension.value, &label);
+                }
+            }
+        }
+    }
+
+    /// A dimension of the distance from `p` to `q` measured along `along`, its line set off
+    /// to the side facing away from `middle`.
+    fn linear(&mut self, p: DVec2, q: DVec2, along: DVec2, middle: DVec2, label: &str) {
+        if along == DVec2::ZERO {
+            return;
+        }
+        let u = along.normalize();
+        let mut n = u.perp();
+        if n.dot((p + q) / 2. - middle) < 0. {
+            n = -n;
+        }
+
+        let gap = 2. * self.options.text_height / SCALE;
+        // Beyond both points, so extension lines only run outwards
+        let offset = p.dot(n).max(q.dot(n)) + gap;
+        let a = p + (offset - p.dot(n)) * n;
+        let b = p + u * (q - p).dot(u) + (offset - p.dot(n)) * n;
+        let overshoot = n * gap / 4.;
+
+        self.line("dimension", p, a + overshoot, "");
+        self.line("dimension", q, b + overshoot, "");
+        self.line(
+            "dimension",
+            a,
+            b,
+            r#" marker-start="url(#arrow)" marker-end="url(#arrow)""#,
+        );
+        self.label((a + b) / 2., n, angle(u), label);
+    }
+
+    /// An angle turning counter-clockwise from the line through `a` along `u` to the line
+    /// through `b` along `v`, drawn as an arc around where the lines cross.
+    fn angular(&mut self, a: DVec2, u: DVec2, b: DVec2, v: DVec2, value: f64, label: &str) {
+        if Tolerance::current().parallel(u.extend(0.), v.extend(0.)) {
+            return;
+        }
+        let cross = u.perp_dot(v);
+        let vertex = a + u * (b - a).perp_dot(v) / cross;
+        let radius = 4. * self.options.text_height / SCALE;
+
+        let start = vertex + radius * u;
+        let mut d = self.move_to(start);
+        self.arc_to(&mut d, vertex, start, value);
+        self.path(
+            "dimension",
+            &d,
+            r#" marker-start="url(#arrow)" marker-end="url(#arrow)""#,
+        );
+
+        let middle = DVec2::from_angle(angle(u) + value / 2.);
+        self.label(vertex + radius * middle, middle, 0., label);
+    }
+
+    /// Dimension text beside `at`, on the side `outwards` points to, running along the
+    /// direction at `rotation` radians from the x axis of the plane but never upside down.
+    fn label(&mut self, at: DVec2, outwards: DVec2, rotation: f64, label: &str) {
+        let h = self.options.text_height;
+        let at = self.map(at + outwards.normalize() * h * 0.75 / SCALE);
+        let mut degrees = -rotation.to_degrees();
+        if !(-90. ..90.).contains(&degrees) {
+            degrees = (degrees + 180.).rem_euclid(360.);
+            if degrees >= 180. {
+                degrees -= 360.;
+            }
+        }
+        // The text is centred on its baseline, so drop it by a third of its height
+        let baseline = at + DVec2::from_angle(degrees.to_radians()).perp() * h / 3.;
+
+        let half = DVec2::new(0.3 * h * label.chars().count() as f64, h);
+        self.include(at - half);
+        self.include(at + half);
+        let _ = writeln!(
+            self.body,
+            r#"<text class="dimension" x="{}" y="{}" transform="rotate({} {} {})">{label}</text>"#,
+            num(baseline.x),
+            num(baseline.y),
+            num(degrees),
+            num(baseline.x),
+            num(baseline.y)
+        );
+    }
+}
+
+/// The counter-clockwise angle of a direction from the x axis, in `[0, 2π)`.
+fn angle(v: DVec2) -> f64 {
+    v.y.atan2(v.x).rem_euclid(TAU)
+}
+
+/// The control points of the Bézier pieces a polynomial spline is made of, one piece of
+/// `degree + 1` points for every knot span.
+fn bezier_pieces(curve: &NurbsCurve) -> Vec<Vec<DVec3>> {
+    let p = curve.degree;
+    let (start, end) = curve.domain();
+    let mut curve = curve.clone();
+
+    let mut interior: Vec<f64> = curve.knots.clone();
+    interior.dedup();
+    for t in interior.into_iter().filter(|&t| t > start && t < end) {
+        let times = p.saturating_sub(curve.knots.iter().filter(|&&k| k == t).count());
+        if times > 0 {
+            // Cannot fail: the knot is interior and its multiplicity stays within the degree
+            let _ = curve.insert_knot(t, times);
+        }
+    }
+
+    // With every interior knot repeated `degree` times, the span ending at knot `i + 1`
+    // depends on the control points `i - degree..=i` alone
+    let n = curve.control_points.len();
+    (p..n)
+        .filter(|&i| curve.knots[i] < curve.knots[i + 1])
+        .map(|i| curve.control_points[i - p..=i].to_vec())
+        .collect()
+}
+
+/// A coordinate, rounded to a ten-thousandth of a millimetre.
+fn num(x: f64) -> String {
+    round(x, 4)
+}
+
+/// A number with at most `decimals` digits after the point and no trailing zeros.
+fn round(x: f64, decimals: i32) -> String {
+    let scale = 10f64.powi(decimals);
+    // Avoid writing `-0`
+    format!("{}", (x * scale).round() / scale + 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DVec2, DVec3};
+
+    use crate::arc::Arc;
+    use crate::circle::Circle;
+    use crate::nurbs::NurbsCurve;
+    use crate::test_util::line;
+    use crate::*;
+
+    fn view_box(svg: &str) -> [f64; 4] {
+        let start = svg.find("viewBox=\"").unwrap() + 9;
+        let end = start + svg[start..].find('"').unwrap();
+        let values: Vec<f64> = svg[start..end]
+            .split(' ')
+            .map(|v| v.parse().unwrap())
+            .collect();
+        values.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_sketch() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        line(&mut sketch, (0., 0.), (0.02, 0.));
+        // A quarter turn counter-clockwise, then a clockwise quarter turn up
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::new(0.02, 0.01, 0.),
+                DVec3::Z,
+                DVec3::new(0.02, 0., 0.),
+                DVec3::new(0.03, 0.01, 0.),
+            )
+            .unwrap(),
+        )));
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            Arc::new(
+                DVec3::new(0.04, 0.01, 0.),
+                -DVec3::Z,
+                DVec3::new(0.03, 0.01, 0.),
+                DVec3::new(0.04, 0.02, 0.),
+            )
+            .unwrap(),
+        )));
+        sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(DVec3::new(0.01, 0.02, 0.), DVec3::Z, 0.005).unwrap(),
+        )));
+        let axis = line(&mut sketch, (0.01, 0.), (0.01, 0.04));
+        sketch.set_construction(axis, true);
+
+        let mut drawing = SvgDrawing::new(SvgOptions::default());
+        drawing.add_sketch(&sketch);
+        let svg = drawing.to_svg();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains(r#"<line class="geometry" x1="0" y1="0" x2="20" y2="0"/>"#));
+        assert!(svg.contains(r#"<path class="geometry" d="M 20 0 A 10 10 0 0 0 30 -10"/>"#));
+        assert!(svg.contains(r#"<path class="geometry" d="M 30 -10 A 10 10 0 0 1 40 -20"/>"#));
+        assert!(svg.contains(r#"<circle class="geometry" cx="10" cy="-20" r="5"/>"#));
+        assert!(svg.contains(r#"<line class="construction" x1="10" y1="0" x2="10" y2="-40"/>"#));
+
+        // From x = 0 to 40 and y = 0 to 40, with the margin around
+        assert_eq!(view_box(&svg), [-5., -45., 50., 50.]);
+        assert!(svg.contains(r#"width="50mm" height="50mm""#));
+        assert!(!svg.contains("<text"));
+    }
+
+    #[test]
+    fn test_profile_with_hole() {
+        let plane = Plane::from_axes(DVec3::new(0., 0., 1.), DVec3::Y, DVec3::Z);
+        let world = |x: f64, y: f64| plane.to_world(DVec2::new(x, y));
+        let corners = [
+            world(0., 0.),
+            world(0.03, 0.),
+            world(0.03, 0.02),
+            world(0., 0.02),
+        ];
+        let boundary = BoundaryLoop {
+            elements: (0..4)
+                .map(|i| {
+                    BoundaryElement::BoundaryLine(BoundaryLine::new(
+                        corners[i],
+                        corners[(i + 1) % 4],
+                    ))
+                })
+                .collect(),
+        };
+        let hole = BoundaryLoop {
+            elements: vec![BoundaryElement::BoundaryArc(
+                BoundaryArc::new(
+                    world(0.015, 0.01),
+                    plane.normal,
+                    0.005,
+                    world(0.02, 0.01),
+                    world(0.02, 0.01),
+                    Direction::CW,
+                )
+                .unwrap(),
+            )],
+        };
+        let profile = BoundarySurface::new(plane.clone(), boundary, vec![hole]);
+
+        let mut drawing = SvgDrawing::new(SvgOptions {
+            margin: 0.,
+            ..Default::default()
+        });
+        drawing.add_profile(&profile);
+        let svg = drawing.to_svg();
+
+        assert!(svg.contains(concat!(
+            r#"<path class="profile" d="M 0 0 L 30 0 L 30 -20 L 0 -20 L 0 0 Z "#,
+            r#"M 20 -10 A 5 5 0 0 1 10 -10 A 5 5 0 0 1 20 -10 Z"/>"#
+        )));
+        assert_eq!(view_box(&svg), [0., -20., 30., 20.]);
+    }
+
+    #[test]
+    fn test_splines() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let points = [
+            (0., 0.),
+            (0.01, 0.02),
+            (0.02, -0.02),
+            (0.03, 0.),
+            (0.04, 0.01),
+        ]
+        .map(|(x, y)| DVec3::new(x, y, 0.));
+        let cubic = NurbsCurve::uniform(3, points.to_vec()).unwrap();
+        sketch.add_element(SketchElement::Spline(SketchSpline::Control(cubic.clone())));
+        let quarter = Arc::new(DVec3::ZERO, DVec3::Z, DVec3::X * 0.01, DVec3::Y * 0.01).unwrap();
+        sketch.add_element(SketchElement::Spline(SketchSpline::Control(
+            quarter.to_nurbs(),
+        )));
+
+        let mut drawing = SvgDrawing::new(SvgOptions::default());
+        drawing.add_sketch(&sketch);
+        let svg = drawing.to_svg();
+
+        // Two cubic pieces, and straight segments for the rational arc
+        let paths: Vec<&str> = svg.lines().filter(|l| l.starts_with("<path")).collect();
+        assert_eq!(paths[0].matches(" C ").count(), 2);
+        assert!(paths[0].starts_with(r#"<path class="geometry" d="M 0 0 C 10 -20 "#));
+        assert!(paths[0].ends_with(r#" 40 -10"/>"#));
+        assert!(paths[1].matches(" L ").count() > 4);
+
+        // The view box follows the curve rather than its control points
+        let [_, y, _, h] = view_box(&svg);
+        let low = (0..=100)
+            .map(|i| cubic.point_at(i as f64 / 100.).y)
+            .fold(0., f64::min);
+        assert!((y + h - 5. + low * 1000.).abs() < 0.1);
+        assert!(y + h - 5. < 20.);
+
+        // A knot repeated past the degree, as a hand-built curve may have, starts a new piece
+        let points = [
+            (0., 0.),
+            (0.01, 0.01),
+            (0.02, 0.),
+            (0.02, 0.),
+            (0.03, 0.01),
+            (0.04, 0.),
+        ];
+        let kinked = NurbsCurve {
+            degree: 2,
+            control_points: points.map(|(x, y)| DVec3::new(x, y, 0.)).to_vec(),
+            weights: vec![1.; 6],
+            knots: vec![0., 0., 0., 1., 1., 1., 2., 2., 2.],
+        };
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(SketchElement::Spline(SketchSpline::Control(kinked)));
+        let mut drawing = SvgDrawing::new(SvgOptions::default());
+        drawing.add_sketch(&sketch);
+        assert!(drawing
+            .to_svg()
+            .contains(r#"d="M 0 0 Q 10 -10 20 0 Q 30 -10 40 0""#));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        let bottom = line(&mut sketch, (0., 0.), (0.02, 0.));
+        let side = line(&mut sketch, (0.02, 0.), (0.02, 0.01));
+        let hole = sketch.add_element(SketchElement::Circle(SketchCircle(
+            Circle::new(DVec3::new(0.01, 0.005, 0.), DVec3::Z, 0.0025).unwrap(),
+        )));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Length(bottom), 0.02));
+        sketch.add_dimension(Dimension::driven(DimensionKind::Diameter(hole)));
+        sketch.add_dimension(Dimension::driving(DimensionKind::Radius(hole), 0.0025));
+        sketch.add_dimension(Dimension::driving(
+            DimensionKind::Angle(bottom, side),
+            std::f64::consts::FRAC_PI_2,
+        ));
+
+        let plain = SvgDrawing::new(SvgOptions::default());
+        let mut drawing = SvgDrawing::new(SvgOptions {
+            dimensions: true,
+            ..Default::default()
+        });
+        drawing.add_sketch(&sketch);
+        let svg = drawing.to_svg();
+        assert!(!plain.to_svg().contains("<text"));
+
+        let texts: Vec<&str> = svg
+            .lines()
+            .filter(|l| l.starts_with("<text"))
+            .map(|l| &l[l.find('>').unwrap() + 1..l.rfind('<').unwrap()])
+            .collect();
+        assert_eq!(texts, ["20", "(⌀5)", "R2.5", "90°"]);
+
+        // The length is set off below the bottom line, away from the rest of the sketch
+        let dimension_line = svg
+            .lines()
+            .find(|l| l.contains("marker-start") && l.starts_with("<line"))
+            .unwrap();
+        assert!(dimension_line.contains(r#"x1="0" y1="7" x2="20" y2="7""#));
+        let [_, y, _, h] = view_box(&svg);
+        assert!(y + h > 7. + 5.);
+    }
+}
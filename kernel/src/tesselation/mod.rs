@@ -0,0 +1,115 @@
+//! Triangle meshes for display and export.
+
+mod planar;
+mod solid;
+mod tolerance;
+mod triangulate;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use glam::DVec3;
+
+use crate::FaceId;
+
+pub use tolerance::*;
+pub(crate) use triangulate::triangulate;
+
+/// An indexed triangle mesh. Triangles run counter-clockwise seen from the side their normals
+/// point to.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<DVec3>,
+    pub normals: Vec<DVec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the triangles of another mesh to this one.
+    pub fn append(&mut self, other: &Mesh) {
+        let offset = self.positions.len() as u32;
+
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.indices
+            .extend(other.indices.iter().map(|t| t.map(|i| i + offset)));
+    }
+
+    /// The total area of the triangles.
+    pub fn area(&self) -> f64 {
+        self.indices
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| self.positions[i as usize]);
+                (b - a).cross(c - a).length() / 2.
+            })
+            .sum()
+    }
+
+    /// The volume enclosed by the triangles, positive when they face outwards. Only meaningful
+    /// for closed meshes.
+    pub fn volume(&self) -> f64 {
+        self.indices
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| self.positions[i as usize]);
+                a.dot(b.cross(c)) / 6.
+            })
+            .sum()
+    }
+
+    /// The number of triangle edges not matched by exactly one edge running the other way
+    /// between the same positions. A closed, consistently oriented mesh has none.
+    pub fn open_edges(&self) -> usize {
+        let key = |p: DVec3| p.to_array().map(f64::to_bits);
+
+        let mut counts: HashMap<_, i64> = HashMap::new();
+        for t in &self.indices {
+            let [a, b, c] = t.map(|i| key(self.positions[i as usize]));
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *counts.entry((from, to)).or_default() += 1;
+            }
+        }
+
+        counts
+            .iter()
+            .map(|(&(from, to), &n)| {
+                let back = counts.get(&(to, from)).copied().unwrap_or(0);
+                if n == 1 && back == 1 {
+                    0
+                } else {
+                    n as usize
+                }
+            })
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TesselationError {
+    /// A boundary loop has no elements.
+    EmptyLoop,
+    /// An element of a boundary loop does not start where the previous one ends.
+    OpenLoop,
+    /// The loops of a face cannot be laid out flat in the parameters of its surface, as when
+    /// they wrap around a cylinder without a seam edge.
+    UnsupportedFace(FaceId),
+}
+
+impl fmt::Display for TesselationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TesselationError::EmptyLoop => write!(f, "boundary loop has no elements"),
+            TesselationError::OpenLoop => write!(f, "boundary loop is not closed"),
+            TesselationError::UnsupportedFace(id) => {
+                write!(f, "face {} cannot be laid out flat", id.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TesselationError {}
@@ -0,0 +1,177 @@
+use glam::{DVec2, DVec3};
+
+use crate::{profile_loops, BoundarySurface, LoopError, Tolerance};
+
+use super::{triangulate, Mesh, TesselationError, TesselationTolerance};
+
+impl BoundarySurface {
+    /// Triangulates the region, splitting arcs and curves into straight pieces within
+    /// `tolerance`. The
+    /// triangles run counter-clockwise around the plane normal, which is also the normal of
+    /// every vertex. The same region always gives the same mesh.
+    pub fn tesselate(&self, tolerance: &TesselationTolerance) -> Result<Mesh, TesselationError> {
+        let normal = self.plane.normal;
+        let loops = profile_loops(self, normal).map_err(|e| match e {
+            LoopError::Empty => TesselationError::EmptyLoop,
+            LoopError::Open => TesselationError::OpenLoop,
+        })?;
+
+        // Consecutive boundary points that coincide are merged
+        let merge = Tolerance::current();
+        let mut loops = loops.into_iter().map(|segments| {
+            let mut points: Vec<DVec3> = Vec::new();
+            for segment in segments {
+                // Where the segment is split, from its start up to but not including its end
+                let parameters: Vec<f64> = if let Some(curve) = &segment.curve {
+                    let (start, end) = curve.domain();
+                    let mut parameters =
+                        tolerance.curve_parameters(start, end, |t| curve.point_at(t));
+                    parameters.pop();
+                    parameters
+                        .iter()
+                        .map(|t| (t - start) / (end - start))
+                        .collect()
+                } else {
+                    let pieces = match segment.arc {
+                        None => 1,
+                        Some(arc) => tolerance.arc_segments(arc.radius, segment.sweep()).max(2),
+                    };
+                    (0..pieces).map(|k| k as f64 / pieces as f64).collect()
+                };
+                for t in parameters {
+                    let p = segment.point(t);
+                    if points.last().is_none_or(|&last| !merge.coincident(last, p)) {
+                        points.push(p);
+                    }
+                }
+            }
+            while points.len() > 1 && merge.coincident(points[0], points[points.len() - 1]) {
+                points.pop();
+            }
+            points
+        });
+
+        let outer = loops.next().unwrap_or_default();
+        if outer.len() < 3 {
+            return Ok(Mesh::new());
+        }
+        let holes: Vec<Vec<DVec3>> = loops.filter(|l| l.len() >= 3).collect();
+
+        let origin = self.plane.center;
+        let (x, y) = (self.plane.x_axis, self.plane.y_axis());
+        let flat = |l: &Vec<DVec3>| -> Vec<DVec2> {
+            l.iter()
+                .map(|&p| DVec2::new((p - origin).dot(x), (p - origin).dot(y)))
+                .collect()
+        };
+
+        let triangles = triangulate(&flat(&outer), &holes.iter().map(flat).collect::<Vec<_>>());
+
+        let positions: Vec<DVec3> = std::iter::once(outer).chain(holes).flatten().collect();
+        Ok(Mesh {
+            normals: vec![normal; positions.len()],
+            positions,
+            indices: triangles.into_iter().map(|t| t.map(|i| i as u32)).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use glam::DVec3;
+
+    use crate::*;
+
+    fn polygon(corners: &[DVec3]) -> BoundaryLoop {
+        BoundaryLoop {
+            elements: (0..corners.len())
+                .map(|i| {
+                    BoundaryElement::BoundaryLine(BoundaryLine::new(
+                        corners[i],
+                        corners[(i + 1) % corners.len()],
+                    ))
+                })
+                .collect(),
+        }
+    }
+
+    fn circle(center: DVec3, radius: f64, start: DVec3, direction: Direction) -> BoundaryLoop {
+        BoundaryLoop {
+            elements: vec![BoundaryElement::BoundaryArc(
+                BoundaryArc::new(center, DVec3::Z, radius, start, start, direction).unwrap(),
+            )],
+        }
+    }
+
+    /// Asserts that every triangle winds counter-clockwise around `normal`.
+    fn assert_facing(mesh: &Mesh, normal: DVec3) {
+        for t in &mesh.indices {
+            let [a, b, c] = t.map(|i| mesh.positions[i as usize]);
+            assert!((b - a).cross(c - a).dot(normal) > 0.);
+        }
+        assert!(mesh.normals.iter().all(|&n| n == normal));
+    }
+
+    #[test]
+    fn test_square_with_round_hole() {
+        let outer = polygon(&[
+            DVec3::new(-2., -2., 0.),
+            DVec3::new(2., -2., 0.),
+            DVec3::new(2., 2., 0.),
+            DVec3::new(-2., 2., 0.),
+        ]);
+        let hole = circle(DVec3::ZERO, 1., DVec3::X, Direction::CW);
+        let surface = BoundarySurface::new(Plane::XY, outer, vec![hole]);
+        let tolerance = TesselationTolerance::new(1., PI / 8.);
+
+        let mesh = surface.tesselate(&tolerance).unwrap();
+
+        assert_eq!(mesh.positions.len(), 4 + 16);
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        // A polygon with one hole splits into as many triangles as it has vertices
+        assert_eq!(mesh.indices.len(), 20);
+        let polygon_area = 16. - 8. * (PI / 8.).sin();
+        assert!((mesh.area() - polygon_area).abs() < 1e-4);
+        assert_facing(&mesh, DVec3::Z);
+
+        // The mesh is the same every time
+        assert_eq!(surface.tesselate(&tolerance).unwrap(), mesh);
+    }
+
+    #[test]
+    fn test_follows_plane_orientation() {
+        // The loops are given clockwise around the normal and get turned around
+        let plane = Plane::new(DVec3::new(0., -1., 1.), DVec3::ZERO);
+        let outer = polygon(&[
+            DVec3::new(0., 0., 0.),
+            DVec3::new(0., 2., 2.),
+            DVec3::new(3., 2., 2.),
+            DVec3::new(3., 1., 1.),
+            DVec3::new(1., 1., 1.),
+            DVec3::new(1., 0., 0.),
+        ]);
+        let surface = BoundarySurface::new(plane, outer, Vec::new());
+        let tolerance = TesselationTolerance::default();
+
+        let mesh = surface.tesselate(&tolerance).unwrap();
+
+        assert_eq!(mesh.indices.len(), 4);
+        assert!((mesh.area() - 4. * 2f64.sqrt()).abs() < 1e-4);
+        assert_facing(&mesh, surface.plane.normal);
+    }
+
+    #[test]
+    fn test_open_loop() {
+        let mut outer = polygon(&[DVec3::ZERO, DVec3::X, DVec3::Y]);
+        outer.elements.pop();
+        let surface = BoundarySurface::new(Plane::XY, outer, Vec::new());
+        let tolerance = TesselationTolerance::default();
+
+        assert_eq!(
+            surface.tesselate(&tolerance),
+            Err(TesselationError::OpenLoop)
+        );
+    }
+}
@@ -0,0 +1,1003 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f64::consts::TAU;
+
+use glam::{DVec2, DVec3};
+
+use crate::curve::Curve;
+use crate::surface::Surface;
+use crate::transform::Transform;
+use crate::{Brep, EdgeId, FaceId, Plane, SolidId, Tolerance};
+
+use super::{triangulate, Mesh, TesselationError, TesselationTolerance};
+
+/// The most interior edges split while refining the triangles of one face.
+const MAX_SPLITS: usize = 100_000;
+
+impl Brep {
+    /// Triangulates the faces of a solid within `tolerance`. Every edge is split once and
+    /// shared by the faces on either side, so a closed solid gives a closed mesh. Triangles run
+    /// counter-clockwise around the face normals, and vertices carry the face normal.
+    pub fn tesselate_solid(
+        &self,
+        id: SolidId,
+        tolerance: &TesselationTolerance,
+    ) -> Result<Mesh, TesselationError> {
+        let mut edges = HashMap::new();
+        let mut mesh = Mesh::new();
+
+        for face in self.solid_faces(id) {
+            mesh.append(&self.tesselate_face(face, tolerance, &mut edges)?);
+        }
+
+        Ok(mesh)
+    }
+
+    /// The points of an edge from its start to its end, split within the tolerance.
+    pub(crate) fn edge_points(&self, id: EdgeId, tolerance: &TesselationTolerance) -> Vec<DVec3> {
+        let edge = self.edge(id);
+        let (start, end) = (
+            self.vertex(edge.start).point.0,
+            self.vertex(edge.end).point.0,
+        );
+
+        let mut t0 = edge.curve.parameter_of(start);
+        let mut t1 = edge.curve.parameter_of(end);
+        match &edge.curve {
+            Curve::Line(_) => return vec![start, end],
+            Curve::Circle(_) | Curve::Ellipse(_) => {
+                if t1 <= t0 + Tolerance::current().parametric {
+                    t1 += TAU;
+                }
+            }
+            Curve::Nurbs(nurbs) => {
+                if edge.start == edge.end {
+                    (t0, t1) = nurbs.domain();
+                }
+            }
+        }
+
+        let parameters = tolerance.curve_parameters(t0, t1, |t| edge.curve.point_at(t));
+        let mut points: Vec<DVec3> = parameters.iter().map(|&t| edge.curve.point_at(t)).collect();
+        let last = points.len() - 1;
+        points[0] = start;
+        points[last] = end;
+        points
+    }
+
+    pub(crate) fn tesselate_face(
+        &self,
+        id: FaceId,
+        tolerance: &TesselationTolerance,
+        edges: &mut HashMap<EdgeId, Vec<DVec3>>,
+    ) -> Result<Mesh, TesselationError> {
+        let face = self.face(id);
+
+        // The points of every coedge from its start to its end, and whether the face uses its
+        // edge twice
+        let loops: Vec<Vec<BoundaryPiece>> = self
+            .face_loops(id)
+            .into_iter()
+            .map(|l| {
+                self.loop_coedges(l)
+                    .into_iter()
+                    .map(|c| {
+                        let coedge = self.coedge(c);
+                        let mut points = edges
+                            .entry(coedge.edge)
+                            .or_insert_with(|| self.edge_points(coedge.edge, tolerance))
+                            .clone();
+                        if coedge.reversed {
+                            points.reverse();
+                        }
+                        let uses = self.edge(coedge.edge).coedges.iter();
+                        let seam = uses.filter(|&&u| self.coedge_face(u) == id).count() > 1;
+                        BoundaryPiece {
+                            edge: coedge.edge,
+                            seam,
+                            points,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // The first chart the face lies flat in, preferring one where the outer loop encloses
+        // some area
+        let mut fallback = None;
+        let mut found = None;
+        for chart in Chart::candidates(self, id) {
+            let Some(flat) = chart.flatten(&loops, face.reversed) else {
+                continue;
+            };
+            if signed_area(&flat[0]) > 0. {
+                found = Some((chart, flat));
+                break;
+            }
+            fallback.get_or_insert((chart, flat));
+        }
+        let (chart, flat) = found
+            .or(fallback)
+            .ok_or(TesselationError::UnsupportedFace(id))?;
+
+        if flat[0].len() < 3 {
+            return Ok(Mesh::new());
+        }
+        let kept: Vec<&Vec<FlatPoint>> = std::iter::once(&flat[0])
+            .chain(flat[1..].iter().filter(|l| l.len() >= 3))
+            .collect();
+
+        let mut points: Vec<FlatPoint> = kept.iter().flat_map(|l| l.iter().copied()).collect();
+        let mut boundary = HashSet::new();
+        let mut offset = 0;
+        for l in &kept {
+            let n = l.len();
+            for i in 0..n {
+                let (a, b) = (offset + i, offset + (i + 1) % n);
+                boundary.insert((a.min(b), a.max(b)));
+            }
+            offset += n;
+        }
+
+        // Reversed faces run clockwise in the parameters, so they are triangulated with v
+        // turned around
+        let flip = |p: &FlatPoint| {
+            if face.reversed {
+                DVec2::new(p.uv.x, -p.uv.y)
+            } else {
+                p.uv
+            }
+        };
+        let outer: Vec<DVec2> = kept[0].iter().map(flip).collect();
+        let holes: Vec<Vec<DVec2>> = kept[1..]
+            .iter()
+            .map(|l| l.iter().map(flip).collect())
+            .collect();
+        let mut triangles = triangulate(&outer, &holes);
+
+        if !matches!(face.surface, Surface::Plane(_)) {
+            refine(
+                &chart,
+                tolerance,
+                face.reversed,
+                &mut points,
+                &mut triangles,
+                &boundary,
+            );
+        }
+
+        // Triangles with two corners at a pole add nothing, and their other edges cancel
+        triangles.retain(|t| {
+            let [a, b, c] = t.map(|i| points[i].position);
+            a != b && b != c && c != a
+        });
+
+        let sign = if face.reversed { -1. } else { 1. };
+        Ok(Mesh {
+            normals: points
+                .iter()
+                .map(|p| sign * chart.normal(p.uv).normalize_or_zero())
+                .collect(),
+            positions: points.iter().map(|p| p.position).collect(),
+            indices: triangles.into_iter().map(|t| t.map(|i| i as u32)).collect(),
+        })
+    }
+}
+
+/// The use of an edge by a loop, as points from where the loop enters it to where it leaves.
+struct BoundaryPiece {
+    edge: EdgeId,
+    /// Whether the face runs along the edge twice, once on either side of a seam.
+    seam: bool,
+    points: Vec<DVec3>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FlatPoint {
+    uv: DVec2,
+    position: DVec3,
+}
+
+/// The parameters of a face's surface, in which its loops are laid out flat. Spheres get a
+/// frame of their own, so their poles can be put where the face allows.
+struct Chart<'a> {
+    /// The surface laid out, which a transformed surface is the image of.
+    surface: &'a Surface,
+    /// For spheres, the frame whose normal runs through the poles.
+    frame: Option<Plane>,
+    /// For transformed surfaces, the transform and its inverse.
+    transform: Option<(Transform, Transform)>,
+}
+
+impl<'a> Chart<'a> {
+    /// The charts to try for a face, most likely first. A sphere swept around an axis is best
+    /// laid out with its poles on that axis, which the circles and arcs bounding it point to.
+    fn candidates(brep: &'a Brep, id: FaceId) -> Vec<Chart<'a>> {
+        let (surface, transform) = match &brep.face(id).surface {
+            Surface::Transformed { surface, transform } => {
+                (surface.as_ref(), Some((*transform, transform.inverse())))
+            }
+            surface => (surface, None),
+        };
+        let back = |p: DVec3| transform.map_or(p, |(_, inverse)| inverse.point(p));
+        let &Surface::Sphere { center, radius } = surface else {
+            return vec![Chart {
+                surface,
+                frame: None,
+                transform,
+            }];
+        };
+
+        let tolerance = Tolerance::current();
+        let on_pole = |axis: DVec3, p: DVec3| {
+            tolerance.coincident(p, center + radius * axis)
+                || tolerance.coincident(p, center - radius * axis)
+        };
+        let vertices: Vec<DVec3> = brep
+            .face_edges(id)
+            .iter()
+            .flat_map(|&e| [brep.edge(e).start, brep.edge(e).end])
+            .map(|v| back(brep.vertex(v).point.0))
+            .collect();
+
+        // Parallels first, then great circles with a vertex at their pole, then arcs between
+        // opposite points, then any other great circle
+        let mut ranked: Vec<(u8, DVec3)> = Vec::new();
+        for e in brep.face_edges(id) {
+            let edge = brep.edge(e);
+            let (start, end) = (
+                back(brep.vertex(edge.start).point.0),
+                back(brep.vertex(edge.end).point.0),
+            );
+            let curve = match transform {
+                Some((_, inverse)) => edge.curve.transformed(&inverse),
+                None => edge.curve.clone(),
+            };
+            if let Curve::Circle(circle) = &curve {
+                let rank = if !tolerance.coincident(circle.center, center) {
+                    0
+                } else if vertices.iter().any(|&p| on_pole(circle.normal, p)) {
+                    1
+                } else {
+                    3
+                };
+                ranked.push((rank, circle.normal));
+            }
+            if tolerance.coincident(start + end, 2. * center) && edge.start != edge.end {
+                ranked.push((2, (end - start).normalize()));
+            }
+        }
+        ranked.sort_by_key(|&(rank, _)| rank);
+
+        ranked
+            .into_iter()
+            .map(|(_, axis)| axis)
+            .chain([DVec3::Z, DVec3::X, DVec3::Y])
+            .map(|axis| Chart {
+                surface,
+                frame: Some(Plane::new(axis, center)),
+                transform,
+            })
+            .collect()
+    }
+
+    /// The point on the laid out surface that `p` is the image of.
+    fn back(&self, p: DVec3) -> DVec3 {
+        self.transform.map_or(p, |(_, inverse)| inverse.point(p))
+    }
+
+    fn uv(&self, p: DVec3) -> DVec2 {
+        let p = self.back(p);
+        match (self.surface, &self.frame) {
+            (Surface::Sphere { center, .. }, Some(frame)) => {
+                let d = p - *center;
+                let (x, y) = (d.dot(frame.x_axis), d.dot(frame.y_axis()));
+                let z = d.dot(frame.normal);
+                DVec2::new(y.atan2(x).rem_euclid(TAU), z.atan2(x.hypot(y)))
+            }
+            _ => {
+                let (u, v) = self.surface.parameters_of(p);
+                DVec2::new(u, v)
+            }
+        }
+    }
+
+    fn point(&self, uv: DVec2) -> DVec3 {
+        let p = self.base_point(self.wrap(uv));
+        self.transform
+            .map_or(p, |(transform, _)| transform.point(p))
+    }
+
+    fn base_point(&self, uv: DVec2) -> DVec3 {
+        match (self.surface, &self.frame) {
+            (Surface::Sphere { center, radius }, Some(frame)) => {
+                let (sin_u, cos_u) = uv.x.sin_cos();
+                let (sin_v, cos_v) = uv.y.sin_cos();
+                let ring = cos_u * frame.x_axis + sin_u * frame.y_axis();
+                *center + *radius * (cos_v * ring + sin_v * frame.normal)
+            }
+            _ => self.surface.point_at(uv.x, uv.y),
+        }
+    }
+
+    /// The partial derivatives along u and along v.
+    fn derivatives(&self, uv: DVec2) -> (DVec3, DVec3) {
+        let (du, dv) = self.base_derivatives(self.wrap(uv));
+        match self.transform {
+            Some((transform, _)) => (transform.vector(du), transform.vector(dv)),
+            None => (du, dv),
+        }
+    }
+
+    fn base_derivatives(&self, uv: DVec2) -> (DVec3, DVec3) {
+        match (self.surface, &self.frame) {
+            (Surface::Sphere { radius, .. }, Some(frame)) => {
+                let (sin_u, cos_u) = uv.x.sin_cos();
+                let (sin_v, cos_v) = uv.y.sin_cos();
+                let ring = cos_u * frame.x_axis + sin_u * frame.y_axis();
+                let turn = cos_u * frame.y_axis() - sin_u * frame.x_axis;
+                (
+                    *radius * cos_v * turn,
+                    *radius * (cos_v * frame.normal - sin_v * ring),
+                )
+            }
+            _ => {
+                let (_, du, dv) = self.surface.derivatives_at(uv.x, uv.y);
+                (du, dv)
+            }
+        }
+    }
+
+    /// The surface normal, before the face orientation is applied.
+    fn normal(&self, uv: DVec2) -> DVec3 {
+        let uv = self.wrap(uv);
+        let normal = match self.surface {
+            Surface::Sphere { center, .. } => self.base_point(uv) - *center,
+            _ => self.surface.normal_at_parameters(uv.x, uv.y),
+        };
+        self.transform
+            .map_or(normal, |(transform, _)| transform.normal(normal))
+    }
+
+    /// How far u and v run before they repeat: a full turn around an axis, or the domain
+    /// of a NURBS surface along a direction in which it closes up.
+    fn periods(&self) -> [Option<f64>; 2] {
+        match self.surface {
+            Surface::Cylinder { .. } | Surface::Cone { .. } | Surface::Sphere { .. } => {
+                [Some(TAU), None]
+            }
+            Surface::Torus { .. } => [Some(TAU), Some(TAU)],
+            Surface::Nurbs(nurbs) => {
+                let tolerance = Tolerance::current();
+                let rows = &nurbs.control_points;
+                let ((u0, u1), (v0, v1)) = nurbs.domain();
+                let u_closed = rows[0]
+                    .iter()
+                    .zip(&rows[rows.len() - 1])
+                    .all(|(&a, &b)| tolerance.coincident(a, b));
+                let v_closed = rows
+                    .iter()
+                    .all(|row| tolerance.coincident(row[0], row[row.len() - 1]));
+                [u_closed.then_some(u1 - u0), v_closed.then_some(v1 - v0)]
+            }
+            Surface::Plane(_) | Surface::Transformed { .. } => [None, None],
+        }
+    }
+
+    /// Moves `uv` by whole periods into the domain of a NURBS surface, which is only defined
+    /// there.
+    fn wrap(&self, mut uv: DVec2) -> DVec2 {
+        if let Surface::Nurbs(nurbs) = self.surface {
+            let ((u0, _), (v0, _)) = nurbs.domain();
+            for ((d, period), low) in self.periods().into_iter().enumerate().zip([u0, v0]) {
+                if let Some(period) = period {
+                    uv[d] = low + (uv[d] - low).rem_euclid(period);
+                }
+            }
+        }
+        uv
+    }
+
+    /// Whether `p` is where the whole range of u meets, the poles of a sphere or the apex of
+    /// a cone.
+    fn is_singular(&self, p: DVec3) -> bool {
+        let tolerance = Tolerance::current();
+        let p = self.back(p);
+        match (self.surface, &self.frame) {
+            (Surface::Sphere { center, radius }, Some(frame)) => {
+                tolerance.coincident(p, *center + *radius * frame.normal)
+                    || tolerance.coincident(p, *center - *radius * frame.normal)
+            }
+            (Surface::Cone { apex, .. }, _) => tolerance.coincident(p, *apex),
+            _ => false,
+        }
+    }
+
+    /// Moves `uv` by whole periods to where it is closest to `near`.
+    fn unwrap(&self, mut uv: DVec2, near: DVec2) -> DVec2 {
+        for (d, period) in self.periods().into_iter().enumerate() {
+            if let Some(period) = period {
+                uv[d] += ((near[d] - uv[d]) / period).round() * period;
+            }
+        }
+        uv
+    }
+
+    /// Lays out the loops of a face in the parameters, the outer loop first. Each loop runs on
+    /// from the point before it; the second use of a seam lies a period away from the first, on
+    /// the side the face is on, and poles get a point for each side they are reached from.
+    /// Gives `None` when a loop does not close, as when it wraps around the surface without a
+    /// seam.
+    fn flatten(&self, loops: &[Vec<BoundaryPiece>], reversed: bool) -> Option<Vec<Vec<FlatPoint>>> {
+        let mut flat: Vec<Vec<FlatPoint>> = Vec::new();
+        for pieces in loops {
+            let mut points = self.flatten_loop(pieces, reversed)?;
+
+            // Holes are moved by whole periods to lie over the outer loop
+            if let Some(outer) = flat.first() {
+                for (d, period) in self.periods().into_iter().enumerate() {
+                    let low = outer.iter().map(|p| p.uv[d]).fold(f64::INFINITY, f64::min);
+                    let mean =
+                        points.iter().map(|p| p.uv[d]).sum::<f64>() / points.len().max(1) as f64;
+                    if let (Some(period), true) = (period, low.is_finite()) {
+                        let shift = ((mean - low) / period).floor() * period;
+                        for p in &mut points {
+                            p.uv[d] -= shift;
+                        }
+                    }
+                }
+            }
+            flat.push(points);
+        }
+        Some(flat)
+    }
+
+    fn flatten_loop(&self, pieces: &[BoundaryPiece], reversed: bool) -> Option<Vec<FlatPoint>> {
+        let periods = self.periods();
+
+        // Poles have no u yet, so it is left as NaN
+        let mut out: Vec<FlatPoint> = Vec::new();
+        let mut seams: HashMap<EdgeId, f64> = HashMap::new();
+        let mut last: Option<DVec2> = None;
+
+        for piece in pieces {
+            let mut previous = last;
+            let mut uvs: Vec<DVec2> = Vec::with_capacity(piece.points.len());
+            for &p in &piece.points {
+                let uv = self.uv(p);
+                if self.is_singular(p) {
+                    uvs.push(DVec2::new(f64::NAN, uv.y));
+                    continue;
+                }
+                let uv = previous.map_or(uv, |l| self.unwrap(uv, l));
+                previous = Some(uv);
+                uvs.push(uv);
+            }
+
+            let known: Vec<DVec2> = uvs.iter().filter(|uv| !uv.x.is_nan()).copied().collect();
+            if piece.seam && !known.is_empty() {
+                let span = |values: &mut dyn Iterator<Item = f64>| {
+                    let (low, high) = values
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), x| {
+                            (l.min(x), h.max(x))
+                        });
+                    high - low
+                };
+                let u_span = span(&mut known.iter().map(|uv| uv.x));
+                let v_span = span(&mut uvs.iter().map(|uv| uv.y));
+                // A seam keeps one parameter fixed and runs along the other
+                let d = if u_span < v_span { 0 } else { 1 };
+
+                if let Some(period) = periods[d] {
+                    match seams.get(&piece.edge) {
+                        None => {
+                            seams.insert(piece.edge, known[0][d]);
+                        }
+                        Some(&placed) => {
+                            // The face lies left of the loop, or right of it when reversed
+                            let mut side = if d == 0 {
+                                (uvs[uvs.len() - 1].y - uvs[0].y).signum()
+                            } else {
+                                -(known[known.len() - 1].x - known[0].x).signum()
+                            };
+                            if reversed {
+                                side = -side;
+                            }
+                            let target = placed + side * period;
+                            let shift = ((target - known[0][d]) / period).round() * period;
+                            for uv in &mut uvs {
+                                uv[d] += shift;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(&end) = uvs.iter().rev().find(|uv| !uv.x.is_nan()) {
+                last = Some(end);
+            }
+            let n = piece.points.len() - 1;
+            out.extend(
+                uvs.into_iter()
+                    .zip(&piece.points)
+                    .take(n)
+                    .map(|(uv, &position)| FlatPoint { uv, position }),
+            );
+        }
+
+        // The loop has to come back to where it started
+        let tolerance = Tolerance::current();
+        let first = out.first()?;
+        if !first.uv.x.is_nan() && last?.distance(first.uv) > tolerance.parametric {
+            return None;
+        }
+
+        // Poles take the u of the points on either side of them
+        let n = out.len();
+        let mut flat: Vec<FlatPoint> = Vec::new();
+        for i in 0..n {
+            let point = out[i];
+            if point.uv.x.is_nan() {
+                let find = |step: usize| {
+                    (1..n)
+                        .map(|k| out[(i + n + step * k) % n].uv)
+                        .find(|uv| !uv.x.is_nan())
+                };
+                let (before, after) = (find(n - 1)?, find(1)?);
+                flat.push(FlatPoint {
+                    uv: DVec2::new(before.x, point.uv.y),
+                    ..point
+                });
+                flat.push(FlatPoint {
+                    uv: DVec2::new(after.x, point.uv.y),
+                    ..point
+                });
+            } else {
+                flat.push(point);
+            }
+        }
+
+        // Points that land on each other are merged
+        let same = |a: DVec2, b: DVec2| a.distance(b) <= tolerance.parametric;
+        flat.dedup_by(|b, a| same(a.uv, b.uv));
+        while flat.len() > 1 && same(flat[0].uv, flat[flat.len() - 1].uv) {
+            flat.pop();
+        }
+
+        Some(flat)
+    }
+}
+
+/// Twice the signed area of a loop in the parameters, positive when it runs
+/// counter-clockwise.
+fn signed_area(points: &[FlatPoint]) -> f64 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].uv.perp_dot(points[(i + 1) % n].uv))
+        .sum()
+}
+
+/// Splits interior edges at their middle in the parameters until every one follows the
+/// surface within the tolerance, flipping edges after every split so the triangles stay
+/// Delaunay and keep a good shape. Boundary edges are left alone, so the faces on either side
+/// of an edge keep meeting at the same points.
+fn refine(
+    chart: &Chart,
+    tolerance: &TesselationTolerance,
+    reversed: bool,
+    points: &mut Vec<FlatPoint>,
+    triangles: &mut Vec<[usize; 3]>,
+    boundary: &HashSet<(usize, usize)>,
+) {
+    let too_far = |a: &FlatPoint, b: &FlatPoint, middle: &FlatPoint| {
+        let deviation = middle.position.distance((a.position + b.position) / 2.);
+        let turn = chart.normal(a.uv).angle_between(chart.normal(b.uv));
+        deviation > tolerance.chord || (tolerance.angle > 0. && turn > tolerance.angle)
+    };
+
+    // How far the surface moves for a step in u and in v, so triangles are judged by their
+    // shape on the surface rather than in the parameters. Reversed faces run with v turned
+    // around.
+    let scale = points.iter().fold(DVec2::ZERO, |sum, p| {
+        let (du, dv) = chart.derivatives(p.uv);
+        sum + DVec2::new(du.length(), dv.length())
+    }) / points.len() as f64;
+    let flip = if reversed { -1. } else { 1. };
+    let scale = DVec2::new(scale.x.max(1e-12), flip * scale.y.max(1e-12));
+
+    let mut mesh = Triangulation::new(points, triangles, boundary, scale);
+    let mut edges: Vec<(usize, usize)> = mesh.sharing.keys().copied().collect();
+    edges.sort_unstable();
+    mesh.legalize(edges);
+
+    // Every edge left after flipping is checked, not only those of the first triangles
+    let mut edges: Vec<(usize, usize)> = mesh.sharing.keys().copied().collect();
+    edges.sort_unstable();
+
+    let mut queue: VecDeque<(usize, usize)> = edges.into();
+    let mut splits = 0;
+    while let Some(edge) = queue.pop_front() {
+        if splits >= MAX_SPLITS {
+            break;
+        }
+        if boundary.contains(&edge) || !mesh.sharing.contains_key(&edge) {
+            continue;
+        }
+        let (a, b) = edge;
+        let uv = (mesh.points[a].uv + mesh.points[b].uv) / 2.;
+        let middle = FlatPoint {
+            uv,
+            position: chart.point(uv),
+        };
+        if !too_far(&mesh.points[a], &mesh.points[b], &middle) {
+            continue;
+        }
+
+        queue.extend(mesh.split(edge, middle));
+        splits += 1;
+    }
+}
+
+/// Triangles together with the triangles using each edge, kept up to date as they change.
+struct Triangulation<'a> {
+    points: &'a mut Vec<FlatPoint>,
+    triangles: &'a mut Vec<[usize; 3]>,
+    sharing: HashMap<(usize, usize), Vec<usize>>,
+    boundary: &'a HashSet<(usize, usize)>,
+    /// Stretches the parameters to the shape of the surface, see [`refine`].
+    scale: DVec2,
+}
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn triangle_edges(t: [usize; 3]) -> [(usize, usize); 3] {
+    [
+        edge_key(t[0], t[1]),
+        edge_key(t[1], t[2]),
+        edge_key(t[2], t[0]),
+    ]
+}
+
+impl<'a> Triangulation<'a> {
+    fn new(
+        points: &'a mut Vec<FlatPoint>,
+        triangles: &'a mut Vec<[usize; 3]>,
+        boundary: &'a HashSet<(usize, usize)>,
+        scale: DVec2,
+    ) -> Self {
+        let mut sharing: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (t, &triangle) in triangles.iter().enumerate() {
+            for edge in triangle_edges(triangle) {
+                sharing.entry(edge).or_default().push(t);
+            }
+        }
+        Self {
+            points,
+            triangles,
+            sharing,
+            boundary,
+            scale,
+        }
+    }
+
+    /// Replaces triangle `t`, or adds it when `t` is past the end.
+    fn set(&mut self, t: usize, triangle: [usize; 3]) {
+        if t < self.triangles.len() {
+            for edge in triangle_edges(self.triangles[t]) {
+                let owners = self.sharing.get_mut(&edge).unwrap();
+                owners.retain(|&o| o != t);
+                if owners.is_empty() {
+                    self.sharing.remove(&edge);
+                }
+            }
+            self.triangles[t] = triangle;
+        } else {
+            self.triangles.push(triangle);
+        }
+        for edge in triangle_edges(triangle) {
+            self.sharing.entry(edge).or_default().push(t);
+        }
+    }
+
+    /// The triangle `t` turned to start with the corners of `edge`, in its own order.
+    fn starting_at(&self, t: usize, edge: (usize, usize)) -> [usize; 3] {
+        let triangle = self.triangles[t];
+        let r = (0..3)
+            .find(|&r| edge_key(triangle[r], triangle[(r + 1) % 3]) == edge)
+            .unwrap();
+        [triangle[r], triangle[(r + 1) % 3], triangle[(r + 2) % 3]]
+    }
+
+    /// Splits an edge at `middle`, together with the triangles on either side of it, and gives
+    /// the edges that are new or changed.
+    fn split(&mut self, edge: (usize, usize), middle: FlatPoint) -> Vec<(usize, usize)> {
+        let m = self.points.len();
+        self.points.push(middle);
+
+        let mut around = Vec::new();
+        let mut changed = Vec::new();
+        for o in self.sharing[&edge].clone() {
+            let [x, y, z] = self.starting_at(o, edge);
+            self.set(o, [x, m, z]);
+            self.set(self.triangles.len(), [m, y, z]);
+            around.extend([edge_key(x, z), edge_key(y, z)]);
+            changed.extend([edge_key(x, m), edge_key(m, y), edge_key(m, z)]);
+        }
+
+        changed.extend(self.legalize(around));
+        changed.sort_unstable();
+        changed.dedup();
+        changed
+    }
+
+    /// Flips edges, starting from `edges`, until no interior edge has the far corner of one of
+    /// its triangles inside the circumcircle of the other. Gives the edges flipped in.
+    fn legalize(&mut self, mut edges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        let scale = self.scale;
+        let at = |points: &[FlatPoint], i: usize| points[i].uv * scale;
+        let orient = |a: DVec2, b: DVec2, c: DVec2| (b - a).perp_dot(c - a);
+
+        let mut flipped = Vec::new();
+        // Undoing the fans ear clipping leaves along a long strip takes quadratically many
+        // flips; the budget only stops flips going round in circles on rounding
+        let n = self.triangles.len() + edges.len();
+        let mut budget = 100 * n + n * n;
+        while let Some(edge) = edges.pop() {
+            if budget == 0 {
+                break;
+            }
+            budget -= 1;
+
+            let Some(&[t1, t2]) = self.sharing.get(&edge).map(Vec::as_slice) else {
+                continue;
+            };
+            if self.boundary.contains(&edge) {
+                continue;
+            }
+
+            // `t1` runs from a to b and `t2` back, with c and d their other corners
+            let [a, b, c] = self.starting_at(t1, edge);
+            let [b2, a2, d] = self.starting_at(t2, edge);
+            if a2 != a || b2 != b || self.sharing.contains_key(&edge_key(c, d)) {
+                continue;
+            }
+
+            let points = &*self.points;
+            let (pa, pb, pc, pd) = (at(points, a), at(points, b), at(points, c), at(points, d));
+            // The new edge has to cross the old one for both new triangles to face up
+            if orient(pc, pd, pa) >= 0. || orient(pc, pd, pb) <= 0. {
+                continue;
+            }
+            if in_circle(pa, pb, pc, pd) <= 0. {
+                continue;
+            }
+
+            self.set(t1, [a, d, c]);
+            self.set(t2, [d, b, c]);
+            flipped.push(edge_key(c, d));
+            edges.extend([
+                edge_key(a, d),
+                edge_key(d, b),
+                edge_key(b, c),
+                edge_key(c, a),
+            ]);
+        }
+        flipped
+    }
+}
+
+/// Positive when `d` lies inside the circle through the counter-clockwise triangle `a`, `b`,
+/// `c`.
+fn in_circle(a: DVec2, b: DVec2, c: DVec2, d: DVec2) -> f64 {
+    let (a, b, c) = (a - d, b - d, c - d);
+    a.length_squared() * b.perp_dot(c) - b.length_squared() * a.perp_dot(c)
+        + c.length_squared() * a.perp_dot(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, TAU};
+
+    use glam::DVec3;
+
+    use crate::line::{Line, ParametricLine};
+    use crate::*;
+
+    fn polygon(corners: &[DVec3]) -> BoundaryLoop {
+        BoundaryLoop {
+            elements: (0..corners.len())
+                .map(|i| {
+                    BoundaryElement::BoundaryLine(BoundaryLine::new(
+                        corners[i],
+                        corners[(i + 1) % corners.len()],
+                    ))
+                })
+                .collect(),
+        }
+    }
+
+    fn circle(center: DVec3, radius: f64) -> BoundaryLoop {
+        let start = center + DVec3::X * radius;
+        BoundaryLoop {
+            elements: vec![BoundaryElement::BoundaryArc(
+                BoundaryArc::new(center, DVec3::Z, radius, start, start, Direction::CCW).unwrap(),
+            )],
+        }
+    }
+
+    fn y_axis() -> Line {
+        Line::Parametric(ParametricLine::new(DVec3::ZERO, DVec3::Y))
+    }
+
+    /// Asserts that the mesh is closed, faces outwards and that its points lie on the solid.
+    fn assert_closed(mesh: &Mesh, volume: f64, tolerance: f64) {
+        assert_eq!(mesh.open_edges(), 0);
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        assert!(
+            (mesh.volume() - volume).abs() < tolerance * volume,
+            "{} != {volume}",
+            mesh.volume()
+        );
+    }
+
+    #[test]
+    fn test_cuboid() {
+        let (brep, solid) = crate::test_util::cuboid(DVec3::new(1., 2., 3.));
+
+        let mesh = brep
+            .tesselate_solid(solid, &TesselationTolerance::default())
+            .unwrap();
+
+        assert_eq!(mesh.indices.len(), 12);
+        assert!((mesh.area() - 22.).abs() < 1e-9);
+        assert_closed(&mesh, 6., 1e-9);
+    }
+
+    #[test]
+    fn test_plate_with_bore() {
+        let outer = polygon(&[
+            DVec3::new(-2., -2., 0.),
+            DVec3::new(2., -2., 0.),
+            DVec3::new(2., 2., 0.),
+            DVec3::new(-2., 2., 0.),
+        ]);
+        let profile = BoundarySurface::new(Plane::XY, outer, vec![circle(DVec3::ZERO, 1.)]);
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&profile, DVec3::Z, ExtrudeExtent::OneSided(1.))
+            .unwrap();
+        let tolerance = TesselationTolerance::new(1e-3, PI / 12.);
+
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+
+        assert_closed(&mesh, 16. - PI, 1e-3);
+        // Every point of the bore stays on it, and its normals face the axis
+        for (p, n) in mesh.positions.iter().zip(&mesh.normals) {
+            let radial = p.truncate().length();
+            if radial < 1.5 {
+                assert!((radial - 1.).abs() < 1e-9);
+            }
+            if n.z == 0. && radial < 1.5 {
+                assert!(n.truncate().dot(p.truncate()) < 0.);
+            }
+        }
+    }
+
+    #[test]
+    fn test_revolved_surfaces() {
+        let tolerance = TesselationTolerance::new(1e-3, PI / 12.);
+
+        // A sphere around Y, with its poles off the Z axis the surface is parametrised around
+        let arc = BoundaryArc::new(
+            DVec3::ZERO,
+            DVec3::Z,
+            1.,
+            -DVec3::Y,
+            DVec3::Y,
+            Direction::CCW,
+        )
+        .unwrap();
+        let profile = BoundarySurface::new(
+            Plane::XY,
+            BoundaryLoop {
+                elements: vec![
+                    BoundaryElement::BoundaryArc(arc),
+                    BoundaryElement::BoundaryLine(BoundaryLine::new(DVec3::Y, -DVec3::Y)),
+                ],
+            },
+            Vec::new(),
+        );
+        let mut brep = Brep::new();
+        let solid = brep.revolve(&profile, &y_axis(), TAU).unwrap();
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_closed(&mesh, 4. / 3. * PI, 1e-2);
+        for p in &mesh.positions {
+            assert!((p.length() - 1.).abs() < 1e-9);
+        }
+
+        // A torus, seamed both ways
+        let profile =
+            BoundarySurface::new(Plane::XY, circle(DVec3::new(3., 0., 0.), 1.), Vec::new());
+        let mut brep = Brep::new();
+        let solid = brep.revolve(&profile, &y_axis(), TAU).unwrap();
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_closed(&mesh, 2. * PI * PI * 3., 1e-2);
+
+        // A cylinder with a conical tip, meeting the axis at the apex
+        let profile = BoundarySurface::new(
+            Plane::XY,
+            polygon(&[
+                DVec3::new(0., 0., 0.),
+                DVec3::new(1., 0., 0.),
+                DVec3::new(1., 2., 0.),
+                DVec3::new(0., 3., 0.),
+            ]),
+            Vec::new(),
+        );
+        let mut brep = Brep::new();
+        let solid = brep.revolve(&profile, &y_axis(), TAU).unwrap();
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_closed(&mesh, 2. * PI + PI / 3., 1e-2);
+
+        // A quarter of a ring, closed by planar caps
+        let profile = BoundarySurface::new(
+            Plane::XY,
+            polygon(&[
+                DVec3::new(1., 0., 0.),
+                DVec3::new(2., 0., 0.),
+                DVec3::new(2., 1., 0.),
+                DVec3::new(1., 1., 0.),
+            ]),
+            Vec::new(),
+        );
+        let mut brep = Brep::new();
+        let solid = brep.revolve(&profile, &y_axis(), PI / 2.).unwrap();
+        let mesh = brep.tesselate_solid(solid, &tolerance).unwrap();
+        assert_closed(&mesh, 3. * PI / 4., 1e-2);
+    }
+
+    #[test]
+    fn test_finer_tolerance_adds_triangles() {
+        let profile = BoundarySurface::new(Plane::XY, circle(DVec3::ZERO, 1.), Vec::new());
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&profile, DVec3::Z, ExtrudeExtent::OneSided(2.))
+            .unwrap();
+
+        let coarse = brep
+            .tesselate_solid(solid, &TesselationTolerance::new(1e-2, PI / 4.))
+            .unwrap();
+        let fine = brep
+            .tesselate_solid(solid, &TesselationTolerance::new(1e-4, PI / 4.))
+            .unwrap();
+
+        assert!(fine.indices.len() > coarse.indices.len());
+        assert_closed(&coarse, 2. * PI, 5e-2);
+        assert_closed(&fine, 2. * PI, 1e-3);
+    }
+
+    #[test]
+    fn test_fine_strip_follows_surface() {
+        // A long side split finely around makes ear clipping leave wide fans across it
+        let profile = BoundarySurface::new(Plane::XY, circle(DVec3::ZERO, 0.3), Vec::new());
+        let mut brep = Brep::new();
+        let solid = brep
+            .extrude(&profile, DVec3::Z, ExtrudeExtent::OneSided(3.))
+            .unwrap();
+        let chord = 2e-5;
+
+        let mesh = brep
+            .tesselate_solid(solid, &TesselationTolerance::new(chord, PI / 12.))
+            .unwrap();
+
+        assert_closed(&mesh, 0.27 * PI, 1e-3);
+        for t in &mesh.indices {
+            let [a, b, c] = t.map(|i| mesh.positions[i as usize]);
+            if a.z != b.z || b.z != c.z {
+                let middle = (a + b + c) / 3.;
+                assert!(middle.truncate().length() > 0.3 - chord);
+            }
+        }
+    }
+}
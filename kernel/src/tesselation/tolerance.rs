@@ -0,0 +1,141 @@
+use std::f64::consts::{PI, TAU};
+
+use glam::DVec3;
+
+/// The deepest a curve piece is halved while looking for a fine enough split.
+const MAX_DEPTH: u32 = 16;
+
+/// How closely straight pieces have to follow a curve: no piece may stray further than `chord`
+/// from the curve or turn through more than `angle` radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TesselationTolerance {
+    pub chord: f64,
+    pub angle: f64,
+}
+
+impl Default for TesselationTolerance {
+    fn default() -> Self {
+        Self {
+            chord: 0.01,
+            angle: PI / 12.,
+        }
+    }
+}
+
+impl TesselationTolerance {
+    pub fn new(chord: f64, angle: f64) -> Self {
+        Self { chord, angle }
+    }
+
+    /// The number of equal pieces a circular arc of `radius` sweeping through `sweep` radians
+    /// is split into. Always at least one.
+    pub fn arc_segments(&self, radius: f64, sweep: f64) -> usize {
+        let sweep = sweep.abs().min(TAU);
+
+        // A piece spanning `a` radians strays r(1 - cos(a/2)) from the arc
+        let mut step = TAU;
+        if self.chord > 0. && self.chord < radius {
+            step = step.min(2. * (1. - self.chord / radius).acos());
+        }
+        if self.angle > 0. {
+            step = step.min(self.angle);
+        }
+
+        ((sweep / step).ceil() as usize).max(1)
+    }
+
+    /// Parameters splitting a curve over `start..=end` into pieces within the tolerance, found
+    /// by halving pieces that stray too far or turn too much. Starts with `start` and ends with
+    /// `end`.
+    pub fn curve_parameters(&self, start: f64, end: f64, point: impl Fn(f64) -> DVec3) -> Vec<f64> {
+        // A few even pieces first, so a curve that crosses its chord in the middle is not
+        // mistaken for a straight one
+        let initial = 4;
+        let mut out = vec![start];
+        for i in 0..initial {
+            let a = start + (end - start) * i as f64 / initial as f64;
+            let b = start + (end - start) * (i + 1) as f64 / initial as f64;
+            self.subdivide(&point, (a, point(a)), (b, point(b)), 0, &mut out);
+        }
+        out
+    }
+
+    fn subdivide(
+        &self,
+        point: &impl Fn(f64) -> DVec3,
+        (a, pa): (f64, DVec3),
+        (b, pb): (f64, DVec3),
+        depth: u32,
+        out: &mut Vec<f64>,
+    ) {
+        let m = (a + b) / 2.;
+        let pm = point(m);
+
+        let chord = pb - pa;
+        let deviation = match chord.try_normalize() {
+            Some(direction) => {
+                let d = pm - pa;
+                (d - d.dot(direction) * direction).length()
+            }
+            None => (pm - pa).length(),
+        };
+        // The chords to and from the middle turn by about half of what the curve does
+        let turn = 2. * (pm - pa).angle_between(pb - pm);
+
+        let fine =
+            deviation <= self.chord && (self.angle <= 0. || turn.is_nan() || turn <= self.angle);
+        if fine || depth >= MAX_DEPTH {
+            out.push(b);
+        } else {
+            self.subdivide(point, (a, pa), (m, pm), depth + 1, out);
+            self.subdivide(point, (m, pm), (b, pb), depth + 1, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arc_segments_scale_with_radius() {
+        let tolerance = TesselationTolerance::new(0.01, PI);
+
+        let small = tolerance.arc_segments(1., TAU);
+        let large = tolerance.arc_segments(100., TAU);
+        assert!(large > small);
+
+        // Every piece stays within the chord tolerance, but not by much
+        for (radius, n) in [(1., small), (100., large)] {
+            let deviation = |n: usize| radius * (1. - (TAU / n as f64 / 2.).cos());
+            assert!(deviation(n) <= 0.01);
+            assert!(deviation(n - 1) > 0.01);
+        }
+
+        // The angle limit applies when the radius is tiny
+        let tolerance = TesselationTolerance::new(0.01, PI / 4.);
+        assert_eq!(tolerance.arc_segments(0.001, TAU), 8);
+        assert_eq!(tolerance.arc_segments(0.001, PI / 2.), 2);
+    }
+
+    #[test]
+    fn test_curve_parameters() {
+        let tolerance = TesselationTolerance::new(1e-3, PI);
+        let parabola = |t: f64| DVec3::new(t, t * t, 0.);
+
+        let parameters = tolerance.curve_parameters(-1., 1., parabola);
+
+        assert_eq!(parameters.first(), Some(&-1.));
+        assert_eq!(parameters.last(), Some(&1.));
+        for pair in parameters.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(a < b);
+
+            // A parabola strays furthest from a chord at the middle parameter
+            let (pa, pb) = (parabola(a), parabola(b));
+            let direction = (pb - pa).normalize();
+            let d = parabola((a + b) / 2.) - pa;
+            assert!((d - d.dot(direction) * direction).length() <= 1e-3);
+        }
+    }
+}
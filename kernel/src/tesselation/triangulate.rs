@@ -0,0 +1,219 @@
+//! Ear clipping of simple polygons with holes.
+
+use glam::DVec2;
+
+/// Triangulates a polygon given by a counter-clockwise outer loop and clockwise holes. The
+/// triangles index into the outer loop followed by the holes in order, and run
+/// counter-clockwise.
+pub(crate) fn triangulate(outer: &[DVec2], holes: &[Vec<DVec2>]) -> Vec<[usize; 3]> {
+    let mut points: Vec<DVec2> = outer.to_vec();
+    let mut polygon: Vec<usize> = (0..outer.len()).collect();
+
+    // Holes are joined to the outer loop from right to left so bridges never cross
+    let mut offsets = Vec::new();
+    for hole in holes {
+        offsets.push(points.len());
+        points.extend(hole);
+    }
+    let mut order: Vec<usize> = (0..holes.len()).collect();
+    order.sort_by(|&a, &b| {
+        let max = |h: usize| holes[h].iter().map(|p| p.x).fold(f64::MIN, f64::max);
+        max(b).total_cmp(&max(a))
+    });
+
+    for h in order {
+        if holes[h].is_empty() {
+            continue;
+        }
+        let hole: Vec<usize> = (offsets[h]..offsets[h] + holes[h].len()).collect();
+        polygon = bridge(&points, &polygon, &hole);
+    }
+
+    ear_clip(&points, polygon)
+}
+
+/// Joins a hole to a polygon with a pair of coincident edges from the rightmost point of the
+/// hole to a visible vertex of the polygon.
+fn bridge(points: &[DVec2], polygon: &[usize], hole: &[usize]) -> Vec<usize> {
+    let start = (0..hole.len())
+        .max_by(|&a, &b| {
+            let (pa, pb) = (points[hole[a]], points[hole[b]]);
+            pa.x.total_cmp(&pb.x).then(pb.y.total_cmp(&pa.y))
+        })
+        .unwrap();
+    let m = points[hole[start]];
+
+    // The closest edge hit by a ray from the hole to the right
+    let n = polygon.len();
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let a = points[polygon[i]];
+        let b = points[polygon[(i + 1) % n]];
+
+        if (a.y - m.y) * (b.y - m.y) > 0. || a.y == b.y {
+            continue;
+        }
+
+        let x = a.x + (m.y - a.y) / (b.y - a.y) * (b.x - a.x);
+        if x < m.x {
+            continue;
+        }
+
+        // Of the two endpoints, the one further right is a candidate for the bridge
+        let candidate = if a.x > b.x { i } else { (i + 1) % n };
+        if best.is_none_or(|(bx, _)| x < bx) {
+            best = Some((x, candidate));
+        }
+    }
+
+    let target = match best {
+        Some((x, candidate)) => {
+            let p = points[polygon[candidate]];
+            let hit = DVec2::new(x, m.y);
+
+            // Reflex vertices inside the triangle between the hole, the hit and the candidate
+            // could block the view; take the one closest in angle to the ray instead
+            (0..n)
+                .filter(|&i| {
+                    i == candidate
+                        || (is_reflex(points, polygon, i)
+                            && inside_triangle(points[polygon[i]], m, hit, p))
+                })
+                .min_by(|&i, &j| {
+                    let key = |k: usize| {
+                        let d = points[polygon[k]] - m;
+                        ((d.y / d.length()).abs(), d.length_squared())
+                    };
+                    let (a, b) = (key(i), key(j));
+                    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+                })
+                .unwrap()
+        }
+        None => (0..n)
+            .min_by(|&i, &j| {
+                (points[polygon[i]] - m)
+                    .length_squared()
+                    .total_cmp(&(points[polygon[j]] - m).length_squared())
+            })
+            .unwrap(),
+    };
+
+    let mut out = Vec::with_capacity(n + hole.len() + 2);
+    out.extend_from_slice(&polygon[..=target]);
+    for k in 0..=hole.len() {
+        out.push(hole[(start + k) % hole.len()]);
+    }
+    out.extend_from_slice(&polygon[target..]);
+    out
+}
+
+fn is_reflex(points: &[DVec2], polygon: &[usize], i: usize) -> bool {
+    let n = polygon.len();
+    let a = points[polygon[(i + n - 1) % n]];
+    let b = points[polygon[i]];
+    let c = points[polygon[(i + 1) % n]];
+    (b - a).perp_dot(c - b) < 0.
+}
+
+fn inside_triangle(p: DVec2, a: DVec2, b: DVec2, c: DVec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    let negative = d1 < 0. || d2 < 0. || d3 < 0.;
+    let positive = d1 > 0. || d2 > 0. || d3 > 0.;
+    !(negative && positive)
+}
+
+fn ear_clip(points: &[DVec2], mut polygon: Vec<usize>) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::new();
+
+    while polygon.len() > 3 {
+        let n = polygon.len();
+
+        let is_ear = |i: usize| {
+            let (ia, ib, ic) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+            let (a, b, c) = (points[ia], points[ib], points[ic]);
+            if (b - a).perp_dot(c - b) <= 0. {
+                return false;
+            }
+
+            polygon.iter().all(|&k| {
+                let p = points[k];
+                // Bridge vertices repeat positions of the corners
+                p == a || p == b || p == c || !inside_triangle(p, a, b, c)
+            })
+        };
+
+        // Degenerate input can leave no proper ears; clip the flattest corner to make progress
+        let ear = (0..n).find(|&i| is_ear(i)).unwrap_or_else(|| {
+            (0..n)
+                .max_by(|&i, &j| {
+                    let turn = |k: usize| {
+                        let a = points[polygon[(k + n - 1) % n]];
+                        let b = points[polygon[k]];
+                        let c = points[polygon[(k + 1) % n]];
+                        (b - a).perp_dot(c - b)
+                    };
+                    turn(i).total_cmp(&turn(j))
+                })
+                .unwrap()
+        });
+
+        triangles.push([
+            polygon[(ear + n - 1) % n],
+            polygon[ear],
+            polygon[(ear + 1) % n],
+        ]);
+        polygon.remove(ear);
+    }
+
+    if polygon.len() == 3 {
+        triangles.push([polygon[0], polygon[1], polygon[2]]);
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[DVec2], triangles: &[[usize; 3]]) -> f64 {
+        triangles
+            .iter()
+            .map(|t| (points[t[1]] - points[t[0]]).perp_dot(points[t[2]] - points[t[0]]) / 2.)
+            .sum()
+    }
+
+    #[test]
+    fn test_square_with_hole() {
+        let outer = [(0., 0.), (4., 0.), (4., 4.), (0., 4.)]
+            .map(DVec2::from)
+            .to_vec();
+        let hole = [(1., 1.), (1., 3.), (3., 3.), (3., 1.)]
+            .map(DVec2::from)
+            .to_vec();
+
+        let triangles = triangulate(&outer, std::slice::from_ref(&hole));
+        let points: Vec<DVec2> = outer.iter().chain(&hole).copied().collect();
+
+        assert_eq!(triangles.len(), 8);
+        assert!((area(&points, &triangles) - 12.).abs() < 1e-12);
+        for t in &triangles {
+            let a = (points[t[1]] - points[t[0]]).perp_dot(points[t[2]] - points[t[0]]);
+            assert!(a > 0.);
+        }
+    }
+
+    #[test]
+    fn test_concave() {
+        let outer = [(0., 0.), (3., 0.), (3., 3.), (2., 3.), (2., 1.), (0., 1.)]
+            .map(DVec2::from)
+            .to_vec();
+
+        let triangles = triangulate(&outer, &[]);
+
+        assert_eq!(triangles.len(), 4);
+        assert!((area(&outer, &triangles) - 5.).abs() < 1e-12);
+    }
+}
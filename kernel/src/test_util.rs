@@ -0,0 +1,112 @@
+//! Sketches and solids shared by the tests of several modules.
+
+use glam::DVec3;
+
+use crate::arc::Arc;
+use crate::line::{Line, TwoPointLine};
+use crate::surface::Surface;
+use crate::*;
+
+/// An axis-aligned box with its minimum corner at the origin.
+pub fn cuboid(size: DVec3) -> (Brep, SolidId) {
+    let mut brep = Brep::new();
+
+    let corner = |i: usize| {
+        DVec3::new(
+            (i & 1) as f64 * size.x,
+            ((i >> 1) & 1) as f64 * size.y,
+            ((i >> 2) & 1) as f64 * size.z,
+        )
+    };
+    let v: Vec<VertexId> = (0..8).map(|i| brep.add_vertex(corner(i))).collect();
+
+    // Every edge joins two corners that differ in one coordinate bit
+    let mut edges = Vec::new();
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                edges.push(((i, i | bit), brep.add_line_edge(v[i], v[i | bit])));
+            }
+        }
+    }
+    let edge = |a: usize, b: usize| {
+        edges
+            .iter()
+            .find_map(|&((s, e), id)| {
+                if (s, e) == (a, b) {
+                    Some((id, false))
+                } else if (s, e) == (b, a) {
+                    Some((id, true))
+                } else {
+                    None
+                }
+            })
+            .unwrap()
+    };
+
+    // Corners of each face, counter-clockwise seen from outside
+    let faces = [
+        ([0, 2, 3, 1], -DVec3::Z, DVec3::ZERO),
+        ([4, 5, 7, 6], DVec3::Z, DVec3::Z * size.z),
+        ([0, 1, 5, 4], -DVec3::Y, DVec3::ZERO),
+        ([2, 6, 7, 3], DVec3::Y, DVec3::Y * size.y),
+        ([0, 4, 6, 2], -DVec3::X, DVec3::ZERO),
+        ([1, 3, 7, 5], DVec3::X, DVec3::X * size.x),
+    ];
+
+    let faces = faces
+        .iter()
+        .map(|(c, normal, center)| {
+            let outer: Vec<_> = (0..4).map(|i| edge(c[i], c[(i + 1) % 4])).collect();
+            let surface = Surface::Plane(Plane::new(*normal, *center));
+            brep.add_face(surface, false, &outer, &[]).unwrap()
+        })
+        .collect();
+
+    let shell = brep.add_shell(faces);
+    let solid = brep.add_solid(vec![shell]);
+    (brep, solid)
+}
+
+/// An axis-aligned box from `min` to `max`, extruded from a sketch.
+pub fn block(brep: &mut Brep, min: DVec3, max: DVec3) -> SolidId {
+    let mut sketch = Sketch::new(SketchPlane::XY);
+    rectangle(&mut sketch, (min.x, min.y), (max.x, max.y));
+
+    let region = &sketch.find_regions()[0];
+    let extent = ExtrudeExtent::TwoSided {
+        forward: max.z,
+        backward: -min.z,
+    };
+    brep.extrude(region, DVec3::Z, extent).unwrap()
+}
+
+/// A line of a sketch on the XY plane from `a` to `b`.
+pub fn line(sketch: &mut Sketch, a: (f64, f64), b: (f64, f64)) -> ElementId {
+    sketch.add_element(SketchElement::Line(SketchLine(Line::TwoPoint(
+        TwoPointLine::new(DVec3::new(a.0, a.1, 0.), DVec3::new(b.0, b.1, 0.)),
+    ))))
+}
+
+/// The lines around the closed polygon through `points`, in order.
+pub fn polygon(sketch: &mut Sketch, points: &[(f64, f64)]) -> Vec<ElementId> {
+    (0..points.len())
+        .map(|i| line(sketch, points[i], points[(i + 1) % points.len()]))
+        .collect()
+}
+
+/// The bottom, right, top and left sides of an axis-aligned rectangle from `min` to `max`,
+/// running counter-clockwise.
+pub fn rectangle(sketch: &mut Sketch, min: (f64, f64), max: (f64, f64)) -> [ElementId; 4] {
+    let corners = [min, (max.0, min.1), max, (min.0, max.1)];
+    polygon(sketch, &corners).try_into().unwrap()
+}
+
+/// A full circle on the XY plane, drawn as an arc from its rightmost point back to it.
+pub fn circle(sketch: &mut Sketch, center: (f64, f64), radius: f64) -> ElementId {
+    let center = DVec3::new(center.0, center.1, 0.);
+    let start = center + DVec3::X * radius;
+    sketch.add_element(SketchElement::Arc(SketchArc(
+        Arc::new(center, DVec3::Z, start, start).unwrap(),
+    )))
+}
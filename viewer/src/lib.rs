@@ -1,4 +1,4 @@
-use glam::Vec3;
+use glam::{DVec3, Vec3};
 use kernel::{Sketch, SketchArc, SketchElement, SketchPlane};
 use std::sync::Arc;
 use vertex::Vertex;
@@ -98,16 +98,17 @@ impl<'a> State<'a> {
         let camera_state = CameraState::new(camera, &device);
 
         let mut sketch_state = SketchState::new(0.1, &device, &config);
-        sketch_state.add_sketch(Sketch {
-            plane: SketchPlane::XY,
-            elements: vec![SketchElement::Arc(SketchArc(kernel::arc::Arc {
-                radius: 0.5,
-                start: Vec3::new(0.5, 0., 0.),
-                end: Vec3::new(0., 0.5, 0.),
-                center: Vec3::new(0., 0., 0.),
-                direction: kernel::arc::ArcDirection::CW,
-            }))],
-        });
+        let mut sketch = Sketch::new(SketchPlane::XY);
+        sketch.add_element(SketchElement::Arc(SketchArc(
+            kernel::arc::Arc::new(
+                DVec3::new(0., 0., 0.),
+                -DVec3::Z,
+                DVec3::new(0.5, 0., 0.),
+                DVec3::new(0., 0.5, 0.),
+            )
+            .unwrap(),
+        )));
+        sketch_state.add_sketch(sketch);
 
         sketch_state.generate_lines();
 
@@ -168,10 +169,13 @@ impl<'a> State<'a> {
         let mut line_verts = Vec::new();
 
         for line in &self.sketch_state.lines {
-            let tpl = line.to_two_point_line();
+            let Ok(tpl) = line.to_two_point_line() else {
+                continue;
+            };
 
-            let a = tpl.a.0.to_array();
-            let b = tpl.b.0.to_array();
+            // The kernel works in f64, the GPU in f32
+            let a = tpl.a.0.as_vec3().to_array();
+            let b = tpl.b.0.as_vec3().to_array();
 
             let v_a = Vertex {
                 position: a,
@@ -230,7 +234,9 @@ impl<'a> State<'a> {
             rpass.set_pipeline(&self.sketch_state.render_pipeline);
             //rpass.set_bind_group(0, &self.camera_state.bind_group, &[]);
             rpass.set_vertex_buffer(0, self.sketch_state.tesselated_sketch_buffer.slice(..));
-            rpass.draw(0..self.sketch_state.lines.len() as u32 * 2, 0..1);
+            let vertex_count = self.sketch_state.tesselated_sketch_buffer.size()
+                / std::mem::size_of::<Vertex>() as BufferAddress;
+            rpass.draw(0..vertex_count as u32, 0..1);
         }
 
         self.queue.submit(Some(encoder.finish()));
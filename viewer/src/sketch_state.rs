@@ -1,5 +1,5 @@
 use kernel::line::Line;
-use kernel::Sketch;
+use kernel::{Sketch, TesselationTolerance};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{include_wgsl, BufferAddress};
 
@@ -121,7 +121,7 @@ impl SketchState {
         self.lines = Vec::new();
 
         for sketch in &self.sketches {
-            let mut l = sketch.to_lines();
+            let mut l = sketch.to_lines(&TesselationTolerance::default());
 
             self.lines.append(&mut l);
         }